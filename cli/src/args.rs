@@ -1,6 +1,10 @@
 use anyhow::Context;
+use decomp::symbols::SymbolMap;
 use pico_args::Arguments;
-use ppc32::decoder::{AddrRange, AddrRangeEnd};
+use ppc32::{
+    decoder::{AddrRange, AddrRangeEnd},
+    format::Radix,
+};
 use std::{num::ParseIntError, path::PathBuf, str::FromStr};
 
 macro_rules! define_args {
@@ -37,18 +41,35 @@ macro_rules! define_args {
 }
 
 define_args! {
+    verbose("-v") exists: bool,
     input("-i") required: PathBuf,
-    addr("-x"): Option<AddrRange> = parse_addr_range,
+    // Parsed with `parse_addr_range` once the symbol map is loaded.
+    addr("-x"): Option<String>,
     entrypoint("--entrypoint") exists: bool,
     headers("--headers") exists: bool,
     sections("--sections") exists: bool,
-    disasm("--disasm"): Option<DisassemblyLanguage> = DisassemblyLanguage::from_str
+    disasm("--disasm"): Option<DisassemblyLanguage> = DisassemblyLanguage::from_str,
+    sweep("--sweep"): Option<SweepTarget> = SweepTarget::from_str,
+    const_sections("--const-sections"): Option<Vec<usize>> = parse_section_list,
+    trace("--trace") exists: bool,
+    depth("--depth"): Option<u32>,
+    output("--output"): Option<PathBuf>,
+    symbols("--symbols"): Option<PathBuf>,
+    format("--format"): Option<OutputFormat> = OutputFormat::from_str,
+    radix("--radix"): Option<Radix> = parse_radix,
+    unsigned("--unsigned") exists: bool
 }
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone)]
 pub enum DisassemblyLanguage {
     Asm,
     C,
+    /// Assembly and C next to each other.
+    Both,
+    /// Assembly annotated with the values that the value analysis computed for the registers each instruction writes.
+    Ir,
+    /// Only the detected prototype, stack frame and calls of the function.
+    Summary,
 }
 
 impl FromStr for DisassemblyLanguage {
@@ -58,31 +79,128 @@ impl FromStr for DisassemblyLanguage {
         match s {
             "asm" => Ok(DisassemblyLanguage::Asm),
             "c" => Ok(DisassemblyLanguage::C),
+            "both" => Ok(DisassemblyLanguage::Both),
+            "ir" => Ok(DisassemblyLanguage::Ir),
+            "summary" => Ok(DisassemblyLanguage::Summary),
             _ => Err(anyhow::anyhow!("invalid disassembly language: {}", s)),
         }
     }
 }
 
-fn parse_addr_range(source: &str) -> anyhow::Result<AddrRange> {
-    fn parse_hex(s: &str) -> Result<u32, ParseIntError> {
-        u32::from_str_radix(s.trim_start_matches("0x"), 16)
+/// How `--headers`, `--sections` and `--trace` are printed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    /// Only for `--headers` and `--sections`.
+    Json,
+    /// A Graphviz digraph, only for `--trace`.
+    Dot,
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "dot" => Ok(OutputFormat::Dot),
+            _ => Err(anyhow::anyhow!("invalid output format: {}", s)),
+        }
+    }
+}
+
+/// Parses the radix of immediate operands in assembly output, `hex` or `dec`.
+fn parse_radix(s: &str) -> anyhow::Result<Radix> {
+    match s {
+        "hex" => Ok(Radix::Hex),
+        "dec" => Ok(Radix::Decimal),
+        _ => Err(anyhow::anyhow!("invalid radix: {}", s)),
+    }
+}
+
+/// The sections to walk in `--sweep` mode.
+#[derive(Debug, Copy, Clone)]
+pub enum SweepTarget {
+    /// All (non-empty) text sections.
+    Text,
+    /// A single section by index.
+    Section(usize),
+}
+
+impl FromStr for SweepTarget {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "text" {
+            return Ok(SweepTarget::Text);
+        }
+
+        let section: usize = s
+            .parse()
+            .context("invalid sweep target, expected `text` or a section index")?;
+        anyhow::ensure!(section <= 17, "section index out of range: {section}");
+        Ok(SweepTarget::Section(section))
+    }
+}
+
+/// Parses a comma separated list of section indices, e.g. `--const-sections 8,9`.
+fn parse_section_list(s: &str) -> anyhow::Result<Vec<usize>> {
+    s.split(',')
+        .map(|index| {
+            let index: usize = index
+                .parse()
+                .with_context(|| format!("invalid section index: {index}"))?;
+            anyhow::ensure!(index <= 17, "section index out of range: {index}");
+            Ok(index)
+        })
+        .collect()
+}
+
+/// Parses a number that is hex with a `0x` prefix and decimal otherwise.
+fn parse_number(s: &str) -> Result<u32, ParseIntError> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+}
+
+/// Parses an address, which is either a number (see [`parse_number`]) or the name of a symbol.
+fn parse_addr(s: &str, symbols: &SymbolMap) -> anyhow::Result<u32> {
+    if s.starts_with(|c: char| c.is_ascii_digit()) {
+        Ok(parse_number(s)?)
+    } else {
+        symbols
+            .address_of(s)
+            .with_context(|| format!("`{s}` is neither a number nor a known symbol"))
     }
+}
 
+/// Parses the `-x <start>:<end?>` address range, where `<end>` can also be `+<length>` in bytes
+/// or `n<count>` in instructions.
+pub fn parse_addr_range(source: &str, symbols: &SymbolMap) -> anyhow::Result<AddrRange> {
     let (start, end) = source
         .split_once(':')
         .context("invalid address range format, expected -x <start>:<end?> (end is optional)")?;
 
-    let start = parse_hex(start).context("failed to parse start address")?;
+    let start = parse_addr(start, symbols).context("failed to parse start address")?;
     let end = if end.is_empty() {
         AddrRangeEnd::Unbounded
+    } else if let Some(count) = end
+        .strip_prefix('n')
+        // Could also be a symbol name that starts with n
+        .filter(|count| count.starts_with(|c: char| c.is_ascii_digit()))
+    {
+        let count = parse_number(count).context("failed to parse instruction count")?;
+        AddrRangeEnd::Count(count)
     } else {
         let end = if let Some(rest) = end.strip_prefix('+') {
-            let relative: u32 = rest
-                .parse()
-                .context("failed to parse relative end address")?;
-            start + relative
+            let relative = parse_number(rest).context("failed to parse relative end address")?;
+            start
+                .checked_add(relative)
+                .context("relative end address overflows")?
         } else {
-            parse_hex(end).context("failed to parse end address")?
+            parse_addr(end, symbols).context("failed to parse end address")?
         };
 
         AddrRangeEnd::Bounded(end)
@@ -1,59 +1,363 @@
+use std::{collections::BTreeSet, io::Write};
+
 use anyhow::Context;
 use decomp::{
-    ast::write::StringWriter,
-    dataflow::{Instructions, InstructionsDeref},
+    ast::{ty::TyKind, write::StringWriter},
+    dataflow::{
+        Instructions, InstructionsDeref,
+        value::{ConstSection, ValueAnalysis},
+    },
     detect_fn_boundaries,
+    symbols::SymbolMap,
 };
-use dol::Dol;
+use dol::{Dol, SectionKind, UnmappedAddress};
 use ppc32::{
     Decoder,
-    decoder::{AddrRange, AddrRangeEnd},
+    decoder::{AddrRange, AddrRangeEnd, Address},
+    format::AsmFormatOptions,
+    instruction::BranchTarget,
+    word::Word,
 };
 
 use crate::args::DisassemblyLanguage;
 
-pub fn disasm(dol: &Dol, range: AddrRange, lang: DisassemblyLanguage) -> anyhow::Result<()> {
+pub fn disasm(
+    dol: &Dol,
+    range: AddrRange,
+    lang: DisassemblyLanguage,
+    const_sections: &[usize],
+    asm_format: AsmFormatOptions,
+    symbols: &SymbolMap,
+    out: &mut dyn Write,
+) -> anyhow::Result<()> {
     let fn_addr = range.0;
+    if let Some(section) = dol.section_of_load_addr(fn_addr)
+        && section.kind == SectionKind::Data
+    {
+        tracing::warn!("{fn_addr:#x} is in a data section, this is probably not code");
+    }
     let buffer = dol
         .slice_from_load_addr(fn_addr)
-        .context("address is not in any section")?;
+        .ok_or(UnmappedAddress(fn_addr))?;
 
     let boundary = match range.1 {
         AddrRangeEnd::Unbounded => detect_fn_boundaries(buffer, fn_addr, 0),
         AddrRangeEnd::Bounded(end_addr) => {
-            let end = end_addr - fn_addr;
-            &buffer[..end as usize]
+            let end = end_addr
+                .checked_sub(fn_addr)
+                .context("end address is before the start address")?;
+            buffer
+                .get(..end as usize)
+                .context("end address is past the end of the section")?
+        }
+        AddrRangeEnd::Count(count) => {
+            // Instructions are always 4 bytes, so the count maps directly to a length.
+            buffer
+                .get(..count as usize * 4)
+                .with_context(|| format!("section ends before {count} instructions"))?
         }
     };
-    let mut decoder = Decoder::new(boundary);
-    let instructions = decoder
-        .iter_until_eof(fn_addr)
-        .collect::<Result<Instructions, _>>()
-        .context("decode error")?;
+    let const_sections = self::const_sections(dol, const_sections)?;
+    disasm_function(
+        boundary,
+        fn_addr,
+        &const_sections,
+        lang,
+        asm_format,
+        symbols,
+        out,
+    )
+}
+
+/// The data sections at the given indices, for the decompiler to resolve loads from. A .dol doesn't say which of its
+/// sections are read-only, so they have to be passed with `--const-sections`.
+pub fn const_sections<'a>(
+    dol: &'a Dol,
+    indices: &[usize],
+) -> anyhow::Result<Vec<ConstSection<'a>>> {
+    indices
+        .iter()
+        .map(|&index| (index, dol.section(index)))
+        .filter(|(_, section)| !section.empty())
+        .map(|(index, section)| {
+            anyhow::ensure!(
+                section.kind == SectionKind::Data,
+                "section #{index} is not a data section"
+            );
+            Ok(ConstSection {
+                address: section.load_offset,
+                bytes: dol
+                    .slice_from_load_addr(section.load_offset)
+                    .with_context(|| {
+                        format!("section #{index} is not fully contained in the file")
+                    })?,
+            })
+        })
+        .collect()
+}
+
+/// Decodes and disassembles a single function whose code is exactly `code`.
+pub fn disasm_function(
+    code: &[u8],
+    fn_addr: u32,
+    const_sections: &[ConstSection<'_>],
+    lang: DisassemblyLanguage,
+    asm_format: AsmFormatOptions,
+    symbols: &SymbolMap,
+    out: &mut dyn Write,
+) -> anyhow::Result<()> {
+    // The listings print the raw words next to the instructions, so keep them around.
+    let mut decoder = Decoder::new(code);
+    let mut words = Vec::new();
+    let mut instructions = Instructions::new();
+    while decoder.peek().is_some() {
+        let addr = Address(fn_addr + decoder.offset_u32());
+        let (word, ins) = decoder
+            .decode_instruction_with_word()
+            .context("decode error")?;
+        words.push(word);
+        instructions.push((addr, ins));
+    }
 
     match lang {
-        DisassemblyLanguage::Asm => disasm_asm(&instructions)?,
-        DisassemblyLanguage::C => disasm_c(&instructions, fn_addr)?,
+        DisassemblyLanguage::Asm => disasm_asm(&words, &instructions, asm_format, symbols, out)?,
+        DisassemblyLanguage::C => disasm_c(&instructions, fn_addr, const_sections, symbols, out)?,
+        DisassemblyLanguage::Both => disasm_both(
+            &words,
+            fn_addr,
+            &instructions,
+            const_sections,
+            asm_format,
+            symbols,
+            out,
+        )?,
+        DisassemblyLanguage::Ir => {
+            disasm_ir(&instructions, fn_addr, const_sections, asm_format, out)?
+        }
+        DisassemblyLanguage::Summary => {
+            disasm_summary(&instructions, fn_addr, const_sections, symbols, out)?
+        }
     }
 
     Ok(())
 }
 
-/// Disassemble as assembly code.
-fn disasm_asm(instructions: &InstructionsDeref) -> anyhow::Result<()> {
-    for (addr, ins) in instructions {
-        println!("{addr} {ins:?}")
+/// Disassemble as assembly code, with the address and the raw bytes of each instruction in front of it.
+/// Branch targets within the function get a `loc_` label, which the branches to them refer to.
+fn disasm_asm(
+    words: &[Word],
+    instructions: &InstructionsDeref,
+    asm_format: AsmFormatOptions,
+    symbols: &SymbolMap,
+    out: &mut dyn Write,
+) -> anyhow::Result<()> {
+    for line in asm_listing(words, instructions, asm_format, symbols)
+        .iter()
+        .flatten()
+    {
+        writeln!(out, "{line}")?;
     }
 
     Ok(())
 }
 
+/// The lines that [`disasm_asm`] prints for each instruction, which is the instruction itself and its label, if any.
+fn asm_listing(
+    words: &[Word],
+    instructions: &InstructionsDeref,
+    asm_format: AsmFormatOptions,
+    symbols: &SymbolMap,
+) -> Vec<Vec<String>> {
+    let in_function = |target: u32| {
+        instructions
+            .binary_search_by_key(&target, |(addr, _)| addr.0)
+            .is_ok()
+    };
+    let labels: BTreeSet<u32> = instructions
+        .iter()
+        .filter_map(|(addr, ins)| ins.branch_target(addr.0)?.address())
+        .filter(|&target| in_function(target))
+        .collect();
+
+    instructions
+        .iter()
+        .zip(words)
+        .map(|((addr, ins), word)| {
+            let mut lines = Vec::new();
+            if labels.contains(&addr.0) {
+                lines.push(format!("loc_{addr}:"));
+            }
+            let [b0, b1, b2, b3] = word.0.to_be_bytes();
+            let mut line = format!(
+                "{addr}: {b0:02x} {b1:02x} {b2:02x} {b3:02x}  {}",
+                ins.display(asm_format)
+            );
+            match ins.branch_target(addr.0).and_then(BranchTarget::address) {
+                Some(target) if labels.contains(&target) => {
+                    line.push_str(&format!(" -> loc_{target:08x}"))
+                }
+                Some(target) => match symbols.get(target) {
+                    Some(name) => line.push_str(&format!(" -> {name}")),
+                    None => line.push_str(&format!(" -> {target:#010x}")),
+                },
+                None => {}
+            }
+            lines.push(line);
+            lines
+        })
+        .collect()
+}
+
 /// Disassemble as C code.
-fn disasm_c(instructions: &InstructionsDeref, fn_addr: u32) -> anyhow::Result<()> {
+fn disasm_c(
+    instructions: &InstructionsDeref,
+    fn_addr: u32,
+    const_sections: &[ConstSection<'_>],
+    symbols: &SymbolMap,
+    out: &mut dyn Write,
+) -> anyhow::Result<()> {
+    let mut output = StringWriter::new();
+    decomp::decompile_into_ast_writer(instructions, fn_addr, const_sections, symbols, &mut output)
+        .context("decompilation error")?;
+    writeln!(out, "{}", output.into_string())?;
+
+    Ok(())
+}
+
+/// Disassemble as assembly on the left and C on the right, to check the decompiled code against the instructions it
+/// came from. Both are rendered from the same decoded instructions. Each statement is printed next to the last
+/// instruction it was decompiled from, and lines without a statement (braces, `else`, ...) get a row of their own.
+fn disasm_both(
+    words: &[Word],
+    fn_addr: u32,
+    instructions: &InstructionsDeref,
+    const_sections: &[ConstSection<'_>],
+    asm_format: AsmFormatOptions,
+    symbols: &SymbolMap,
+    out: &mut dyn Write,
+) -> anyhow::Result<()> {
+    let listing = asm_listing(words, instructions, asm_format, symbols);
     let mut output = StringWriter::new();
-    decomp::decompile_into_ast_writer(instructions, fn_addr, &mut output)
-        .map_err(|err| anyhow::anyhow!("decompilation error: {err:#x?}"))?;
-    println!("{}", output.into_string());
+    decomp::decompile_into_ast_writer(instructions, fn_addr, const_sections, symbols, &mut output)
+        .context("decompilation error")?;
+    let (c, sources) = output.into_string_with_sources();
+
+    let mut rows = Vec::new();
+    // The asm of the instructions before this one has already been printed.
+    let mut next_inst = 0;
+    for (line, c) in c.lines().enumerate() {
+        match sources.get(&line) {
+            Some(source) if source.end.0 as usize >= next_inst => {
+                let end = source.end.0 as usize + 1;
+                let mut asm: Vec<&str> = listing[next_inst..end]
+                    .iter()
+                    .flatten()
+                    .map(String::as_str)
+                    .collect();
+                let last = asm.pop().unwrap_or_default();
+                rows.extend(asm.into_iter().map(|asm| (asm, "")));
+                rows.push((last, c));
+                next_inst = end;
+            }
+            // Statements whose instructions were printed already, e.g. because they were reordered.
+            _ => rows.push(("", c)),
+        }
+    }
+    rows.extend(
+        listing[next_inst..]
+            .iter()
+            .flatten()
+            .map(|asm| (asm.as_str(), "")),
+    );
+
+    let width = listing.iter().flatten().map(String::len).max().unwrap_or(0);
+    for (asm, c) in rows {
+        writeln!(out, "{}", format!("{asm:width$} | {c}").trim_end())?;
+    }
+
+    Ok(())
+}
+
+/// Disassemble with the value of every GPR an instruction writes after it executed, as far as the value analysis can
+/// tell, e.g. `80003100: Addi { .. } => r3 = (r4 + 0x8)`.
+fn disasm_ir(
+    instructions: &InstructionsDeref,
+    fn_addr: u32,
+    const_sections: &[ConstSection<'_>],
+    asm_format: AsmFormatOptions,
+    out: &mut dyn Write,
+) -> anyhow::Result<()> {
+    let analysis = ValueAnalysis {
+        insts: instructions,
+        fn_address: fn_addr,
+        const_sections,
+    };
+    let results = analysis.run().context("value analysis error")?;
+
+    let mut lines = Vec::new();
+    results.for_each_with_input(&analysis, |cx| {
+        let (addr, _) = instructions[cx.idx()];
+        let ins = cx.item();
+        cx.effect();
+
+        let mut writes = Vec::new();
+        ins.for_each_written_gpr(|gpr| {
+            writes.push(format!("r{} = {:?}", gpr.0, cx.state().gpr(gpr)));
+        });
+        if writes.is_empty() {
+            lines.push(format!("{addr}: {}", ins.display(asm_format)));
+        } else {
+            lines.push(format!(
+                "{addr}: {} => {}",
+                ins.display(asm_format),
+                writes.join(", ")
+            ));
+        }
+    });
+
+    for line in lines {
+        writeln!(out, "{line}")?;
+    }
+    Ok(())
+}
+
+/// Print the prototype of the function and what else the decompiler found out about it, one fact per line.
+fn disasm_summary(
+    instructions: &InstructionsDeref,
+    fn_addr: u32,
+    const_sections: &[ConstSection<'_>],
+    symbols: &SymbolMap,
+    out: &mut dyn Write,
+) -> anyhow::Result<()> {
+    let summary =
+        decomp::summarize(instructions, fn_addr, const_sections).context("decompilation error")?;
+    writeln!(out, "Function {}", symbols.describe(summary.address))?;
+    let params: Vec<String> = summary
+        .params
+        .iter()
+        .map(|gpr| format!("r{}", gpr.0))
+        .collect();
+    writeln!(
+        out,
+        "  parameters: {} ({})",
+        params.len(),
+        params.join(", ")
+    )?;
+    let return_ty = match summary.return_ty {
+        TyKind::Void => "void",
+        TyKind::U32 => "u32",
+        TyKind::Ptr => "void *",
+        TyKind::F32 => "float",
+        TyKind::F64 => "double",
+    };
+    writeln!(out, "  returns: {return_ty}")?;
+    match summary.frame_size {
+        Some(size) => writeln!(out, "  stack frame: {size:#x} bytes")?,
+        None => writeln!(out, "  stack frame: none")?,
+    }
+    for &call in &summary.calls {
+        writeln!(out, "  calls {}", symbols.describe(call))?;
+    }
 
     Ok(())
 }
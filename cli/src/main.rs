@@ -1,36 +1,68 @@
-use std::fs;
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+};
 
 use anyhow::{Context, anyhow, bail, ensure};
+use decomp::symbols::SymbolMap;
 use dol::Dol;
-use ppc32::decoder::{AddrRange, AddrRangeEnd};
+use ppc32::{
+    decoder::{AddrRange, AddrRangeEnd},
+    format::{AsmFormatOptions, Radix},
+};
+use tracing::Level;
 
-use crate::args::Args;
+use crate::args::{Args, OutputFormat};
 
 mod args;
 mod disasm;
+mod sweep;
+mod trace;
 
 fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt::init();
-
     let Args {
+        verbose,
         input,
         addr,
         entrypoint,
         headers,
         sections,
         disasm,
+        sweep,
+        const_sections,
+        trace,
+        depth,
+        output,
+        symbols,
+        format,
+        radix,
+        unsigned,
     } = Args::parse()?;
 
+    // Logs go to stderr so that they don't end up mixed into the disassembly.
+    tracing_subscriber::fmt()
+        .with_writer(io::stderr)
+        .with_max_level(if verbose { Level::DEBUG } else { Level::WARN })
+        .init();
+
     let dol = Dol::new(fs::read(input).context("failed to read input file")?)
         .map_err(|err| anyhow!("dol validation failed: {err}"))?;
 
+    let symbols = match symbols {
+        Some(path) => {
+            SymbolMap::parse(&fs::read_to_string(path).context("failed to read symbol map")?)
+                .context("failed to parse symbol map")?
+        }
+        None => SymbolMap::default(),
+    };
+
     let addr = || {
-        if let Some(addr) = addr {
+        if let Some(addr) = &addr {
             ensure!(
                 entrypoint == false,
                 "cannot provide both -x and --entrypoint"
             );
-            Ok(addr)
+            args::parse_addr_range(addr, &symbols)
         } else if entrypoint {
             Ok(AddrRange(dol.entrypoint(), AddrRangeEnd::Unbounded))
         } else {
@@ -38,20 +70,78 @@ fn main() -> anyhow::Result<()> {
         }
     };
 
+    let mut out: Box<dyn Write> = match output {
+        Some(path) => Box::new(BufWriter::new(
+            File::create(path).context("failed to create output file")?,
+        )),
+        None => Box::new(io::stdout().lock()),
+    };
+
     let mut did_anything = false;
 
-    if headers {
-        print_headers(&dol)?;
-        did_anything = true;
+    let format = format.unwrap_or(OutputFormat::Text);
+    match format {
+        OutputFormat::Text => {
+            if headers {
+                print_headers(&dol, &mut out)?;
+                did_anything = true;
+            }
+
+            if sections {
+                print_sections(&dol, &mut out)?;
+                did_anything = true;
+            }
+        }
+        OutputFormat::Json => {
+            ensure!(
+                disasm.is_none() && sweep.is_none() && !trace,
+                "--format json only applies to --headers and --sections"
+            );
+            if headers || sections {
+                print_json(&dol, headers, sections, &mut out)?;
+                did_anything = true;
+            }
+        }
+        OutputFormat::Dot => {
+            ensure!(
+                trace && !headers && !sections,
+                "--format dot only applies to --trace"
+            );
+        }
     }
 
-    if sections {
-        print_sections(&dol)?;
+    ensure!(trace || depth.is_none(), "--depth requires --trace");
+    let asm_format = AsmFormatOptions {
+        radix: radix.unwrap_or(Radix::Decimal),
+        signed: !unsigned,
+    };
+    let const_sections = const_sections.unwrap_or_default();
+    if trace {
+        let dot = format == OutputFormat::Dot;
+        trace::trace(&dol, addr()?.0, depth, &symbols, dot, &mut out)?;
         did_anything = true;
-    }
-
-    if let Some(lang) = disasm {
-        disasm::disasm(&dol, addr()?, lang)?;
+    } else if let Some(target) = sweep {
+        let lang = disasm.context("--sweep requires --disasm <language>")?;
+        sweep::sweep(
+            &dol,
+            target,
+            lang,
+            &const_sections,
+            asm_format,
+            &symbols,
+            &mut out,
+        )?;
+        did_anything = true;
+    } else if let Some(lang) = disasm {
+        disasm::disasm(
+            &dol,
+            addr()?,
+            lang,
+            &const_sections,
+            asm_format,
+            &symbols,
+            &mut out,
+        )?;
         did_anything = true;
     }
 
@@ -59,37 +149,74 @@ fn main() -> anyhow::Result<()> {
         eprintln!("No action specified!");
     }
 
+    out.flush().context("failed to write output")?;
+
     Ok(())
 }
 
-fn print_headers(dol: &Dol) -> anyhow::Result<()> {
-    println!("BSS address: {:#x}", dol.bss_address());
-    println!("BSS size: {:#x}", dol.bss_size());
-    println!("Entrypoint: {:#x}\n", dol.entrypoint());
+fn print_headers(dol: &Dol, out: &mut dyn Write) -> anyhow::Result<()> {
+    writeln!(out, "BSS address: {:#x}", dol.bss_address())?;
+    writeln!(out, "BSS size: {:#x}", dol.bss_size())?;
+    writeln!(out, "Entrypoint: {:#x}\n", dol.entrypoint())?;
 
     Ok(())
 }
 
-fn print_sections(dol: &Dol) -> anyhow::Result<()> {
+fn print_sections(dol: &Dol, out: &mut dyn Write) -> anyhow::Result<()> {
     let mut zero_filtered = 0;
 
     for (i, section) in dol.sections().enumerate() {
         if section.empty() {
             zero_filtered += 1;
         } else {
-            println!(
+            writeln!(
+                out,
                 "Section #{}: file offset {:#x}, load address {:#x}, size {:#x}",
                 i, section.file_offset, section.load_offset, section.size
-            );
+            )?;
         }
     }
 
     if zero_filtered > 0 {
-        println!(
+        writeln!(
+            out,
             "(Note: {} sections with size 0 were omitted)",
             zero_filtered
-        );
+        )?;
     }
 
     Ok(())
 }
+
+/// Prints the headers and/or the non-empty sections as a single JSON object.
+fn print_json(dol: &Dol, headers: bool, sections: bool, out: &mut dyn Write) -> anyhow::Result<()> {
+    let mut fields = Vec::new();
+
+    if headers {
+        fields.push(format!(
+            "\"headers\":{{\"entrypoint\":{},\"bss_address\":{},\"bss_size\":{}}}",
+            dol.entrypoint(),
+            dol.bss_address(),
+            dol.bss_size()
+        ));
+    }
+
+    if sections {
+        let sections: Vec<String> = dol
+            .sections()
+            .enumerate()
+            .filter(|(_, section)| !section.empty())
+            .map(|(i, section)| {
+                format!(
+                    "{{\"index\":{},\"file_offset\":{},\"load_address\":{},\"size\":{}}}",
+                    i, section.file_offset, section.load_offset, section.size
+                )
+            })
+            .collect();
+        fields.push(format!("\"sections\":[{}]", sections.join(",")));
+    }
+
+    writeln!(out, "{{{}}}", fields.join(","))?;
+
+    Ok(())
+}
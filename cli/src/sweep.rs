@@ -0,0 +1,115 @@
+use std::io::Write;
+
+use anyhow::Context;
+use decomp::{dataflow::value::ConstSection, detect_fn_boundaries, symbols::SymbolMap};
+use dol::{Dol, MemoryImage, SectionInfo};
+use ppc32::format::AsmFormatOptions;
+
+use crate::{
+    args::{DisassemblyLanguage, SweepTarget},
+    disasm::{self, disasm_function},
+};
+
+#[derive(Default)]
+struct SweepStats {
+    functions: u32,
+    failed: u32,
+    skipped_bytes: u32,
+}
+
+/// The state shared by all sections of a sweep.
+struct Sweeper<'a> {
+    image: MemoryImage<'a>,
+    lang: DisassemblyLanguage,
+    asm_format: AsmFormatOptions,
+    const_sections: Vec<ConstSection<'a>>,
+    symbols: &'a SymbolMap,
+    stats: SweepStats,
+    out: &'a mut dyn Write,
+}
+
+/// Walks the given sections from start to end, splitting them into functions using the boundary detection heuristics
+/// and disassembling each one of them.
+pub fn sweep(
+    dol: &Dol,
+    target: SweepTarget,
+    lang: DisassemblyLanguage,
+    const_sections: &[usize],
+    asm_format: AsmFormatOptions,
+    symbols: &SymbolMap,
+    out: &mut dyn Write,
+) -> anyhow::Result<()> {
+    let sections: Vec<(usize, SectionInfo)> = match target {
+        SweepTarget::Text => dol.text_sections().collect(),
+        SweepTarget::Section(index) => vec![(index, dol.section(index))],
+    };
+
+    let mut sweeper = Sweeper {
+        image: dol.memory_image(),
+        const_sections: disasm::const_sections(dol, const_sections)?,
+        lang,
+        asm_format,
+        symbols,
+        stats: SweepStats::default(),
+        out,
+    };
+    sections
+        .iter()
+        .filter(|(_, section)| !section.empty())
+        .try_for_each(|(index, section)| sweeper.sweep_section(*index, section))?;
+
+    let stats = &sweeper.stats;
+    writeln!(
+        sweeper.out,
+        "Swept {} functions ({} failed), skipped {:#x} bytes that could not be decoded",
+        stats.functions, stats.failed, stats.skipped_bytes
+    )?;
+
+    Ok(())
+}
+
+impl Sweeper<'_> {
+    fn sweep_section(&mut self, index: usize, section: &SectionInfo) -> anyhow::Result<()> {
+        let code = self
+            .image
+            .read(section.load_offset, section.size)
+            .with_context(|| format!("section #{index} is not fully contained in the file"))?;
+
+        let mut offset = 0;
+        while offset < code.len() {
+            let fn_addr = section.load_offset + offset as u32;
+            let rest = &code[offset..];
+
+            let len = detect_fn_boundaries(rest, fn_addr, 0).len();
+            if len == 0 {
+                // Not decodable as code (padding, data or an unimplemented instruction), so try the next word.
+                self.stats.skipped_bytes += 4;
+                offset += 4;
+                continue;
+            }
+
+            writeln!(
+                self.out,
+                "// Function {fn_addr:#x} (section #{index}, size {len:#x})"
+            )?;
+            if let Err(err) = disasm_function(
+                &rest[..len],
+                fn_addr,
+                &self.const_sections,
+                self.lang,
+                self.asm_format,
+                self.symbols,
+                self.out,
+            ) {
+                tracing::error!("failed to disassemble function {fn_addr:#x}: {err:#}");
+                self.stats.failed += 1;
+            }
+            writeln!(self.out)?;
+
+            self.stats.functions += 1;
+            offset += len;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    io::Write,
+};
+
+use decomp::{dataflow::Instructions, detect_fn_boundaries, find_calls, symbols::SymbolMap};
+use dol::Dol;
+use ppc32::{Decoder, Instruction, decoder::Address};
+
+/// A function discovered by [`trace`].
+struct TracedFunction {
+    size: u32,
+    /// Functions called from this one, including tail calls.
+    calls: BTreeSet<u32>,
+    /// How many calls go through a function pointer (`bctrl`/`blrl`), whose targets can't be traced.
+    indirect_calls: usize,
+}
+
+#[derive(Default)]
+struct CallGraph {
+    functions: BTreeMap<u32, TracedFunction>,
+    /// Called addresses that could not be traced, and why.
+    failed: BTreeMap<u32, String>,
+}
+
+/// Discovers all functions reachable from `start` by following calls, and prints the call graph,
+/// either as a list or as a Graphviz digraph if `dot` is set.
+/// With a `max_depth`, calls are only followed up to that many levels from `start`.
+pub fn trace(
+    dol: &Dol,
+    start: u32,
+    max_depth: Option<u32>,
+    symbols: &SymbolMap,
+    dot: bool,
+    out: &mut dyn Write,
+) -> anyhow::Result<()> {
+    let graph = discover(dol, start, max_depth);
+    if dot {
+        return print_dot(&graph, symbols, out);
+    }
+
+    for (&addr, function) in &graph.functions {
+        writeln!(
+            out,
+            "Function {} (size {:#x})",
+            symbols.describe(addr),
+            function.size
+        )?;
+        for &call in &function.calls {
+            writeln!(out, "  calls {}", symbols.describe(call))?;
+        }
+        if function.indirect_calls > 0 {
+            writeln!(out, "  makes {} indirect calls", function.indirect_calls)?;
+        }
+    }
+    for (&addr, reason) in &graph.failed {
+        writeln!(
+            out,
+            "Function {} could not be traced: {reason}",
+            symbols.describe(addr)
+        )?;
+    }
+    writeln!(
+        out,
+        "Traced {} functions ({} failed)",
+        graph.functions.len(),
+        graph.failed.len()
+    )?;
+
+    Ok(())
+}
+
+/// One node per function, labeled with its symbol name or address, and one edge per call.
+/// Functions that could not be traced are dashed.
+fn print_dot(graph: &CallGraph, symbols: &SymbolMap, out: &mut dyn Write) -> anyhow::Result<()> {
+    let label = |addr: u32| match symbols.get(addr) {
+        Some(name) => name.replace('\\', "\\\\").replace('"', "\\\""),
+        None => format!("{addr:#x}"),
+    };
+
+    writeln!(out, "digraph calls {{")?;
+    for &addr in graph.functions.keys() {
+        writeln!(out, "    fn_{addr:08x} [label=\"{}\"];", label(addr))?;
+    }
+    for &addr in graph.failed.keys() {
+        writeln!(
+            out,
+            "    fn_{addr:08x} [label=\"{}\", style=dashed];",
+            label(addr)
+        )?;
+    }
+    for (&addr, function) in &graph.functions {
+        for &call in &function.calls {
+            writeln!(out, "    fn_{addr:08x} -> fn_{call:08x};")?;
+        }
+    }
+    writeln!(out, "}}")?;
+
+    Ok(())
+}
+
+fn discover(dol: &Dol, start: u32, max_depth: Option<u32>) -> CallGraph {
+    let mut graph = CallGraph::default();
+    let mut visited = BTreeSet::from([start]);
+    // Breadth-first, so the depth of a function is the length of the shortest call chain to it.
+    let mut queue = VecDeque::from([(start, 0)]);
+
+    while let Some((fn_addr, depth)) = queue.pop_front() {
+        let function = match trace_function(dol, fn_addr) {
+            Ok(function) => function,
+            Err(reason) => {
+                graph.failed.insert(fn_addr, reason);
+                continue;
+            }
+        };
+
+        // The calls are still part of the graph, their targets just aren't traced.
+        if max_depth.is_none_or(|max| depth < max) {
+            for &call in &function.calls {
+                if visited.insert(call) {
+                    queue.push_back((call, depth + 1));
+                }
+            }
+        }
+        graph.functions.insert(fn_addr, function);
+    }
+
+    graph
+}
+
+/// Finds the end of the function at `fn_addr` and the functions it calls.
+fn trace_function(dol: &Dol, fn_addr: u32) -> Result<TracedFunction, String> {
+    let code = dol
+        .slice_from_load_addr(fn_addr)
+        .ok_or_else(|| "address is not in any section".to_owned())?;
+
+    let code = detect_fn_boundaries(code, fn_addr, 0);
+    if code.is_empty() {
+        return Err("not decodable as code".to_owned());
+    }
+
+    let instructions: Instructions = Decoder::new(code)
+        .instructions()
+        .map_while(Result::ok)
+        .map(|(offset, inst)| (Address(fn_addr + offset), inst))
+        .collect();
+    let indirect_calls = instructions
+        .iter()
+        .filter(|(_, ins)| {
+            matches!(
+                ins,
+                Instruction::Bclr { link: true, .. } | Instruction::Bcctr { link: true, .. }
+            )
+        })
+        .count();
+
+    Ok(TracedFunction {
+        size: code.len() as u32,
+        calls: find_calls(&instructions, fn_addr),
+        indirect_calls,
+    })
+}
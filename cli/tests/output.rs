@@ -0,0 +1,696 @@
+use std::{fs, path::PathBuf, process::Command};
+
+use dol::DolBuilder;
+
+const FN_ADDRESS: u32 = 0x80003100;
+
+/// Builds a .dol with a single text section containing `code`, which is also the entrypoint.
+fn dol_with_code(code: &[u8]) -> Vec<u8> {
+    DolBuilder::new()
+        .text_section(FN_ADDRESS, code.to_vec())
+        .entrypoint(FN_ADDRESS)
+        .build()
+        .unwrap()
+        .as_bytes()
+        .to_vec()
+}
+
+fn tmp_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name)
+}
+
+fn run_into_file(name: &str, dol: &[u8], args: &[&str]) -> String {
+    let dir = tmp_path("");
+    let input = dir.join(format!("{name}.dol"));
+    let output = dir.join(format!("{name}.out"));
+    fs::write(&input, dol).unwrap();
+    let _ = fs::remove_file(&output);
+
+    let result = Command::new(env!("CARGO_BIN_EXE_cli"))
+        .arg("-i")
+        .arg(&input)
+        .arg("--output")
+        .arg(&output)
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        result.status.success(),
+        "{}",
+        String::from_utf8_lossy(&result.stderr)
+    );
+    assert!(
+        result.stdout.is_empty(),
+        "nothing should be printed to stdout with --output"
+    );
+
+    fs::read_to_string(&output).unwrap()
+}
+
+/// Splits the lines of the `both` mode into the address or label that starts the assembly column, and the C column.
+fn both_columns(output: &str) -> Vec<(&str, &str)> {
+    output
+        .lines()
+        .map(|line| {
+            let (asm, c) = line
+                .split_once(" |")
+                .unwrap_or_else(|| panic!("no separator in {line:?}"));
+            (
+                asm.split_whitespace().next().unwrap_or(""),
+                c.strip_prefix(' ').unwrap_or(c),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn asm_goes_to_output_file() {
+    let dol = dol_with_code(&[
+        0x38, 0x60, 0x00, 0x05, // li      r3,5
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+    let output = run_into_file("asm", &dol, &["--entrypoint", "--disasm", "asm"]);
+    assert_eq!(
+        output,
+        "\
+80003100: 38 60 00 05  Addi { dest: r3, source: r0, imm: Immediate(5) }
+80003104: 4e 80 00 20  Bclr { bo: BranchAlways, hint: false, bi: 0, link: false }
+"
+    );
+}
+
+#[test]
+fn c_goes_to_output_file() {
+    let dol = dol_with_code(&[
+        0x38, 0x60, 0x00, 0x05, // li      r3,5
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+    let output = run_into_file("c", &dol, &["--entrypoint", "--disasm", "c"]);
+    assert!(output.starts_with("u32 0x80003100() {"), "{output}");
+}
+
+#[test]
+fn ir_annotates_written_registers() {
+    let dol = dol_with_code(&[
+        0x38, 0x64, 0x00, 0x08, // addi    r3,r4,8
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+    let output = run_into_file("ir", &dol, &["--entrypoint", "--disasm", "ir"]);
+    let lines: Vec<&str> = output.lines().collect();
+
+    assert_eq!(lines.len(), 2, "{output}");
+    assert!(lines[0].starts_with("80003100: Addi"), "{output}");
+    assert!(lines[0].ends_with(" => r3 = (r4 + 0x8)"), "{output}");
+    assert!(!lines[1].contains("=>"), "{output}");
+}
+
+#[test]
+fn both_prints_asm_next_to_c() {
+    let dol = dol_with_code(&[
+        0x38, 0x63, 0x00, 0x02, // addi    r3,r3,2
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+    let output = run_into_file("both", &dol, &["--entrypoint", "--disasm", "both"]);
+    let separators: Vec<_> = output.lines().map(|line| line.find(" |")).collect();
+
+    assert!(
+        separators.iter().all(|&sep| sep == separators[0]),
+        "{output}"
+    );
+    assert!(
+        output.contains("80003100: 38 63 00 02  Addi { dest: r3, source: r3, imm: Immediate(2) }"),
+        "{output}"
+    );
+    assert_eq!(
+        both_columns(&output),
+        [
+            ("", "u32 0x80003100(u32 v20) {"),
+            ("80003100:", ""),
+            ("80003104:", "    return v20 + 2;"),
+            ("", "}"),
+        ]
+    );
+}
+
+#[test]
+fn both_aligns_statements_with_their_instructions() {
+    let dol = dol_with_code(&[
+        0x2c, 0x03, 0x00, 0x00, // cmpwi   r3,0
+        0x41, 0x82, 0x00, 0x08, // beq     8000310c
+        0x38, 0x60, 0x00, 0x01, // li      r3,1
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+    let output = run_into_file("both_if", &dol, &["--entrypoint", "--disasm", "both"]);
+
+    assert_eq!(
+        both_columns(&output),
+        [
+            ("", "void 0x80003100(u32 v20) {"),
+            ("80003100:", ""),
+            ("80003104:", "    if (v20 != 0) {"),
+            ("80003108:", "        v20 = 1;"),
+            ("", "    }"),
+            ("loc_8000310c:", ""),
+            ("8000310c:", "    return;"),
+            ("", "}"),
+        ]
+    );
+}
+
+#[test]
+fn summary_lists_prototype_and_calls() {
+    let dol = dol_with_code(&[
+        0x94, 0x21, 0xff, 0xe0, // stwu    r1,-32(r1)
+        0x7c, 0x08, 0x02, 0xa6, // mflr    r0
+        0x90, 0x01, 0x00, 0x24, // stw     r0,36(r1)
+        0x90, 0x83, 0x00, 0x08, // stw     r4,8(r3)
+        0x48, 0x00, 0x01, 0x01, // bl      80003210
+        0x80, 0x01, 0x00, 0x24, // lwz     r0,36(r1)
+        0x7c, 0x08, 0x03, 0xa6, // mtlr    r0
+        0x38, 0x21, 0x00, 0x20, // addi    r1,r1,32
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+    let output = run_into_file("summary", &dol, &["--entrypoint", "--disasm", "summary"]);
+    assert_eq!(
+        output,
+        "Function 0x80003100\n\
+         \x20 parameters: 2 (r3, r4)\n\
+         \x20 returns: u32\n\
+         \x20 stack frame: 0x20 bytes\n\
+         \x20 calls 0x80003210\n"
+    );
+}
+
+#[test]
+fn asm_labels_branch_targets() {
+    let dol = dol_with_code(&[
+        0x38, 0x80, 0x00, 0x00, // li      r4,0
+        0x38, 0xa0, 0x00, 0x0a, // li      r5,10
+        0x7c, 0xa9, 0x03, 0xa6, // mtctr   r5
+        0x38, 0x84, 0x00, 0x03, // addi    r4,r4,3
+        0x42, 0x00, 0xff, 0xfc, // bdnz    8000310c
+        0x7c, 0x83, 0x23, 0x78, // mr      r3,r4
+        0x48, 0x00, 0x01, 0x00, // b       80003218
+    ]);
+    let output = run_into_file("labels", &dol, &["--entrypoint", "--disasm", "asm"]);
+    let lines: Vec<&str> = output.lines().collect();
+
+    assert_eq!(lines[3], "loc_8000310c:");
+    assert!(
+        lines[4].starts_with("8000310c: 38 84 00 03  Addi"),
+        "{output}"
+    );
+    assert!(lines[5].ends_with(" -> loc_8000310c"), "{output}");
+    // Outside of the function, so there is no label
+    assert!(lines[7].ends_with(" -> 0x80003218"), "{output}");
+}
+
+#[test]
+fn symbols_name_branch_and_call_targets() {
+    let dol = dol_with_code(&[
+        0x94, 0x21, 0xff, 0xf0, // stwu    r1,-16(r1)
+        0x7c, 0x08, 0x02, 0xa6, // mflr    r0
+        0x90, 0x01, 0x00, 0x14, // stw     r0,20(r1)
+        0x38, 0x60, 0x00, 0x01, // li      r3,1
+        0x48, 0x00, 0x01, 0x01, // bl      80003210
+        0x80, 0x01, 0x00, 0x14, // lwz     r0,20(r1)
+        0x7c, 0x08, 0x03, 0xa6, // mtlr    r0
+        0x38, 0x21, 0x00, 0x10, // addi    r1,r1,16
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+    let symbols = tmp_path("symbols.txt");
+    fs::write(&symbols, "0x80003210 OSReport\n").unwrap();
+    let symbols = symbols.to_str().unwrap();
+
+    let asm = run_into_file(
+        "symbols_asm",
+        &dol,
+        &["--entrypoint", "--disasm", "asm", "--symbols", symbols],
+    );
+    assert!(
+        asm.lines().nth(4).unwrap().ends_with(" -> OSReport"),
+        "{asm}"
+    );
+
+    let c = run_into_file(
+        "symbols_c",
+        &dol,
+        &["--entrypoint", "--disasm", "c", "--symbols", symbols],
+    );
+    assert!(c.contains(" = OSReport(1);"), "{c}");
+}
+
+#[test]
+fn headers_and_sections_as_json() {
+    let dol = DolBuilder::new()
+        .text_section(
+            FN_ADDRESS,
+            vec![
+                0x4e, 0x80, 0x00, 0x20, // blr
+            ],
+        )
+        .entrypoint(FN_ADDRESS)
+        .bss(0x80400000, 0x100)
+        .build()
+        .unwrap();
+
+    let output = run_into_file(
+        "json",
+        dol.as_bytes(),
+        &["--headers", "--sections", "--format", "json"],
+    );
+    assert_eq!(
+        output,
+        "{\"headers\":{\"entrypoint\":2147496192,\"bss_address\":2151677952,\"bss_size\":256},\
+         \"sections\":[{\"index\":0,\"file_offset\":256,\"load_address\":2147496192,\"size\":4}]}\n"
+    );
+}
+
+#[test]
+fn unmapped_address_is_an_error() {
+    let dir = tmp_path("");
+    let input = dir.join("unmapped.dol");
+    fs::write(&input, dol_with_code(&[0x4e, 0x80, 0x00, 0x20])).unwrap();
+
+    let result = Command::new(env!("CARGO_BIN_EXE_cli"))
+        .arg("-i")
+        .arg(&input)
+        .args(["-x", "0x90000000:", "--disasm", "asm"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert!(!result.status.success());
+    assert!(
+        stderr.contains("address 0x90000000 is not in any section"),
+        "{stderr}"
+    );
+    assert!(!stderr.contains("panicked"), "{stderr}");
+}
+
+#[test]
+fn trace_follows_calls() {
+    let code = [
+        0x48000011u32, // 0x80003100: bl 0x80003110
+        0x48000011,    // 0x80003104: bl 0x80003114
+        0x4e800020,    // 0x80003108: blr
+        0x60000000,    // 0x8000310c: nop
+        0x4e800020,    // 0x80003110: blr
+        0x38600001,    // 0x80003114: li r3, 1
+        0x4e800020,    // 0x80003118: blr
+    ];
+    let code: Vec<u8> = code.iter().flat_map(|word| word.to_be_bytes()).collect();
+    let output = run_into_file("trace", &dol_with_code(&code), &["--entrypoint", "--trace"]);
+    assert_eq!(
+        output,
+        "Function 0x80003100 (size 0xc)\n\
+         \x20 calls 0x80003110\n\
+         \x20 calls 0x80003114\n\
+         Function 0x80003110 (size 0x4)\n\
+         Function 0x80003114 (size 0x8)\n\
+         Traced 3 functions (0 failed)\n"
+    );
+}
+
+#[test]
+fn trace_terminates_on_recursion() {
+    let code = [
+        0x48000009u32, // 0x80003100: bl 0x80003108
+        0x4e800020,    // 0x80003104: blr
+        0x4bfffff9,    // 0x80003108: bl 0x80003100
+        0x4bfffffd,    // 0x8000310c: bl 0x80003108
+        0x4e800020,    // 0x80003110: blr
+    ];
+    let code: Vec<u8> = code.iter().flat_map(|word| word.to_be_bytes()).collect();
+    let output = run_into_file(
+        "trace_recursion",
+        &dol_with_code(&code),
+        &["--entrypoint", "--trace"],
+    );
+    assert_eq!(
+        output,
+        "Function 0x80003100 (size 0x8)\n\
+         \x20 calls 0x80003108\n\
+         Function 0x80003108 (size 0xc)\n\
+         \x20 calls 0x80003100\n\
+         \x20 calls 0x80003108\n\
+         Traced 2 functions (0 failed)\n"
+    );
+}
+
+#[test]
+fn trace_as_dot() {
+    let code = [
+        0x48000011u32, // 0x80003100: bl 0x80003110
+        0x48000011,    // 0x80003104: bl 0x80003114
+        0x4e800020,    // 0x80003108: blr
+        0x60000000,    // 0x8000310c: nop
+        0x4e800020,    // 0x80003110: blr
+        0x4bffffe8,    // 0x80003114: b 0x800030fc (tail call to an unmapped address)
+    ];
+    let code: Vec<u8> = code.iter().flat_map(|word| word.to_be_bytes()).collect();
+    let symbols = tmp_path("trace_dot.sym");
+    fs::write(&symbols, "0x80003100 main\n0x80003110 \"quoted\"\n").unwrap();
+    let output = run_into_file(
+        "trace_dot",
+        &dol_with_code(&code),
+        &[
+            "--entrypoint",
+            "--trace",
+            "--format",
+            "dot",
+            "--symbols",
+            symbols.to_str().unwrap(),
+        ],
+    );
+    assert_eq!(
+        output,
+        "digraph calls {\n\
+         \x20   fn_80003100 [label=\"main\"];\n\
+         \x20   fn_80003110 [label=\"\\\"quoted\\\"\"];\n\
+         \x20   fn_80003114 [label=\"0x80003114\"];\n\
+         \x20   fn_800030fc [label=\"0x800030fc\", style=dashed];\n\
+         \x20   fn_80003100 -> fn_80003110;\n\
+         \x20   fn_80003100 -> fn_80003114;\n\
+         \x20   fn_80003114 -> fn_800030fc;\n\
+         }\n"
+    );
+}
+
+#[test]
+fn trace_depth_limit() {
+    let code = [
+        0x48000009u32, // 0x80003100: bl 0x80003108
+        0x4e800020,    // 0x80003104: blr
+        0x48000009,    // 0x80003108: bl 0x80003110
+        0x4e800020,    // 0x8000310c: blr
+        0x4e800020,    // 0x80003110: blr
+    ];
+    let code: Vec<u8> = code.iter().flat_map(|word| word.to_be_bytes()).collect();
+    let output = run_into_file(
+        "trace_depth",
+        &dol_with_code(&code),
+        &["--entrypoint", "--trace", "--depth", "1"],
+    );
+    assert_eq!(
+        output,
+        "Function 0x80003100 (size 0x8)\n\
+         \x20 calls 0x80003108\n\
+         Function 0x80003108 (size 0x8)\n\
+         \x20 calls 0x80003110\n\
+         Traced 2 functions (0 failed)\n"
+    );
+}
+
+#[test]
+fn trace_counts_indirect_calls() {
+    let code = [
+        0x48000011u32, // 0x80003100: bl 0x80003110
+        0x4e800421,    // 0x80003104: bctrl
+        0x4e800421,    // 0x80003108: bctrl
+        0x4e800020,    // 0x8000310c: blr
+        0x4e800420,    // 0x80003110: bctr
+    ];
+    let code: Vec<u8> = code.iter().flat_map(|word| word.to_be_bytes()).collect();
+    let output = run_into_file(
+        "trace_indirect",
+        &dol_with_code(&code),
+        &["--entrypoint", "--trace"],
+    );
+    // The `bctr` is a jump (e.g. a tail call through a function pointer), not a call.
+    assert_eq!(
+        output,
+        "Function 0x80003100 (size 0x10)\n\
+         \x20 calls 0x80003110\n\
+         \x20 makes 2 indirect calls\n\
+         Function 0x80003110 (size 0x4)\n\
+         Traced 2 functions (0 failed)\n"
+    );
+}
+
+/// Runs with the given arguments and returns stderr, asserting that it failed.
+fn run_failing(name: &str, dol: &[u8], args: &[&str]) -> String {
+    let input = tmp_path(&format!("{name}.dol"));
+    fs::write(&input, dol).unwrap();
+
+    let result = Command::new(env!("CARGO_BIN_EXE_cli"))
+        .arg("-i")
+        .arg(&input)
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(!result.status.success());
+    String::from_utf8_lossy(&result.stderr).into_owned()
+}
+
+#[test]
+fn addr_range_in_hex_decimal_and_by_symbol() {
+    let dol = dol_with_code(&[
+        0x38, 0x60, 0x00, 0x05, // li      r3,5
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+    let expected = "80003100: 38 60 00 05  Addi { dest: r3, source: r0, imm: Immediate(5) }\n";
+
+    let hex = run_into_file("x_hex", &dol, &["-x", "0x80003100:+4", "--disasm", "asm"]);
+    assert_eq!(hex, expected);
+
+    let decimal = run_into_file(
+        "x_decimal",
+        &dol,
+        &["-x", "2147496192:2147496196", "--disasm", "asm"],
+    );
+    assert_eq!(decimal, expected);
+
+    let symbols = tmp_path("x_symbol.sym");
+    fs::write(&symbols, "0x80003100 main\n").unwrap();
+    let symbol = run_into_file(
+        "x_symbol",
+        &dol,
+        &[
+            "-x",
+            "main:+0x4",
+            "--disasm",
+            "asm",
+            "--symbols",
+            symbols.to_str().unwrap(),
+        ],
+    );
+    assert_eq!(symbol, expected);
+}
+
+#[test]
+fn malformed_addr_range_is_an_error() {
+    let dol = dol_with_code(&[0x4e, 0x80, 0x00, 0x20]);
+
+    let stderr = run_failing(
+        "x_malformed",
+        &dol,
+        &["-x", "0x8000zz00:", "--disasm", "asm"],
+    );
+    assert!(stderr.contains("failed to parse start address"), "{stderr}");
+
+    let stderr = run_failing("x_no_colon", &dol, &["-x", "0x80003100", "--disasm", "asm"]);
+    assert!(stderr.contains("invalid address range format"), "{stderr}");
+
+    let stderr = run_failing(
+        "x_unknown_symbol",
+        &dol,
+        &["-x", "main:", "--disasm", "asm"],
+    );
+    assert!(
+        stderr.contains("`main` is neither a number nor a known symbol"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn addr_range_with_instruction_count() {
+    let dol = dol_with_code(&[
+        0x38, 0x60, 0x00, 0x05, // li      r3,5
+        0x38, 0x60, 0x00, 0x06, // li      r3,6
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    let output = run_into_file("x_count", &dol, &["-x", "0x80003100:n2", "--disasm", "asm"]);
+    assert_eq!(
+        output,
+        "\
+80003100: 38 60 00 05  Addi { dest: r3, source: r0, imm: Immediate(5) }
+80003104: 38 60 00 06  Addi { dest: r3, source: r0, imm: Immediate(6) }
+"
+    );
+
+    let output = run_into_file(
+        "x_count_one",
+        &dol,
+        &["-x", "0x80003104:n1", "--disasm", "asm"],
+    );
+    assert_eq!(
+        output,
+        "80003104: 38 60 00 06  Addi { dest: r3, source: r0, imm: Immediate(6) }\n"
+    );
+
+    let stderr = run_failing(
+        "x_count_too_many",
+        &dol,
+        &["-x", "0x80003100:n4", "--disasm", "asm"],
+    );
+    assert!(
+        stderr.contains("section ends before 4 instructions"),
+        "{stderr}"
+    );
+
+    let stderr = run_failing(
+        "x_count_malformed",
+        &dol,
+        &["-x", "0x80003100:n2x", "--disasm", "asm"],
+    );
+    assert!(
+        stderr.contains("failed to parse instruction count"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn asm_immediates_in_hex_and_unsigned() {
+    let dol = dol_with_code(&[
+        0x94, 0x21, 0xff, 0xe0, // stwu    r1,-32(r1)
+        0x54, 0x64, 0x80, 0x1e, // rlwinm  r4,r3,16,0,15
+    ]);
+
+    let hex = run_into_file(
+        "radix_hex",
+        &dol,
+        &["-x", "0x80003100:n2", "--disasm", "asm", "--radix", "hex"],
+    );
+    assert_eq!(
+        hex,
+        "\
+80003100: 94 21 ff e0  Stwu { source: r1, dest: r1, imm: Immediate(-0x20) }
+80003104: 54 64 80 1e  Rlwinm { source: r3, dest: r4, rot_bits: Immediate(16), mask_start: Immediate(0), mask_end: Immediate(15), rc: false }
+"
+    );
+
+    let unsigned = run_into_file(
+        "radix_hex_unsigned",
+        &dol,
+        &[
+            "-x",
+            "0x80003100:n1",
+            "--disasm",
+            "asm",
+            "--radix",
+            "hex",
+            "--unsigned",
+        ],
+    );
+    assert_eq!(
+        unsigned,
+        "80003100: 94 21 ff e0  Stwu { source: r1, dest: r1, imm: Immediate(0xffe0) }\n"
+    );
+
+    let stderr = run_failing(
+        "radix_invalid",
+        &dol,
+        &["-x", "0x80003100:n1", "--disasm", "asm", "--radix", "oct"],
+    );
+    assert!(stderr.contains("invalid radix: oct"), "{stderr}");
+}
+
+#[test]
+fn sweep_section_at_the_top_of_the_address_space() {
+    let code = [
+        0x38, 0x60, 0x00, 0x01, // li      r3,1
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]
+    .repeat(2);
+    let dol = DolBuilder::new()
+        .text_section(0xFFFFFFF0, code)
+        .build()
+        .unwrap();
+    let output = run_into_file(
+        "sweep_top",
+        dol.as_bytes(),
+        &["--sweep", "text", "--disasm", "summary"],
+    );
+    assert!(output.contains("// Function 0xfffffff8"), "{output}");
+    assert!(
+        output.ends_with(
+            "Swept 2 functions (0 failed), skipped 0x0 bytes that could not be decoded\n"
+        ),
+        "{output}"
+    );
+}
+
+#[test]
+fn sweep_decompiles_unmodeled_instructions() {
+    let dol = dol_with_code(&[
+        0x2c, 0x03, 0x00, 0x00, // cmpwi   r3,0
+        0x4c, 0x42, 0x11, 0x82, // crxor   eq,eq,eq
+        0x41, 0x82, 0x00, 0x08, // beq     0x80003110
+        0x38, 0x60, 0x00, 0x01, // li      r3,1
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+    let output = run_into_file(
+        "sweep_unmodeled",
+        &dol,
+        &["--sweep", "text", "--disasm", "c"],
+    );
+    assert!(output.contains("= __crxor("), "{output}");
+    assert!(output.contains("Swept 1 functions (0 failed)"), "{output}");
+}
+
+#[test]
+fn loads_from_const_sections_decide_branches() {
+    let code = vec![
+        0x3c, 0x60, 0x80, 0x00, // lis     r3,0x8000
+        0x80, 0x83, 0x40, 0x00, // lwz     r4,0x4000(r3)
+        0x2c, 0x04, 0x00, 0x00, // cmpwi   r4,0
+        0x41, 0x82, 0x00, 0x08, // beq     +0x8
+        0x48, 0x00, 0x00, 0xf1, // bl      0x80003200
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ];
+    let c = |flag: u8, args: &[&str]| {
+        let dol = DolBuilder::new()
+            .text_section(FN_ADDRESS, code.clone())
+            .data_section(0x80004000, vec![0, 0, 0, flag])
+            .entrypoint(FN_ADDRESS)
+            .build()
+            .unwrap();
+        let args = [&["--entrypoint", "--disasm", "c"], args].concat();
+        run_into_file(
+            &format!("const_flag_{flag}_{}", args.len()),
+            dol.as_bytes(),
+            &args,
+        )
+    };
+
+    let unset = c(0, &["--const-sections", "7"]);
+    assert!(!unset.contains("0x80003200"), "{unset}");
+    let set = c(1, &["--const-sections", "7"]);
+    assert!(set.contains("0x80003200"), "{set}");
+    // Data sections may be written to, so they are only used when asked to.
+    let writable = c(0, &[]);
+    assert!(writable.contains("0x80003200"), "{writable}");
+}
+
+#[test]
+fn const_sections_must_be_data_sections() {
+    let dol = dol_with_code(&[
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+    let input = tmp_path("").join("const_text.dol");
+    fs::write(&input, dol).unwrap();
+
+    let result = Command::new(env!("CARGO_BIN_EXE_cli"))
+        .arg("-i")
+        .arg(&input)
+        .args(["--entrypoint", "--disasm", "c", "--const-sections", "0"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert!(!result.status.success());
+    assert!(
+        stderr.contains("section #0 is not a data section"),
+        "{stderr}"
+    );
+}
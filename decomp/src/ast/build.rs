@@ -1,10 +1,15 @@
-use std::{collections::HashSet, convert::Infallible, ops::ControlFlow};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    convert::Infallible,
+    iter,
+    ops::ControlFlow,
+};
 
 use ppc32::{
     Instruction,
     instruction::{
-        BranchOptions, Crb, Crf, Gpr, Register, Spr, XerRegister, compute_branch_target,
-        crb_from_index,
+        BranchOptions, Cr0Result, Crb, Crf, Fpr, Gpr, Immediate, MacroSpr, Register,
+        RegisterVisitor, Spr, XerRegister, compute_branch_target, crb_from_index,
     },
 };
 
@@ -12,16 +17,17 @@ use crate::{
     ast::{
         Ast,
         expr::{BinaryExpr, BinaryOp, Expr, ExprKind, FnCallTarget, UnaryExpr, UnaryOp},
-        item::{Function, Item, ItemKind, Parameter},
-        stmt::{Stmt, StmtKind, VarId, VariableVisibility},
+        item::{Function, Item, ItemKind, Local, Parameter},
+        stmt::{SourceSpan, Stmt, StmtKind, VarId, VariableVisibility},
         ty::{Ty, TyKind},
     },
     dataflow::{
         InstId, InstructionsDeref,
-        core::{Dataflow, Results, Successors, for_each_transitive_successor},
+        cfg::inst_at,
+        core::{Dataflow, Results, SuccessorTarget, Successors, for_each_transitive_successor},
         loops::{LoopId, LoopMap},
-        ssa::{BlockState, DefUseMap, Generation, LocalGenerationAnalysis},
-        variables::{Variables, cr_bits_variables, xer_variables},
+        ssa::{BlockState, DefUseMap, Generation, LocalGenerationAnalysis, decided_branch},
+        variables::{Variables, cr_bits_variables, opaque_operands, xer_variables},
     },
     ti_utils::ti_iter,
 };
@@ -35,6 +41,19 @@ pub struct AstBuildParams<'a, 'b> {
     pub variables: &'a Variables,
     pub succs: &'a Successors<LocalGenerationAnalysis<'b>>,
     pub loops: &'a LoopMap,
+    /// Whether the value analysis found a pointer in r3 at every return.
+    pub returns_pointer: bool,
+    /// The instructions that save and restore LR, see
+    /// [`ValueAnalysis::lr_save_restore`](crate::dataflow::value::ValueAnalysis::lr_save_restore).
+    pub lr_save_restore: &'a BTreeSet<InstId>,
+    /// The argument registers of every call, see [`CallArguments`](crate::dataflow::arguments::CallArguments).
+    pub arguments: &'a BTreeMap<InstId, Vec<Gpr>>,
+    /// The `bdnz` instructions that close a loop that runs a known, nonzero number of times, see
+    /// [`ValueAnalysis::ctr_loop_count`](crate::dataflow::value::ValueAnalysis::ctr_loop_count).
+    pub counted_loops: &'a BTreeSet<InstId>,
+    /// The GPR writes that store a pointer, see
+    /// [`ValueAnalysis::pointer_writes`](crate::dataflow::value::ValueAnalysis::pointer_writes).
+    pub pointer_writes: &'a BTreeSet<(InstId, Gpr)>,
 }
 
 struct BuildPathResult {
@@ -77,6 +96,7 @@ fn build_crf_assignments(
                         }),
                     },
                 },
+                source: None,
             });
         }
     }
@@ -130,6 +150,7 @@ fn build_xer_assignments(
                             }),
                         },
                     },
+                    source: None,
                 })
             }
             XerRegister::So => {
@@ -165,21 +186,252 @@ fn append_phi_merge_assignments(
             {
                 let next_vis = variables.get_vis(next_var);
 
+                // A hidden variable is never assigned, e.g. a CR bit that a record form sets but nothing reads, so
+                // copying it would read an uninitialized variable.
                 if next_vis == VariableVisibility::Visible
                     && let Some(cur_var) =
                         variables.optional_id_by_reg(cur_reg, cur_state.generation)
+                    && variables.get_vis(cur_var) == VariableVisibility::Visible
                 {
                     stmts.push(Stmt {
                         kind: StmtKind::Assign {
                             dest: Expr::var(next_var),
                             value: Expr::var(cur_var),
                         },
+                        source: None,
                     });
                 }
             }
         });
 }
 
+/// If going to `target` ends the current iteration of `current_loop`, the `continue` (to the loop start) or `break`
+/// (to the instruction after the loop) that does it, after the phi assignments for `target`.
+fn loop_jump(
+    target: InstId,
+    current_loop: Option<LoopId>,
+    loops: &LoopMap,
+    state: &BlockState,
+    local_generations: &Results<LocalGenerationAnalysis<'_>>,
+    variables: &Variables,
+) -> Option<Vec<Stmt>> {
+    let current_loop = loops.get(current_loop?);
+    let kind = if target == current_loop.start {
+        StmtKind::Continue
+    } else if Some(target) == current_loop.common_merge_inst {
+        StmtKind::Break
+    } else {
+        return None;
+    };
+
+    let mut stmts = Vec::with_capacity(2);
+    append_phi_merge_assignments(
+        state,
+        local_generations.state_at(target).unwrap(),
+        variables,
+        &mut stmts,
+    );
+    stmts.push(Stmt { kind, source: None });
+    Some(stmts)
+}
+
+fn ends_in_loop_jump(stmts: &[Stmt]) -> bool {
+    matches!(
+        stmts.last(),
+        Some(Stmt {
+            kind: StmtKind::Continue | StmtKind::Break,
+            ..
+        })
+    )
+}
+
+/// The arguments of the call at `idx`, from the registers that
+/// [`CallArguments`](crate::dataflow::arguments::CallArguments) found to be set up for it.
+fn call_arguments(
+    idx: InstId,
+    state: &BlockState,
+    variables: &Variables,
+    arguments: &BTreeMap<InstId, Vec<Gpr>>,
+) -> Vec<Expr> {
+    // Registers that aren't parameters of this function don't have a variable on entry, which ends the list as well.
+    arguments
+        .get(&idx)
+        .into_iter()
+        .flatten()
+        .map_while(|&gpr| {
+            let generation = state.registers.gprs[gpr.0 as usize].generation;
+            variables.optional_id_by_reg(Register::Gpr(gpr), generation)
+        })
+        .map(Expr::var)
+        .collect()
+}
+
+/// `r3 = target(arguments)`, where `state` is the state after the call.
+fn call_stmt(
+    target: FnCallTarget,
+    arguments: Vec<Expr>,
+    state: &BlockState,
+    variables: &Variables,
+) -> Stmt {
+    // TODO: check if r3 with this generation is used anywhere to tell if it even returns a value at all.
+    let return_var = variables.id_by_gpr(Gpr::RETURN, state);
+    Stmt {
+        kind: StmtKind::Assign {
+            dest: Expr::var(return_var),
+            value: Expr {
+                kind: ExprKind::FnCall(target, arguments),
+            },
+        },
+        source: None,
+    }
+}
+
+/// The condition under which a conditional `blr` or call with the options `bo` is taken. For `bdnzlr` and friends,
+/// `ctr` is the already decremented CTR.
+fn branch_condition(
+    bo: BranchOptions,
+    bi: u8,
+    ctr: Option<VarId>,
+    state: &BlockState,
+    variables: &Variables,
+) -> Expr {
+    match bo {
+        BranchOptions::BranchIfTrue | BranchOptions::BranchIfFalse => {
+            let (crf, crb) = crb_from_index(bi);
+            let var_id = variables.id_by_reg(
+                Register::Cr(crf, crb),
+                state.registers.sprs.cr(crf, crb).generation,
+            );
+            let condition = Expr::var(var_id);
+            if let BranchOptions::BranchIfFalse = bo {
+                !condition
+            } else {
+                condition
+            }
+        }
+        BranchOptions::DecCTRBranchIfNotZero => compare_ctr(ctr.unwrap(), BinaryOp::Ne),
+        BranchOptions::DecCTRBranchIfZero => compare_ctr(ctr.unwrap(), BinaryOp::Eq),
+        BranchOptions::DecCTRBranchIfFalse => logical_and(
+            branch_condition(
+                BranchOptions::DecCTRBranchIfNotZero,
+                bi,
+                ctr,
+                state,
+                variables,
+            ),
+            branch_condition(BranchOptions::BranchIfFalse, bi, ctr, state, variables),
+        ),
+        BranchOptions::DecCTRZeroBranchIfFalse => logical_and(
+            branch_condition(BranchOptions::DecCTRBranchIfZero, bi, ctr, state, variables),
+            branch_condition(BranchOptions::BranchIfFalse, bi, ctr, state, variables),
+        ),
+        BranchOptions::DecCTRBranchIfTrue => logical_and(
+            branch_condition(
+                BranchOptions::DecCTRBranchIfNotZero,
+                bi,
+                ctr,
+                state,
+                variables,
+            ),
+            branch_condition(BranchOptions::BranchIfTrue, bi, ctr, state, variables),
+        ),
+        BranchOptions::DecCTRZeroBranchIfTrue => logical_and(
+            branch_condition(BranchOptions::DecCTRBranchIfZero, bi, ctr, state, variables),
+            branch_condition(BranchOptions::BranchIfTrue, bi, ctr, state, variables),
+        ),
+        BranchOptions::BranchAlways => unreachable!(),
+    }
+}
+
+fn logical_and(left: Expr, right: Expr) -> Expr {
+    Expr {
+        kind: ExprKind::Binary(BinaryExpr {
+            op: BinaryOp::LogicalAnd,
+            left: Box::new(left),
+            right: Box::new(right),
+        }),
+    }
+}
+
+/// A call to the intrinsic `name` with the SPR number as the first argument, followed by `args`.
+fn spr_intrinsic(name: &'static str, spr: MacroSpr, args: Vec<Expr>) -> Expr {
+    let number = Expr {
+        kind: ExprKind::Immediate16(spr.number() as i16),
+    };
+    Expr {
+        kind: ExprKind::FnCall(
+            FnCallTarget::Intrinsic(name),
+            iter::once(number).chain(args).collect(),
+        ),
+    }
+}
+
+/// Marks `stmts` as decompiled from the instruction at `inst`, along with the statements nested in them that don't have
+/// a source yet, e.g. the `return` of a `beqlr`.
+fn set_source(stmts: &mut [Stmt], inst: InstId) {
+    for stmt in stmts {
+        stmt.source.get_or_insert(SourceSpan::at(inst));
+        match stmt.kind {
+            StmtKind::If {
+                ref mut then_stmts,
+                ref mut else_stmts,
+                ..
+            } => {
+                set_source(then_stmts, inst);
+                set_source(else_stmts, inst);
+            }
+            StmtKind::While { ref mut body, .. }
+            | StmtKind::DoWhile { ref mut body, .. }
+            | StmtKind::For { ref mut body, .. } => set_source(body, inst),
+            StmtKind::Assign { .. }
+            | StmtKind::Expr(_)
+            | StmtKind::Return(_)
+            | StmtKind::Continue
+            | StmtKind::Break => {}
+        }
+    }
+}
+
+/// Appends the statements of the path after the instruction at `inst`, which already have their sources, after marking
+/// the ones from `first_stmt` on as decompiled from that instruction.
+fn append_path(stmts: &mut Vec<Stmt>, first_stmt: &mut usize, inst: InstId, path: Vec<Stmt>) {
+    set_source(&mut stmts[*first_stmt..], inst);
+    stmts.extend(path);
+    *first_stmt = stmts.len();
+}
+
+/// `ctr = old_ctr - 1`, the first half of `bdnz` and friends.
+fn decrement_ctr(ctr: VarId, old_ctr: VarId) -> Stmt {
+    Stmt {
+        kind: StmtKind::Assign {
+            dest: Expr::var(ctr),
+            value: Expr {
+                kind: ExprKind::Binary(BinaryExpr {
+                    op: BinaryOp::Sub,
+                    left: Box::new(Expr::var(old_ctr)),
+                    right: Box::new(Expr {
+                        kind: ExprKind::Immediate16(1),
+                    }),
+                }),
+            },
+        },
+        source: None,
+    }
+}
+
+/// `ctr op 0`, the condition that `bdnz` and friends branch on.
+fn compare_ctr(ctr: VarId, op: BinaryOp) -> Expr {
+    Expr {
+        kind: ExprKind::Binary(BinaryExpr {
+            op,
+            left: Box::new(Expr::var(ctr)),
+            right: Box::new(Expr {
+                kind: ExprKind::Immediate16(0),
+            }),
+        }),
+    }
+}
+
 fn build_path(
     instructions: &InstructionsDeref,
     start_index: InstId,
@@ -189,6 +441,8 @@ fn build_path(
     variables: &Variables,
     def_use_map: &DefUseMap,
     loops: &LoopMap,
+    lr_save_restore: &BTreeSet<InstId>,
+    arguments: &BTreeMap<InstId, Vec<Gpr>>,
     succs: &Successors<LocalGenerationAnalysis<'_>>,
     prev_state: Option<&BlockState>,
     current_loop: Option<LoopId>,
@@ -216,6 +470,8 @@ fn build_path(
             variables,
             def_use_map,
             loops,
+            lr_save_restore,
+            arguments,
             succs,
             prev_state,
             Some(id),
@@ -231,6 +487,8 @@ fn build_path(
                 variables,
                 def_use_map,
                 loops,
+                lr_save_restore,
+                arguments,
                 succs,
                 prev_state,
                 current_loop,
@@ -244,6 +502,7 @@ fn build_path(
                         },
                         body: loop_result.stmts,
                     },
+                    source: None,
                 },
             );
 
@@ -257,7 +516,7 @@ fn build_path(
         }
     }
 
-    let mut state = local_generations.get(start_index).map_or_else(
+    let mut state = local_generations.state_at(start_index).map_or_else(
         || {
             assert_eq!(start_index, InstId(0));
             BlockState::default()
@@ -267,10 +526,32 @@ fn build_path(
 
     let mut stmts = Vec::new();
     let mut has_return_value = false;
+    // The statements of the instruction at `current` start at `first_stmt`.
+    let mut first_stmt = 0;
+    let mut current = start_index;
 
     for (idx, (inst_addr, instruction)) in ti_iter(&instructions[start_index..]) {
         let absolute_index = InstId(start_index.0 + idx.0);
-        if absolute_index != start_index && local_generations.get(absolute_index).is_some() {
+        set_source(&mut stmts[first_stmt..], current);
+        first_stmt = stmts.len();
+        current = absolute_index;
+        if absolute_index != start_index && local_generations.state_at(absolute_index).is_some() {
+            if let Some(jump) = loop_jump(
+                absolute_index,
+                current_loop,
+                loops,
+                &state,
+                local_generations,
+                variables,
+            ) {
+                stmts.extend(jump);
+                return BuildPathResult {
+                    stmts,
+                    has_return_value,
+                    state,
+                };
+            }
+
             let next_result = build_path(
                 instructions,
                 absolute_index,
@@ -280,6 +561,8 @@ fn build_path(
                 variables,
                 def_use_map,
                 loops,
+                lr_save_restore,
+                arguments,
                 succs,
                 Some(&state),
                 current_loop,
@@ -294,6 +577,11 @@ fn build_path(
                 state,
             };
         }
+        if lr_save_restore.contains(&absolute_index) {
+            // ABI boilerplate of a function that makes calls, not part of what the function does.
+            analysis.apply_effect(&mut state, idx, instruction);
+            continue;
+        }
         match *instruction {
             Instruction::Stwu {
                 source,
@@ -365,37 +653,38 @@ fn build_path(
                                 dest: Expr::var(dest),
                                 value: Expr::var(source),
                             },
+                            source: None,
                         });
                     }
 
                     if rc {
                         // Make sure code doesn't try to branch on a hidden variable. This could happen, but I'm not sure how to deal with that yet.
                         assert!(visibility == VariableVisibility::Visible);
-
-                        build_crf_assignments(
-                            &state,
-                            def_use_map,
-                            variables,
-                            &mut stmts,
-                            Crf(0),
-                            Expr::var(dest),
-                            Expr {
-                                kind: ExprKind::Immediate16(0),
-                            },
-                        );
                     }
                 } else {
                     // TODO: once we implement this, most of the above also applies here (Rc=1).
                     todo!()
                 }
             }
-            Instruction::Mfspr { dest: _, spr } => {
-                if let Spr::Lr = spr {
-                    // Probably nothing to do?
-                    analysis.apply_effect(&mut state, idx, instruction);
-                } else {
-                    todo!("{instruction:?}"); // TODO: make sure to have apply_effect here too
-                }
+            Instruction::Mfspr {
+                dest: _,
+                spr: Spr::Lr,
+            } => {
+                // Probably nothing to do?
+                analysis.apply_effect(&mut state, idx, instruction);
+            }
+            // Reads of CTR, XER and MSR aren't modeled as variables either, so they are as opaque as the SPRs that
+            // aren't tracked at all.
+            Instruction::Mfspr { dest, spr } => {
+                analysis.apply_effect(&mut state, idx, instruction);
+                let dest = variables.id_by_gpr(dest, &state);
+                stmts.push(Stmt {
+                    kind: StmtKind::Assign {
+                        dest: Expr::var(dest),
+                        value: spr_intrinsic("__mfspr", spr, Vec::new()),
+                    },
+                    source: None,
+                });
             }
             Instruction::Addi {
                 dest: Gpr::STACK_POINTER,
@@ -426,21 +715,10 @@ fn build_path(
                                 }),
                             },
                         },
+                        source: None,
                     });
                 }
 
-                build_crf_assignments(
-                    &state,
-                    def_use_map,
-                    variables,
-                    &mut stmts,
-                    Crf(0),
-                    Expr::var(dest),
-                    Expr {
-                        kind: ExprKind::Immediate16(0),
-                    },
-                );
-
                 build_xer_assignments(
                     &state,
                     def_use_map,
@@ -453,6 +731,35 @@ fn build_path(
                     Expr::var(dest),
                 );
             }
+            Instruction::Addis { dest, add, imm } => {
+                let shifted = Expr {
+                    kind: ExprKind::Immediate32((imm.0 as u32) << 16),
+                };
+                let source = match add {
+                    // lis
+                    None => shifted,
+                    Some(add) => Expr {
+                        kind: ExprKind::Binary(BinaryExpr {
+                            op: BinaryOp::Add,
+                            left: Box::new(Expr::var(variables.id_by_gpr(add, &state))),
+                            right: Box::new(shifted),
+                        }),
+                    },
+                };
+
+                analysis.apply_effect(&mut state, idx, instruction);
+
+                let dest = variables.id_by_gpr(dest, &state);
+                if variables.get_vis(dest) == VariableVisibility::Visible {
+                    stmts.push(Stmt {
+                        kind: StmtKind::Assign {
+                            dest: Expr::var(dest),
+                            value: source,
+                        },
+                        source: None,
+                    });
+                }
+            }
             Instruction::Addi { dest, source, imm } => {
                 let source = if source == Gpr::ZERO {
                     ExprKind::Immediate16(imm.0)
@@ -479,6 +786,7 @@ fn build_path(
                             dest: Expr::var(dest),
                             value: Expr { kind: source },
                         },
+                        source: None,
                     });
                 }
             }
@@ -505,6 +813,7 @@ fn build_path(
                                 }),
                             },
                         },
+                        source: None,
                     });
 
                     // XER[CA] bit: simm >= source
@@ -521,6 +830,7 @@ fn build_path(
                                 }),
                             },
                         },
+                        source: None,
                     });
                 }
             }
@@ -529,7 +839,7 @@ fn build_path(
                 source_b,
                 source_a,
                 oe,
-                rc,
+                rc: _,
             } => {
                 let source_a = variables.id_by_gpr(source_a, &state);
                 let source_b = variables.id_by_gpr(source_b, &state);
@@ -549,23 +859,10 @@ fn build_path(
                                 }),
                             },
                         },
+                        source: None,
                     });
                 }
 
-                if rc {
-                    build_crf_assignments(
-                        &state,
-                        def_use_map,
-                        variables,
-                        &mut stmts,
-                        Crf(0),
-                        Expr::var(dest),
-                        Expr {
-                            kind: ExprKind::Immediate16(0),
-                        },
-                    );
-                }
-
                 if oe {
                     build_xer_assignments(
                         &state,
@@ -583,7 +880,7 @@ fn build_path(
                 source_a,
                 source_b,
                 oe,
-                rc,
+                rc: _,
             } => {
                 // source_a - source_b - (1 - CA)
                 let source_a = variables.id_by_gpr(source_a, &state);
@@ -626,6 +923,7 @@ fn build_path(
                             dest: Expr::var(dest),
                             value: expr,
                         },
+                        source: None,
                     });
                 }
 
@@ -655,6 +953,7 @@ fn build_path(
                                 }),
                             },
                         },
+                        source: None,
                     });
                 }
 
@@ -669,22 +968,8 @@ fn build_path(
                         Expr::var(dest),
                     );
                 }
-
-                if rc {
-                    build_crf_assignments(
-                        &state,
-                        def_use_map,
-                        variables,
-                        &mut stmts,
-                        Crf(0),
-                        Expr::var(dest),
-                        Expr {
-                            kind: ExprKind::Immediate16(0),
-                        },
-                    );
-                }
             }
-            Instruction::Andi { source, dest, simm } => {
+            Instruction::Andi { source, dest, imm } => {
                 let source = variables.id_by_gpr(source, &state);
                 analysis.apply_effect(&mut state, idx, instruction);
                 let dest = variables.id_by_gpr(dest, &state);
@@ -698,11 +983,38 @@ fn build_path(
                                     op: BinaryOp::BitAnd,
                                     left: Box::new(Expr::var(source)),
                                     right: Box::new(Expr {
-                                        kind: ExprKind::Immediate16(simm),
+                                        kind: ExprKind::Immediate16(imm.0 as i16),
                                     }),
                                 }),
                             },
                         },
+                        source: None,
+                    });
+                }
+            }
+            // `not`, which is the only way compilers use `nor` with the same register twice. Anything else would
+            // come out as `~(a | a)`.
+            Instruction::Nor {
+                source,
+                dest,
+                rhs,
+                rc: _,
+            } if source == rhs => {
+                let source = variables.id_by_gpr(source, &state);
+                analysis.apply_effect(&mut state, idx, instruction);
+                let dest = variables.id_by_gpr(dest, &state);
+                if variables.get_vis(dest) == VariableVisibility::Visible {
+                    stmts.push(Stmt {
+                        kind: StmtKind::Assign {
+                            dest: Expr::var(dest),
+                            value: Expr {
+                                kind: ExprKind::Unary(UnaryExpr {
+                                    op: UnaryOp::BitNot,
+                                    operand: Box::new(Expr::var(source)),
+                                }),
+                            },
+                        },
+                        source: None,
                     });
                 }
             }
@@ -724,94 +1036,164 @@ fn build_path(
                                 dest: Expr::var(dest),
                                 value: Expr::var(source),
                             },
+                            source: None,
                         });
                     }
                 } else {
-                    todo!()
+                    // A store through a pointer (or to an absolute address if the base is r0)
+                    let source = variables.id_by_gpr(source, &state);
+                    let base = (dest != Gpr::ZERO).then(|| variables.id_by_gpr(dest, &state));
+
+                    analysis.apply_effect(&mut state, idx, instruction);
+
+                    stmts.push(Stmt {
+                        kind: StmtKind::Assign {
+                            dest: Expr::deref(base, imm.0),
+                            value: Expr::var(source),
+                        },
+                        source: None,
+                    });
                 }
             }
             Instruction::Branch { target, mode, link } => {
                 let target = compute_branch_target(inst_addr.0, mode, target);
                 if link {
                     // Function call. Probably.
-
-                    // Figure out arguments.
-                    let mut arguments = Vec::new();
-                    for reg in 3..=8 {
-                        let register = Register::Gpr(Gpr(reg));
-                        let generation = state.registers.gprs[reg as usize].generation;
-                        if let Some(var_id) = variables.optional_id_by_reg(register, generation)
-                            && !def_use_map.has_uses(register, generation)
-                        {
-                            arguments.push(Expr::var(var_id));
-                        }
-                    }
+                    let arguments = call_arguments(absolute_index, &state, variables, arguments);
 
                     analysis.apply_effect(&mut state, idx, instruction);
 
-                    // TODO: check if r3 with this generation is used anywhere to tell if it even returns a value at all.
-                    let return_var = variables.id_by_gpr(Gpr::RETURN, &state);
-                    stmts.push(Stmt {
-                        kind: StmtKind::Assign {
-                            dest: Expr::var(return_var),
-                            value: Expr {
-                                kind: ExprKind::FnCall(FnCallTarget::Addr(target), arguments),
-                            },
-                        },
-                    });
-                } else {
+                    stmts.push(call_stmt(
+                        FnCallTarget::Addr(target),
+                        arguments,
+                        &state,
+                        variables,
+                    ));
+                } else if let Some(target_idx) =
+                    inst_at(analysis.fn_address, instructions.len(), target)
+                {
                     analysis.apply_effect(&mut state, idx, instruction);
 
-                    let idx = InstId((target - analysis.fn_address) / 4);
+                    if let Some(jump) = loop_jump(
+                        target_idx,
+                        current_loop,
+                        loops,
+                        &state,
+                        local_generations,
+                        variables,
+                    ) {
+                        stmts.extend(jump);
+                        break;
+                    }
+
                     let path_result = build_path(
                         instructions,
-                        idx,
+                        target_idx,
                         end_index,
                         local_generations,
                         analysis,
                         variables,
                         def_use_map,
                         loops,
+                        lr_save_restore,
+                        arguments,
                         succs,
                         Some(&state),
                         current_loop,
                     );
-                    stmts.extend(path_result.stmts);
+                    append_path(
+                        &mut stmts,
+                        &mut first_stmt,
+                        absolute_index,
+                        path_result.stmts,
+                    );
                     has_return_value |= path_result.has_return_value;
                     break;
+                } else {
+                    // A tail call, i.e. `return f(...);`.
+                    let arguments = call_arguments(absolute_index, &state, variables, arguments);
+                    analysis.apply_effect(&mut state, idx, instruction);
+
+                    stmts.push(Stmt {
+                        kind: StmtKind::Return(Some(Expr {
+                            kind: ExprKind::FnCall(FnCallTarget::Addr(target), arguments),
+                        })),
+                        source: None,
+                    });
+                    has_return_value = true;
+                    break;
                 }
             }
             Instruction::Bc {
                 bo,
+                hint: _,
                 bi,
                 target,
                 mode,
-                link,
-            } => {
-                assert!(!link);
+                link: false,
+            } if inst_at(
+                analysis.fn_address,
+                instructions.len(),
+                compute_branch_target(inst_addr.0, mode, target),
+            )
+            .is_none() =>
+            {
+                // A conditional tail call, i.e. `if (...) return f(...);`.
+                let target = compute_branch_target(inst_addr.0, mode, target);
+                let arguments = call_arguments(absolute_index, &state, variables, arguments);
+                let old_ctr = bo.decrements_ctr().then(|| {
+                    variables
+                        .id_by_reg(Register::Spr(Spr::Ctr), state.registers.sprs.ctr.generation)
+                });
+
+                analysis.apply_effect(&mut state, idx, instruction);
 
+                let ctr = old_ctr.map(|old_ctr| {
+                    let ctr = variables
+                        .id_by_reg(Register::Spr(Spr::Ctr), state.registers.sprs.ctr.generation);
+                    stmts.push(decrement_ctr(ctr, old_ctr));
+                    ctr
+                });
+                let tail_call = Stmt {
+                    kind: StmtKind::Return(Some(Expr {
+                        kind: ExprKind::FnCall(FnCallTarget::Addr(target), arguments),
+                    })),
+                    source: None,
+                };
+                // The value analysis may have proved that the call is always or never made.
+                match decided_branch(succs, absolute_index) {
+                    Some(SuccessorTarget::Return) => {
+                        stmts.push(tail_call);
+                        has_return_value = true;
+                        break;
+                    }
+                    Some(SuccessorTarget::Id(_)) => {}
+                    None => {
+                        stmts.push(Stmt {
+                            kind: StmtKind::If {
+                                condition: branch_condition(bo, bi, ctr, &state, variables),
+                                then_stmts: vec![tail_call],
+                                else_stmts: Vec::new(),
+                            },
+                            source: None,
+                        });
+                        has_return_value = true;
+                    }
+                }
+            }
+            Instruction::Bc {
+                bo,
+                hint: _,
+                bi,
+                target,
+                mode,
+                link: false,
+            } => {
                 let true_idx = InstId(
                     (compute_branch_target(inst_addr.0, mode, target) - analysis.fn_address) / 4,
                 );
                 let false_idx = InstId(absolute_index.0 + 1);
 
-                let (true_loop_target, false_loop_target, true_is_break, false_is_break) =
-                    if let Some(loop_id) = current_loop
-                        && let current_loop = loops.get(loop_id)
-                        && let true_is_start = current_loop.start == true_idx
-                        && let false_is_start = current_loop.start == false_idx
-                        && (true_is_start || false_is_start)
-                    {
-                        (
-                            true_is_start.then_some(loop_id),
-                            false_is_start.then_some(loop_id),
-                            Some(true_idx) == current_loop.common_merge_inst,
-                            Some(false_idx) == current_loop.common_merge_inst,
-                        )
-                    } else {
-                        (None, None, false, false)
-                    };
-
                 // TODO: maybe compute this one for each entry in successors and then just use the map here?
                 let mut true_transitive_successors = HashSet::new();
                 for_each_transitive_successor(succs, true_idx, &mut |inst| {
@@ -830,95 +1212,114 @@ fn build_path(
                     })
                     .break_value();
 
-                let (crf, crb) = crb_from_index(bi);
-                let generation = state.registers.sprs.cr(crf, crb).generation;
-                let condition = variables.id_by_reg(Register::Cr(crf, crb), generation);
+                let condition = bo.tests_condition().then(|| {
+                    let (crf, crb) = crb_from_index(bi);
+                    let generation = state.registers.sprs.cr(crf, crb).generation;
+                    variables.id_by_reg(Register::Cr(crf, crb), generation)
+                });
+                let ctr = bo.decrements_ctr().then(|| {
+                    variables
+                        .id_by_reg(Register::Spr(Spr::Ctr), state.registers.sprs.ctr.generation)
+                });
 
                 analysis.apply_effect(&mut state, idx, instruction);
 
-                let BuildPathResult {
-                    stmts: then_stmts,
-                    has_return_value: then_has_return_value,
-                    state: then_state,
-                } = if true_loop_target.is_some() {
-                    let mut stmts = Vec::with_capacity(2);
-                    append_phi_merge_assignments(
-                        &state,
-                        local_generations.get(true_idx).unwrap(),
-                        variables,
-                        &mut stmts,
-                    );
-                    stmts.push(Stmt {
-                        kind: StmtKind::Continue,
-                    });
+                // `bdnz` and friends decrement CTR and then branch depending on the new value
+                let ctr = ctr.map(|old_ctr| {
+                    let ctr = variables
+                        .id_by_reg(Register::Spr(Spr::Ctr), state.registers.sprs.ctr.generation);
+                    stmts.push(decrement_ctr(ctr, old_ctr));
+                    ctr
+                });
 
-                    BuildPathResult {
-                        stmts,
-                        has_return_value,
-                        state: state.clone(),
+                // The value analysis proved that the branch always goes the same way, so there's nothing to decide.
+                if let Some(SuccessorTarget::Id(target_idx)) = decided_branch(succs, absolute_index)
+                {
+                    if target_idx == false_idx {
+                        continue;
                     }
-                } else if true_is_break {
-                    let mut stmts = Vec::with_capacity(2);
-                    append_phi_merge_assignments(
+                    if let Some(jump) = loop_jump(
+                        target_idx,
+                        current_loop,
+                        loops,
                         &state,
-                        local_generations.get(true_idx).unwrap(),
+                        local_generations,
                         variables,
-                        &mut stmts,
-                    );
-                    stmts.push(Stmt {
-                        kind: StmtKind::Break,
-                    });
-                    BuildPathResult {
-                        stmts,
-                        has_return_value,
-                        state: state.clone(),
+                    ) {
+                        stmts.extend(jump);
+                        break;
                     }
-                } else {
-                    build_path(
+                    let path_result = build_path(
                         instructions,
-                        true_idx,
-                        common_merge_inst,
+                        target_idx,
+                        end_index,
                         local_generations,
                         analysis,
                         variables,
                         def_use_map,
                         loops,
+                        lr_save_restore,
+                        arguments,
                         succs,
                         Some(&state),
                         current_loop,
-                    )
-                };
-                let BuildPathResult {
-                    stmts: else_stmts,
-                    has_return_value: else_has_return_value,
-                    state: else_state,
-                } = if false_loop_target.is_some() {
-                    let mut stmts = Vec::with_capacity(2);
-                    append_phi_merge_assignments(
-                        &state,
-                        local_generations.get(false_idx).unwrap(),
-                        variables,
+                    );
+                    append_path(
                         &mut stmts,
+                        &mut first_stmt,
+                        absolute_index,
+                        path_result.stmts,
                     );
-                    stmts.push(Stmt {
-                        kind: StmtKind::Continue,
-                    });
+                    has_return_value |= path_result.has_return_value;
+                    break;
+                }
+
+                let BuildPathResult {
+                    stmts: then_stmts,
+                    has_return_value: then_has_return_value,
+                    state: then_state,
+                } = if let Some(stmts) = loop_jump(
+                    true_idx,
+                    current_loop,
+                    loops,
+                    &state,
+                    local_generations,
+                    variables,
+                ) {
                     BuildPathResult {
                         stmts,
                         has_return_value,
                         state: state.clone(),
                     }
-                } else if false_is_break {
-                    let mut stmts = Vec::with_capacity(2);
-                    append_phi_merge_assignments(
-                        &state,
-                        local_generations.get(false_idx).unwrap(),
+                } else {
+                    build_path(
+                        instructions,
+                        true_idx,
+                        common_merge_inst,
+                        local_generations,
+                        analysis,
                         variables,
-                        &mut stmts,
-                    );
-                    stmts.push(Stmt {
-                        kind: StmtKind::Break,
-                    });
+                        def_use_map,
+                        loops,
+                        lr_save_restore,
+                        arguments,
+                        succs,
+                        Some(&state),
+                        current_loop,
+                    )
+                };
+                let BuildPathResult {
+                    stmts: else_stmts,
+                    has_return_value: else_has_return_value,
+                    state: else_state,
+                } = if let Some(stmts) = loop_jump(
+                    false_idx,
+                    current_loop,
+                    loops,
+                    &state,
+                    local_generations,
+                    variables,
+                ) {
                     BuildPathResult {
                         stmts,
                         has_return_value,
@@ -934,6 +1335,8 @@ fn build_path(
                         variables,
                         def_use_map,
                         loops,
+                        lr_save_restore,
+                        arguments,
                         succs,
                         Some(&state),
                         current_loop,
@@ -942,23 +1345,35 @@ fn build_path(
 
                 has_return_value |= then_has_return_value | else_has_return_value;
 
-                let mut condition = Expr::var(condition);
-                match bo {
-                    BranchOptions::DecCTRBranchIfFalse => todo!(),
-                    BranchOptions::BranchIfFalse => {
-                        condition = Expr {
-                            kind: ExprKind::Unary(UnaryExpr {
-                                op: UnaryOp::Not,
-                                operand: Box::new(condition),
-                            }),
-                        }
+                let (condition, negated) = match bo {
+                    BranchOptions::DecCTRBranchIfFalse
+                    | BranchOptions::DecCTRZeroBranchIfFalse
+                    | BranchOptions::DecCTRBranchIfTrue
+                    | BranchOptions::DecCTRZeroBranchIfTrue => {
+                        (branch_condition(bo, bi, ctr, &state, variables), false)
+                    }
+                    BranchOptions::BranchIfFalse => (Expr::var(condition.unwrap()), true),
+                    BranchOptions::BranchIfTrue => (Expr::var(condition.unwrap()), false),
+                    BranchOptions::DecCTRBranchIfNotZero => {
+                        (compare_ctr(ctr.unwrap(), BinaryOp::Ne), false)
+                    }
+                    BranchOptions::DecCTRBranchIfZero => {
+                        (compare_ctr(ctr.unwrap(), BinaryOp::Eq), false)
                     }
-                    BranchOptions::DecCTRBranchIfTrue => todo!(),
-                    BranchOptions::BranchIfTrue => {}
-                    BranchOptions::DecCTRBranchIfNotZero => todo!(),
-                    BranchOptions::DecCTRBranchIfZero => todo!(),
                     BranchOptions::BranchAlways => todo!(),
-                }
+                };
+                // `if (!c) { a } else { b }` reads better as `if (c) { b } else { a }`, and `if (c) {} else { b }` as
+                // `if (!c) { b }`
+                let (condition, then_stmts, else_stmts, then_state, else_state) =
+                    if negated && !else_stmts.is_empty() {
+                        (condition, else_stmts, then_stmts, else_state, then_state)
+                    } else if negated {
+                        (!condition, then_stmts, else_stmts, then_state, else_state)
+                    } else if then_stmts.is_empty() && !else_stmts.is_empty() {
+                        (!condition, else_stmts, then_stmts, else_state, then_state)
+                    } else {
+                        (condition, then_stmts, else_stmts, then_state, else_state)
+                    };
 
                 has_return_value |= then_has_return_value | else_has_return_value;
                 stmts.push(Stmt {
@@ -967,6 +1382,7 @@ fn build_path(
                         then_stmts: then_stmts,
                         else_stmts: else_stmts,
                     },
+                    source: None,
                 });
 
                 if let Some(common_merge_inst) = common_merge_inst {
@@ -982,9 +1398,24 @@ fn build_path(
                     // TODO: currently we merge the phis when we process the next block,
                     // but we should really just do it in the places where we transfer to the next block
                     // (call the append_phi... function)
-                    let next_state = local_generations.get(common_merge_inst).unwrap();
-                    append_phi_merge_assignments(&then_state, next_state, variables, then_stmts);
-                    append_phi_merge_assignments(&else_state, next_state, variables, else_stmts);
+                    // An arm that ends in a `continue` or `break` never reaches the merge point.
+                    let next_state = local_generations.state_at(common_merge_inst).unwrap();
+                    if !ends_in_loop_jump(then_stmts) {
+                        append_phi_merge_assignments(
+                            &then_state,
+                            next_state,
+                            variables,
+                            then_stmts,
+                        );
+                    }
+                    if !ends_in_loop_jump(else_stmts) {
+                        append_phi_merge_assignments(
+                            &else_state,
+                            next_state,
+                            variables,
+                            else_stmts,
+                        );
+                    }
 
                     let next_path = build_path(
                         instructions,
@@ -995,18 +1426,26 @@ fn build_path(
                         variables,
                         def_use_map,
                         loops,
+                        lr_save_restore,
+                        arguments,
                         succs,
                         Some(&state),
                         current_loop,
                     );
-                    stmts.extend(next_path.stmts);
+                    append_path(&mut stmts, &mut first_stmt, absolute_index, next_path.stmts);
                     has_return_value |= next_path.has_return_value;
                 }
                 break;
             }
+            Instruction::Lwz {
+                dest: Gpr::STACK_POINTER,
+                source: Gpr::STACK_POINTER,
+                imm: Immediate(0),
+            } => {
+                // Tearing down the stack frame, the counterpart to `stwu`.
+                analysis.apply_effect(&mut state, idx, instruction);
+            }
             Instruction::Lwz { dest, source, imm } => {
-                assert!(source != Gpr(0)); // TODO: source == 0 means no register and it loads from imm alone. handle this.
-
                 if source == Gpr::STACK_POINTER {
                     // Stack-relative load
 
@@ -1022,68 +1461,198 @@ fn build_path(
                                 dest: Expr::var(dest),
                                 value: Expr::var(source),
                             },
+                            source: None,
                         });
                     }
                 } else {
-                    todo!();
+                    // A load through a pointer (or from an absolute address if the base is r0)
+                    let base = (source != Gpr::ZERO).then(|| variables.id_by_gpr(source, &state));
+
+                    analysis.apply_effect(&mut state, idx, instruction);
+
+                    let dest = variables.id_by_gpr(dest, &state);
+                    if variables.get_vis(dest) == VariableVisibility::Visible {
+                        stmts.push(Stmt {
+                            kind: StmtKind::Assign {
+                                dest: Expr::var(dest),
+                                value: Expr::deref(base, imm.0),
+                            },
+                            source: None,
+                        });
+                    }
                 }
             }
-            Instruction::Mtspr { source: _, spr } => {
-                if let Spr::Lr = spr {
-                    analysis.apply_effect(&mut state, idx, instruction);
-                } else {
-                    todo!("{instruction:?}"); // Make sure to add apply_effect here too
+            Instruction::Mtspr {
+                source,
+                spr: spr @ (Spr::Ctr | Spr::Lr),
+            } => {
+                let source = variables.id_by_gpr(source, &state);
+                analysis.apply_effect(&mut state, idx, instruction);
+                let (reg, generation) = match spr {
+                    Spr::Lr => (Spr::Lr, state.registers.sprs.lr.generation),
+                    _ => (Spr::Ctr, state.registers.sprs.ctr.generation),
+                };
+                let dest = variables.id_by_reg(Register::Spr(reg), generation);
+                if variables.get_vis(dest) == VariableVisibility::Visible {
+                    stmts.push(Stmt {
+                        kind: StmtKind::Assign {
+                            dest: Expr::var(dest),
+                            value: Expr::var(source),
+                        },
+                        source: None,
+                    });
                 }
             }
-            Instruction::Bclr { bo, bi, link } => {
-                assert!(!link);
-
+            Instruction::Mtspr { source, spr } => {
+                let source = variables.id_by_gpr(source, &state);
                 analysis.apply_effect(&mut state, idx, instruction);
-
-                let return_reg = Register::Gpr(Gpr::RETURN);
-                let return_generation = state.registers.gprs[Gpr::RETURN.0 as usize].generation;
-                // We assume there is a return value if
-                // 1.) The r3 generation is greater than 0 at this point, meaning that r3 has been assigned a value
-                // 2.) That generation's value is not used anywhere, so the only logical reason for it to be assigned a value is to return it.
-                let cur_has_return_value = return_generation > Generation::INITIAL
-                    && !def_use_map.has_uses(return_reg, return_generation);
-                has_return_value |= cur_has_return_value;
-
-                let return_stmt = Stmt {
-                    kind: StmtKind::Return(if cur_has_return_value {
-                        Some(Expr::var(
-                            variables.id_by_reg(return_reg, return_generation),
+                stmts.push(Stmt {
+                    kind: StmtKind::Expr(spr_intrinsic("__mtspr", spr, vec![Expr::var(source)])),
+                    source: None,
+                });
+            }
+            Instruction::Bc {
+                bo, bi, link: true, ..
+            }
+            | Instruction::Bclr {
+                bo,
+                hint: _,
+                bi,
+                link: true,
+            }
+            | Instruction::Bcctr {
+                bo,
+                hint: _,
+                bi,
+                link: true,
+            } => {
+                // `bcl`, a conditional call, and `blrl` and `bctrl`, a call through a function pointer. The conditional
+                // forms only make the call if the condition holds, and otherwise leave r3 alone.
+                let target = match *instruction {
+                    Instruction::Bc { target, mode, .. } => {
+                        FnCallTarget::Addr(compute_branch_target(inst_addr.0, mode, target))
+                    }
+                    Instruction::Bclr { .. } => FnCallTarget::Indirect(
+                        variables
+                            .id_by_reg(Register::Spr(Spr::Lr), state.registers.sprs.lr.generation),
+                    ),
+                    _ => {
+                        FnCallTarget::Indirect(variables.id_by_reg(
+                            Register::Spr(Spr::Ctr),
+                            state.registers.sprs.ctr.generation,
                         ))
-                    } else {
-                        None
-                    }),
+                    }
                 };
+                let arguments = call_arguments(absolute_index, &state, variables, arguments);
+                let old_return = variables.optional_id_by_reg(
+                    Register::Gpr(Gpr::RETURN),
+                    state.registers.gprs[Gpr::RETURN.0 as usize].generation,
+                );
+                let old_ctr = bo.decrements_ctr().then(|| {
+                    variables
+                        .id_by_reg(Register::Spr(Spr::Ctr), state.registers.sprs.ctr.generation)
+                });
+
+                analysis.apply_effect(&mut state, idx, instruction);
 
+                let call = call_stmt(target, arguments, &state, variables);
                 if bo == BranchOptions::BranchAlways {
-                    stmts.push(return_stmt);
+                    stmts.push(call);
                 } else {
-                    let (crf, crb) = crb_from_index(bi);
-                    let var_id = variables.id_by_reg(
-                        Register::Cr(crf, crb),
-                        state.registers.sprs.cr(crf, crb).generation,
-                    );
-                    let mut condition = Expr::var(var_id);
-                    if let BranchOptions::BranchIfFalse = bo {
-                        condition = Expr {
-                            kind: ExprKind::Unary(UnaryExpr {
-                                op: UnaryOp::Not,
-                                operand: Box::new(condition),
-                            }),
-                        };
-                    }
-
+                    let ctr = old_ctr.map(|old_ctr| {
+                        let ctr = variables.id_by_reg(
+                            Register::Spr(Spr::Ctr),
+                            state.registers.sprs.ctr.generation,
+                        );
+                        stmts.push(decrement_ctr(ctr, old_ctr));
+                        ctr
+                    });
+                    let return_var = variables.id_by_gpr(Gpr::RETURN, &state);
                     stmts.push(Stmt {
                         kind: StmtKind::If {
-                            condition,
-                            then_stmts: vec![return_stmt],
-                            else_stmts: Vec::new(),
+                            condition: branch_condition(bo, bi, ctr, &state, variables),
+                            then_stmts: vec![call],
+                            else_stmts: old_return
+                                .map(|old_return| Stmt {
+                                    kind: StmtKind::Assign {
+                                        dest: Expr::var(return_var),
+                                        value: Expr::var(old_return),
+                                    },
+                                    source: None,
+                                })
+                                .into_iter()
+                                .collect(),
                         },
+                        source: None,
+                    });
+                }
+            }
+            Instruction::Bclr {
+                bo,
+                hint: _,
+                bi,
+                link: false,
+            } => {
+                let old_ctr = bo.decrements_ctr().then(|| {
+                    variables
+                        .id_by_reg(Register::Spr(Spr::Ctr), state.registers.sprs.ctr.generation)
+                });
+
+                analysis.apply_effect(&mut state, idx, instruction);
+
+                // We assume there is a return value in r3 (or f1 for floats) if
+                // 1.) The generation is greater than 0 at this point, meaning that the register has been assigned a value
+                // 2.) That generation's value is not used anywhere, so the only logical reason for it to be assigned a value is to return it.
+                let returned = [
+                    (
+                        Register::Gpr(Gpr::RETURN),
+                        state.registers.gprs[Gpr::RETURN.0 as usize].generation,
+                    ),
+                    (
+                        Register::Fpr(Fpr::RETURN),
+                        state.registers.fprs[Fpr::RETURN.0 as usize].generation,
+                    ),
+                ]
+                .into_iter()
+                .find(|&(reg, generation)| {
+                    generation > Generation::INITIAL && !def_use_map.has_uses(reg, generation)
+                });
+                has_return_value |= returned.is_some();
+
+                let return_stmt =
+                    Stmt {
+                        kind: StmtKind::Return(returned.map(|(reg, generation)| {
+                            Expr::var(variables.id_by_reg(reg, generation))
+                        })),
+                        source: None,
+                    };
+
+                let decided = decided_branch(succs, absolute_index);
+                if bo == BranchOptions::BranchAlways
+                    || matches!(decided, Some(SuccessorTarget::Return))
+                {
+                    stmts.push(return_stmt);
+                } else {
+                    // `bdnzlr` and `bdzlr` decrement CTR and return depending on the new value, like `bdnz`.
+                    let ctr = old_ctr.map(|old_ctr| {
+                        let ctr = variables.id_by_reg(
+                            Register::Spr(Spr::Ctr),
+                            state.registers.sprs.ctr.generation,
+                        );
+                        stmts.push(decrement_ctr(ctr, old_ctr));
+                        ctr
                     });
+                    // Unless the value analysis proved that it never returns here.
+                    if decided.is_none() {
+                        stmts.push(Stmt {
+                            kind: StmtKind::If {
+                                condition: branch_condition(bo, bi, ctr, &state, variables),
+                                then_stmts: vec![return_stmt],
+                                else_stmts: Vec::new(),
+                            },
+                            source: None,
+                        });
+                    }
 
                     let next_path = build_path(
                         instructions,
@@ -1094,19 +1663,267 @@ fn build_path(
                         variables,
                         def_use_map,
                         loops,
+                        lr_save_restore,
+                        arguments,
                         succs,
                         Some(&state),
                         current_loop,
                     );
-                    stmts.extend(next_path.stmts);
+                    append_path(&mut stmts, &mut first_stmt, absolute_index, next_path.stmts);
                     has_return_value |= next_path.has_return_value;
                 }
                 break;
             }
-            _ => todo!("{instruction:?}"),
+            Instruction::Lfs { dest, source, imm } | Instruction::Lfd { dest, source, imm } => {
+                let ty = if let Instruction::Lfs { .. } = *instruction {
+                    TyKind::F32
+                } else {
+                    TyKind::F64
+                };
+                let value = if source == Gpr::STACK_POINTER {
+                    Expr::var(variables.id_by_stack_mem(imm.0))
+                } else {
+                    let base = (source != Gpr::ZERO).then(|| variables.id_by_gpr(source, &state));
+                    Expr::deref_as(ty, base, imm.0)
+                };
+
+                analysis.apply_effect(&mut state, idx, instruction);
+
+                let dest = variables.id_by_fpr(dest, &state);
+                if variables.get_vis(dest) == VariableVisibility::Visible {
+                    stmts.push(Stmt {
+                        kind: StmtKind::Assign {
+                            dest: Expr::var(dest),
+                            value,
+                        },
+                        source: None,
+                    });
+                }
+            }
+            Instruction::Stfs { source, dest, imm } | Instruction::Stfd { source, dest, imm } => {
+                let ty = if let Instruction::Stfs { .. } = *instruction {
+                    TyKind::F32
+                } else {
+                    TyKind::F64
+                };
+                let source = variables.id_by_fpr(source, &state);
+                let base = (dest != Gpr::ZERO).then(|| variables.id_by_gpr(dest, &state));
+
+                analysis.apply_effect(&mut state, idx, instruction);
+
+                let dest = if dest == Gpr::STACK_POINTER {
+                    // Don't create an assignment if this is just saving a callee-saved register
+                    let dest = variables.id_by_stack_mem(imm.0);
+                    (variables.get_vis(dest) == VariableVisibility::Visible)
+                        .then(|| Expr::var(dest))
+                } else {
+                    Some(Expr::deref_as(ty, base, imm.0))
+                };
+                if let Some(dest) = dest {
+                    stmts.push(Stmt {
+                        kind: StmtKind::Assign {
+                            dest,
+                            value: Expr::var(source),
+                        },
+                        source: None,
+                    });
+                }
+            }
+            Instruction::Fadd {
+                dest,
+                source_a,
+                source_b,
+                rc: false,
+            }
+            | Instruction::Fadds {
+                dest,
+                source_a,
+                source_b,
+                rc: false,
+            }
+            | Instruction::Fsub {
+                dest,
+                source_a,
+                source_b,
+                rc: false,
+            }
+            | Instruction::Fsubs {
+                dest,
+                source_a,
+                source_b,
+                rc: false,
+            }
+            | Instruction::Fmul {
+                dest,
+                source_a,
+                source_c: source_b,
+                rc: false,
+            }
+            | Instruction::Fmuls {
+                dest,
+                source_a,
+                source_c: source_b,
+                rc: false,
+            }
+            | Instruction::Fdiv {
+                dest,
+                source_a,
+                source_b,
+                rc: false,
+            }
+            | Instruction::Fdivs {
+                dest,
+                source_a,
+                source_b,
+                rc: false,
+            } => {
+                let op = match *instruction {
+                    Instruction::Fadd { .. } | Instruction::Fadds { .. } => BinaryOp::Add,
+                    Instruction::Fsub { .. } | Instruction::Fsubs { .. } => BinaryOp::Sub,
+                    Instruction::Fmul { .. } | Instruction::Fmuls { .. } => BinaryOp::Mul,
+                    _ => BinaryOp::Div,
+                };
+                let source_a = variables.id_by_fpr(source_a, &state);
+                let source_b = variables.id_by_fpr(source_b, &state);
+
+                analysis.apply_effect(&mut state, idx, instruction);
+
+                let dest = variables.id_by_fpr(dest, &state);
+                if variables.get_vis(dest) == VariableVisibility::Visible {
+                    stmts.push(Stmt {
+                        kind: StmtKind::Assign {
+                            dest: Expr::var(dest),
+                            value: Expr {
+                                kind: ExprKind::Binary(BinaryExpr {
+                                    op,
+                                    left: Box::new(Expr::var(source_a)),
+                                    right: Box::new(Expr::var(source_b)),
+                                }),
+                            },
+                        },
+                        source: None,
+                    });
+                }
+            }
+            Instruction::Fmr {
+                dest,
+                source,
+                rc: false,
+            }
+            | Instruction::Fneg {
+                dest,
+                source,
+                rc: false,
+            }
+            | Instruction::Fabs {
+                dest,
+                source,
+                rc: false,
+            }
+            | Instruction::Fnabs {
+                dest,
+                source,
+                rc: false,
+            }
+            | Instruction::Frsp {
+                dest,
+                source,
+                rc: false,
+            } => {
+                let source = Expr::var(variables.id_by_fpr(source, &state));
+                let value = match *instruction {
+                    Instruction::Fmr { .. } => source,
+                    Instruction::Fneg { .. } => Expr {
+                        kind: ExprKind::Unary(UnaryExpr {
+                            op: UnaryOp::Neg,
+                            operand: Box::new(source),
+                        }),
+                    },
+                    // CodeWarrior's intrinsics for these.
+                    Instruction::Fabs { .. } => Expr {
+                        kind: ExprKind::FnCall(FnCallTarget::Intrinsic("__fabs"), vec![source]),
+                    },
+                    Instruction::Fnabs { .. } => Expr {
+                        kind: ExprKind::FnCall(FnCallTarget::Intrinsic("__fnabs"), vec![source]),
+                    },
+                    _ => Expr {
+                        kind: ExprKind::Cast(TyKind::F32, Box::new(source)),
+                    },
+                };
+
+                analysis.apply_effect(&mut state, idx, instruction);
+
+                let dest = variables.id_by_fpr(dest, &state);
+                if variables.get_vis(dest) == VariableVisibility::Visible {
+                    stmts.push(Stmt {
+                        kind: StmtKind::Assign {
+                            dest: Expr::var(dest),
+                            value,
+                        },
+                        source: None,
+                    });
+                }
+            }
+            _ => {
+                // Anything else is written as a call to an intrinsic named after the instruction, taking the
+                // registers it reads that have variables and assigned to each of the registers it writes.
+                let (reads, writes) = opaque_operands(instruction);
+                let var_of = |state: &mut BlockState, reg| {
+                    let generation = state.registers.by_register(reg)?.generation;
+                    variables.optional_id_by_reg(reg, generation)
+                };
+                let args = reads
+                    .into_iter()
+                    .filter_map(|reg| var_of(&mut state, reg))
+                    .map(Expr::var)
+                    .collect();
+                let call = Expr {
+                    kind: ExprKind::FnCall(FnCallTarget::Instruction(instruction.name()), args),
+                };
+
+                analysis.apply_effect(&mut state, idx, instruction);
+
+                let dests: Vec<_> = writes
+                    .into_iter()
+                    .filter_map(|reg| var_of(&mut state, reg))
+                    .filter(|&var| variables.get_vis(var) == VariableVisibility::Visible)
+                    .collect();
+                if dests.is_empty() {
+                    stmts.push(Stmt {
+                        kind: StmtKind::Expr(call.clone()),
+                        source: None,
+                    });
+                }
+                for dest in dests {
+                    stmts.push(Stmt {
+                        kind: StmtKind::Assign {
+                            dest: Expr::var(dest),
+                            value: call.clone(),
+                        },
+                        source: None,
+                    });
+                }
+            }
+        }
+
+        // The record forms (`subf.`, `andi.`, ...) also compare their result against zero.
+        if let Some(Cr0Result::Compare(result)) = instruction.cr0_result() {
+            build_crf_assignments(
+                &state,
+                def_use_map,
+                variables,
+                &mut stmts,
+                Crf(0),
+                Expr::var(variables.id_by_gpr(result, &state)),
+                Expr {
+                    kind: ExprKind::Immediate16(0),
+                },
+            );
         }
     }
 
+    set_source(&mut stmts[first_stmt..], current);
+
     BuildPathResult {
         stmts,
         has_return_value,
@@ -1114,6 +1931,765 @@ fn build_path(
     }
 }
 
+fn for_each_var_in_expr(expr: &Expr, f: &mut impl FnMut(VarId)) {
+    match expr.kind {
+        ExprKind::Var(var_id) | ExprKind::AddrOf(var_id) => f(var_id),
+        ExprKind::Deref(_, ref address) => for_each_var_in_expr(address, f),
+        ExprKind::Unary(UnaryExpr { ref operand, .. }) | ExprKind::Cast(_, ref operand) => {
+            for_each_var_in_expr(operand, f)
+        }
+        ExprKind::Binary(BinaryExpr {
+            ref left,
+            ref right,
+            ..
+        }) => {
+            for_each_var_in_expr(left, f);
+            for_each_var_in_expr(right, f);
+        }
+        ExprKind::FnCall(ref target, ref args) => {
+            if let FnCallTarget::Indirect(var_id) = *target {
+                f(var_id);
+            }
+            args.iter().for_each(|arg| for_each_var_in_expr(arg, f));
+        }
+        ExprKind::Immediate16(_) | ExprKind::Immediate32(_) => {}
+    }
+}
+
+/// Calls `f` with every statement, including the ones nested in ifs and loops.
+fn for_each_stmt(stmts: &[Stmt], f: &mut impl FnMut(&Stmt)) {
+    for stmt in stmts {
+        f(stmt);
+        match stmt.kind {
+            StmtKind::If {
+                ref then_stmts,
+                ref else_stmts,
+                ..
+            } => {
+                for_each_stmt(then_stmts, f);
+                for_each_stmt(else_stmts, f);
+            }
+            StmtKind::While { ref body, .. }
+            | StmtKind::DoWhile { ref body, .. }
+            | StmtKind::For { ref body, .. } => for_each_stmt(body, f),
+            StmtKind::Assign { .. }
+            | StmtKind::Expr(_)
+            | StmtKind::Return(_)
+            | StmtKind::Continue
+            | StmtKind::Break => {}
+        }
+    }
+}
+
+/// Calls `f` with every variable that `stmt` itself reads, not counting the statements nested in it.
+fn for_each_read_in_stmt(stmt: &Stmt, f: &mut impl FnMut(VarId)) {
+    match stmt.kind {
+        StmtKind::Assign {
+            ref dest,
+            ref value,
+        } => {
+            if !matches!(dest.kind, ExprKind::Var(_)) {
+                for_each_var_in_expr(dest, f);
+            }
+            for_each_var_in_expr(value, f);
+        }
+        StmtKind::Expr(ref value) | StmtKind::Return(Some(ref value)) => {
+            for_each_var_in_expr(value, f)
+        }
+        StmtKind::If { ref condition, .. }
+        | StmtKind::While { ref condition, .. }
+        | StmtKind::DoWhile { ref condition, .. } => for_each_var_in_expr(condition, f),
+        StmtKind::For {
+            ref init,
+            ref condition,
+            ref step,
+            ..
+        } => {
+            for_each_var_in_expr(init, f);
+            for_each_var_in_expr(condition, f);
+            for_each_var_in_expr(step, f);
+        }
+        StmtKind::Return(None) | StmtKind::Continue | StmtKind::Break => {}
+    }
+}
+
+/// How often each variable is read in `stmts`.
+fn count_reads(stmts: &[Stmt]) -> BTreeMap<VarId, usize> {
+    let mut reads = BTreeMap::new();
+    for_each_stmt(stmts, &mut |stmt| {
+        for_each_read_in_stmt(stmt, &mut |var_id| *reads.entry(var_id).or_default() += 1)
+    });
+    reads
+}
+
+/// Whether `stmts` contain a `continue` (or a `break`, if `include_break`) of the loop they are in, i.e. one that
+/// isn't nested in another loop.
+fn has_loop_jump(stmts: &[Stmt], include_break: bool) -> bool {
+    stmts.iter().any(|stmt| match stmt.kind {
+        StmtKind::Continue => true,
+        StmtKind::Break => include_break,
+        StmtKind::If {
+            ref then_stmts,
+            ref else_stmts,
+            ..
+        } => has_loop_jump(then_stmts, include_break) || has_loop_jump(else_stmts, include_break),
+        _ => false,
+    })
+}
+
+/// Turns the `while (1)` loops that `build_path` emits into `while (c)`, `do { ... } while (c);` and `for` loops where
+/// that doesn't change what the code does. `reads` are the [read counts](count_reads) of the whole function and
+/// `reads_before` the ones of everything before `stmts`, or `None` in a loop, where that might run again later.
+fn structure_loops(
+    stmts: Vec<Stmt>,
+    reads: &BTreeMap<VarId, usize>,
+    counted_ctrs: &BTreeSet<VarId>,
+    reads_before: &mut Option<BTreeMap<VarId, usize>>,
+) -> Vec<Stmt> {
+    let mut structured = Vec::with_capacity(stmts.len());
+    for stmt in stmts {
+        match stmt.kind {
+            StmtKind::If {
+                condition,
+                then_stmts,
+                else_stmts,
+            } => {
+                if let Some(reads_before) = reads_before {
+                    for_each_var_in_expr(&condition, &mut |var_id| {
+                        *reads_before.entry(var_id).or_default() += 1
+                    });
+                }
+                structured.push(Stmt {
+                    kind: StmtKind::If {
+                        condition,
+                        then_stmts: structure_loops(then_stmts, reads, counted_ctrs, reads_before),
+                        else_stmts: structure_loops(else_stmts, reads, counted_ctrs, reads_before),
+                    },
+                    source: stmt.source,
+                });
+            }
+            StmtKind::While { condition, body } => {
+                let outer_reads_before = reads_before.take();
+                let body = structure_loops(body, reads, counted_ctrs, &mut None);
+                *reads_before = outer_reads_before;
+
+                let mut loop_reads = count_reads(&body);
+                for_each_var_in_expr(&condition, &mut |var_id| {
+                    *loop_reads.entry(var_id).or_default() += 1
+                });
+                structure_loop(
+                    condition,
+                    body,
+                    reads,
+                    counted_ctrs,
+                    reads_before.as_ref(),
+                    &mut structured,
+                );
+                if let Some(reads_before) = reads_before {
+                    for (var_id, count) in loop_reads {
+                        *reads_before.entry(var_id).or_default() += count;
+                    }
+                }
+            }
+            kind => {
+                let stmt = Stmt {
+                    kind,
+                    source: stmt.source,
+                };
+                if let Some(reads_before) = reads_before {
+                    for_each_read_in_stmt(&stmt, &mut |var_id| {
+                        *reads_before.entry(var_id).or_default() += 1
+                    });
+                }
+                structured.push(stmt);
+            }
+        }
+    }
+    structured
+}
+
+/// Appends the structured form of `while (condition) { body }` to `stmts`.
+///
+/// Every loop comes out of `build_path` as `while (1) { s; if (c) { x; continue; } else { y; break; } }` (possibly with
+/// the arms swapped), where `s` is what happens before the loop decides whether to go on. If `s` only computes the
+/// condition, that's a `while (c) { x } y`. If `x` instead only copies the values for the next iteration, it's
+/// `do { s; x } while (c); y`, as long as nothing after the loop reads what `x` copied.
+fn structure_loop(
+    condition: Expr,
+    body: Vec<Stmt>,
+    reads: &BTreeMap<VarId, usize>,
+    counted_ctrs: &BTreeSet<VarId>,
+    reads_before: Option<&BTreeMap<VarId, usize>>,
+    stmts: &mut Vec<Stmt>,
+) {
+    let Some((
+        Stmt {
+            kind:
+                StmtKind::If {
+                    condition: exit_condition,
+                    then_stmts,
+                    else_stmts,
+                },
+            ..
+        },
+        before_exit,
+    )) = body.split_last()
+    else {
+        stmts.push(Stmt {
+            kind: StmtKind::While { condition, body },
+            source: None,
+        });
+        return;
+    };
+    let (exit_condition, stay, leave) = match (
+        then_stmts
+            .split_last()
+            .map(|(last, rest)| (&last.kind, rest)),
+        else_stmts
+            .split_last()
+            .map(|(last, rest)| (&last.kind, rest)),
+    ) {
+        (Some((StmtKind::Continue, stay)), Some((StmtKind::Break, leave))) => {
+            (exit_condition.clone(), stay, leave)
+        }
+        (Some((StmtKind::Break, leave)), Some((StmtKind::Continue, stay))) => {
+            (!exit_condition.clone(), stay, leave)
+        }
+        _ => {
+            stmts.push(Stmt {
+                kind: StmtKind::While { condition, body },
+                source: None,
+            });
+            return;
+        }
+    };
+
+    let reads_of = |stmts: &[Stmt], var_id: VarId| {
+        count_reads(stmts).get(&var_id).copied().unwrap_or_default()
+    };
+    let condition_var = match exit_condition.kind {
+        ExprKind::Var(var_id) => Some((var_id, false)),
+        ExprKind::Unary(UnaryExpr {
+            op: UnaryOp::Not,
+            ref operand,
+        }) => match operand.kind {
+            ExprKind::Var(var_id) => Some((var_id, true)),
+            _ => None,
+        },
+        _ => None,
+    };
+    // `y` moves out of the loop, so a `break` or `continue` in it would belong to another loop.
+    let is_exit = !has_loop_jump(leave, true);
+
+    let while_condition = match *before_exit {
+        [] => Some(exit_condition.clone()),
+        // `v = a < b; if (v) ...` where nothing else reads `v` can just test `a < b`.
+        [
+            Stmt {
+                kind:
+                    StmtKind::Assign {
+                        dest:
+                            Expr {
+                                kind: ExprKind::Var(dest),
+                            },
+                        ref value,
+                    },
+                ..
+            },
+        ] => match condition_var {
+            Some((var_id, negated)) if var_id == dest && reads.get(&dest) == Some(&1) => {
+                Some(if negated {
+                    !value.clone()
+                } else {
+                    value.clone()
+                })
+            }
+            _ => None,
+        },
+        _ => None,
+    };
+    let reads_of_condition = |var_id: VarId| {
+        let mut count = 0;
+        for_each_var_in_expr(&exit_condition, &mut |read| {
+            count += usize::from(read == var_id)
+        });
+        count
+    };
+    // Copying the values for the next iteration once more when the loop exits is harmless if nothing reads them
+    // afterwards, i.e. if the only reads are in the loop or before it.
+    let only_copies_for_next_iteration = || {
+        stay.iter().all(|stmt| match stmt.kind {
+            StmtKind::Assign {
+                dest: Expr {
+                    kind: ExprKind::Var(dest),
+                },
+                ..
+            } => {
+                reads_of_condition(dest) == 0
+                    && reads.get(&dest).copied().unwrap_or_default()
+                        == reads_of(before_exit, dest)
+                            + reads_of(stay, dest)
+                            + reads_before
+                                .and_then(|r| r.get(&dest))
+                                .copied()
+                                .unwrap_or_default()
+            }
+            _ => false,
+        })
+    };
+
+    if is_exit && let Some(condition) = while_condition {
+        structure_while(condition, stay.to_vec(), reads, stmts);
+    } else if is_exit && !has_loop_jump(before_exit, false) && only_copies_for_next_iteration() {
+        if structure_counted_loop(
+            &exit_condition,
+            before_exit,
+            stay,
+            reads,
+            counted_ctrs,
+            stmts,
+        ) {
+            stmts.extend_from_slice(leave);
+            return;
+        }
+        stmts.push(Stmt {
+            kind: StmtKind::DoWhile {
+                body: before_exit.iter().chain(stay).cloned().collect(),
+                condition: exit_condition,
+            },
+            source: None,
+        });
+    } else {
+        stmts.push(Stmt {
+            kind: StmtKind::While { condition, body },
+            source: None,
+        });
+        return;
+    }
+    stmts.extend_from_slice(leave);
+}
+
+/// Appends `for (ctr = n; ctr != 0; ctr = ctr - 1) { body }` to `stmts` if `do { body } while (c);` is a counted
+/// loop, i.e. a `bdnz` loop that is known to run at least once, so testing CTR before the body changes nothing. That's
+/// the case if `c` tests one of the `counted_ctrs` and the statement before the loop sets CTR. Returns whether it did.
+fn structure_counted_loop(
+    condition: &Expr,
+    before_exit: &[Stmt],
+    stay: &[Stmt],
+    reads: &BTreeMap<VarId, usize>,
+    counted_ctrs: &BTreeSet<VarId>,
+    stmts: &mut Vec<Stmt>,
+) -> bool {
+    // The decremented CTR is only read by the condition and by copying it for the next iteration.
+    let Some(&ctr) = counted_ctrs
+        .iter()
+        .find(|&&ctr| *condition == compare_ctr(ctr, BinaryOp::Ne))
+    else {
+        return false;
+    };
+    if reads.get(&ctr) != Some(&2) {
+        return false;
+    }
+    let Some((copy, old_ctr)) = stay
+        .iter()
+        .enumerate()
+        .find_map(|(i, stmt)| match stmt.kind {
+            StmtKind::Assign {
+                dest: Expr {
+                    kind: ExprKind::Var(dest),
+                },
+                ref value,
+            } if *value == Expr::var(ctr) => Some((i, dest)),
+            _ => None,
+        })
+    else {
+        return false;
+    };
+    let Some((decrement, body)) = before_exit.split_last() else {
+        return false;
+    };
+    // The step now happens after all of `stay`, so nothing after the copy may read CTR.
+    if decrement.kind != decrement_ctr(ctr, old_ctr).kind
+        || count_reads(&stay[copy + 1..]).contains_key(&old_ctr)
+    {
+        return false;
+    }
+    let Some(Stmt {
+        kind:
+            StmtKind::Assign {
+                dest: Expr {
+                    kind: ExprKind::Var(init_dest),
+                },
+                value: init,
+            },
+        ..
+    }) = stmts.last()
+    else {
+        return false;
+    };
+    if *init_dest != old_ctr {
+        return false;
+    }
+
+    let init = init.clone();
+    let init_source = stmts.pop().and_then(|stmt| stmt.source);
+    let StmtKind::Assign { value: step, .. } = decrement.kind.clone() else {
+        unreachable!()
+    };
+    stmts.push(Stmt {
+        kind: StmtKind::For {
+            var: old_ctr,
+            init,
+            condition: compare_ctr(old_ctr, BinaryOp::Ne),
+            step,
+            body: body
+                .iter()
+                .chain(&stay[..copy])
+                .chain(&stay[copy + 1..])
+                .cloned()
+                .collect(),
+        },
+        // The header of the loop is written where its initialization was.
+        source: init_source,
+    });
+    true
+}
+
+/// Appends `while (condition) { body }` to `stmts`, or a `for` loop if the statement before it initializes a variable
+/// that the condition tests and the body ends with stepping it.
+fn structure_while(
+    condition: Expr,
+    mut body: Vec<Stmt>,
+    reads: &BTreeMap<VarId, usize>,
+    stmts: &mut Vec<Stmt>,
+) {
+    let mut condition_reads = Vec::new();
+    for_each_var_in_expr(&condition, &mut |var_id| condition_reads.push(var_id));
+
+    if let Some(Stmt {
+        kind:
+            StmtKind::Assign {
+                dest:
+                    Expr {
+                        kind: ExprKind::Var(var),
+                    },
+                value: init,
+            },
+        ..
+    }) = stmts.last()
+        && let Some(Stmt {
+            kind:
+                StmtKind::Assign {
+                    dest:
+                        Expr {
+                            kind: ExprKind::Var(step_var),
+                        },
+                    value: step,
+                },
+            ..
+        }) = body.last()
+        && step_var == var
+        && condition_reads.contains(var)
+        // A `continue` in the body of a `for` would step the variable, which the loop didn't do.
+        && !has_loop_jump(&body[..body.len() - 1], false)
+    {
+        let var = *var;
+        let init = init.clone();
+        let mut step = step.clone();
+        let init_source = stmts.pop().and_then(|stmt| stmt.source);
+        body.pop();
+
+        // `t = v + 1; v = t;` steps by `v + 1` if that's the only read of `t`.
+        if let ExprKind::Var(temp) = step.kind
+            && reads.get(&temp) == Some(&1)
+            && let Some(Stmt {
+                kind:
+                    StmtKind::Assign {
+                        dest:
+                            Expr {
+                                kind: ExprKind::Var(dest),
+                            },
+                        value,
+                    },
+                ..
+            }) = body.last()
+            && *dest == temp
+        {
+            step = value.clone();
+            body.pop();
+        }
+
+        stmts.push(Stmt {
+            kind: StmtKind::For {
+                var,
+                init,
+                condition,
+                step,
+                body,
+            },
+            source: init_source,
+        });
+    } else {
+        stmts.push(Stmt {
+            kind: StmtKind::While { condition, body },
+            source: None,
+        });
+    }
+}
+
+/// Replaces `v = e; s` by `s` with `e` in place of `v`, for the `single_use` variables that are read by the statement
+/// right after their assignment. Calls stay statements of their own, so that they keep their order.
+fn inline_single_use_vars(stmts: &mut Vec<Stmt>, single_use: &BTreeSet<VarId>) {
+    for stmt in stmts.iter_mut() {
+        match stmt.kind {
+            StmtKind::If {
+                ref mut then_stmts,
+                ref mut else_stmts,
+                ..
+            } => {
+                inline_single_use_vars(then_stmts, single_use);
+                inline_single_use_vars(else_stmts, single_use);
+            }
+            StmtKind::While { ref mut body, .. }
+            | StmtKind::DoWhile { ref mut body, .. }
+            | StmtKind::For { ref mut body, .. } => inline_single_use_vars(body, single_use),
+            StmtKind::Assign { .. }
+            | StmtKind::Expr(_)
+            | StmtKind::Return(_)
+            | StmtKind::Continue
+            | StmtKind::Break => {}
+        }
+    }
+
+    let mut i = 0;
+    while i + 1 < stmts.len() {
+        let (stmt, rest) = stmts[i..].split_first_mut().unwrap();
+        if let StmtKind::Assign {
+            dest: Expr {
+                kind: ExprKind::Var(var_id),
+            },
+            ref value,
+        } = stmt.kind
+            && single_use.contains(&var_id)
+            && !has_call(value)
+            && substitute_in_stmt(&mut rest[0], var_id, value)
+        {
+            rest[0].source = match (stmt.source, rest[0].source) {
+                (Some(inlined), Some(into)) => Some(inlined.join(into)),
+                (inlined, into) => inlined.or(into),
+            };
+            stmts.remove(i);
+            // The statement before might be read by the one the value was just moved into.
+            i = i.saturating_sub(1);
+        } else {
+            i += 1;
+        }
+    }
+}
+
+fn has_call(expr: &Expr) -> bool {
+    match expr.kind {
+        ExprKind::FnCall(..) => true,
+        ExprKind::Deref(_, ref address) => has_call(address),
+        ExprKind::Unary(UnaryExpr { ref operand, .. }) | ExprKind::Cast(_, ref operand) => {
+            has_call(operand)
+        }
+        ExprKind::Binary(BinaryExpr {
+            ref left,
+            ref right,
+            ..
+        }) => has_call(left) || has_call(right),
+        ExprKind::Var(_)
+        | ExprKind::AddrOf(_)
+        | ExprKind::Immediate16(_)
+        | ExprKind::Immediate32(_) => false,
+    }
+}
+
+/// Replaces the read of `var_id` in the expressions of `stmt` itself (not in nested statements, and not in loop
+/// conditions, which are evaluated more than once) with `value`. Returns whether there was such a read.
+fn substitute_in_stmt(stmt: &mut Stmt, var_id: VarId, value: &Expr) -> bool {
+    match stmt.kind {
+        StmtKind::Assign {
+            ref mut dest,
+            value: ref mut assigned,
+        } => {
+            (!matches!(dest.kind, ExprKind::Var(_)) && substitute(dest, var_id, value))
+                || substitute(assigned, var_id, value)
+        }
+        StmtKind::Expr(ref mut expr)
+        | StmtKind::Return(Some(ref mut expr))
+        | StmtKind::If {
+            condition: ref mut expr,
+            ..
+        } => substitute(expr, var_id, value),
+        StmtKind::Return(None)
+        | StmtKind::While { .. }
+        | StmtKind::DoWhile { .. }
+        | StmtKind::For { .. }
+        | StmtKind::Continue
+        | StmtKind::Break => false,
+    }
+}
+
+/// Replaces `var_id` in `expr` with `value`, folding the two halves of a `lis`/`addi` constant back together and
+/// negations into comparisons.
+fn substitute(expr: &mut Expr, var_id: VarId, value: &Expr) -> bool {
+    match expr.kind {
+        ExprKind::Var(id) if id == var_id => {
+            *expr = value.clone();
+            true
+        }
+        ExprKind::Deref(_, ref mut address) => substitute(address, var_id, value),
+        // `!c` becomes e.g. `a != b` when the comparison is moved into it.
+        ExprKind::Unary(UnaryExpr {
+            op: UnaryOp::Not,
+            ref mut operand,
+        }) => {
+            if !substitute(operand, var_id, value) {
+                return false;
+            }
+            *expr = !(**operand).clone();
+            true
+        }
+        ExprKind::Unary(UnaryExpr {
+            ref mut operand, ..
+        })
+        | ExprKind::Cast(_, ref mut operand) => substitute(operand, var_id, value),
+        ExprKind::Binary(BinaryExpr {
+            op,
+            ref mut left,
+            ref mut right,
+        }) => {
+            if !substitute(left, var_id, value) && !substitute(right, var_id, value) {
+                return false;
+            }
+            if let (BinaryOp::Add, ExprKind::Immediate32(high), ExprKind::Immediate16(low)) =
+                (op, &left.kind, &right.kind)
+            {
+                expr.kind = ExprKind::Immediate32(high.wrapping_add_signed((*low).into()));
+            }
+            true
+        }
+        ExprKind::FnCall(_, ref mut args) => {
+            args.iter_mut().any(|arg| substitute(arg, var_id, value))
+        }
+        ExprKind::Var(_)
+        | ExprKind::AddrOf(_)
+        | ExprKind::Immediate16(_)
+        | ExprKind::Immediate32(_) => false,
+    }
+}
+
+/// Collects every variable the body refers to that needs a declaration, i.e. anything but the parameters and r1.
+/// Variables that are only ever assigned an address are pointers, as are the `pointers` that the value analysis found,
+/// and everything else is assumed to be a `u32`.
+/// The types of the FPRs that `inst` reads and writes, or `None` if it keeps the type of its operand, like `fmr`.
+fn fpr_tys(inst: &Instruction) -> Option<(TyKind, TyKind)> {
+    match inst {
+        Instruction::Fmr { .. }
+        | Instruction::Fneg { .. }
+        | Instruction::Fabs { .. }
+        | Instruction::Fnabs { .. } => None,
+        Instruction::Frsp { .. } => Some((TyKind::F64, TyKind::F32)),
+        Instruction::Lfs { .. }
+        | Instruction::Lfsu { .. }
+        | Instruction::Stfs { .. }
+        | Instruction::Stfsu { .. }
+        | Instruction::Fadds { .. }
+        | Instruction::Fsubs { .. }
+        | Instruction::Fmuls { .. }
+        | Instruction::Fdivs { .. }
+        | Instruction::Fmadds { .. }
+        | Instruction::Fmsubs { .. }
+        | Instruction::Fnmadds { .. }
+        | Instruction::Fnmsubs { .. } => Some((TyKind::F32, TyKind::F32)),
+        _ => Some((TyKind::F64, TyKind::F64)),
+    }
+}
+
+/// The type of `var_id` if it holds a float. Floats are doubles unless every instruction that touches them is single
+/// precision.
+fn float_ty(
+    var_id: VarId,
+    variables: &Variables,
+    float_tys: &BTreeMap<VarId, TyKind>,
+) -> Option<TyKind> {
+    float_tys
+        .get(&var_id)
+        .copied()
+        .or_else(|| variables.get(var_id).is_fpr().then_some(TyKind::F64))
+}
+
+fn infer_locals(
+    stmts: &[Stmt],
+    params: &[Parameter],
+    variables: &Variables,
+    pointers: &BTreeSet<VarId>,
+    float_tys: &BTreeMap<VarId, TyKind>,
+) -> Vec<Local> {
+    // Maps each variable to whether all of its assignments are addresses, or None if it is never assigned.
+    let mut locals = BTreeMap::new();
+    for_each_stmt(stmts, &mut |stmt| {
+        let mut used = |var_id: VarId| {
+            locals.entry(var_id.0).or_insert(None);
+        };
+        match stmt.kind {
+            StmtKind::Assign {
+                ref dest,
+                ref value,
+            } => {
+                for_each_var_in_expr(value, &mut used);
+                if let ExprKind::Var(var_id) = dest.kind {
+                    let is_ptr =
+                        matches!(value.kind, ExprKind::AddrOf(_)) || pointers.contains(&var_id);
+                    let all_ptrs = locals.entry(var_id.0).or_insert(None);
+                    *all_ptrs = Some(all_ptrs.unwrap_or(true) && is_ptr);
+                } else {
+                    for_each_var_in_expr(dest, &mut used);
+                }
+            }
+            StmtKind::Expr(ref value) | StmtKind::Return(Some(ref value)) => {
+                for_each_var_in_expr(value, &mut used)
+            }
+            StmtKind::If { ref condition, .. }
+            | StmtKind::While { ref condition, .. }
+            | StmtKind::DoWhile { ref condition, .. } => for_each_var_in_expr(condition, &mut used),
+            StmtKind::For {
+                var,
+                ref init,
+                ref condition,
+                ref step,
+                ..
+            } => {
+                for_each_var_in_expr(init, &mut used);
+                for_each_var_in_expr(condition, &mut used);
+                for_each_var_in_expr(step, &mut used);
+                locals.entry(var.0).or_insert(Some(false));
+            }
+            StmtKind::Return(None) | StmtKind::Continue | StmtKind::Break => {}
+        }
+    });
+
+    locals
+        .into_iter()
+        .map(|(var_id, all_ptrs)| (VarId(var_id), all_ptrs == Some(true)))
+        .filter(|&(var_id, _)| {
+            !variables.get(var_id).is_rsp() && params.iter().all(|param| param.var_id != var_id)
+        })
+        .map(|(var_id, is_ptr)| Local {
+            var_id,
+            ty: Ty {
+                kind: match float_ty(var_id, variables, float_tys) {
+                    Some(ty) => ty,
+                    None if is_ptr => TyKind::Ptr,
+                    None => TyKind::U32,
+                },
+            },
+        })
+        .collect()
+}
+
 pub fn build(
     AstBuildParams {
         instructions,
@@ -1124,6 +2700,11 @@ pub fn build(
         variables,
         succs,
         loops,
+        returns_pointer,
+        lr_save_restore,
+        arguments,
+        counted_loops,
+        pointer_writes,
     }: AstBuildParams,
 ) -> Ast {
     // Infer parameters
@@ -1162,18 +2743,178 @@ pub fn build(
         variables,
         def_use_map,
         loops,
+        lr_save_restore,
+        arguments,
         succs,
         None,
         None,
     );
 
+    struct Writes<'a>(&'a mut Vec<Register>);
+    impl RegisterVisitor for Writes<'_> {
+        fn write_gpr(&mut self, gpr: Gpr) {
+            self.0.push(Register::Gpr(gpr));
+        }
+        fn write_fpr(&mut self, fpr: Fpr) {
+            self.0.push(Register::Fpr(fpr));
+        }
+    }
+
+    /// Records that `var_id` is used as a `ty`, returning whether that changed its type.
+    fn join_float_ty(float_tys: &mut BTreeMap<VarId, TyKind>, var_id: VarId, ty: TyKind) -> bool {
+        let joined = match float_tys.get(&var_id) {
+            Some(&old) if old != ty => TyKind::F64,
+            _ => ty,
+        };
+        float_tys.insert(var_id, joined) != Some(joined)
+    }
+
+    // The CTR after the `bdnz` of each counted loop, which is what the loop tests.
+    let mut counted_ctrs = BTreeSet::new();
+    // Whether every instruction that writes a variable stores a pointer in it.
+    let mut pointers = BTreeMap::new();
+    // The precision of the floats, and the float variables that are copies of each other, e.g. by `fmr`.
+    let mut float_tys = BTreeMap::new();
+    let mut float_copies = Vec::new();
+    local_generations.for_each_with_input(analysis, |cx| {
+        let fpr_var = |fpr: Fpr, state: &BlockState| {
+            let generation = state.registers.fprs[fpr.0 as usize].generation;
+            variables.optional_id_by_reg(Register::Fpr(fpr), generation)
+        };
+        let mut read = Vec::new();
+        cx.item()
+            .for_each_read_fpr(|fpr| read.extend(fpr_var(fpr, cx.state())));
+        let tys = fpr_tys(&cx.item());
+        if let Some((read_ty, _)) = tys {
+            for &read in &read {
+                join_float_ty(&mut float_tys, read, read_ty);
+            }
+        }
+        let mut written = Vec::new();
+        cx.item().visit_registers(Writes(&mut written));
+        cx.effect();
+        if counted_loops.contains(&cx.idx()) {
+            let generation = cx.state().registers.sprs.ctr.generation;
+            counted_ctrs.insert(variables.id_by_reg(Register::Spr(Spr::Ctr), generation));
+        }
+        for reg in written {
+            match reg {
+                Register::Gpr(gpr) => {
+                    let generation = cx.state().registers.gprs[gpr.0 as usize].generation;
+                    if let Some(var_id) = variables.optional_id_by_reg(reg, generation) {
+                        let is_ptr = pointer_writes.contains(&(cx.idx(), gpr));
+                        *pointers.entry(var_id).or_insert(true) &= is_ptr;
+                    }
+                }
+                Register::Fpr(fpr) => {
+                    let Some(var_id) = fpr_var(fpr, cx.state()) else {
+                        continue;
+                    };
+                    match tys {
+                        Some((_, write_ty)) => {
+                            join_float_ty(&mut float_tys, var_id, write_ty);
+                        }
+                        None => float_copies.extend(read.iter().map(|&read| (read, var_id))),
+                    }
+                }
+                _ => {}
+            }
+        }
+    });
+    // Plain copies between variables, which includes the stack slots that floats are spilled to and the merges of
+    // floats at the end of an if.
+    for_each_stmt(&stmts, &mut |stmt| {
+        if let StmtKind::Assign {
+            dest: Expr {
+                kind: ExprKind::Var(dest),
+            },
+            value: Expr {
+                kind: ExprKind::Var(source),
+            },
+        } = stmt.kind
+        {
+            float_copies.push((source, dest));
+        }
+    });
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &(a, b) in &float_copies {
+            for (from, to) in [(a, b), (b, a)] {
+                if let Some(&ty) = float_tys.get(&from) {
+                    changed |= join_float_ty(&mut float_tys, to, ty);
+                }
+            }
+        }
+    }
+    let pointers = pointers
+        .into_iter()
+        .filter_map(|(var_id, is_ptr)| is_ptr.then_some(var_id))
+        .collect();
+
+    // Float parameters come after the integer ones, since there's no telling how they were interleaved.
+    for reg in 1..=8 {
+        let register = Register::Fpr(Fpr(reg));
+        if def_use_map.has_uses(register, Generation::INITIAL) {
+            let var_id = variables.id_by_reg(register, Generation::INITIAL);
+            params.push(Parameter {
+                var_id,
+                ty: Ty {
+                    kind: float_ty(var_id, variables, &float_tys).unwrap(),
+                },
+            });
+        }
+    }
+
+    let mut return_float_ty = None;
+    for_each_stmt(&stmts, &mut |stmt| {
+        if let StmtKind::Return(Some(Expr {
+            kind: ExprKind::Var(var_id),
+        })) = stmt.kind
+        {
+            return_float_ty = return_float_ty.or(float_ty(var_id, variables, &float_tys));
+        }
+    });
+
+    let reads = count_reads(&stmts);
+    let mut stmts = structure_loops(stmts, &reads, &counted_ctrs, &mut Some(BTreeMap::new()));
+    // Variables that are assigned once and read once, other than the parameters, whose initial value counts as an
+    // assignment too.
+    let mut assignments = BTreeMap::new();
+    for_each_stmt(&stmts, &mut |stmt| match stmt.kind {
+        StmtKind::Assign {
+            dest: Expr {
+                kind: ExprKind::Var(var_id),
+            },
+            ..
+        } => *assignments.entry(var_id).or_default() += 1,
+        StmtKind::For { var, .. } => *assignments.entry(var).or_default() += 2,
+        _ => {}
+    });
+    let single_use = count_reads(&stmts)
+        .into_iter()
+        .filter(|&(var_id, reads)| {
+            reads == 1
+                && assignments.get(&var_id) == Some(&1)
+                && params.iter().all(|param| param.var_id != var_id)
+                && !variables.get(var_id).is_rsp()
+        })
+        .map(|(var_id, _)| var_id)
+        .collect();
+    inline_single_use_vars(&mut stmts, &single_use);
+
     let function = Function {
         name: format!("{fn_address:#X}"),
-        return_ty: if has_return_value {
+        return_ty: if let Some(ty) = return_float_ty {
+            Ty { kind: ty }
+        } else if has_return_value && returns_pointer {
+            Ty { kind: TyKind::Ptr }
+        } else if has_return_value {
             Ty { kind: TyKind::U32 } // TODO: figure out the type based on its uses?
         } else {
             Ty { kind: TyKind::Void }
         },
+        locals: infer_locals(&stmts, &params, variables, &pointers, &float_tys),
         params,
         stmts,
     };
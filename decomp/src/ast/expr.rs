@@ -1,31 +1,93 @@
-use crate::ast::stmt::VarId;
+use std::ops::Not;
+
+use crate::ast::{stmt::VarId, ty::TyKind};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Expr {
     pub kind: ExprKind,
 }
 
+impl Not for Expr {
+    type Output = Self;
+
+    /// Negates the expression, flipping the operator of a comparison instead of wrapping it, e.g. `a != b` rather than
+    /// `!(a == b)`.
+    fn not(self) -> Self {
+        match self.kind {
+            ExprKind::Binary(BinaryExpr { op, left, right }) if let Some(op) = op.negated() => {
+                Self {
+                    kind: ExprKind::Binary(BinaryExpr { op, left, right }),
+                }
+            }
+            kind => Self {
+                kind: ExprKind::Unary(UnaryExpr {
+                    op: UnaryOp::Not,
+                    operand: Box::new(Self { kind }),
+                }),
+            },
+        }
+    }
+}
+
 impl Expr {
     pub fn var(id: VarId) -> Self {
         Self {
             kind: ExprKind::Var(id),
         }
     }
+
+    /// The word at `base + offset`, or at `offset` if there is no base register.
+    pub fn deref(base: Option<VarId>, offset: i16) -> Self {
+        Self::deref_as(TyKind::U32, base, offset)
+    }
+
+    /// Like [`Expr::deref`], but accesses a value of type `ty` instead of a word.
+    pub fn deref_as(ty: TyKind, base: Option<VarId>, offset: i16) -> Self {
+        let offset = Expr {
+            kind: ExprKind::Immediate16(offset),
+        };
+        let address = match base {
+            Some(base) if offset.kind == ExprKind::Immediate16(0) => Expr::var(base),
+            Some(base) => Expr {
+                kind: ExprKind::Binary(BinaryExpr {
+                    op: BinaryOp::Add,
+                    left: Box::new(Expr::var(base)),
+                    right: Box::new(offset),
+                }),
+            },
+            None => offset,
+        };
+        Self {
+            kind: ExprKind::Deref(ty, Box::new(address)),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ExprKind {
     Var(VarId),
     AddrOf(VarId),
+    /// A memory access of the given type at the given address.
+    Deref(TyKind, Box<Expr>),
+    /// A conversion to the given type, e.g. the `(float)` of `frsp`.
+    Cast(TyKind, Box<Expr>),
     Unary(UnaryExpr),
     Binary(BinaryExpr),
     Immediate16(i16),
+    /// A full 32-bit constant, e.g. the upper half set by `lis`. Written in hex since these tend to be addresses.
+    Immediate32(u32),
     FnCall(FnCallTarget, Vec<Expr>),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FnCallTarget {
     Addr(u32),
+    /// A call through a function pointer, e.g. `blrl` or `bctrl`.
+    Indirect(VarId),
+    /// A compiler intrinsic like `__mfspr`, for instructions that have no C equivalent.
+    Intrinsic(&'static str),
+    /// An instruction that the decompiler doesn't model, written as an intrinsic named after it, e.g. `__crxor`.
+    Instruction(&'static str),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -39,14 +101,40 @@ pub struct BinaryExpr {
 pub enum BinaryOp {
     Add,
     Sub,
+    Mul,
+    Div,
     Xor,
     Rhs,
     Lt,
+    Le,
     Gt,
     Ge,
     Eq,
     Ne,
     BitAnd,
+    LogicalAnd,
+}
+
+impl BinaryOp {
+    /// The comparison that holds exactly when this one doesn't, or `None` if this isn't a comparison.
+    pub fn negated(self) -> Option<Self> {
+        match self {
+            BinaryOp::Lt => Some(BinaryOp::Ge),
+            BinaryOp::Le => Some(BinaryOp::Gt),
+            BinaryOp::Gt => Some(BinaryOp::Le),
+            BinaryOp::Ge => Some(BinaryOp::Lt),
+            BinaryOp::Eq => Some(BinaryOp::Ne),
+            BinaryOp::Ne => Some(BinaryOp::Eq),
+            BinaryOp::Add
+            | BinaryOp::Sub
+            | BinaryOp::Mul
+            | BinaryOp::Div
+            | BinaryOp::Xor
+            | BinaryOp::Rhs
+            | BinaryOp::BitAnd
+            | BinaryOp::LogicalAnd => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -58,4 +146,6 @@ pub struct UnaryExpr {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum UnaryOp {
     Not,
+    BitNot,
+    Neg,
 }
@@ -14,6 +14,8 @@ pub struct Function {
     pub name: String,
     pub return_ty: Ty,
     pub params: Vec<Parameter>,
+    /// Variables other than the parameters, declared at the start of the body.
+    pub locals: Vec<Local>,
     pub stmts: Vec<Stmt>,
 }
 
@@ -21,3 +23,8 @@ pub struct Parameter {
     pub var_id: VarId,
     pub ty: Ty,
 }
+
+pub struct Local {
+    pub var_id: VarId,
+    pub ty: Ty,
+}
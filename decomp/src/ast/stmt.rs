@@ -2,11 +2,39 @@ use std::ops::{BitAnd, BitOr};
 
 use bitflags::bitflags;
 
-use crate::ast::expr::Expr;
+use crate::{ast::expr::Expr, dataflow::InstId};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Stmt {
     pub kind: StmtKind,
+    /// The instructions this statement was decompiled from, or `None` for the ones that aren't made by a single
+    /// instruction, like loops.
+    pub source: Option<SourceSpan>,
+}
+
+/// A range of instructions, from the first to the last one (inclusive).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SourceSpan {
+    pub start: InstId,
+    pub end: InstId,
+}
+
+impl SourceSpan {
+    pub fn at(inst: InstId) -> Self {
+        Self {
+            start: inst,
+            end: inst,
+        }
+    }
+
+    /// The span that covers both `self` and `other`.
+    #[must_use]
+    pub fn join(self, other: Self) -> Self {
+        Self {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -15,6 +43,8 @@ pub enum StmtKind {
         dest: Expr,
         value: Expr,
     },
+    /// An expression that is only evaluated for its side effects, e.g. an intrinsic like `__mtspr`.
+    Expr(Expr),
     Return(Option<Expr>),
     If {
         condition: Expr,
@@ -25,11 +55,24 @@ pub enum StmtKind {
         condition: Expr,
         body: Vec<Stmt>,
     },
+    /// A loop that tests its condition after the body, i.e. a `do { ... } while (condition);`.
+    DoWhile {
+        body: Vec<Stmt>,
+        condition: Expr,
+    },
+    /// `for (var = init; condition; var = step) { ... }`
+    For {
+        var: VarId,
+        init: Expr,
+        condition: Expr,
+        step: Expr,
+        body: Vec<Stmt>,
+    },
     Continue,
     Break,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct VarId(pub u32);
 
 impl From<usize> for VarId {
@@ -79,6 +122,8 @@ bitflags! {
     pub struct VariableFlags: u8 {
         const VISIBLE = 1 << 0;
         const RSP = 1 << 1;
+        /// The variable lives in a floating point register.
+        const FPR = 1 << 2;
     }
 }
 
@@ -118,4 +163,8 @@ impl Variable {
     pub fn is_rsp(&self) -> bool {
         self.flags.contains(VariableFlags::RSP)
     }
+
+    pub fn is_fpr(&self) -> bool {
+        self.flags.contains(VariableFlags::FPR)
+    }
 }
@@ -2,7 +2,14 @@ pub struct Ty {
     pub kind: TyKind,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum TyKind {
     U32,
     Void,
+    /// A pointer to memory of unknown type, i.e. `void *`.
+    Ptr,
+    /// A single precision float, as produced by `lfs` or `fadds`.
+    F32,
+    /// A double precision float, as produced by `lfd` or `fadd`.
+    F64,
 }
@@ -1,14 +1,16 @@
 use core::fmt;
+use std::collections::BTreeMap;
 
 use crate::{
     ast::{
         Ast,
         expr::{BinaryExpr, BinaryOp, Expr, ExprKind, FnCallTarget, UnaryExpr, UnaryOp},
-        item::{Function, Item, ItemKind, Parameter},
-        stmt::{Stmt, StmtKind, VarId},
+        item::{Function, Item, ItemKind, Local, Parameter},
+        stmt::{SourceSpan, Stmt, StmtKind, VarId},
         ty::{self, TyKind},
     },
     dataflow::variables::Variables,
+    symbols::SymbolMap,
 };
 
 pub trait Writer {
@@ -16,15 +18,21 @@ pub trait Writer {
     fn write_fmt(&mut self, args: fmt::Arguments);
     fn with_scope(&mut self, f: &mut dyn FnMut(&mut dyn Writer));
     fn next_line(&mut self);
+    /// Called before writing a statement, with the instructions it was decompiled from.
+    fn stmt_source(&mut self, _source: SourceSpan) {}
 }
 
 pub struct WriteContext<'a> {
     pub variables: &'a Variables,
+    /// Called functions and constants that are the address of a symbol are written as the symbol's name.
+    pub symbols: &'a SymbolMap,
 }
 
 pub struct StringWriter {
     buf: String,
     indentation: u32,
+    line: usize,
+    sources: BTreeMap<usize, SourceSpan>,
 }
 
 impl StringWriter {
@@ -32,12 +40,20 @@ impl StringWriter {
         Self {
             buf: String::new(),
             indentation: 0,
+            line: 0,
+            sources: BTreeMap::new(),
         }
     }
 
     pub fn into_string(self) -> String {
         self.buf
     }
+
+    /// Like [`StringWriter::into_string`], but also returns the instructions that the statement on each line was
+    /// decompiled from, by line number.
+    pub fn into_string_with_sources(self) -> (String, BTreeMap<usize, SourceSpan>) {
+        (self.buf, self.sources)
+    }
 }
 
 impl Writer for StringWriter {
@@ -57,11 +73,16 @@ impl Writer for StringWriter {
     }
 
     fn next_line(&mut self) {
+        self.line += 1;
         self.buf.push_str("\n");
         for _ in 0..self.indentation {
             self.buf.push_str("    ");
         }
     }
+
+    fn stmt_source(&mut self, source: SourceSpan) {
+        self.sources.insert(self.line, source);
+    }
 }
 
 fn write_var_id(var_id: VarId, cx: &WriteContext<'_>, writer: &mut dyn Writer) {
@@ -72,8 +93,39 @@ fn write_var_id(var_id: VarId, cx: &WriteContext<'_>, writer: &mut dyn Writer) {
     }
 }
 
+/// How tightly an expression binds, as in C's operator precedence table: lower levels bind tighter.
+fn precedence(expr: &Expr) -> u8 {
+    match expr.kind {
+        ExprKind::Var(_)
+        | ExprKind::Immediate16(_)
+        | ExprKind::Immediate32(_)
+        | ExprKind::FnCall(..) => 1,
+        ExprKind::Unary(_) | ExprKind::AddrOf(_) | ExprKind::Deref(..) | ExprKind::Cast(..) => 2,
+        ExprKind::Binary(BinaryExpr { op, .. }) => match op {
+            BinaryOp::Mul | BinaryOp::Div => 3,
+            BinaryOp::Add | BinaryOp::Sub => 4,
+            BinaryOp::Rhs => 5,
+            BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => 6,
+            BinaryOp::Eq | BinaryOp::Ne => 7,
+            BinaryOp::BitAnd => 8,
+            BinaryOp::Xor => 9,
+            BinaryOp::LogicalAnd => 11,
+        },
+    }
+}
+
+/// Writes an operand of an operator with the given precedence, adding parentheses if it binds looser than that.
+fn write_operand(expr: &Expr, max_precedence: u8, cx: &WriteContext<'_>, writer: &mut dyn Writer) {
+    if precedence(expr) > max_precedence {
+        writer.write_str("(");
+        write_expr(expr, cx, writer);
+        writer.write_str(")");
+    } else {
+        write_expr(expr, cx, writer);
+    }
+}
+
 fn write_expr(expr: &Expr, cx: &WriteContext<'_>, writer: &mut dyn Writer) {
-    // TODO: add parentheses when necessary!
     match expr.kind {
         ExprKind::Var(var_id) => {
             write_var_id(var_id, cx, writer);
@@ -83,11 +135,17 @@ fn write_expr(expr: &Expr, cx: &WriteContext<'_>, writer: &mut dyn Writer) {
             op,
             ref right,
         }) => {
-            write_expr(left, cx, writer);
+            // All binary operators are left-associative, so only the right operand needs parentheses
+            // if it has the same precedence.
+            let precedence = precedence(expr);
+            write_operand(left, precedence, cx, writer);
             match op {
                 BinaryOp::Add => writer.write_str(" + "),
                 BinaryOp::Sub => writer.write_str(" - "),
+                BinaryOp::Mul => writer.write_str(" * "),
+                BinaryOp::Div => writer.write_str(" / "),
                 BinaryOp::Lt => writer.write_str(" < "),
+                BinaryOp::Le => writer.write_str(" <= "),
                 BinaryOp::Gt => writer.write_str(" > "),
                 BinaryOp::Ge => writer.write_str(" >= "),
                 BinaryOp::Eq => writer.write_str(" == "),
@@ -95,18 +153,40 @@ fn write_expr(expr: &Expr, cx: &WriteContext<'_>, writer: &mut dyn Writer) {
                 BinaryOp::BitAnd => writer.write_str(" & "),
                 BinaryOp::Xor => writer.write_str(" ^ "),
                 BinaryOp::Rhs => writer.write_str(" >> "),
+                BinaryOp::LogicalAnd => writer.write_str(" && "),
             }
-            write_expr(right, cx, writer);
+            write_operand(right, precedence - 1, cx, writer);
         }
         ExprKind::Unary(UnaryExpr { op, ref operand }) => {
             match op {
                 UnaryOp::Not => writer.write_str("!"),
+                UnaryOp::BitNot => writer.write_str("~"),
+                UnaryOp::Neg => writer.write_str("-"),
             }
-            write_expr(operand, cx, writer);
+            write_operand(operand, precedence(expr), cx, writer);
         }
         ExprKind::Immediate16(value) => writer.write_fmt(format_args!("{}", value)),
-        ExprKind::FnCall(FnCallTarget::Addr(addr), ref args) => {
-            writer.write_fmt(format_args!("{:#X}", addr));
+        ExprKind::Immediate32(value) => match cx.symbols.get(value) {
+            Some(name) => writer.write_fmt(format_args!("&{name}")),
+            None => writer.write_fmt(format_args!("{:#X}", value)),
+        },
+        ExprKind::FnCall(ref target, ref args) => {
+            match *target {
+                FnCallTarget::Addr(addr) => match cx.symbols.get(addr) {
+                    Some(name) => writer.write_str(name),
+                    None => writer.write_fmt(format_args!("{:#X}", addr)),
+                },
+                FnCallTarget::Indirect(var_id) => {
+                    writer.write_str("((u32 (*)())");
+                    write_var_id(var_id, cx, writer);
+                    writer.write_str(")");
+                }
+                FnCallTarget::Intrinsic(name) => writer.write_str(name),
+                FnCallTarget::Instruction(name) => {
+                    writer.write_str("__");
+                    writer.write_str(name);
+                }
+            }
             writer.write_str("(");
             for (i, arg) in args.iter().enumerate() {
                 if i > 0 {
@@ -120,10 +200,23 @@ fn write_expr(expr: &Expr, cx: &WriteContext<'_>, writer: &mut dyn Writer) {
             writer.write_str("&");
             write_var_id(var, cx, writer);
         }
+        ExprKind::Deref(ty, ref address) => {
+            writer.write_str("*(");
+            write_ty(&ty::Ty { kind: ty }, writer);
+            writer.write_str("*)");
+            write_operand(address, precedence(expr), cx, writer);
+        }
+        ExprKind::Cast(ty, ref operand) => {
+            writer.write_fmt(format_args!("({})", ty_name(ty)));
+            write_operand(operand, precedence(expr), cx, writer);
+        }
     }
 }
 
 fn write_stmt(stmt: &Stmt, cx: &WriteContext<'_>, writer: &mut dyn Writer) {
+    if let Some(source) = stmt.source {
+        writer.stmt_source(source);
+    }
     match stmt.kind {
         StmtKind::Assign {
             ref dest,
@@ -134,6 +227,10 @@ fn write_stmt(stmt: &Stmt, cx: &WriteContext<'_>, writer: &mut dyn Writer) {
             write_expr(value, cx, writer);
             writer.write_str(";");
         }
+        StmtKind::Expr(ref expr) => {
+            write_expr(expr, cx, writer);
+            writer.write_str(";");
+        }
         StmtKind::Return(ref expr) => {
             writer.write_str("return");
             if let Some(expr) = expr {
@@ -188,15 +285,70 @@ fn write_stmt(stmt: &Stmt, cx: &WriteContext<'_>, writer: &mut dyn Writer) {
             writer.next_line();
             writer.write_str("}");
         }
+        StmtKind::DoWhile {
+            ref body,
+            ref condition,
+        } => {
+            writer.write_str("do {");
+            writer.with_scope(&mut |writer| {
+                for stmt in body.iter() {
+                    writer.next_line();
+                    write_stmt(stmt, cx, writer);
+                }
+            });
+            writer.next_line();
+            writer.write_str("} while (");
+            write_expr(condition, cx, writer);
+            writer.write_str(");");
+        }
+        StmtKind::For {
+            var,
+            ref init,
+            ref condition,
+            ref step,
+            ref body,
+        } => {
+            writer.write_str("for (");
+            write_var_id(var, cx, writer);
+            writer.write_str(" = ");
+            write_expr(init, cx, writer);
+            writer.write_str("; ");
+            write_expr(condition, cx, writer);
+            writer.write_str("; ");
+            write_var_id(var, cx, writer);
+            writer.write_str(" = ");
+            write_expr(step, cx, writer);
+            writer.write_str(") {");
+            writer.with_scope(&mut |writer| {
+                for stmt in body.iter() {
+                    writer.next_line();
+                    write_stmt(stmt, cx, writer);
+                }
+            });
+            writer.next_line();
+            writer.write_str("}");
+        }
         StmtKind::Continue => writer.write_str("continue;"),
         StmtKind::Break => writer.write_str("break;"),
     }
 }
 
+fn ty_name(ty: TyKind) -> &'static str {
+    match ty {
+        TyKind::Void => "void",
+        TyKind::U32 => "u32",
+        TyKind::Ptr => "void *",
+        TyKind::F32 => "float",
+        TyKind::F64 => "double",
+    }
+}
+
+/// Writes the type of a declaration, followed by whatever separates it from the declared name.
 fn write_ty(ty: &ty::Ty, writer: &mut dyn Writer) {
-    match ty.kind {
-        TyKind::Void => writer.write_str("void"),
-        TyKind::U32 => writer.write_str("u32"),
+    let name = ty_name(ty.kind);
+    writer.write_str(name);
+    if !name.ends_with('*') {
+        writer.write_str(" ");
     }
 }
 
@@ -204,6 +356,7 @@ fn write_function(
     Function {
         return_ty,
         params,
+        locals,
         stmts,
         name,
     }: &Function,
@@ -211,7 +364,6 @@ fn write_function(
     writer: &mut dyn Writer,
 ) {
     write_ty(return_ty, writer);
-    writer.write_str(" ");
     writer.write_str(name);
     writer.write_str("(");
     for (i, &Parameter { ref ty, var_id }) in params.iter().enumerate() {
@@ -219,11 +371,16 @@ fn write_function(
             writer.write_str(", ");
         }
         write_ty(ty, writer);
-        writer.write_str(" ");
         write_var_id(var_id, cx, writer);
     }
     writer.write_str(") {");
     writer.with_scope(&mut |writer| {
+        for &Local { ref ty, var_id } in locals {
+            writer.next_line();
+            write_ty(ty, writer);
+            write_var_id(var_id, cx, writer);
+            writer.write_str(";");
+        }
         for stmt in stmts.iter() {
             writer.next_line();
             write_stmt(stmt, cx, writer);
@@ -0,0 +1,124 @@
+use std::{collections::BTreeMap, iter};
+
+use ppc32::{
+    Instruction,
+    instruction::{Gpr, RegisterVisitor},
+};
+
+use crate::{
+    dataflow::{
+        InstId, InstructionsDeref,
+        core::{self, DEFAULT_MAX_ITERATIONS, Dataflow, DataflowArgs, DataflowError, Join},
+        liveness::GprSet,
+        ssa::compute_preds_and_succs,
+    },
+    ti_utils::ti_iter,
+};
+
+/// The argument registers whose definitions reach a point on every path to it, i.e. that were either passed in as a
+/// parameter or written in this function, and not clobbered by a call since.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ReachingArguments(pub GprSet);
+
+impl Join<()> for ReachingArguments {
+    fn join(&self, other: &Self, _: &mut ()) -> Self {
+        Self(self.0.iter().filter(|&gpr| other.0.contains(gpr)).collect())
+    }
+}
+
+/// Finds the arguments of every call (and tail call) in a function. Arguments are passed in r3-r10 in order, so they
+/// are the registers from r3 up to the first one that wasn't set up for the call.
+pub struct CallArguments<'a> {
+    pub insts: &'a InstructionsDeref,
+    pub fn_address: u32,
+}
+
+impl<'a> CallArguments<'a> {
+    /// Runs the analysis over the function and returns the argument registers of every branch, which includes all
+    /// calls and tail calls.
+    pub fn run(&self) -> Result<BTreeMap<InstId, Vec<Gpr>>, DataflowError<InstId>> {
+        let mut preds = BTreeMap::default();
+        let mut succs = BTreeMap::default();
+        compute_preds_and_succs(self.insts, self.fn_address, &mut preds, &mut succs);
+
+        let results = core::run(
+            self,
+            DataflowArgs {
+                preds: &preds,
+                succs: &succs,
+                max_iterations: DEFAULT_MAX_ITERATIONS,
+            },
+        )?;
+
+        let mut arguments = BTreeMap::new();
+        results.for_each_with_input(self, |cx| {
+            let (addr, inst) = self.insts[cx.idx()];
+            if inst.branch_target(addr.0).is_some() {
+                let reaching = cx.state().0;
+                let gprs = (3..=10)
+                    .map(Gpr)
+                    .take_while(|&gpr| reaching.contains(gpr))
+                    .collect();
+                arguments.insert(cx.idx(), gprs);
+            }
+            cx.effect();
+        });
+        Ok(arguments)
+    }
+}
+
+impl<'a> Dataflow for CallArguments<'a> {
+    type Idx = InstId;
+    type BlockState = ReachingArguments;
+    type BlockItem = Instruction;
+    type RecordingState = ();
+
+    fn pre_block_record(&self, _: &mut Self::RecordingState, _: &mut Self::BlockState) {}
+
+    fn post_block_record(&self, _: &mut Self::RecordingState, _: &mut Self::BlockState) {}
+
+    fn initial_idx() -> Self::Idx {
+        InstId(0)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (Self::Idx, Self::BlockItem)> {
+        ti_iter(self.insts).map(|(i, &(_, inst))| (i, inst))
+    }
+
+    fn iter_block(&self, idx: Self::Idx) -> impl Iterator<Item = (Self::Idx, Self::BlockItem)> {
+        // `run` stops at the end of the block, so this doesn't need to know where the block ends.
+        // Branches can point past the end of the function, in which case the block is empty.
+        iter::zip(idx.0.., self.insts.get(idx..).unwrap_or_default())
+            .map(|(i, &(_, inst))| (InstId(i), inst))
+    }
+
+    /// Any of the argument registers may hold a parameter on entry. Whether it actually does is up to the caller
+    /// of the analysis to decide.
+    fn initial_state(&self) -> Self::BlockState {
+        ReachingArguments((3..=10).map(Gpr).collect())
+    }
+
+    fn apply_effect(&self, state: &mut Self::BlockState, _: Self::Idx, data: &Self::BlockItem) {
+        struct Vis<'a>(&'a mut GprSet);
+        impl RegisterVisitor for Vis<'_> {
+            fn write_gpr(&mut self, gpr: Gpr) {
+                self.0.insert(gpr);
+            }
+        }
+
+        // The callee clobbers the volatile registers, except for the return value in r3, which is written below.
+        if matches!(
+            data,
+            Instruction::Branch { link: true, .. }
+                | Instruction::Bc { link: true, .. }
+                | Instruction::Bclr { link: true, .. }
+                | Instruction::Bcctr { link: true, .. }
+        ) {
+            (0..32)
+                .map(Gpr)
+                .filter(Gpr::is_volatile)
+                .for_each(|gpr| state.0.remove(gpr));
+        }
+        data.visit_registers(Vis(&mut state.0));
+    }
+}
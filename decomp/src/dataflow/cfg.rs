@@ -0,0 +1,185 @@
+use std::ops::Range;
+
+use ppc32::{
+    Instruction,
+    instruction::{BranchOptions, compute_branch_target},
+};
+use typed_index_collections::TiVec;
+
+use crate::{
+    dataflow::{InstId, InstructionsDeref},
+    ti_utils::ti_iter,
+};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockId(pub u32);
+impl From<BlockId> for usize {
+    fn from(value: BlockId) -> Self {
+        value.0 as usize
+    }
+}
+
+impl From<usize> for BlockId {
+    fn from(value: usize) -> Self {
+        Self(value as u32)
+    }
+}
+
+/// Where control can go after the last instruction of a block.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Edge {
+    /// Continue at the block starting at this instruction.
+    Inst(InstId),
+    /// Leave the function.
+    Return,
+}
+
+/// A maximal straight-line sequence of instructions: control can only enter at the first instruction
+/// and only leave after the last one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BasicBlock {
+    pub start: InstId,
+    /// Exclusive.
+    pub end: InstId,
+    pub succs: Vec<Edge>,
+}
+
+impl BasicBlock {
+    pub fn insts(&self) -> Range<usize> {
+        self.start.into()..self.end.into()
+    }
+
+    pub fn last(&self) -> InstId {
+        InstId(self.end.0 - 1)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Cfg {
+    pub blocks: TiVec<BlockId, BasicBlock>,
+}
+
+impl Cfg {
+    /// Splits the instructions of a function into basic blocks. A new block starts at the first instruction,
+    /// at every branch target and after every instruction that doesn't simply fall through to the next one.
+    pub fn new(insts: &InstructionsDeref, fn_address: u32) -> Self {
+        let edges: Vec<Vec<Edge>> = ti_iter(insts)
+            .map(|(idx, &(off, inst))| inst_edges(idx, off.0, inst, fn_address, insts.len()))
+            .collect();
+
+        let mut is_leader = vec![false; insts.len()];
+        if let Some(first) = is_leader.first_mut() {
+            *first = true;
+        }
+        for (idx, edges) in edges.iter().enumerate() {
+            let next = InstId::from(idx + 1);
+            if matches!(**edges, [Edge::Inst(target)] if target == next) {
+                continue;
+            }
+
+            // This ends a block, so both the instruction after it and any instruction it can jump to start a new one.
+            let targets = edges.iter().filter_map(|&edge| match edge {
+                Edge::Inst(target) => Some(target.0 as usize),
+                Edge::Return => None,
+            });
+            for target in targets.chain([idx + 1]) {
+                if let Some(leader) = is_leader.get_mut(target) {
+                    *leader = true;
+                }
+            }
+        }
+
+        let mut blocks = TiVec::new();
+        let mut start = 0;
+        for end in 1..=insts.len() {
+            if end == insts.len() || is_leader[end] {
+                blocks.push(BasicBlock {
+                    start: InstId::from(start),
+                    end: InstId::from(end),
+                    succs: edges[end - 1].clone(),
+                });
+                start = end;
+            }
+        }
+
+        Self { blocks }
+    }
+
+    /// Returns the block that contains the given instruction.
+    pub fn block_of(&self, inst: InstId) -> Option<BlockId> {
+        let idx = self.blocks.partition_point(|block| block.end <= inst);
+        let block = self.blocks.get(idx)?;
+        (block.start <= inst).then_some(idx)
+    }
+}
+
+/// The index of the instruction at the address `target` in a function of `len` instructions, or `None` if `target` is
+/// outside of the function, in which case a branch there is a tail call.
+pub fn inst_at(fn_address: u32, len: usize, target: u32) -> Option<InstId> {
+    // The checked_sub catches branches to before the function. The difference is in bytes, so the instruction
+    // difference is that divided by 4.
+    target
+        .checked_sub(fn_address)
+        .map(|target| (target / 4) as usize)
+        .filter(|&target| target < len)
+        .map(InstId::from)
+}
+
+/// Where control can go after executing `inst`. Calls are assumed to return to the next instruction.
+fn inst_edges(idx: InstId, off: u32, inst: Instruction, fn_address: u32, len: usize) -> Vec<Edge> {
+    let next = Edge::Inst(idx + 1);
+    // A branch to an address outside of the function can only be a tail call, so as far as this function is concerned
+    // that leaves the function.
+    let target = |mode, target| {
+        inst_at(fn_address, len, compute_branch_target(off, mode, target))
+            .map_or(Edge::Return, Edge::Inst)
+    };
+
+    match inst {
+        Instruction::Bc {
+            bo: _,
+            hint: _,
+            bi: _,
+            target: branch_target,
+            mode,
+            link: false,
+        } => vec![target(mode, branch_target), next],
+        // `blrl` calls whatever is in LR, and like any other call returns to the next instruction.
+        Instruction::Bclr { link: true, .. } => vec![next],
+        Instruction::Bclr {
+            bo,
+            hint: _,
+            bi: _,
+            link: false,
+        } => {
+            // The conditional forms, including `bdnzlr` and friends, either return or fall through.
+            if bo == BranchOptions::BranchAlways {
+                vec![Edge::Return]
+            } else {
+                vec![Edge::Return, next]
+            }
+        }
+        Instruction::Bcctr {
+            bo,
+            hint: _,
+            bi: _,
+            link: false,
+        } => {
+            // The target is whatever is in CTR, which we can't know here (typically a jump table or a tail call
+            // through a function pointer), so treat it as leaving the function.
+            if bo == BranchOptions::BranchAlways {
+                vec![Edge::Return]
+            } else {
+                vec![Edge::Return, next]
+            }
+        }
+        Instruction::Branch {
+            target: branch_target,
+            mode,
+            link: false,
+        } => vec![target(mode, branch_target)],
+        // Control never reaches the next instruction, so there are no successors.
+        _ if inst.is_unconditional_trap() => Vec::new(),
+        _ => vec![next],
+    }
+}
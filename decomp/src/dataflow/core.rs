@@ -1,8 +1,11 @@
 use std::{
     array,
-    collections::{BTreeMap, HashMap, HashSet},
-    fmt::Debug,
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    error::Error,
+    fmt::{Debug, Display},
     hash::Hash,
+    iter,
     ops::ControlFlow,
 };
 
@@ -107,6 +110,33 @@ pub trait Dataflow: Sized {
     fn iter_block(&self, block: Self::Idx) -> impl Iterator<Item = (Self::Idx, Self::BlockItem)>;
     fn iter(&self) -> impl Iterator<Item = (Self::Idx, Self::BlockItem)>;
     fn apply_effect(&self, state: &mut Self::BlockState, idx: Self::Idx, data: &Self::BlockItem);
+    /// The state on entry to the initial block.
+    fn initial_state(&self) -> Self::BlockState {
+        Self::BlockState::default()
+    }
+    /// Called on the entry states of blocks that were still changing when `run` gave up on reaching a fixpoint.
+    /// This should make the state at least as imprecise as anything the analysis could still have computed.
+    /// By default this resets the state, which is the right thing to do if the default state is "nothing is known".
+    fn widen(&self, state: &mut Self::BlockState) {
+        *state = Self::BlockState::default();
+    }
+    /// Describes how the entry state of a block changed from `old` to `new`, for the warning that [`run`] emits when it
+    /// gives up on reaching a fixpoint. By default there is no description.
+    fn describe_change(&self, _old: &Self::BlockState, _new: &Self::BlockState) -> Option<String> {
+        None
+    }
+    /// Whether control can flow from the block ending with `item` to `succ`, given the state after `item`. [`run`] does
+    /// not propagate the state along edges for which this returns false, so that e.g. a branch whose condition is known
+    /// doesn't make the path it never takes reachable.
+    fn is_feasible(
+        &self,
+        _state: &Self::BlockState,
+        _idx: Self::Idx,
+        _item: &Self::BlockItem,
+        _succ: SuccessorTarget<Self>,
+    ) -> bool {
+        true
+    }
     fn pre_block_record(
         &self,
         rec_state: &mut Self::RecordingState,
@@ -122,30 +152,101 @@ pub trait Dataflow: Sized {
 pub struct DataflowArgs<'a, D: Dataflow> {
     pub preds: &'a Predecessors<D>,
     pub succs: &'a Successors<D>,
+    /// How many blocks to process before giving up on reaching a fixpoint and widening the remaining states.
+    pub max_iterations: usize,
+}
+
+/// A limit for [`DataflowArgs::max_iterations`] that no function should reasonably need.
+pub const DEFAULT_MAX_ITERATIONS: usize = 100_000;
+
+/// Something about the CFG that makes it impossible to analyze the function, usually caused by wrongly detected
+/// function boundaries or by trying to decompile data.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DataflowError<Idx> {
+    /// Control flow reaches `block`, but there are no instructions there.
+    OutOfBounds { block: Idx },
+    /// The block starting at `block` ran out of instructions at `last` without having any successors.
+    Unterminated { block: Idx, last: Idx },
+}
+
+impl<Idx: Debug> Display for DataflowError<Idx> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DataflowError::OutOfBounds { block } => {
+                write!(
+                    f,
+                    "control flow reaches {block:?}, which is outside of the function"
+                )
+            }
+            DataflowError::Unterminated { block, last } => {
+                write!(f, "block {block:?} ends at {last:?} without any successors")
+            }
+        }
+    }
 }
 
-pub fn run<D: Dataflow>(dataflow: &D, args: DataflowArgs<'_, D>) -> Results<D>
+impl<Idx: Debug> Error for DataflowError<Idx> {}
+
+pub fn run<D: Dataflow>(
+    dataflow: &D,
+    args: DataflowArgs<'_, D>,
+) -> Result<Results<D>, DataflowError<D::Idx>>
 where
     D::Idx: Debug,
     D::BlockState: Debug,
     D::BlockItem: Debug,
 {
-    let mut queue = vec![D::initial_idx()];
+    // Process blocks in reverse post-order, so that (ignoring back edges) all predecessors of a block have been visited
+    // before the block itself. This avoids having to revisit blocks just because a join changed their entry state.
+    let rpo = reverse_post_order(args.succs, D::initial_idx());
+    let mut queue = BTreeSet::from([(rpo[&D::initial_idx()], D::initial_idx())]);
 
     let mut entry_states: HashMap<D::Idx, D::BlockState> = HashMap::default();
+    // The entry state of every block before it last changed, to explain why a block is still changing if we give up.
+    let mut previous_states: HashMap<D::Idx, D::BlockState> = HashMap::default();
 
     let mut record_state = D::RecordingState::default();
 
-    while let Some(idx) = queue.pop() {
-        let mut state = entry_states.get(&idx).cloned().unwrap_or_else(|| {
-            assert_eq!(idx, D::initial_idx());
-            D::BlockState::default()
-        });
+    let mut iterations = 0;
+    let mut widened = false;
+    let mut last_change = None;
+
+    while let Some((_, block)) = queue.pop_first() {
+        iterations += 1;
+        if iterations > args.max_iterations {
+            last_change = previous_states
+                .get(&block)
+                .zip(entry_states.get(&block))
+                .and_then(|(old, new)| dataflow.describe_change(old, new));
+            match &last_change {
+                Some(change) => tracing::warn!(
+                    "dataflow analysis did not converge after {} iterations, widening. The entry state of {block:?} \
+                     last changed: {change}",
+                    args.max_iterations
+                ),
+                None => tracing::warn!(
+                    "dataflow analysis did not converge after {} iterations, widening",
+                    args.max_iterations
+                ),
+            }
+            widen_remaining(dataflow, args.succs, &mut entry_states, block, queue);
+            widened = true;
+            break;
+        }
+
+        // Only the initial block is queued without an entry state.
+        let mut state = entry_states
+            .get(&block)
+            .cloned()
+            .unwrap_or_else(|| dataflow.initial_state());
 
         dataflow.pre_block_record(&mut record_state, &mut state);
-        entry_states.insert(idx, state.clone());
+        entry_states.insert(block, state.clone());
 
-        for (idx, item) in dataflow.iter_block(idx) {
+        let mut last = None;
+        let mut terminated = false;
+        for (idx, item) in dataflow.iter_block(block) {
+            last = Some(idx);
             let span = tracing::span!(tracing::Level::DEBUG, "item", "{idx:?} {item:?}");
             let _enter = span.enter();
             dataflow.apply_effect(&mut state, idx, &item);
@@ -156,7 +257,10 @@ where
                 dataflow.post_block_record(&mut record_state, &mut state);
                 // Note: `succs` may be empty for `blr` (exit blocks).
 
-                for &succ in succs {
+                let feasible = succs
+                    .iter()
+                    .filter(|&&succ| dataflow.is_feasible(&state, idx, &item, succ));
+                for &succ in feasible {
                     if let SuccessorTarget::Id(succ) = succ {
                         if let Some(succ_state) = entry_states.get(&succ) {
                             // NOTE: join() argument order matters here -- the state of the successor block needs to come first
@@ -165,31 +269,222 @@ where
                             let state_changed = &succ_state_joined != succ_state;
 
                             if state_changed {
-                                entry_states.insert(succ, succ_state_joined);
+                                if let Some(old) = entry_states.insert(succ, succ_state_joined) {
+                                    previous_states.insert(succ, old);
+                                }
                                 if D::REVISIT_BLOCKS {
-                                    queue.push(succ);
+                                    queue.insert((rpo[&succ], succ));
                                 }
                             }
                         } else {
                             // First time visiting this successor.
-                            queue.push(succ);
+                            queue.insert((rpo[&succ], succ));
                             entry_states.insert(succ, state.clone());
                         }
                     }
                 }
 
+                terminated = true;
                 break;
             }
         }
+
+        if !terminated {
+            match last {
+                Some(last) => return Err(DataflowError::Unterminated { block, last }),
+                // An empty function has nothing to analyze, but any other block must contain at least one instruction.
+                None if block != D::initial_idx() => {
+                    return Err(DataflowError::OutOfBounds { block });
+                }
+                None => {}
+            }
+        }
     }
 
-    Results {
+    Ok(Results {
         states: entry_states,
+        preds: args.preds.clone(),
+        succs: args.succs.clone(),
+        widened,
+        last_change,
+    })
+}
+
+/// Like [`run`], but propagates states backwards, from the exits of the function towards its entry (e.g. for liveness).
+///
+/// The items of a block are visited in reverse order, starting with the join of the states of the block's successors,
+/// and the recorded state of a block is the one *before* its first item. Returns, as well as successors that have not
+/// been visited yet, contribute a default state. The `*_block_record` hooks are not called.
+pub fn run_backward<D: Dataflow>(
+    dataflow: &D,
+    args: DataflowArgs<'_, D>,
+) -> Result<Results<D>, DataflowError<D::Idx>>
+where
+    D::Idx: Debug,
+    D::BlockState: Debug,
+    D::BlockItem: Debug,
+{
+    // Collect the blocks reachable from the entry up front, since we need to walk their items backwards.
+    let mut blocks: HashMap<D::Idx, Vec<_>> = HashMap::default();
+    // Maps the last item of a block to its first one, since that's what predecessors are keyed by.
+    let mut block_starts: HashMap<D::Idx, D::Idx> = HashMap::default();
+    let mut stack = vec![D::initial_idx()];
+
+    while let Some(block) = stack.pop() {
+        if blocks.contains_key(&block) {
+            continue;
+        }
+
+        let mut items = Vec::new();
+        let mut terminated = false;
+        for (idx, item) in dataflow.iter_block(block) {
+            items.push((idx, item));
+
+            if let Some(succs) = args.succs.get(&idx) {
+                block_starts.insert(idx, block);
+                stack.extend(succs.iter().filter_map(SuccessorTarget::idx));
+                terminated = true;
+                break;
+            }
+        }
+
+        if !terminated {
+            match items.last() {
+                Some(&(last, _)) => return Err(DataflowError::Unterminated { block, last }),
+                None if block != D::initial_idx() => {
+                    return Err(DataflowError::OutOfBounds { block });
+                }
+                None => {}
+            }
+        }
+        blocks.insert(block, items);
     }
+
+    // Going in post-order means that (ignoring back edges) all successors of a block are visited before the block.
+    let rpo = reverse_post_order(args.succs, D::initial_idx());
+    let mut queue: BTreeSet<(Reverse<usize>, D::Idx)> = blocks
+        .keys()
+        .map(|&block| (Reverse(rpo[&block]), block))
+        .collect();
+
+    let mut states: HashMap<D::Idx, D::BlockState> = HashMap::default();
+    let mut record_state = D::RecordingState::default();
+
+    let mut iterations = 0;
+    let mut widened = false;
+
+    while let Some((_, block)) = queue.pop_first() {
+        iterations += 1;
+        if iterations > args.max_iterations {
+            tracing::warn!(
+                "backward dataflow analysis did not converge after {} iterations, widening",
+                args.max_iterations
+            );
+            // Every state can be affected by the ones that are still changing, so widen all of them.
+            states.values_mut().for_each(|state| dataflow.widen(state));
+            widened = true;
+            break;
+        }
+
+        let items = &blocks[&block];
+        let mut state = items
+            .last()
+            .and_then(|(last, _)| {
+                args.succs[last]
+                    .iter()
+                    .map(|succ| match succ {
+                        SuccessorTarget::Id(succ) => states.get(succ).cloned().unwrap_or_default(),
+                        SuccessorTarget::Return => D::BlockState::default(),
+                    })
+                    .reduce(|a, b| a.join(&b, &mut record_state))
+            })
+            .unwrap_or_default();
+
+        for (idx, item) in items.iter().rev() {
+            let span = tracing::span!(tracing::Level::DEBUG, "item", "{idx:?} {item:?}");
+            let _enter = span.enter();
+            dataflow.apply_effect(&mut state, *idx, item);
+        }
+
+        if states.get(&block) != Some(&state) {
+            states.insert(block, state);
+
+            for pred in args.preds.get(&block).into_iter().flatten() {
+                if let Some(&pred) = block_starts.get(pred) {
+                    queue.insert((Reverse(rpo[&pred]), pred));
+                }
+            }
+        }
+    }
+
+    Ok(Results {
+        states,
+        preds: args.preds.clone(),
+        succs: args.succs.clone(),
+        widened,
+        last_change: None,
+    })
+}
+
+/// Widens the entry states of all blocks that are still queued, as well as every block reachable from them, since
+/// changes would have propagated to those too.
+fn widen_remaining<D: Dataflow>(
+    dataflow: &D,
+    succs: &Successors<D>,
+    entry_states: &mut HashMap<D::Idx, D::BlockState>,
+    idx: D::Idx,
+    queue: BTreeSet<(usize, D::Idx)>,
+) {
+    for idx in iter::once(idx).chain(queue.into_iter().map(|(_, idx)| idx)) {
+        let _ = for_each_transitive_successor::<(), D>(succs, idx, &mut |block| {
+            if let Some(state) = entry_states.get_mut(&block) {
+                dataflow.widen(state);
+            }
+            ControlFlow::Continue(())
+        });
+    }
+}
+
+/// Numbers the blocks reachable from `start` in reverse post-order.
+fn reverse_post_order<D: Dataflow>(succs: &Successors<D>, start: D::Idx) -> HashMap<D::Idx, usize> {
+    fn visit<D: Dataflow>(
+        succs: &Successors<D>,
+        block: D::Idx,
+        seen: &mut HashSet<D::Idx>,
+        post_order: &mut Vec<D::Idx>,
+    ) {
+        if !seen.insert(block) {
+            return;
+        }
+
+        // Blocks are keyed by their last instruction. Edges can point past the end of the function, in which case
+        // there is no block.
+        if let Some((_, edges)) = succs.range(block..).next() {
+            for edge in edges {
+                if let SuccessorTarget::Id(next) = *edge {
+                    visit(succs, next, seen, post_order);
+                }
+            }
+        }
+        post_order.push(block);
+    }
+
+    let mut post_order = Vec::new();
+    visit(succs, start, &mut HashSet::new(), &mut post_order);
+    post_order
+        .into_iter()
+        .rev()
+        .enumerate()
+        .map(|(number, block)| (block, number))
+        .collect()
 }
 
 pub struct Results<D: Dataflow> {
     states: HashMap<D::Idx, D::BlockState>,
+    preds: Predecessors<D>,
+    succs: Successors<D>,
+    widened: bool,
+    last_change: Option<String>,
 }
 
 pub struct ForEachCtxt<'analysis, 'iter, D: Dataflow> {
@@ -229,24 +524,53 @@ impl<'analysis, 'iter, D: Dataflow> ForEachCtxt<'analysis, 'iter, D> {
 }
 
 impl<D: Dataflow> Results<D> {
-    pub fn get(&self, idx: D::Idx) -> Option<&D::BlockState> {
+    /// The state on entry to the block starting at `idx`, or `None` if `idx` doesn't start a block (or is unreachable).
+    pub fn state_at(&self, idx: D::Idx) -> Option<&D::BlockState> {
         self.states.get(&idx)
     }
 
+    /// The predecessors of every block that has any, keyed by the first instruction of the block.
+    pub fn predecessors(&self) -> &Predecessors<D> {
+        &self.preds
+    }
+
+    /// The successors of every block, keyed by the last instruction of the block.
+    pub fn successors(&self) -> &Successors<D> {
+        &self.succs
+    }
+
+    /// Whether the analysis hit [`DataflowArgs::max_iterations`] and had to widen states to stop.
+    pub fn widened(&self) -> bool {
+        self.widened
+    }
+
+    /// If the analysis was widened, how the entry state of the block that was still changing last changed, as
+    /// described by [`Dataflow::describe_change`].
+    pub fn last_change(&self) -> Option<&str> {
+        self.last_change.as_deref()
+    }
+
     /// Iterates over the results along with the input items.
+    /// Every block starts out with its entry state, or the default state if the block is unreachable.
     pub fn for_each_with_input<'analysis>(
         &self,
         analysis: &'analysis D,
         mut on_instruction: impl FnMut(&mut ForEachCtxt<'analysis, '_, D>),
     ) -> D::BlockState {
         let mut state: D::BlockState = D::BlockState::default();
+        let mut block_ended = false;
 
         for (idx, item) in analysis.iter() {
             let mut is_entry = false;
             if let Some(new_state) = self.states.get(&idx) {
                 state = new_state.clone();
                 is_entry = true;
+            } else if block_ended {
+                // The analysis never reached this block, so the state at the end of whatever block happens to be
+                // laid out before it has nothing to do with it.
+                state = D::BlockState::default();
             }
+            block_ended = self.succs.contains_key(&idx);
 
             let mut cx = ForEachCtxt {
                 analysis,
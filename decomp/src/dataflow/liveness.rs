@@ -0,0 +1,223 @@
+use std::{collections::BTreeMap, iter};
+
+use ppc32::{
+    Instruction,
+    instruction::{BranchTarget, Gpr, RegisterVisitor},
+};
+
+use crate::{
+    dataflow::{
+        InstId, InstructionsDeref,
+        cfg::{Cfg, Edge, inst_at},
+        core::{
+            self, DEFAULT_MAX_ITERATIONS, Dataflow, DataflowArgs, DataflowError, Join, Results,
+        },
+        ssa::compute_preds_and_succs,
+    },
+    ti_utils::ti_iter,
+};
+
+/// A set of GPRs.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GprSet(u32);
+
+impl GprSet {
+    pub fn contains(self, gpr: Gpr) -> bool {
+        self.0 & (1 << gpr.0) != 0
+    }
+
+    pub fn insert(&mut self, gpr: Gpr) {
+        self.0 |= 1 << gpr.0;
+    }
+
+    pub fn remove(&mut self, gpr: Gpr) {
+        self.0 &= !(1 << gpr.0);
+    }
+
+    pub fn iter(self) -> impl Iterator<Item = Gpr> {
+        (0..32).map(Gpr).filter(move |&gpr| self.contains(gpr))
+    }
+}
+
+impl FromIterator<Gpr> for GprSet {
+    fn from_iter<T: IntoIterator<Item = Gpr>>(iter: T) -> Self {
+        let mut set = Self::default();
+        iter.into_iter().for_each(|gpr| set.insert(gpr));
+        set
+    }
+}
+
+impl Join<()> for GprSet {
+    fn join(&self, other: &Self, _: &mut ()) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+/// Computes which GPRs are live, i.e. may still be read before being overwritten, at the start of every block.
+pub struct Liveness<'a> {
+    pub insts: &'a InstructionsDeref,
+    pub fn_address: u32,
+}
+
+impl<'a> Liveness<'a> {
+    /// Runs the analysis over the function.
+    pub fn run(&self) -> Result<Results<Self>, DataflowError<InstId>> {
+        let mut preds = BTreeMap::default();
+        let mut succs = BTreeMap::default();
+        compute_preds_and_succs(self.insts, self.fn_address, &mut preds, &mut succs);
+
+        core::run_backward(
+            self,
+            DataflowArgs {
+                preds: &preds,
+                succs: &succs,
+                max_iterations: DEFAULT_MAX_ITERATIONS,
+            },
+        )
+    }
+
+    /// Whether `inst` is a branch to somewhere outside of this function.
+    fn is_tail_call(&self, idx: InstId, inst: &Instruction) -> bool {
+        inst.branch_target(self.insts[idx].0.0)
+            .and_then(BranchTarget::address)
+            .is_some_and(|target| inst_at(self.fn_address, self.insts.len(), target).is_none())
+    }
+
+    /// Returns all GPR writes whose value is never read afterwards, e.g. because the register is overwritten first.
+    /// Note that the instructions themselves might still be needed for their other effects (e.g. `stwu` or calls).
+    pub fn dead_writes(&self, results: &Results<Self>) -> Vec<(InstId, Gpr)> {
+        struct Writes<'a>(&'a mut GprSet);
+        impl RegisterVisitor for Writes<'_> {
+            fn write_gpr(&mut self, gpr: Gpr) {
+                self.0.insert(gpr);
+            }
+        }
+
+        let mut dead = Vec::new();
+        for block in Cfg::new(self.insts, self.fn_address).blocks {
+            // Only blocks that are reachable have a state.
+            if results.state_at(block.start).is_none() {
+                continue;
+            }
+
+            let mut live = block
+                .succs
+                .iter()
+                .map(|edge| match *edge {
+                    Edge::Inst(succ) => results.state_at(succ).copied().unwrap_or_default(),
+                    Edge::Return => GprSet::default(),
+                })
+                .fold(GprSet::default(), |a, b| a.join(&b, &mut ()));
+
+            for idx in block.insts().rev().map(InstId::from) {
+                let inst = self.insts[idx].1;
+                let mut writes = GprSet::default();
+                inst.visit_registers(Writes(&mut writes));
+                dead.extend(
+                    writes
+                        .iter()
+                        .filter(|&gpr| !live.contains(gpr))
+                        .map(|gpr| (idx, gpr)),
+                );
+
+                self.apply_effect(&mut live, idx, &inst);
+            }
+        }
+
+        dead.sort();
+        dead
+    }
+}
+
+impl<'a> Dataflow for Liveness<'a> {
+    type Idx = InstId;
+    type BlockState = GprSet;
+    type BlockItem = Instruction;
+    type RecordingState = ();
+
+    fn pre_block_record(&self, _: &mut Self::RecordingState, _: &mut Self::BlockState) {}
+
+    fn post_block_record(&self, _: &mut Self::RecordingState, _: &mut Self::BlockState) {}
+
+    fn initial_idx() -> Self::Idx {
+        InstId(0)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (Self::Idx, Self::BlockItem)> {
+        ti_iter(self.insts).map(|(i, &(_, inst))| (i, inst))
+    }
+
+    fn iter_block(&self, idx: Self::Idx) -> impl Iterator<Item = (Self::Idx, Self::BlockItem)> {
+        // `run_backward` stops at the end of the block, so this doesn't need to know where the block ends.
+        // Branches can point past the end of the function, in which case the block is empty.
+        iter::zip(idx.0.., self.insts.get(idx..).unwrap_or_default())
+            .map(|(i, &(_, inst))| (InstId(i), inst))
+    }
+
+    /// Since this is a backward analysis, `state` is what's live *after* the instruction and this turns it into what's
+    /// live before it.
+    fn apply_effect(&self, state: &mut Self::BlockState, idx: Self::Idx, data: &Self::BlockItem) {
+        struct Vis<'a> {
+            reads: &'a mut GprSet,
+            writes: &'a mut GprSet,
+        }
+        impl RegisterVisitor for Vis<'_> {
+            fn read_gpr(&mut self, gpr: Gpr) {
+                self.reads.insert(gpr);
+            }
+            fn write_gpr(&mut self, gpr: Gpr) {
+                self.writes.insert(gpr);
+            }
+        }
+
+        let mut reads = GprSet::default();
+        let mut writes = GprSet::default();
+        data.visit_registers(Vis {
+            reads: &mut reads,
+            writes: &mut writes,
+        });
+
+        match *data {
+            Instruction::Addi {
+                source: Gpr::ZERO, ..
+            } => {
+                // li, which doesn't actually read r0
+                reads.remove(Gpr::ZERO);
+            }
+            Instruction::Branch { link: true, .. }
+            | Instruction::Bc { link: true, .. }
+            | Instruction::Bclr { link: true, .. }
+            | Instruction::Bcctr { link: true, .. } => {
+                // The callee may clobber all volatile registers and may read any of the argument registers.
+                writes = (0..32).map(Gpr).filter(Gpr::is_volatile).collect();
+                reads = (3..=10).map(Gpr).collect();
+            }
+            Instruction::Bclr { link: false, .. } => {
+                // The caller reads the return value and the stack pointer, and expects the non-volatile registers
+                // to still have their values.
+                reads = [Gpr::STACK_POINTER, Gpr::RETURN]
+                    .into_iter()
+                    .chain((14..32).map(Gpr))
+                    .collect();
+            }
+            Instruction::Bcctr { link: false, .. } => {
+                // Either a tail call or a jump table, so anything could be read.
+                reads = GprSet(u32::MAX);
+            }
+            Instruction::Branch { link: false, .. } | Instruction::Bc { link: false, .. }
+                if self.is_tail_call(idx, data) =>
+            {
+                // Like a call followed by a return.
+                reads = [Gpr::STACK_POINTER]
+                    .into_iter()
+                    .chain((3..=10).map(Gpr))
+                    .chain((14..32).map(Gpr))
+                    .collect();
+            }
+            _ => {}
+        }
+
+        state.0 &= !writes.0;
+        state.0 |= reads.0;
+    }
+}
@@ -1,45 +1,57 @@
-use std::{collections::HashSet, hash::Hash};
+use std::{
+    collections::{BTreeSet, HashSet},
+    hash::Hash,
+};
 
 use indexmap::IndexSet;
 use typed_index_collections::TiVec;
 
 use crate::dataflow::{
     InstId,
-    core::{Predecessors, SuccessorTarget, Successors},
+    core::{SuccessorTarget, Successors},
     ssa::LocalGenerationAnalysis,
 };
 
-fn find_cycle(inst_id: InstId, succs: &Successors<LocalGenerationAnalysis<'_>>) -> bool {
-    fn find_cycle_inner(
-        start_inst_id: InstId,
-        cur_inst_id: InstId,
+/// The targets of the back edges that a depth-first search from the entry finds, i.e. the headers of the loops.
+/// Only these are loop starts: the other blocks in a loop are on a cycle too, but they are part of the loop body.
+fn find_loop_headers(succs: &Successors<LocalGenerationAnalysis<'_>>) -> BTreeSet<InstId> {
+    fn visit(
+        inst_id: InstId,
         succs: &Successors<LocalGenerationAnalysis<'_>>,
+        on_path: &mut HashSet<InstId>,
         seen: &mut HashSet<InstId>,
-    ) -> bool {
-        if !seen.insert(cur_inst_id) {
-            // We've got a cycle.
-            if cur_inst_id == start_inst_id {
-                return true;
-            } else {
-                return false;
-            }
-        }
+        headers: &mut BTreeSet<InstId>,
+    ) {
+        seen.insert(inst_id);
+        on_path.insert(inst_id);
 
-        let (_, edges) = succs.range(cur_inst_id..).next().unwrap();
+        let (_, edges) = succs.range(inst_id..).next().unwrap();
         for edge in edges {
-            match *edge {
-                SuccessorTarget::Id(id) => {
-                    if find_cycle_inner(start_inst_id, id, succs, seen) {
-                        return true;
-                    }
+            if let SuccessorTarget::Id(id) = *edge {
+                if on_path.contains(&id) {
+                    headers.insert(id);
+                } else if !seen.contains(&id) {
+                    visit(id, succs, on_path, seen, headers);
                 }
-                SuccessorTarget::Return => {}
             }
         }
 
-        false
+        on_path.remove(&inst_id);
     }
-    find_cycle_inner(inst_id, inst_id, succs, &mut HashSet::new())
+
+    let mut headers = BTreeSet::new();
+    if succs.is_empty() {
+        // An empty function
+        return headers;
+    }
+    visit(
+        InstId(0),
+        succs,
+        &mut HashSet::new(),
+        &mut HashSet::new(),
+        &mut headers,
+    );
+    headers
 }
 
 fn intersection<T: Eq + Hash>(mut left: IndexSet<T>, right: &IndexSet<T>) -> IndexSet<T> {
@@ -145,25 +157,20 @@ impl LoopMap {
     }
 }
 
-pub fn find_loops<'a>(
-    preds: &Predecessors<LocalGenerationAnalysis<'a>>,
-    succs: &Successors<LocalGenerationAnalysis<'a>>,
-) -> LoopMap {
+pub fn find_loops(succs: &Successors<LocalGenerationAnalysis<'_>>) -> LoopMap {
     let mut loops = TiVec::new();
 
-    for &inst_id in preds.keys() {
-        if find_cycle(inst_id, succs) {
-            // Starting at `inst_id`, visit all successor paths, ignore backedges,
-            // and for paths that aren't backedges, collect the path into an IndexSet when returning recursively (causing the first one to be inserted last),
-            // `&` the results together when visiting multiple successors,
-            // then at the end do `.last()` -- that is the common merge point between all paths.
-            let common_merge_inst = find_common_mergepoint_of_loop(inst_id, succs);
-
-            loops.push(Loop {
-                start: inst_id,
-                common_merge_inst,
-            });
-        }
+    for inst_id in find_loop_headers(succs) {
+        // Starting at `inst_id`, visit all successor paths, ignore backedges,
+        // and for paths that aren't backedges, collect the path into an IndexSet when returning recursively (causing the first one to be inserted last),
+        // `&` the results together when visiting multiple successors,
+        // then at the end do `.last()` -- that is the common merge point between all paths.
+        let common_merge_inst = find_common_mergepoint_of_loop(inst_id, succs);
+
+        loops.push(Loop {
+            start: inst_id,
+            common_merge_inst,
+        });
     }
 
     LoopMap(loops)
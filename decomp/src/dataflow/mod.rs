@@ -1,12 +1,19 @@
 use std::ops::{Add, Deref};
 
-use ppc32::{Instruction, decoder::Address};
+use ppc32::{
+    Decoder, Instruction,
+    decoder::{Address, DecodeError},
+};
 use typed_index_collections::TiVec;
 
+pub mod arguments;
+pub mod cfg;
 pub mod core;
+pub mod liveness;
 pub mod loops;
 pub mod register_state;
 pub mod ssa;
+pub mod value;
 pub mod variables;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -33,3 +40,11 @@ impl From<usize> for InstId {
 
 pub type Instructions = TiVec<InstId, (Address, Instruction)>;
 pub type InstructionsDeref = <Instructions as Deref>::Target;
+
+/// Decodes the code of a function starting at `fn_address`, up to the end of `code`.
+pub fn decode_instructions(code: &[u8], fn_address: u32) -> Result<Instructions, DecodeError> {
+    Decoder::new(code)
+        .instructions()
+        .map(|res| res.map(|(offset, inst)| (Address(fn_address + offset), inst)))
+        .collect()
+}
@@ -1,10 +1,11 @@
-use ppc32::instruction::{Crb, Crf, Gpr, Register, Spr, XerRegister};
+use ppc32::instruction::{Crb, Crf, Fpr, Gpr, Register, Spr, XerRegister};
 
 use crate::dataflow::core::Join;
 
 #[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
 pub struct RegisterState<S> {
     pub gprs: [S; 32],
+    pub fprs: [S; 32],
     pub sprs: SprState<S>,
 }
 
@@ -33,23 +34,36 @@ pub struct XerState<S> {
 }
 
 impl<S> RegisterState<S> {
-    pub fn by_register(&mut self, reg: Register) -> &mut S {
+    /// The state of `reg`, or `None` for SPRs that aren't tracked (e.g. `SRR0` or the SPRGs), which are treated as
+    /// opaque.
+    pub fn by_register(&mut self, reg: Register) -> Option<&mut S> {
         match reg {
-            Register::Gpr(gpr) => &mut self.gprs[gpr.0 as usize],
-            Register::Cr(crf, crb) => self.sprs.cr_mut(crf, crb),
-            Register::Spr(Spr::Ctr) => &mut self.sprs.ctr,
-            Register::Spr(Spr::Lr) => &mut self.sprs.lr,
-            Register::Spr(Spr::Msr) => &mut self.sprs.msr,
-            Register::Spr(Spr::Xer(XerRegister::So)) => &mut self.sprs.xer.so,
-            Register::Spr(Spr::Xer(XerRegister::Ov)) => &mut self.sprs.xer.ov,
-            Register::Spr(Spr::Xer(XerRegister::Ca)) => &mut self.sprs.xer.ca,
-            Register::Spr(Spr::Pc | Spr::Other(_)) => todo!(),
+            Register::Gpr(gpr) => Some(&mut self.gprs[gpr.0 as usize]),
+            Register::Fpr(fpr) => Some(&mut self.fprs[fpr.0 as usize]),
+            Register::Cr(crf, crb) => Some(self.sprs.cr_mut(crf, crb)),
+            Register::Spr(Spr::Ctr) => Some(&mut self.sprs.ctr),
+            Register::Spr(Spr::Lr) => Some(&mut self.sprs.lr),
+            Register::Spr(Spr::Msr) => Some(&mut self.sprs.msr),
+            Register::Spr(Spr::Xer(XerRegister::So)) => Some(&mut self.sprs.xer.so),
+            Register::Spr(Spr::Xer(XerRegister::Ov)) => Some(&mut self.sprs.xer.ov),
+            Register::Spr(Spr::Xer(XerRegister::Ca)) => Some(&mut self.sprs.xer.ca),
+            Register::Spr(
+                Spr::Pc
+                | Spr::Srr0
+                | Spr::Srr1
+                | Spr::Sprg(_)
+                | Spr::Dec
+                | Spr::Dar
+                | Spr::Dsisr
+                | Spr::Other(_),
+            ) => None,
         }
     }
 
     pub fn register_iter(&self) -> impl Iterator<Item = (Register, &S)> {
         let Self {
             gprs,
+            fprs,
             sprs:
                 SprState {
                     lr,
@@ -65,6 +79,11 @@ impl<S> RegisterState<S> {
             .enumerate()
             .map(|(gpr, state)| (Register::Gpr(Gpr(gpr as u8)), state));
 
+        let fprs = fprs
+            .iter()
+            .enumerate()
+            .map(|(fpr, state)| (Register::Fpr(Fpr(fpr as u8)), state));
+
         let sprs = [(Spr::Lr, lr), (Spr::Ctr, ctr), (Spr::Msr, msr)]
             .into_iter()
             .map(|(spr, state)| (Register::Spr(spr), state));
@@ -79,7 +98,7 @@ impl<S> RegisterState<S> {
             ]
         });
 
-        gprs.chain(sprs).chain(crs)
+        gprs.chain(fprs).chain(sprs).chain(crs)
     }
 
     pub fn register_iter_mut(&mut self) -> impl Iterator<Item = (Register, &mut S)> {
@@ -87,6 +106,7 @@ impl<S> RegisterState<S> {
         // TODO: figure out if we can deduplicate
         let Self {
             gprs,
+            fprs,
             sprs:
                 SprState {
                     lr,
@@ -102,6 +122,11 @@ impl<S> RegisterState<S> {
             .enumerate()
             .map(|(gpr, state)| (Register::Gpr(Gpr(gpr as u8)), state));
 
+        let fprs = fprs
+            .iter_mut()
+            .enumerate()
+            .map(|(fpr, state)| (Register::Fpr(Fpr(fpr as u8)), state));
+
         let sprs = [(Spr::Lr, lr), (Spr::Ctr, ctr), (Spr::Msr, msr)]
             .into_iter()
             .map(|(spr, state)| (Register::Spr(spr), state));
@@ -116,7 +141,7 @@ impl<S> RegisterState<S> {
             ]
         });
 
-        gprs.chain(sprs).chain(crs)
+        gprs.chain(fprs).chain(sprs).chain(crs)
     }
 }
 
@@ -146,6 +171,7 @@ impl<S: Join<T>, T> Join<RegisterState<T>> for RegisterState<S> {
     fn join(&self, other: &Self, arg: &mut RegisterState<T>) -> Self {
         Self {
             gprs: Join::join(&self.gprs, &other.gprs, &mut arg.gprs),
+            fprs: Join::join(&self.fprs, &other.fprs, &mut arg.fprs),
             sprs: Join::join(&self.sprs, &other.sprs, &mut arg.sprs),
         }
     }
@@ -155,7 +181,7 @@ impl<S: Join<T>, T> Join<SprState<T>> for SprState<S> {
     fn join(&self, other: &Self, arg: &mut SprState<T>) -> Self {
         Self {
             lr: Join::join(&self.lr, &other.lr, &mut arg.lr),
-            ctr: Join::join(&self.ctr, &other.ctr, &mut arg.lr),
+            ctr: Join::join(&self.ctr, &other.ctr, &mut arg.ctr),
             xer: Join::join(&self.xer, &other.xer, &mut arg.xer),
             msr: Join::join(&self.msr, &other.msr, &mut arg.msr),
             cr: Join::join(&self.cr, &other.cr, &mut arg.cr),
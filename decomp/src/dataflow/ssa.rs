@@ -1,90 +1,75 @@
 use std::{
     collections::HashMap,
+    fmt,
     iter::{self},
 };
 
 use ppc32::{
     Instruction,
-    instruction::{
-        BranchOptions, Crb, Crf, Gpr, MicroSpr, Register, RegisterVisitor, Spr, XerRegister,
-        compute_branch_target,
-    },
+    instruction::{Crb, Crf, Fpr, Gpr, MicroSpr, Register, RegisterVisitor, Spr, XerRegister},
 };
 
 use crate::{
     dataflow::{
         InstId, InstructionsDeref,
+        cfg::{Cfg, Edge},
         core::{Dataflow, ForEachCtxt, Join, Predecessors, Results, SuccessorTarget, Successors},
         register_state::{CrFieldState, RegisterState},
     },
     ti_utils::ti_iter,
 };
 
-pub fn compute_preds_and_succs(
+/// If one of the two edges of the conditional branch at `last` was pruned by
+/// [`compute_feasible_preds_and_succs`], i.e. the branch always goes the same way, returns the one that is left.
+pub fn decided_branch<D: Dataflow<Idx = InstId>>(
+    succs: &Successors<D>,
+    last: InstId,
+) -> Option<SuccessorTarget<D>> {
+    match **succs.get(&last)? {
+        [only] => Some(only),
+        _ => None,
+    }
+}
+
+/// Computes the edges between the basic blocks of a function. Successors are keyed by the last instruction of a block
+/// and predecessors by the first one.
+pub fn compute_preds_and_succs<D: Dataflow<Idx = InstId>>(
     insts: &InstructionsDeref,
     fn_address: u32,
-    preds: &mut Predecessors<LocalGenerationAnalysis<'_>>,
-    succs: &mut Successors<LocalGenerationAnalysis<'_>>,
+    preds: &mut Predecessors<D>,
+    succs: &mut Successors<D>,
 ) {
-    let mut store_mapping = |from: InstId, to: SuccessorTarget<_>| {
-        if let Some(to) = to.idx() {
-            preds.entry(to).or_default().push(from);
-        }
-        succs.entry(from).or_default().push(to);
-    };
-
-    for (idx, &(off, inst)) in ti_iter(insts) {
-        let next_instruction_idx = InstId(idx.0 + 1);
-        if let Instruction::Bc {
-            bo: _,
-            bi: _,
-            target,
-            mode,
-            link: false,
-        } = inst
-        {
-            if let Some(target) = compute_branch_target(off.0, mode, target).checked_sub(fn_address)
-            {
-                // If we have a conditional branch to an address before the function itself (i.e. checked_sub = None due to overflow),
-                // then that isn't part of this function and thus not something we need to analyze, hence the checked_sub.
-                // The difference is also in bytes, so the instruction difference is that divided by 4.
-                store_mapping(idx, SuccessorTarget::Id(InstId(target / 4)));
-            }
-
-            store_mapping(idx, SuccessorTarget::Id(next_instruction_idx));
-        } else if let Instruction::Bclr { bo, bi: _, link } = inst {
-            assert!(!link, "linking bclr not supported yet");
+    compute_feasible_preds_and_succs(insts, fn_address, |_, _| true, preds, succs);
+}
 
-            store_mapping(idx, SuccessorTarget::Return);
-            match bo {
-                BranchOptions::BranchIfFalse | BranchOptions::BranchIfTrue => {
-                    store_mapping(idx, SuccessorTarget::Id(next_instruction_idx))
+/// Like [`compute_preds_and_succs`], but leaves out the edges for which `is_feasible` returns false, given the last
+/// instruction of the block and the edge. Used to drop the edges that the value analysis proved are never taken.
+pub fn compute_feasible_preds_and_succs<D: Dataflow<Idx = InstId>>(
+    insts: &InstructionsDeref,
+    fn_address: u32,
+    is_feasible: impl Fn(InstId, Edge) -> bool,
+    preds: &mut Predecessors<D>,
+    succs: &mut Successors<D>,
+) {
+    for block in Cfg::new(insts, fn_address).blocks {
+        let from = block.last();
+        // Note: this may be empty for blocks that never continue, e.g. ones ending in a trap.
+        let edges = succs.entry(from).or_default();
+
+        for edge in block
+            .succs
+            .into_iter()
+            .filter(|&edge| is_feasible(from, edge))
+        {
+            match edge {
+                Edge::Inst(to) => {
+                    preds.entry(to).or_default().push(from);
+                    edges.push(SuccessorTarget::Id(to));
                 }
-                BranchOptions::BranchAlways => {}
-                BranchOptions::DecCTRBranchIfFalse => todo!(),
-                BranchOptions::DecCTRBranchIfTrue => todo!(),
-                BranchOptions::DecCTRBranchIfNotZero => todo!(),
-                BranchOptions::DecCTRBranchIfZero => todo!(),
+                Edge::Return => edges.push(SuccessorTarget::Return),
             }
-        } else if let Instruction::Branch {
-            target,
-            mode,
-            link: false,
-        } = inst
-            && let Some(target) = compute_branch_target(off.0, mode, target).checked_sub(fn_address)
-        {
-            store_mapping(idx, SuccessorTarget::Id(InstId(target / 4)));
-        } else {
-            store_mapping(idx, SuccessorTarget::Id(next_instruction_idx));
         }
     }
-
-    succs.retain(|_, edges| match **edges {
-        [SuccessorTarget::Id(target)] => preds[&target].len() > 1,
-        // Make sure we always stop at `blr`
-        [SuccessorTarget::Return] => true,
-        [..] => true,
-    });
 }
 
 #[derive(Default, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -146,6 +131,45 @@ pub struct BlockState {
     pub registers: RegisterState<LocalRegisterState>,
 }
 
+/// A single register whose state differs between two [`BlockState`]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateChange {
+    pub register: Register,
+    pub old: LocalRegisterState,
+    pub new: LocalRegisterState,
+}
+
+impl fmt::Display for StateChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_state(f: &mut fmt::Formatter<'_>, state: &LocalRegisterState) -> fmt::Result {
+            write!(f, "{}", state.generation.0)?;
+            if let Some([a, b]) = state.phi_origins {
+                write!(f, " (phi of {} and {})", a.0, b.0)?;
+            }
+            Ok(())
+        }
+
+        write!(f, "{:?}: ", self.register)?;
+        write_state(f, &self.old)?;
+        write!(f, " -> ")?;
+        write_state(f, &self.new)
+    }
+}
+
+impl BlockState {
+    /// Returns every register whose state differs between `self` (the old state) and `other` (the new state).
+    /// This is mostly useful for figuring out why a block's entry state keeps changing during the dataflow run.
+    pub fn diff(&self, other: &Self) -> Vec<StateChange> {
+        iter::zip(
+            self.registers.register_iter(),
+            other.registers.register_iter(),
+        )
+        .filter(|((_, old), (_, new))| old != new)
+        .map(|((register, &old), (_, &new))| StateChange { register, old, new })
+        .collect()
+    }
+}
+
 impl Join<RecordingState> for BlockState {
     fn join(&self, other: &Self, arg: &mut RecordingState) -> Self {
         Self {
@@ -155,6 +179,11 @@ impl Join<RecordingState> for BlockState {
                     &other.registers.gprs,
                     &mut arg.register_generations.gprs,
                 ),
+                fprs: Join::join(
+                    &self.registers.fprs,
+                    &other.registers.fprs,
+                    &mut arg.register_generations.fprs,
+                ),
                 sprs: Join::join(
                     &self.registers.sprs,
                     &other.registers.sprs,
@@ -183,6 +212,17 @@ impl<'a> Dataflow for LocalGenerationAnalysis<'a> {
 
     const REVISIT_BLOCKS: bool = false;
 
+    fn describe_change(&self, old: &Self::BlockState, new: &Self::BlockState) -> Option<String> {
+        let changes = old.diff(new);
+        (!changes.is_empty()).then(|| {
+            changes
+                .iter()
+                .map(StateChange::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+    }
+
     fn pre_block_record(
         &self,
         record_state: &mut Self::RecordingState,
@@ -221,11 +261,11 @@ impl<'a> Dataflow for LocalGenerationAnalysis<'a> {
         ti_iter(&self.insts).map(|(i, &(_, inst))| (i, inst))
     }
 
-    fn iter_block(
-        &self,
-        InstId(idx): Self::Idx,
-    ) -> impl Iterator<Item = (Self::Idx, Self::BlockItem)> {
-        self.iter().skip(idx as usize)
+    fn iter_block(&self, idx: Self::Idx) -> impl Iterator<Item = (Self::Idx, Self::BlockItem)> {
+        // `run` stops at the end of the block, so this doesn't need to know where the block ends.
+        // Branches can point past the end of the function, in which case the block is empty.
+        iter::zip(idx.0.., self.insts.get(idx..).unwrap_or_default())
+            .map(|(i, &(_, inst))| (InstId(i), inst))
     }
 
     fn apply_effect(&self, state: &mut Self::BlockState, _: Self::Idx, data: &Self::BlockItem) {
@@ -250,16 +290,27 @@ impl<'a> Dataflow for LocalGenerationAnalysis<'a> {
                 let generation = self.state.registers.gprs[gpr.0 as usize].next_generation();
                 tracing::debug!(?gpr, ?generation);
             }
+            fn write_fpr(&mut self, fpr: Fpr) {
+                let generation = self.state.registers.fprs[fpr.0 as usize].next_generation();
+                tracing::debug!(?fpr, ?generation);
+            }
             fn write_spr(&mut self, spr: MicroSpr) {
                 let generation = match spr {
                     Spr::Xer(XerRegister::So) => self.state.registers.sprs.xer.so.next_generation(),
                     Spr::Xer(XerRegister::Ov) => self.state.registers.sprs.xer.ov.next_generation(),
                     Spr::Xer(XerRegister::Ca) => self.state.registers.sprs.xer.ca.next_generation(),
                     Spr::Lr => self.state.registers.sprs.lr.next_generation(),
-                    Spr::Ctr => todo!(),
+                    Spr::Ctr => self.state.registers.sprs.ctr.next_generation(),
                     Spr::Msr => self.state.registers.sprs.msr.next_generation(),
                     Spr::Pc => todo!(),
-                    Spr::Other(_) => todo!(),
+                    // Not tracked, see `RegisterState::by_register`.
+                    Spr::Srr0
+                    | Spr::Srr1
+                    | Spr::Sprg(_)
+                    | Spr::Dec
+                    | Spr::Dar
+                    | Spr::Dsisr
+                    | Spr::Other(_) => return,
                 };
                 tracing::debug!(?spr, ?generation);
             }
@@ -337,6 +388,12 @@ pub fn def_use_map<'a>(
                     self.cx.state().registers.gprs[gpr.0 as usize].generation,
                 );
             }
+            fn read_fpr(&mut self, fpr: Fpr) {
+                self.register_use(
+                    Register::Fpr(fpr),
+                    self.cx.state().registers.fprs[fpr.0 as usize].generation,
+                );
+            }
             fn read_spr(&mut self, spr: MicroSpr) {
                 self.register_use(
                     Register::Spr(spr),
@@ -351,10 +408,17 @@ pub fn def_use_map<'a>(
                             self.cx.state().registers.sprs.xer.ca.generation
                         }
                         Spr::Lr => self.cx.state().registers.sprs.lr.generation,
-                        Spr::Ctr => todo!(),
+                        Spr::Ctr => self.cx.state().registers.sprs.ctr.generation,
                         Spr::Msr => self.cx.state().registers.sprs.msr.generation,
                         Spr::Pc => todo!(),
-                        Spr::Other(_) => todo!(),
+                        // Not tracked, so there's no generation to record a use of.
+                        Spr::Srr0
+                        | Spr::Srr1
+                        | Spr::Sprg(_)
+                        | Spr::Dec
+                        | Spr::Dar
+                        | Spr::Dsisr
+                        | Spr::Other(_) => return,
                     },
                 );
             }
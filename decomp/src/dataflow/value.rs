@@ -0,0 +1,1619 @@
+use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fmt::Debug,
+    iter,
+    ops::{Add, BitAnd, BitOr, BitXor, Mul, Neg, Not, Shl, Shr, Sub},
+    rc::Rc,
+};
+
+use ppc32::{
+    Instruction,
+    instruction::{
+        BranchOptions, BranchTarget, Cr0Result, Crb, Crf, Gpr, MacroSpr, MicroSpr, RegisterVisitor,
+        Spr, XerRegister, crb_from_index,
+    },
+};
+
+use crate::{
+    dataflow::{
+        InstId, InstructionsDeref,
+        cfg::{Cfg, Edge},
+        core::{
+            self, DEFAULT_MAX_ITERATIONS, Dataflow, DataflowArgs, DataflowError, Join, Results,
+            SuccessorTarget,
+        },
+        register_state::RegisterState,
+        ssa::compute_preds_and_succs,
+    },
+    ti_utils::ti_iter,
+};
+
+/// What kind of integer a [`VInt`] is, as far as we can tell from how it was constructed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum IntType {
+    /// A plain 32-bit integer.
+    U32,
+    /// The upper half of a 32-bit constant, as loaded by `lis`. The lower 16 bits are zero and are usually filled in
+    /// by a following `addi` or `ori`.
+    HighHalf,
+    /// A 32-bit constant assembled from a `lis` and an `addi`/`ori`. These are almost always addresses of globals.
+    Ptr,
+}
+
+/// A constant integer.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VInt {
+    pub value: u32,
+    pub ty: IntType,
+}
+
+impl VInt {
+    pub fn u32(value: u32) -> Self {
+        Self {
+            value,
+            ty: IntType::U32,
+        }
+    }
+
+    /// The type of `self op other` where `op` is an operation that can be used to fill in the lower half of a `lis`.
+    fn combined_ty(self, other: Self) -> IntType {
+        match (self.ty, other.ty) {
+            (IntType::HighHalf, IntType::HighHalf) => IntType::HighHalf,
+            (IntType::HighHalf | IntType::Ptr, _) | (_, IntType::HighHalf | IntType::Ptr) => {
+                IntType::Ptr
+            }
+            (IntType::U32, IntType::U32) => IntType::U32,
+        }
+    }
+}
+
+/// The value of a register at some point in a function.
+#[derive(Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Value {
+    /// Nothing is known about the value. This is the top of the lattice.
+    #[default]
+    Any,
+    /// No value has reached this point yet, e.g. because the code is never executed or the register is a scratch
+    /// register that hasn't been written since the function was entered. This is the bottom of the lattice: joining it
+    /// with any other value gives that value.
+    Uninit,
+    Int(VInt),
+    /// Whatever the register held on entry to the function, e.g. an argument or the caller's stack pointer.
+    Param(Gpr),
+    /// The value of LR on entry to the function, i.e. the address it returns to.
+    ReturnAddress,
+    /// A computation on other values that can't be folded into a constant.
+    Expr(Rc<Expr>),
+}
+
+/// A symbolic computation. The operators on [`Value`] keep these in a canonical form, so that two ways of computing
+/// the same thing usually compare equal.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Expr {
+    /// A sum of terms, built as a left-leaning tree (`(a + b) + c`) with the terms sorted and the constant, if any,
+    /// as the last term.
+    Add(Value, Value),
+    Neg(Value),
+    /// A product, with the constant factor (if any) on the right. Multiplications by a power of two are [`Expr::Shl`]s
+    /// instead.
+    Mul(Value, Value),
+    Shl(Value, Value),
+    /// An arithmetic (sign-extending) right shift.
+    Shr(Value, Value),
+    /// Like the other commutative operations, the constant operand (if any) is on the right.
+    BitAnd(Value, Value),
+    BitOr(Value, Value),
+    BitXor(Value, Value),
+    Not(Value),
+    /// The word at the given address on entry to the function.
+    Load(Value),
+}
+
+thread_local! {
+    static INTERNER: RefCell<Interner> = RefCell::default();
+}
+
+/// Keeps track of all expressions that are alive, so that equal expressions can share an allocation. A dataflow
+/// analysis that has to revisit blocks computes the same expressions over and over again, which would otherwise all be
+/// allocated separately. It also makes comparing equal expressions cheap, since `Rc` compares pointers first.
+#[derive(Default)]
+struct Interner {
+    exprs: HashSet<Rc<Expr>>,
+    /// Once the set has grown to this size, the expressions that nothing else refers to anymore are removed.
+    next_cleanup: usize,
+}
+
+impl Interner {
+    fn intern(&mut self, expr: Expr) -> Rc<Expr> {
+        if let Some(expr) = self.exprs.get(&expr) {
+            return expr.clone();
+        }
+
+        if self.exprs.len() >= self.next_cleanup {
+            self.exprs.retain(|expr| Rc::strong_count(expr) > 1);
+            self.next_cleanup = (self.exprs.len() * 2).max(1024);
+        }
+
+        let expr = Rc::new(expr);
+        self.exprs.insert(expr.clone());
+        expr
+    }
+}
+
+impl Value {
+    pub fn u32(value: u32) -> Self {
+        Self::Int(VInt::u32(value))
+    }
+
+    /// A constant loaded into the upper half of a register by `lis`.
+    pub fn high_half(imm: i16) -> Self {
+        Self::Int(VInt {
+            value: (imm as u32) << 16,
+            ty: IntType::HighHalf,
+        })
+    }
+
+    /// Equal expressions share an allocation, see [`Interner`].
+    pub fn expr(expr: Expr) -> Self {
+        Self::Expr(INTERNER.with_borrow_mut(|interner| interner.intern(expr)))
+    }
+
+    pub fn as_int(&self) -> Option<VInt> {
+        match *self {
+            Self::Int(int) => Some(int),
+            _ => None,
+        }
+    }
+
+    pub fn as_expr(&self) -> Option<&Expr> {
+        match self {
+            Self::Expr(expr) => Some(expr),
+            _ => None,
+        }
+    }
+
+    /// `rlwinm`: rotates left by `sh` and keeps the bits `mb` through `me`, where bit 0 is the most significant bit.
+    ///
+    /// Constants are folded exactly. Otherwise, only the forms that compilers use for shifts and masks can be
+    /// represented (anything that doesn't keep bits that were rotated around), e.g. `rlwinm r3,r4,2,0,29` is `r4 << 2`
+    /// and `rlwinm r3,r4,0,24,31` is `r4 & 0xff`.
+    pub fn rotate_and_mask(self, sh: u8, mb: u8, me: u8) -> Self {
+        let sh = u32::from(sh);
+        let mask = if mb <= me {
+            (u32::MAX >> mb) & (u32::MAX << (31 - me))
+        } else {
+            (u32::MAX >> mb) | (u32::MAX << (31 - me))
+        };
+
+        match self {
+            Self::Any | Self::Uninit => self,
+            Self::Int(int) => Self::u32(int.value.rotate_left(sh) & mask),
+            // None of the bits that wrapped around are kept, so this is a left shift.
+            _ if mask & !(u32::MAX << sh) == 0 => {
+                let shifted = self << Self::u32(sh);
+                if mask == u32::MAX << sh {
+                    shifted
+                } else {
+                    shifted & Self::u32(mask)
+                }
+            }
+            // Only the bits that wrapped around are kept, so this is a (logical) right shift by `32 - sh`.
+            // The mask takes care of the sign bits shifted in by the arithmetic shift.
+            _ if mask & (u32::MAX << sh) == 0 => (self >> Self::u32(32 - sh)) & Self::u32(mask),
+            _ => Self::Any,
+        }
+    }
+
+    /// The carry out of `self + other + carry_in`, i.e. the value of XER[CA] after an `adde`. `carry_in` is the
+    /// previous value of XER[CA], which is either 0 or 1.
+    ///
+    /// Only constants are folded: the carry out of a symbolic sum is unknown.
+    pub fn carry(&self, other: &Self, carry_in: &Self) -> Self {
+        let (Some(a), Some(b), Some(carry_in)) = (self.as_int(), other.as_int(), carry_in.as_int())
+        else {
+            return if [self, other, carry_in].contains(&&Self::Uninit) {
+                Self::Uninit
+            } else {
+                Self::Any
+            };
+        };
+
+        let sum = u64::from(a.value) + u64::from(b.value) + u64::from(carry_in.value != 0);
+        Self::u32((sum >> 32) as u32)
+    }
+
+    /// The outcome of comparing `self` with `other` as signed or unsigned integers, if it is known: either both are
+    /// constants, or they are the same value.
+    pub fn compare(&self, other: &Self, signed: bool) -> Option<Ordering> {
+        match (self.as_int(), other.as_int()) {
+            (Some(a), Some(b)) if signed => Some((a.value as i32).cmp(&(b.value as i32))),
+            (Some(a), Some(b)) => Some(a.value.cmp(&b.value)),
+            _ if Self::unknown(self, other).is_none() && self == other => Some(Ordering::Equal),
+            _ => None,
+        }
+    }
+
+    /// The result of an operation on `a` and `b` if either of them is unknown: no value if either operand has no value
+    /// yet, and any value if either operand could be anything.
+    fn unknown(a: &Self, b: &Self) -> Option<Self> {
+        match (a, b) {
+            (Self::Uninit, _) | (_, Self::Uninit) => Some(Self::Uninit),
+            (Self::Any, _) | (_, Self::Any) => Some(Self::Any),
+            _ => None,
+        }
+    }
+
+    /// Puts the operands of a commutative operation into a canonical order, with the constant (if any) on the right.
+    fn commutative(a: Self, b: Self) -> (Self, Self) {
+        if a.as_int().is_some() || (b.as_int().is_none() && b < a) {
+            (b, a)
+        } else {
+            (a, b)
+        }
+    }
+
+    /// Adds `self`, or its negation, to a list of terms of a sum, flattening nested sums.
+    fn push_terms(&self, negated: bool, terms: &mut Vec<(bool, Value)>) {
+        match self.as_expr() {
+            Some(Expr::Add(a, b)) => {
+                a.push_terms(negated, terms);
+                b.push_terms(negated, terms);
+            }
+            Some(Expr::Neg(value)) => value.push_terms(!negated, terms),
+            _ => terms.push((negated, self.clone())),
+        }
+    }
+
+    /// The type of a sum of constants. Adding integers to a pointer (or subtracting them) gives a pointer, but the
+    /// difference of two pointers is a plain integer. So is anything else that doesn't end up with exactly one pointer,
+    /// such as a negated one.
+    fn sum_ty(ints: &[(bool, VInt)]) -> IntType {
+        let pointers: i32 = ints
+            .iter()
+            .filter(|(_, int)| int.ty != IntType::U32)
+            .map(|&(negated, _)| if negated { -1 } else { 1 })
+            .sum();
+
+        if ints
+            .iter()
+            .all(|&(negated, int)| !negated && int.ty == IntType::HighHalf)
+        {
+            // e.g. `lis` followed by `addis`
+            IntType::HighHalf
+        } else if pointers == 1 {
+            IntType::Ptr
+        } else {
+            IntType::U32
+        }
+    }
+
+    /// If this is an address on the stack, the offset from the stack pointer on entry to the function.
+    pub fn stack_offset(&self) -> Option<i32> {
+        match self {
+            Self::Param(Gpr::STACK_POINTER) => Some(0),
+            Self::Expr(expr) => match &**expr {
+                Expr::Add(Self::Param(Gpr::STACK_POINTER), Self::Int(offset)) => {
+                    Some(offset.value as i32)
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Whether this is the address of a global, possibly with some offset or index added to it (e.g. `&array[r3]`).
+    pub fn is_ptr(&self) -> bool {
+        let mut terms = Vec::new();
+        self.push_terms(false, &mut terms);
+        terms.iter().any(|(negated, term)| {
+            !negated && term.as_int().is_some_and(|int| int.ty == IntType::Ptr)
+        })
+    }
+
+    /// `self + other`, or `self - other` if `negate_other` is set.
+    ///
+    /// All constants are folded into one, terms that cancel each other out are removed, and what remains is sorted, so
+    /// that e.g. `(r3 + 4) - 4` is just `r3` and `r3 - r3` is zero.
+    fn sum(&self, other: &Self, negate_other: bool) -> Self {
+        if let Some(unknown) = Self::unknown(self, other) {
+            return unknown;
+        }
+
+        let mut terms = Vec::new();
+        self.push_terms(false, &mut terms);
+        other.push_terms(negate_other, &mut terms);
+
+        let mut ints = Vec::new();
+        let mut symbolic: Vec<(bool, Value)> = Vec::new();
+        for (negated, term) in terms {
+            if let Some(int) = term.as_int() {
+                ints.push((negated, int));
+            } else if let Some(pos) = symbolic
+                .iter()
+                .position(|(other_negated, other)| *other_negated != negated && *other == term)
+            {
+                symbolic.remove(pos);
+            } else {
+                symbolic.push((negated, term));
+            }
+        }
+        symbolic.sort_by(|(_, a), (_, b)| a.cmp(b));
+
+        let constant = (!ints.is_empty()).then(|| VInt {
+            value: ints.iter().fold(0u32, |acc, &(negated, int)| {
+                if negated {
+                    acc.wrapping_sub(int.value)
+                } else {
+                    acc.wrapping_add(int.value)
+                }
+            }),
+            ty: Self::sum_ty(&ints),
+        });
+
+        let mut terms = symbolic.into_iter().map(|(negated, term)| {
+            if negated {
+                Self::expr(Expr::Neg(term))
+            } else {
+                term
+            }
+        });
+        let Some(first) = terms.next() else {
+            return Self::Int(constant.unwrap_or(VInt::u32(0)));
+        };
+        let constant = constant.filter(|int| int.value != 0).map(Self::Int);
+        terms
+            .chain(constant)
+            .fold(first, |acc, term| Self::expr(Expr::Add(acc, term)))
+    }
+}
+
+impl Debug for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Any => write!(f, "?"),
+            Self::Uninit => write!(f, "uninit"),
+            Self::Int(VInt {
+                value,
+                ty: IntType::U32,
+            }) => write!(f, "{value:#x}"),
+            Self::Int(VInt {
+                value,
+                ty: IntType::HighHalf,
+            }) => write!(f, "{value:#x}@h"),
+            Self::Int(VInt {
+                value,
+                ty: IntType::Ptr,
+            }) => write!(f, "&{value:#x}"),
+            Self::Param(gpr) => write!(f, "{gpr:?}"),
+            Self::ReturnAddress => write!(f, "LR"),
+            Self::Expr(expr) => write!(f, "{expr:?}"),
+        }
+    }
+}
+
+impl Debug for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Add(a, b) => match (b, b.as_expr()) {
+                (_, Some(Expr::Neg(b))) => write!(f, "({a:?} - {b:?})"),
+                (
+                    Value::Int(VInt {
+                        value,
+                        ty: IntType::U32,
+                    }),
+                    _,
+                ) if (*value as i32) < 0 => {
+                    write!(f, "({a:?} - {:#x})", value.wrapping_neg())
+                }
+                _ => write!(f, "({a:?} + {b:?})"),
+            },
+            Self::Neg(value) => write!(f, "-{value:?}"),
+            Self::Mul(a, b) => write!(f, "({a:?} * {b:?})"),
+            Self::Shl(a, b) => write!(f, "({a:?} << {b:?})"),
+            Self::Shr(a, b) => write!(f, "({a:?} >> {b:?})"),
+            Self::BitAnd(a, b) => write!(f, "({a:?} & {b:?})"),
+            Self::BitOr(a, b) => write!(f, "({a:?} | {b:?})"),
+            Self::BitXor(a, b) => write!(f, "({a:?} ^ {b:?})"),
+            Self::Not(value) => write!(f, "~{value:?}"),
+            Self::Load(address) => write!(f, "*{address:?}"),
+        }
+    }
+}
+
+/// Adding a constant to the upper half loaded by `lis` yields a [`IntType::Ptr`].
+impl Add for Value {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        self.sum(&other, false)
+    }
+}
+
+impl Sub for Value {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        self.sum(&other, true)
+    }
+}
+
+impl Neg for Value {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::u32(0) - self
+    }
+}
+
+impl Mul for Value {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        if let Some(unknown) = Self::unknown(&self, &other) {
+            return unknown;
+        }
+        match (self, other) {
+            (Self::Int(a), Self::Int(b)) => Self::u32(a.value.wrapping_mul(b.value)),
+            (Self::Int(int), value) | (value, Self::Int(int)) => match int.value {
+                0 => Self::u32(0),
+                1 => value,
+                factor if factor.is_power_of_two() => value.shl(Self::u32(factor.trailing_zeros())),
+                _ => Self::expr(Expr::Mul(value, Self::Int(int))),
+            },
+            (a, b) => {
+                let (a, b) = Self::commutative(a, b);
+                Self::expr(Expr::Mul(a, b))
+            }
+        }
+    }
+}
+
+/// Shifts by 32 or more clear the register, like `slw` does.
+///
+/// Constants are only folded if they are plain integers: shifting the address of a global is almost certainly part of
+/// some computation that is more readable as an expression.
+impl Shl for Value {
+    type Output = Self;
+
+    fn shl(self, amount: Self) -> Self {
+        if let Some(unknown) = Self::unknown(&self, &amount) {
+            return unknown;
+        }
+        let Some(amount) = amount.as_int() else {
+            return Self::expr(Expr::Shl(self, amount));
+        };
+
+        match self {
+            _ if amount.value == 0 => self,
+            _ if amount.value >= 32 => Self::u32(0),
+            Self::Int(VInt {
+                value,
+                ty: IntType::U32,
+            }) => Self::u32(value << amount.value),
+            _ => match self.as_expr() {
+                // `(x << a) << b` is `x << (a + b)`
+                Some(Expr::Shl(inner, Self::Int(inner_amount))) => {
+                    inner.clone() << Self::u32(inner_amount.value + amount.value)
+                }
+                _ => Self::expr(Expr::Shl(self, Self::u32(amount.value))),
+            },
+        }
+    }
+}
+
+/// An arithmetic right shift, like `sraw`. Shifts by 32 or more fill the register with the sign bit.
+impl Shr for Value {
+    type Output = Self;
+
+    fn shr(self, amount: Self) -> Self {
+        if let Some(unknown) = Self::unknown(&self, &amount) {
+            return unknown;
+        }
+        let Some(amount) = amount.as_int() else {
+            return Self::expr(Expr::Shr(self, amount));
+        };
+
+        match self {
+            _ if amount.value == 0 => self,
+            Self::Int(VInt {
+                value,
+                ty: IntType::U32,
+            }) => Self::u32(((value as i32) >> amount.value.min(31)) as u32),
+            _ => Self::expr(Expr::Shr(self, Self::u32(amount.value))),
+        }
+    }
+}
+
+/// Like addition, this folds a `lis` + `ori` pair into a [`IntType::Ptr`].
+impl BitOr for Value {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        if let Some(unknown) = Self::unknown(&self, &other) {
+            return unknown;
+        }
+        match (self, other) {
+            (Self::Int(a), Self::Int(b)) => Self::Int(VInt {
+                value: a.value | b.value,
+                ty: a.combined_ty(b),
+            }),
+            // `mr` is an `or` with itself.
+            (a, b) if a == b => a,
+            (a, b) => match Self::commutative(a, b) {
+                (a, Self::Int(VInt { value: 0, .. })) => a,
+                (
+                    _,
+                    Self::Int(VInt {
+                        value: u32::MAX, ..
+                    }),
+                ) => Self::u32(u32::MAX),
+                (a, b) => Self::expr(Expr::BitOr(a, b)),
+            },
+        }
+    }
+}
+
+impl BitAnd for Value {
+    type Output = Self;
+
+    fn bitand(self, other: Self) -> Self {
+        if let Some(unknown) = Self::unknown(&self, &other) {
+            return unknown;
+        }
+        match (self, other) {
+            (Self::Int(a), Self::Int(b)) => Self::u32(a.value & b.value),
+            (a, b) if a == b => a,
+            (a, b) => match Self::commutative(a, b) {
+                (_, Self::Int(VInt { value: 0, .. })) => Self::u32(0),
+                (
+                    a,
+                    Self::Int(VInt {
+                        value: u32::MAX, ..
+                    }),
+                ) => a,
+                (a, b) => Self::expr(Expr::BitAnd(a, b)),
+            },
+        }
+    }
+}
+
+impl BitXor for Value {
+    type Output = Self;
+
+    fn bitxor(self, other: Self) -> Self {
+        if let Some(unknown) = Self::unknown(&self, &other) {
+            return unknown;
+        }
+        match (self, other) {
+            (Self::Int(a), Self::Int(b)) => Self::u32(a.value ^ b.value),
+            (a, b) if a == b => Self::u32(0),
+            (a, b) => match Self::commutative(a, b) {
+                (a, Self::Int(VInt { value: 0, .. })) => a,
+                (
+                    a,
+                    Self::Int(VInt {
+                        value: u32::MAX, ..
+                    }),
+                ) => !a,
+                (a, b) => Self::expr(Expr::BitXor(a, b)),
+            },
+        }
+    }
+}
+
+impl Not for Value {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        match self {
+            Self::Any | Self::Uninit => self,
+            Self::Int(int) => Self::u32(!int.value),
+            _ => match self.as_expr() {
+                Some(Expr::Not(value)) => value.clone(),
+                _ => Self::expr(Expr::Not(self)),
+            },
+        }
+    }
+}
+
+/// Values form a lattice with [`Value::Uninit`] at the bottom and [`Value::Any`] at the top. Every other value is
+/// only below itself and `Any`, so joining two different values gives `Any`.
+///
+/// Note that there is no point in keeping the common structure of two different expressions, e.g. joining `r3 + 4` and
+/// `r3 + 8` into `r3 + ?`: an operation with an unknown operand is itself unknown.
+impl Join<()> for Value {
+    fn join(&self, other: &Self, _: &mut ()) -> Self {
+        match (self, other) {
+            (Self::Uninit, value) | (value, Self::Uninit) => value.clone(),
+            (a, b) if a == b => a.clone(),
+            _ => Self::Any,
+        }
+    }
+}
+
+#[derive(Default, PartialEq, Eq, Clone, Debug)]
+pub struct BlockState {
+    pub registers: RegisterState<Value>,
+    /// Known contents of memory, keyed by address. Only word-sized accesses to constant addresses are tracked.
+    pub memory: BTreeMap<u32, Value>,
+    /// Known contents of the stack, keyed by the offset from the stack pointer on entry to the function. Negative offsets
+    /// are in this function's own stack frame. A slot not in here is unknown.
+    ///
+    /// This is kept separate from `memory`, because stores through other pointers are assumed to never modify the stack,
+    /// unless the address of a stack slot was passed to a callee.
+    pub stack: BTreeMap<i32, Value>,
+    /// Whether memory that isn't in `memory` still holds whatever it held on entry to the function, so that loads from
+    /// it can be expressed as [`Expr::Load`]s. Otherwise, any address not in `memory` is unknown.
+    pub entry_memory: bool,
+    /// Values of SPRs that aren't part of [`RegisterState`] (e.g. `SRR0` or the SPRGs), which are rarely touched outside
+    /// of exception handlers. An SPR not in here is unknown.
+    pub other_sprs: BTreeMap<MacroSpr, Value>,
+}
+
+impl BlockState {
+    pub fn gpr(&self, gpr: Gpr) -> Value {
+        self.registers.gprs[gpr.0 as usize].clone()
+    }
+
+    pub fn set_gpr(&mut self, gpr: Gpr, value: Value) {
+        self.registers.gprs[gpr.0 as usize] = value;
+    }
+
+    pub fn spr(&self, spr: MacroSpr) -> Value {
+        let sprs = &self.registers.sprs;
+        match spr {
+            Spr::Lr => sprs.lr.clone(),
+            Spr::Ctr => sprs.ctr.clone(),
+            Spr::Msr => sprs.msr.clone(),
+            Spr::Xer(()) => {
+                // Only the SO, OV and CA bits are modeled, so the rest of the register reads as zero.
+                let bits = [(&sprs.xer.so, 31), (&sprs.xer.ov, 30), (&sprs.xer.ca, 29)];
+                bits.into_iter()
+                    .try_fold(0, |acc, (bit, shift)| {
+                        let bit = bit.as_int()?.value;
+                        Some(acc | (((bit != 0) as u32) << shift))
+                    })
+                    .map_or(Value::Any, Value::u32)
+            }
+            _ => self.other_sprs.get(&spr).cloned().unwrap_or_default(),
+        }
+    }
+
+    pub fn set_spr(&mut self, spr: MacroSpr, value: Value) {
+        let sprs = &mut self.registers.sprs;
+        match spr {
+            Spr::Lr => sprs.lr = value,
+            Spr::Ctr => sprs.ctr = value,
+            Spr::Msr => sprs.msr = value,
+            Spr::Xer(()) => {
+                let bit = |shift: u32| match value.as_int() {
+                    Some(int) => Value::u32((int.value >> shift) & 1),
+                    None => Value::Any,
+                };
+                sprs.xer.so = bit(31);
+                sprs.xer.ov = bit(30);
+                sprs.xer.ca = bit(29);
+            }
+            _ => {
+                self.other_sprs.insert(spr, value);
+            }
+        }
+    }
+
+    /// Sets the LT, GT and EQ bits of a CR field from the outcome of a comparison, or makes them unknown if the outcome
+    /// isn't known. SO is a copy of XER[SO].
+    pub fn update_cr_field(&mut self, crf: Crf, ordering: Option<Ordering>) {
+        let bit = |expected| match ordering {
+            Some(ordering) => Value::u32((ordering == expected) as u32),
+            None => Value::Any,
+        };
+        let so = self.registers.sprs.xer.so.clone();
+        let field = &mut self.registers.sprs.cr[crf.0 as usize];
+        field.lt = bit(Ordering::Less);
+        field.gt = bit(Ordering::Greater);
+        field.eq = bit(Ordering::Equal);
+        field.so = so;
+    }
+
+    /// The effective address of a D-form load or store, `(base)imm`. A base of `r0` means no base register.
+    pub fn effective_address(&self, base: Gpr, imm: i16) -> Value {
+        let base = if base == Gpr::ZERO {
+            Value::u32(0)
+        } else {
+            self.gpr(base)
+        };
+        base + Value::u32(imm as u32)
+    }
+
+    /// The effective address of an X-form load or store, `base + index`. A base of `r0` means no base register.
+    pub fn indexed_address(&self, base: Gpr, index: Gpr) -> Value {
+        let base = if base == Gpr::ZERO {
+            Value::u32(0)
+        } else {
+            self.gpr(base)
+        };
+        base + self.gpr(index)
+    }
+
+    pub fn load_word(&self, address: &Value) -> Value {
+        if let Some(offset) = address.stack_offset() {
+            return self.stack.get(&offset).cloned().unwrap_or_default();
+        }
+
+        match address {
+            Value::Any | Value::Uninit => address.clone(),
+            Value::Int(int) if self.memory.contains_key(&int.value) => {
+                self.memory[&int.value].clone()
+            }
+            _ if self.entry_memory => Value::expr(Expr::Load(address.clone())),
+            _ => Value::Any,
+        }
+    }
+
+    /// The word at a constant address.
+    fn memory_at(&self, address: u32) -> Value {
+        self.load_word(&Value::u32(address))
+    }
+
+    pub fn store_word(&mut self, address: &Value, value: Value) {
+        if let Some(offset) = address.stack_offset() {
+            // Also forget about any slot that partially overlaps this one.
+            self.stack.retain(|&slot, _| slot.abs_diff(offset) >= 4);
+            self.stack.insert(offset, value);
+            return;
+        }
+
+        match address.as_int() {
+            Some(address) => {
+                self.memory.insert(address.value, value);
+            }
+            // This could have overwritten anything we know about memory. Even if the address is e.g. an offset from
+            // the stack pointer, we don't know whether it aliases any of the constant addresses.
+            None => self.clobber_memory(),
+        }
+    }
+
+    /// Forgets everything about memory, e.g. after a call. This doesn't include the stack.
+    pub fn clobber_memory(&mut self) {
+        self.memory.clear();
+        self.entry_memory = false;
+    }
+
+    /// Whether any of the GPRs holds an address on the stack.
+    pub fn stack_address_in_gprs(&self, gprs: impl IntoIterator<Item = Gpr>) -> bool {
+        gprs.into_iter()
+            .any(|gpr| self.gpr(gpr).stack_offset().is_some())
+    }
+}
+
+impl Join<()> for BlockState {
+    fn join(&self, other: &Self, _: &mut ()) -> Self {
+        Self {
+            registers: Join::join(
+                &self.registers,
+                &other.registers,
+                &mut RegisterState::default(),
+            ),
+            memory: self.join_memory(other),
+            stack: join_maps(&self.stack, &other.stack),
+            entry_memory: self.entry_memory && other.entry_memory,
+            other_sprs: join_maps(&self.other_sprs, &other.other_sprs),
+        }
+    }
+}
+
+impl BlockState {
+    fn join_memory(&self, other: &Self) -> BTreeMap<u32, Value> {
+        let entry_memory = self.entry_memory && other.entry_memory;
+        let addresses: BTreeSet<u32> = self
+            .memory
+            .keys()
+            .chain(other.memory.keys())
+            .copied()
+            .collect();
+        addresses
+            .into_iter()
+            .filter_map(|address| {
+                let joined = self
+                    .memory_at(address)
+                    .join(&other.memory_at(address), &mut ());
+                // If memory is otherwise unchanged since the function was entered, an unknown value still needs to be
+                // recorded so that a load doesn't return what was there on entry.
+                (entry_memory || joined != Value::Any).then_some((address, joined))
+            })
+            .collect()
+    }
+}
+
+/// Only keeps the entries that both paths know something about. Anything else is unknown after the join.
+fn join_maps<K: Ord + Copy>(a: &BTreeMap<K, Value>, b: &BTreeMap<K, Value>) -> BTreeMap<K, Value> {
+    a.iter()
+        .filter_map(|(&key, value)| {
+            let joined = value.join(b.get(&key)?, &mut ());
+            (joined != Value::Any).then_some((key, joined))
+        })
+        .collect()
+}
+
+/// A region of memory whose contents never change, e.g. a read-only data section of the executable.
+#[derive(Debug, Copy, Clone)]
+pub struct ConstSection<'a> {
+    pub address: u32,
+    pub bytes: &'a [u8],
+}
+
+impl ConstSection<'_> {
+    pub fn read_word(&self, address: u32) -> Option<u32> {
+        let offset = address.checked_sub(self.address)? as usize;
+        let bytes = self.bytes.get(offset..)?.first_chunk()?;
+        Some(u32::from_be_bytes(*bytes))
+    }
+}
+
+/// Tracks the values held in registers, either as constants or in terms of the values the function was called with.
+pub struct ValueAnalysis<'a> {
+    pub insts: &'a InstructionsDeref,
+    pub fn_address: u32,
+    /// Loads from constant addresses in these sections are replaced with the value that is there, as long as the
+    /// function hasn't made a call or stored through an unknown pointer yet.
+    pub const_sections: &'a [ConstSection<'a>],
+}
+
+impl<'a> ValueAnalysis<'a> {
+    /// Runs the analysis over the function.
+    pub fn run(&self) -> Result<Results<Self>, DataflowError<InstId>> {
+        let mut preds = BTreeMap::default();
+        let mut succs = BTreeMap::default();
+        compute_preds_and_succs(self.insts, self.fn_address, &mut preds, &mut succs);
+
+        core::run(
+            self,
+            DataflowArgs {
+                preds: &preds,
+                succs: &succs,
+                max_iterations: DEFAULT_MAX_ITERATIONS,
+            },
+        )
+    }
+
+    /// The edges that [`Dataflow::is_feasible`] rules out given the results, i.e. the paths of conditional branches
+    /// whose outcome is known, as pairs of the last instruction of the block and the edge. Edges out of blocks that
+    /// the analysis never reached are kept, since there is no state to decide them with.
+    pub fn infeasible_edges(&self, results: &Results<Self>) -> HashSet<(InstId, Edge)> {
+        let succs = results.successors();
+        let mut infeasible = HashSet::new();
+        let mut reached = false;
+        results.for_each_with_input(self, |cx| {
+            reached |= cx.is_entry();
+            cx.effect();
+            let Some(edges) = succs.get(&cx.idx()) else {
+                return;
+            };
+            if reached {
+                for &succ in edges {
+                    if !self.is_feasible(cx.state(), cx.idx(), &cx.item(), succ) {
+                        let edge = match succ {
+                            SuccessorTarget::Id(to) => Edge::Inst(to),
+                            SuccessorTarget::Return => Edge::Return,
+                        };
+                        infeasible.insert((cx.idx(), edge));
+                    }
+                }
+            }
+            reached = false;
+        });
+        infeasible
+    }
+
+    /// Whether r3 holds a pointer at every reachable `blr`, i.e. whether the function (probably) returns a pointer.
+    pub fn returns_pointer(&self, results: &Results<Self>) -> bool {
+        let mut returns = Cfg::new(self.insts, self.fn_address)
+            .blocks
+            .into_iter()
+            .filter(|block| {
+                matches!(
+                    self.insts[block.last()].1,
+                    Instruction::Bclr {
+                        bo: BranchOptions::BranchAlways,
+                        link: false,
+                        ..
+                    }
+                )
+            })
+            // Only blocks that are reachable have a state.
+            .filter_map(|block| {
+                let mut state = results.state_at(block.start)?.clone();
+                for idx in block.start.0..block.last().0 {
+                    self.apply_effect(&mut state, InstId(idx), &self.insts[InstId(idx)].1);
+                }
+                Some(state.gpr(Gpr::RETURN).is_ptr())
+            })
+            .peekable();
+
+        returns.peek().is_some() && returns.all(|is_ptr| is_ptr)
+    }
+
+    /// The instructions that save LR to the stack and restore it again: `mflr r0; stw r0,N(r1)` in the prologue and
+    /// `lwz r0,N(r1); mtlr r0` in the epilogue. Only the ones that provably move the return address around are
+    /// included.
+    pub fn lr_save_restore(&self, results: &Results<Self>) -> BTreeSet<InstId> {
+        let mut insts = BTreeSet::new();
+        results.for_each_with_input(self, |cx| {
+            cx.effect();
+            // The register that the return address is moved through.
+            let gpr = match cx.item() {
+                Instruction::Mfspr { dest, spr: Spr::Lr }
+                | Instruction::Lwz {
+                    dest,
+                    source: Gpr::STACK_POINTER,
+                    imm: _,
+                } => dest,
+                Instruction::Mtspr {
+                    source,
+                    spr: Spr::Lr,
+                }
+                | Instruction::Stw {
+                    source,
+                    dest: Gpr::STACK_POINTER,
+                    imm: _,
+                } => source,
+                _ => return,
+            };
+            if cx.state().gpr(gpr) == Value::ReturnAddress {
+                insts.insert(cx.idx());
+            }
+        });
+        insts
+    }
+
+    /// Every GPR write that stores a pointer (see [`Value::is_ptr`]) in the register, e.g. the `addi` of a
+    /// `lis`/`addi` pair that loads the address of a global.
+    pub fn pointer_writes(&self, results: &Results<Self>) -> BTreeSet<(InstId, Gpr)> {
+        struct Writes<'a>(&'a mut Vec<Gpr>);
+        impl RegisterVisitor for Writes<'_> {
+            fn write_gpr(&mut self, gpr: Gpr) {
+                self.0.push(gpr);
+            }
+        }
+
+        let mut writes = BTreeSet::new();
+        results.for_each_with_input(self, |cx| {
+            let mut written = Vec::new();
+            cx.item().visit_registers(Writes(&mut written));
+            cx.effect();
+            for gpr in written {
+                if cx.state().gpr(gpr).is_ptr() {
+                    writes.insert((cx.idx(), gpr));
+                }
+            }
+        });
+        writes
+    }
+
+    /// The trip count of the counting loop that the backwards `bdnz` at `latch` closes, i.e. the value CTR holds when
+    /// the loop is entered from the instruction before its first one. `None` if `latch` isn't such a `bdnz`, if the
+    /// body changes CTR in some other way (`mtctr`, calls or another `bdnz`), or if nothing is known about the count.
+    pub fn ctr_loop_count(&self, results: &Results<Self>, latch: InstId) -> Option<Value> {
+        let (offset, inst) = *self.insts.get(latch)?;
+        let Instruction::Bc {
+            bo: BranchOptions::DecCTRBranchIfNotZero,
+            link: false,
+            ..
+        } = inst
+        else {
+            return None;
+        };
+        let Some(BranchTarget::Static(target)) = inst.branch_target(offset.0) else {
+            return None;
+        };
+        let header = InstId::from((target.checked_sub(self.fn_address)? / 4) as usize);
+        if header.0 == 0 || header > latch {
+            return None;
+        }
+
+        let writes_ctr = |inst: &Instruction| match *inst {
+            Instruction::Mtspr { spr: Spr::Ctr, .. }
+            | Instruction::Branch { link: true, .. }
+            | Instruction::Bc { link: true, .. }
+            | Instruction::Bclr { link: true, .. }
+            | Instruction::Bcctr { link: true, .. } => true,
+            Instruction::Bc { bo, .. } | Instruction::Bclr { bo, .. } => bo.decrements_ctr(),
+            _ => false,
+        };
+        if self.insts[header..latch]
+            .iter()
+            .any(|(_, inst)| writes_ctr(inst))
+        {
+            return None;
+        }
+
+        // The state at the top of the loop already includes the back edge, so take the one coming from before it.
+        let preheader = InstId(header.0 - 1);
+        let falls_through = !matches!(
+            self.insts[preheader].1,
+            Instruction::Branch { link: false, .. }
+                | Instruction::Bclr {
+                    bo: BranchOptions::BranchAlways,
+                    ..
+                }
+                | Instruction::Bcctr {
+                    bo: BranchOptions::BranchAlways,
+                    link: false,
+                    ..
+                }
+        );
+        if !falls_through {
+            return None;
+        }
+
+        let mut count = None;
+        results.for_each_with_input(self, |cx| {
+            cx.effect();
+            if cx.idx() == preheader {
+                count = Some(cx.state().registers.sprs.ctr.clone());
+            }
+        });
+        count.filter(|count| !matches!(count, Value::Any | Value::Uninit))
+    }
+
+    /// Whether the conditional branch `inst` is taken in `state`, if that is known. `state` is the state after the
+    /// branch, so for `bdnz` and `bdz` it holds the already decremented CTR. The forms that test both CTR and a
+    /// condition are never considered known.
+    pub fn branch_taken(state: &BlockState, inst: &Instruction) -> Option<bool> {
+        let (Instruction::Bc { bo, bi, .. }
+        | Instruction::Bclr { bo, bi, .. }
+        | Instruction::Bcctr { bo, bi, .. }) = *inst
+        else {
+            return None;
+        };
+
+        let bit = || {
+            let (crf, crb) = crb_from_index(bi);
+            let value = state.registers.sprs.cr(crf, crb).as_int()?.value;
+            Some(value != 0)
+        };
+        let ctr_is_zero = || Some(state.registers.sprs.ctr.as_int()?.value == 0);
+        match bo {
+            BranchOptions::BranchAlways => Some(true),
+            BranchOptions::BranchIfTrue => bit(),
+            BranchOptions::BranchIfFalse => bit().map(|bit| !bit),
+            BranchOptions::DecCTRBranchIfNotZero => ctr_is_zero().map(|zero| !zero),
+            BranchOptions::DecCTRBranchIfZero => ctr_is_zero(),
+            BranchOptions::DecCTRBranchIfFalse
+            | BranchOptions::DecCTRZeroBranchIfFalse
+            | BranchOptions::DecCTRBranchIfTrue
+            | BranchOptions::DecCTRZeroBranchIfTrue => None,
+        }
+    }
+}
+
+impl<'a> Dataflow for ValueAnalysis<'a> {
+    type Idx = InstId;
+    type BlockState = BlockState;
+    type BlockItem = Instruction;
+    type RecordingState = ();
+
+    fn pre_block_record(&self, _: &mut Self::RecordingState, _: &mut Self::BlockState) {}
+
+    fn post_block_record(&self, _: &mut Self::RecordingState, _: &mut Self::BlockState) {}
+
+    fn initial_idx() -> Self::Idx {
+        InstId(0)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (Self::Idx, Self::BlockItem)> {
+        ti_iter(self.insts).map(|(i, &(_, inst))| (i, inst))
+    }
+
+    fn iter_block(&self, idx: Self::Idx) -> impl Iterator<Item = (Self::Idx, Self::BlockItem)> {
+        // `run` stops at the end of the block, so this doesn't need to know where the block ends.
+        // Branches can point past the end of the function, in which case the block is empty.
+        iter::zip(idx.0.., self.insts.get(idx..).unwrap_or_default())
+            .map(|(i, &(_, inst))| (InstId(i), inst))
+    }
+
+    /// Every register and all of memory starts out holding whatever the caller put there, except for the volatile
+    /// registers that aren't used to pass arguments (r0, r11 and r12): those hold nothing meaningful until written.
+    fn initial_state(&self) -> Self::BlockState {
+        let mut state = BlockState {
+            entry_memory: true,
+            ..BlockState::default()
+        };
+        for gpr in (0..32).map(Gpr) {
+            let value = if gpr.is_volatile() && !(3..=10).contains(&gpr.0) {
+                Value::Uninit
+            } else {
+                Value::Param(gpr)
+            };
+            state.set_gpr(gpr, value);
+        }
+        state.set_spr(Spr::Lr, Value::ReturnAddress);
+        state
+    }
+
+    /// Drops the edge that a conditional branch with a known outcome never takes.
+    fn is_feasible(
+        &self,
+        state: &Self::BlockState,
+        idx: Self::Idx,
+        item: &Self::BlockItem,
+        succ: SuccessorTarget<Self>,
+    ) -> bool {
+        // A conditional call returns to the next instruction whether it is taken or not.
+        if let Instruction::Bc { link: true, .. }
+        | Instruction::Bclr { link: true, .. }
+        | Instruction::Bcctr { link: true, .. } = item
+        {
+            return true;
+        }
+        let Some(taken) = Self::branch_taken(state, item) else {
+            return true;
+        };
+        let is_next = succ.idx() == Some(idx + 1);
+        // A branch to the next instruction goes there either way.
+        let (offset, _) = self.insts[idx];
+        let targets_next =
+            item.branch_target(offset.0) == Some(BranchTarget::Static(offset.0.wrapping_add(4)));
+
+        if taken {
+            !is_next || targets_next
+        } else {
+            is_next
+        }
+    }
+
+    fn apply_effect(&self, state: &mut Self::BlockState, idx: Self::Idx, data: &Self::BlockItem) {
+        match *data {
+            Instruction::Addi { dest, source, imm } => {
+                let imm = Value::u32(imm.0 as u32);
+                let value = if source == Gpr::ZERO {
+                    // li
+                    imm
+                } else {
+                    state.gpr(source) + imm
+                };
+                state.set_gpr(dest, value);
+            }
+            Instruction::Addis { dest, add, imm } => {
+                let imm = Value::high_half(imm.0);
+                let value = match add {
+                    Some(source) => state.gpr(source) + imm,
+                    // lis
+                    None => imm,
+                };
+                state.set_gpr(dest, value);
+            }
+            Instruction::Ori { source, dest, imm } => {
+                let value = state.gpr(source) | Value::u32(imm.0 as u32);
+                state.set_gpr(dest, value);
+            }
+            Instruction::Oris { source, dest, imm } => {
+                let value = state.gpr(source) | Value::u32((imm.0 as u32) << 16);
+                state.set_gpr(dest, value);
+            }
+            Instruction::Or {
+                source,
+                dest,
+                or_with,
+                rc: _,
+            } => {
+                let value = state.gpr(source) | state.gpr(or_with);
+                state.set_gpr(dest, value);
+            }
+            Instruction::Subf {
+                dest,
+                source_b,
+                source_a,
+                ..
+            } => {
+                let value = state.gpr(source_a) - state.gpr(source_b);
+                // The record and overflow forms also write CR0 and XER.
+                Self::clobber_outputs(state, data);
+                state.set_gpr(dest, value);
+            }
+            // The carrying forms are used to build 64-bit arithmetic out of 32-bit halves: the first instruction sets
+            // the carry for the low words, and the extended forms add it to the high words. Subtractions are computed
+            // as `~a + b + 1`, where the extended forms use the carry instead of the 1.
+            Instruction::Subfic { dest, source, simm } => {
+                let (a, b) = (state.gpr(source), Value::u32(simm as u32));
+                let carry = (!a.clone()).carry(&b, &Value::u32(1));
+                Self::write_with_carry(state, data, dest, b - a, carry);
+            }
+            Instruction::Addc {
+                dest,
+                source_a,
+                source_b,
+                ..
+            } => {
+                let (a, b) = (state.gpr(source_a), state.gpr(source_b));
+                let carry = a.carry(&b, &Value::u32(0));
+                Self::write_with_carry(state, data, dest, a + b, carry);
+            }
+            Instruction::Subfc {
+                dest,
+                source_a,
+                source_b,
+                ..
+            } => {
+                let (a, b) = (state.gpr(source_a), state.gpr(source_b));
+                let carry = (!a.clone()).carry(&b, &Value::u32(1));
+                Self::write_with_carry(state, data, dest, b - a, carry);
+            }
+            Instruction::Adde {
+                dest,
+                source_a,
+                source_b,
+                ..
+            } => {
+                let (a, b) = (state.gpr(source_a), state.gpr(source_b));
+                let ca = state.registers.sprs.xer.ca.clone();
+                let carry = a.carry(&b, &ca);
+                Self::write_with_carry(state, data, dest, a + b + ca, carry);
+            }
+            Instruction::Subfe {
+                dest,
+                source_a,
+                source_b,
+                ..
+            } => {
+                let (a, b) = (!state.gpr(source_a), state.gpr(source_b));
+                let ca = state.registers.sprs.xer.ca.clone();
+                let carry = a.carry(&b, &ca);
+                Self::write_with_carry(state, data, dest, a + b + ca, carry);
+            }
+            Instruction::Addze { dest, source, .. } => {
+                let a = state.gpr(source);
+                let ca = state.registers.sprs.xer.ca.clone();
+                let carry = a.carry(&Value::u32(0), &ca);
+                Self::write_with_carry(state, data, dest, a + ca, carry);
+            }
+            Instruction::Addme { dest, source, .. } => {
+                let a = state.gpr(source);
+                let ca = state.registers.sprs.xer.ca.clone();
+                let carry = a.carry(&Value::u32(u32::MAX), &ca);
+                Self::write_with_carry(state, data, dest, a + ca - Value::u32(1), carry);
+            }
+            Instruction::Add {
+                dest,
+                source_a,
+                source_b,
+                ..
+            } => {
+                let value = state.gpr(source_a) + state.gpr(source_b);
+                // The overflow forms also write XER.
+                Self::clobber_outputs(state, data);
+                state.set_gpr(dest, value);
+            }
+            Instruction::Neg { dest, source, .. } => {
+                let value = -state.gpr(source);
+                Self::clobber_outputs(state, data);
+                state.set_gpr(dest, value);
+            }
+            Instruction::Slw {
+                source,
+                dest,
+                shift,
+                ..
+            } => {
+                let value = state.gpr(source) << shift_amount(state.gpr(shift));
+                Self::clobber_outputs(state, data);
+                state.set_gpr(dest, value);
+            }
+            Instruction::Sraw {
+                source,
+                dest,
+                shift,
+                ..
+            } => {
+                let value = state.gpr(source) >> shift_amount(state.gpr(shift));
+                // Also writes the carry.
+                Self::clobber_outputs(state, data);
+                state.set_gpr(dest, value);
+            }
+            Instruction::Srawi {
+                source, dest, sh, ..
+            } => {
+                let value = state.gpr(source) >> Value::u32(sh.0 as u32);
+                Self::clobber_outputs(state, data);
+                state.set_gpr(dest, value);
+            }
+            Instruction::And {
+                source1,
+                source2,
+                dest,
+                ..
+            } => {
+                let value = state.gpr(source1) & state.gpr(source2);
+                state.set_gpr(dest, value);
+            }
+            Instruction::Andi { source, dest, imm } => {
+                // The immediate is zero-extended, and this always records into CR0.
+                let value = state.gpr(source) & Value::u32(imm.0 as u32);
+                state.set_gpr(dest, value);
+            }
+            Instruction::Xor {
+                source, dest, rhs, ..
+            }
+            | Instruction::Nand {
+                source, dest, rhs, ..
+            }
+            | Instruction::Nor {
+                source, dest, rhs, ..
+            }
+            | Instruction::Eqv {
+                source, dest, rhs, ..
+            }
+            | Instruction::Andc {
+                source, dest, rhs, ..
+            }
+            | Instruction::Orc {
+                source, dest, rhs, ..
+            } => {
+                let (lhs, rhs) = (state.gpr(source), state.gpr(rhs));
+                let value = match *data {
+                    Instruction::Xor { .. } => lhs ^ rhs,
+                    Instruction::Nand { .. } => !(lhs & rhs),
+                    Instruction::Nor { .. } => !(lhs | rhs),
+                    Instruction::Eqv { .. } => !(lhs ^ rhs),
+                    Instruction::Andc { .. } => lhs & !rhs,
+                    Instruction::Orc { .. } => lhs | !rhs,
+                    _ => unreachable!(),
+                };
+                state.set_gpr(dest, value);
+            }
+            Instruction::Rlwinm {
+                source,
+                dest,
+                rot_bits,
+                mask_start,
+                mask_end,
+                rc: _,
+            } => {
+                let value = state
+                    .gpr(source)
+                    .rotate_and_mask(rot_bits.0, mask_start.0, mask_end.0);
+                state.set_gpr(dest, value);
+            }
+            Instruction::Mfspr { dest, spr } => {
+                state.set_gpr(dest, state.spr(spr));
+            }
+            Instruction::Mtspr { source, spr } => {
+                state.set_spr(spr, state.gpr(source));
+            }
+            Instruction::Stw { source, dest, imm } => {
+                let address = state.effective_address(dest, imm.0);
+                state.store_word(&address, state.gpr(source));
+            }
+            Instruction::Stwu { source, dest, imm } => {
+                let address = state.effective_address(dest, imm.0);
+                state.store_word(&address, state.gpr(source));
+                state.set_gpr(dest, address);
+            }
+            Instruction::Stwx {
+                source,
+                dest,
+                index,
+            } => {
+                let address = state.indexed_address(dest, index);
+                state.store_word(&address, state.gpr(source));
+            }
+            Instruction::Stwux {
+                source,
+                dest,
+                index,
+            } => {
+                let address = state.indexed_address(dest, index);
+                state.store_word(&address, state.gpr(source));
+                state.set_gpr(dest, address);
+            }
+            Instruction::Lwz { dest, source, imm } => {
+                let address = state.effective_address(source, imm.0);
+                state.set_gpr(dest, self.load_word(state, &address));
+            }
+            Instruction::Stmw { source, dest, imm } => {
+                // Stores every register from `source` up to r31 to consecutive words.
+                let address = state.effective_address(dest, imm.0);
+                for (i, gpr) in (source.0..32).map(Gpr).enumerate() {
+                    let slot = address.clone() + Value::u32(i as u32 * 4);
+                    state.store_word(&slot, state.gpr(gpr));
+                }
+            }
+            Instruction::Lmw { dest, source, imm } => {
+                // The counterpart to `stmw`, loading every register from `dest` up to r31.
+                let address = state.effective_address(source, imm.0);
+                for (i, gpr) in (dest.0..32).map(Gpr).enumerate() {
+                    let slot = address.clone() + Value::u32(i as u32 * 4);
+                    let value = self.load_word(state, &slot);
+                    state.set_gpr(gpr, value);
+                }
+            }
+            Instruction::Branch { link: true, .. }
+            | Instruction::Bc { link: true, .. }
+            | Instruction::Bclr { link: true, .. }
+            | Instruction::Bcctr { link: true, .. } => {
+                // The callee is free to clobber any of the volatile registers (including the volatile CR fields, CTR
+                // and XER), as well as memory. It can also write to our stack frame if we passed it the address of a
+                // stack slot. For the conditional forms we don't know whether the call happens, so the same applies.
+                if state.stack_address_in_gprs((3..=10).map(Gpr)) {
+                    state.stack.clear();
+                }
+                for gpr in (0..32).map(Gpr).filter(Gpr::is_volatile) {
+                    state.set_gpr(gpr, Value::Any);
+                }
+                for crf in [0, 1, 5, 6, 7].map(Crf) {
+                    state.update_cr_field(crf, None);
+                    state.registers.sprs.cr[crf.0 as usize].so = Value::Any;
+                }
+                state.set_spr(Spr::Ctr, Value::Any);
+                state.set_spr(Spr::Xer(()), Value::Any);
+                state.clobber_memory();
+                // LR is set to the address after the call whether or not a conditional call is taken.
+                let (addr, _) = self.insts[idx];
+                state.set_spr(Spr::Lr, Value::u32(addr.0.wrapping_add(4)));
+            }
+            Instruction::Stb { .. }
+            | Instruction::Stbu { .. }
+            | Instruction::Stbx { .. }
+            | Instruction::Sth { .. }
+            | Instruction::Sthu { .. }
+            | Instruction::Sthx { .. }
+            | Instruction::StwcxRc { .. }
+            | Instruction::Stfs { .. }
+            | Instruction::Stfsu { .. }
+            | Instruction::Stfd { .. }
+            | Instruction::Stfdu { .. }
+            | Instruction::Dcbz { .. } => {
+                // Stores that we don't track, so just forget everything we know about memory, and the stack if this
+                // might be a store to it. The update forms also write back their base register.
+                state.clobber_memory();
+                if state.stack_address_in_gprs(Self::inputs(data)) {
+                    state.stack.clear();
+                }
+                Self::clobber_outputs(state, data);
+            }
+            Instruction::Cmp {
+                source_a,
+                source_b,
+                crf,
+                ..
+            }
+            | Instruction::Cmpl {
+                source_a,
+                source_b,
+                crf,
+                ..
+            } => {
+                let signed = matches!(data, Instruction::Cmp { .. });
+                let ordering = state.gpr(source_a).compare(&state.gpr(source_b), signed);
+                state.update_cr_field(crf, ordering);
+            }
+            Instruction::Cmpi { source, imm, crf } => {
+                // The immediate is sign-extended.
+                let imm = Value::u32(imm.0 as i16 as u32);
+                let ordering = state.gpr(source).compare(&imm, true);
+                state.update_cr_field(crf, ordering);
+            }
+            Instruction::Cmpli {
+                source, imm, crf, ..
+            } => {
+                let imm = Value::u32(imm.0.into());
+                let ordering = state.gpr(source).compare(&imm, false);
+                state.update_cr_field(crf, ordering);
+            }
+            Instruction::Bc { bo, .. } | Instruction::Bclr { bo, .. } if bo.decrements_ctr() => {
+                let sprs = &mut state.registers.sprs;
+                sprs.ctr = sprs.ctr.clone() - Value::u32(1);
+            }
+            Instruction::Branch { link: false, .. }
+            | Instruction::Bc { .. }
+            | Instruction::Bclr { .. } => {}
+            _ => {
+                tracing::warn!("value analysis does not model {data:?}, clobbering its outputs");
+                Self::clobber_outputs(state, data);
+            }
+        }
+
+        // The record forms (`subf.`, `andi.`, ...) also compare their result against zero.
+        match data.cr0_result() {
+            Some(Cr0Result::Compare(result)) => {
+                let ordering = state
+                    .gpr(result)
+                    .as_int()
+                    .map(|int| (int.value as i32).cmp(&0));
+                state.update_cr_field(Crf(0), ordering);
+            }
+            // Whether the reservation still held isn't known.
+            Some(Cr0Result::StoreConditional) => state.update_cr_field(Crf(0), None),
+            None => {}
+        }
+    }
+}
+
+/// Only the low 6 bits of a shift amount in a register are used.
+fn shift_amount(value: Value) -> Value {
+    match value.as_int() {
+        Some(amount) => Value::u32(amount.value & 0x3f),
+        None => value,
+    }
+}
+
+impl ValueAnalysis<'_> {
+    fn load_word(&self, state: &BlockState, address: &Value) -> Value {
+        // The sections are only trusted as long as nothing could have written to them, in case they aren't actually
+        // read-only.
+        if let Some(address) = address.as_int()
+            && state.entry_memory
+            && !state.memory.contains_key(&address.value)
+            && let Some(value) = self
+                .const_sections
+                .iter()
+                .find_map(|section| section.read_word(address.value))
+        {
+            return Value::u32(value);
+        }
+
+        state.load_word(address)
+    }
+
+    /// The GPRs that the instruction reads.
+    fn inputs(inst: &Instruction) -> Vec<Gpr> {
+        struct Vis<'a>(&'a mut Vec<Gpr>);
+        impl RegisterVisitor for Vis<'_> {
+            fn read_gpr(&mut self, gpr: Gpr) {
+                self.0.push(gpr);
+            }
+        }
+
+        let mut inputs = Vec::new();
+        inst.visit_registers(Vis(&mut inputs));
+        inputs
+    }
+
+    /// Writes the result of one of the carrying arithmetic instructions. The record and overflow forms also write CR0
+    /// and XER[OV], which aren't modeled.
+    fn write_with_carry(
+        state: &mut BlockState,
+        inst: &Instruction,
+        dest: Gpr,
+        value: Value,
+        carry: Value,
+    ) {
+        Self::clobber_outputs(state, inst);
+        state.set_gpr(dest, value);
+        state.registers.sprs.xer.ca = carry;
+    }
+
+    /// We don't know what this instruction computes, but we do know which registers it writes to,
+    /// so conservatively forget their values.
+    fn clobber_outputs(state: &mut BlockState, inst: &Instruction) {
+        struct Vis<'a> {
+            state: &'a mut BlockState,
+        }
+        impl RegisterVisitor for Vis<'_> {
+            fn write_gpr(&mut self, gpr: Gpr) {
+                self.state.set_gpr(gpr, Value::Any);
+            }
+            fn write_spr(&mut self, spr: MicroSpr) {
+                let sprs = &mut self.state.registers.sprs;
+                match spr {
+                    Spr::Lr => sprs.lr = Value::Any,
+                    Spr::Ctr => sprs.ctr = Value::Any,
+                    Spr::Msr => sprs.msr = Value::Any,
+                    Spr::Xer(XerRegister::So) => sprs.xer.so = Value::Any,
+                    Spr::Xer(XerRegister::Ov) => sprs.xer.ov = Value::Any,
+                    Spr::Xer(XerRegister::Ca) => sprs.xer.ca = Value::Any,
+                    // The other SPRs are only written by `mtspr`, which is modeled.
+                    _ => {}
+                }
+            }
+            // Without this, a branch after e.g. `crxor` or `fcmpu` would be decided by an older compare.
+            fn write_crb(&mut self, crf: Crf, crb: Crb) {
+                *self.state.registers.sprs.cr_mut(crf, crb) = Value::Any;
+            }
+        }
+
+        inst.visit_registers(Vis { state });
+    }
+}
@@ -1,9 +1,13 @@
-use std::{collections::HashMap, ops::ControlFlow};
+use std::{
+    collections::{BTreeSet, HashMap},
+    ops::ControlFlow,
+};
 
 use ppc32::{
     Instruction,
     instruction::{
-        BranchOptions, Crb, Crf, Gpr, Register, Spr, XerRegister, compute_branch_target,
+        BranchOptions, Crb, Crf, Fpr, Gpr, Immediate, MicroSpr, Register, RegisterVisitor, Spr,
+        XerRegister, compute_branch_target,
     },
 };
 use typed_index_collections::TiVec;
@@ -12,8 +16,12 @@ use crate::{
     ast::stmt::{VarId, Variable, VariableFlags, VariableVisibility},
     dataflow::{
         InstId,
-        core::{Dataflow, Results, Successors},
-        ssa::{BlockState, DefUseMap, Generation, LocalGenerationAnalysis, RegisterWithGeneration},
+        cfg::inst_at,
+        core::{Dataflow, Results, SuccessorTarget, Successors},
+        ssa::{
+            BlockState, DefUseMap, Generation, LocalGenerationAnalysis, RegisterWithGeneration,
+            decided_branch,
+        },
     },
     visit::{self, JoinResult, PhiLocal, SuccessorsVisitor, VisitorCx, VisitorStaticData},
 };
@@ -23,7 +31,7 @@ pub struct StackRelativeAddress {
     offset: i16,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Variables {
     list: TiVec<VarId, Variable>,
     reg_to_var: HashMap<RegisterWithGeneration, VarId>,
@@ -56,6 +64,15 @@ impl Variables {
         )
     }
 
+    #[track_caller]
+    pub fn mk_fpr_var(&mut self, fpr: Fpr, state: &BlockState, origin: VarId) -> VarId {
+        self.mk_reg_var(
+            Register::Fpr(fpr),
+            state.registers.fprs[fpr.0 as usize].generation,
+            origin,
+        )
+    }
+
     #[track_caller]
     pub fn mk_reg_var(&mut self, reg: Register, generation: Generation, origin: VarId) -> VarId {
         self.mk_root_reg_var(reg, generation, self.list[origin].vis())
@@ -75,6 +92,20 @@ impl Variables {
         )
     }
 
+    #[track_caller]
+    pub fn mk_root_fpr_var(
+        &mut self,
+        fpr: Fpr,
+        state: &BlockState,
+        vis: VariableVisibility,
+    ) -> VarId {
+        self.mk_root_reg_var(
+            Register::Fpr(fpr),
+            state.registers.fprs[fpr.0 as usize].generation,
+            vis,
+        )
+    }
+
     #[track_caller]
     pub fn mk_root_reg_var(
         &mut self,
@@ -84,8 +115,10 @@ impl Variables {
     ) -> VarId {
         let mut flags = VariableFlags::from_vis(vis);
 
-        if let Register::Gpr(Gpr::STACK_POINTER) = reg {
-            flags |= VariableFlags::RSP;
+        match reg {
+            Register::Gpr(Gpr::STACK_POINTER) => flags |= VariableFlags::RSP,
+            Register::Fpr(_) => flags |= VariableFlags::FPR,
+            _ => {}
         }
 
         let key = self.list.push_and_get_key(Variable::new(flags));
@@ -102,6 +135,14 @@ impl Variables {
         )
     }
 
+    #[track_caller]
+    pub fn id_by_fpr(&self, reg: Fpr, state: &BlockState) -> VarId {
+        self.id_by_reg(
+            Register::Fpr(reg),
+            state.registers.fprs[reg.0 as usize].generation,
+        )
+    }
+
     pub fn optional_id_by_reg(&self, reg: Register, generation: Generation) -> Option<VarId> {
         let reg = RegisterWithGeneration { reg, generation };
         self.reg_to_var.get(&reg).copied()
@@ -186,6 +227,46 @@ pub fn xer_variables(
     })
 }
 
+/// The registers that an instruction reads and writes. Instructions without a model in the C output are treated as
+/// opaque definitions of what they write, computed from the values they read.
+pub fn opaque_operands(inst: &Instruction) -> (Vec<Register>, Vec<Register>) {
+    #[derive(Default)]
+    struct Vis {
+        reads: Vec<Register>,
+        writes: Vec<Register>,
+    }
+    impl RegisterVisitor for &mut Vis {
+        fn read_gpr(&mut self, gpr: Gpr) {
+            self.reads.push(Register::Gpr(gpr));
+        }
+        fn read_fpr(&mut self, fpr: Fpr) {
+            self.reads.push(Register::Fpr(fpr));
+        }
+        fn write_gpr(&mut self, gpr: Gpr) {
+            self.writes.push(Register::Gpr(gpr));
+        }
+        fn write_fpr(&mut self, fpr: Fpr) {
+            self.writes.push(Register::Fpr(fpr));
+        }
+        fn read_spr(&mut self, spr: MicroSpr) {
+            self.reads.push(Register::Spr(spr));
+        }
+        fn read_crb(&mut self, crf: Crf, crb: Crb) {
+            self.reads.push(Register::Cr(crf, crb));
+        }
+        fn write_crb(&mut self, crf: Crf, crb: Crb) {
+            self.writes.push(Register::Cr(crf, crb));
+        }
+        fn write_spr(&mut self, spr: MicroSpr) {
+            self.writes.push(Register::Spr(spr));
+        }
+    }
+
+    let mut vis = Vis::default();
+    inst.visit_registers(&mut vis);
+    (vis.reads, vis.writes)
+}
+
 fn mk_cr_variables(state: &BlockState, this: &mut CollectVariables<'_>) {
     let crf = Crf(0);
     for (crb, vis) in cr_bits_variables(&state, this.def_use_map, crf) {
@@ -209,6 +290,8 @@ fn mk_xer_variables(state: &BlockState, vars: &[XerRegister], this: &mut Collect
 struct CollectVariables<'a> {
     variables: &'a mut Variables,
     def_use_map: &'a DefUseMap,
+    /// Instructions that only save or restore LR.
+    lr_save_restore: &'a BTreeSet<InstId>,
 }
 
 impl SuccessorsVisitor for CollectVariables<'_> {
@@ -223,6 +306,15 @@ impl SuccessorsVisitor for CollectVariables<'_> {
         state: &mut BlockState,
     ) -> ControlFlow<()> {
         tracing::debug!(?inst);
+        if self.lr_save_restore.contains(&absolute_idx)
+            && matches!(inst, Instruction::Lwz { .. } | Instruction::Mtspr { .. })
+        {
+            // Restoring LR. Stack addresses aren't normalized yet, so if the prologue saved it before `stwu` set up
+            // the frame, the slot would have a different offset here and the load wouldn't find its variable.
+            // The save itself still gets one, for loads that the value analysis couldn't prove to restore LR.
+            cx.analysis().apply_effect(state, idx, &inst);
+            return ControlFlow::Continue(());
+        }
         match inst {
             Instruction::Stwu {
                 source,
@@ -290,16 +382,19 @@ impl SuccessorsVisitor for CollectVariables<'_> {
                 }
                 ControlFlow::Continue(())
             }
-            Instruction::Mfspr { dest, spr } => {
-                if let Spr::Lr = spr {
-                    let spr = self
-                        .variables
-                        .id_by_reg(Register::Spr(Spr::Lr), state.registers.sprs.lr.generation);
-                    cx.analysis().apply_effect(state, idx, &inst);
-                    self.variables.mk_gpr_var(dest, &state, spr);
-                } else {
-                    todo!()
-                }
+            Instruction::Mfspr { dest, spr: Spr::Lr } => {
+                let spr = self
+                    .variables
+                    .id_by_reg(Register::Spr(Spr::Lr), state.registers.sprs.lr.generation);
+                cx.analysis().apply_effect(state, idx, &inst);
+                self.variables.mk_gpr_var(dest, &state, spr);
+                ControlFlow::Continue(())
+            }
+            // Written as an opaque `__mfspr`, see `build_path`.
+            Instruction::Mfspr { dest, spr: _ } => {
+                cx.analysis().apply_effect(state, idx, &inst);
+                self.variables
+                    .mk_root_gpr_var(dest, state, VariableVisibility::Visible);
                 ControlFlow::Continue(())
             }
             Instruction::AddicRc {
@@ -338,6 +433,19 @@ impl SuccessorsVisitor for CollectVariables<'_> {
                 }
                 ControlFlow::Continue(())
             }
+            Instruction::Addis { dest, add, imm: _ } => {
+                if let Some(add) = add {
+                    let source = self.variables.id_by_gpr(add, state);
+                    cx.analysis().apply_effect(state, idx, &inst);
+                    self.variables.mk_gpr_var(dest, state, source);
+                } else {
+                    // lis, a constant just like li
+                    cx.analysis().apply_effect(state, idx, &inst);
+                    self.variables
+                        .mk_root_gpr_var(dest, state, VariableVisibility::Visible);
+                }
+                ControlFlow::Continue(())
+            }
             Instruction::Subfic {
                 dest,
                 source,
@@ -408,7 +516,7 @@ impl SuccessorsVisitor for CollectVariables<'_> {
             Instruction::Andi {
                 source,
                 dest,
-                simm: _,
+                imm: _,
             } => {
                 let source = self.variables.id_by_gpr(source, state);
                 cx.analysis().apply_effect(state, idx, &inst);
@@ -416,6 +524,22 @@ impl SuccessorsVisitor for CollectVariables<'_> {
                 mk_cr_variables(state, self);
                 ControlFlow::Continue(())
             }
+            // `not`
+            Instruction::Nor {
+                source,
+                dest,
+                rhs,
+                rc,
+            } if source == rhs => {
+                let source = self.variables.id_by_gpr(source, state);
+                cx.analysis().apply_effect(state, idx, &inst);
+                self.variables.mk_gpr_var(dest, state, source);
+
+                if rc {
+                    mk_cr_variables(state, self);
+                }
+                ControlFlow::Continue(())
+            }
             Instruction::Stw { source, dest, imm } => {
                 let source = self.variables.id_by_gpr(source, &state);
                 cx.analysis().apply_effect(state, idx, &inst);
@@ -438,27 +562,90 @@ impl SuccessorsVisitor for CollectVariables<'_> {
                         VariableVisibility::Visible,
                     );
                     ControlFlow::Continue(())
-                } else {
-                    let idx = InstId((target - cx.analysis().fn_address) / 4);
+                } else if let Some(idx) =
+                    inst_at(cx.analysis().fn_address, cx.analysis().insts.len(), target)
+                {
                     let _ = visit::visit_path(self, cx, Some(state), idx, end_idx);
                     ControlFlow::Break(())
+                } else {
+                    // A tail call, which leaves the function.
+                    ControlFlow::Break(())
                 }
             }
             Instruction::Bc {
-                bo: _,
+                bo,
+                hint: _,
                 bi: _,
                 target,
                 mode,
-                link,
+                link: false,
+            } if inst_at(
+                cx.analysis().fn_address,
+                cx.analysis().insts.len(),
+                compute_branch_target(inst_addr, mode, target),
+            )
+            .is_none() =>
+            {
+                // A conditional tail call, which either leaves the function or falls through.
+                let ctr = bo.decrements_ctr().then(|| {
+                    self.variables
+                        .id_by_reg(Register::Spr(Spr::Ctr), state.registers.sprs.ctr.generation)
+                });
+                cx.analysis().apply_effect(state, idx, &inst);
+                if let Some(ctr) = ctr {
+                    self.variables.mk_reg_var(
+                        Register::Spr(Spr::Ctr),
+                        state.registers.sprs.ctr.generation,
+                        ctr,
+                    );
+                }
+                if matches!(
+                    decided_branch(cx.data.succs, absolute_idx),
+                    Some(SuccessorTarget::Return)
+                ) {
+                    // The value analysis proved that the call is always made.
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            }
+            Instruction::Bc {
+                bo,
+                hint: _,
+                bi: _,
+                target,
+                mode,
+                link: false,
             } => {
-                assert!(!link);
-
                 let true_idx = InstId(
                     (compute_branch_target(inst_addr, mode, target) - cx.analysis().fn_address) / 4,
                 );
                 let false_idx = InstId(absolute_idx.0 + 1);
 
+                let ctr = bo.decrements_ctr().then(|| {
+                    self.variables
+                        .id_by_reg(Register::Spr(Spr::Ctr), state.registers.sprs.ctr.generation)
+                });
                 cx.analysis().apply_effect(state, idx, &inst);
+                if let Some(ctr) = ctr {
+                    // `bdnz` and friends decrement CTR before checking it
+                    self.variables.mk_reg_var(
+                        Register::Spr(Spr::Ctr),
+                        state.registers.sprs.ctr.generation,
+                        ctr,
+                    );
+                }
+
+                // The value analysis proved that the branch always goes the same way.
+                if let Some(SuccessorTarget::Id(target_idx)) =
+                    decided_branch(cx.data.succs, absolute_idx)
+                {
+                    if target_idx == false_idx {
+                        return ControlFlow::Continue(());
+                    }
+                    let _ = visit::visit_path(self, cx, Some(state), target_idx, end_idx);
+                    return ControlFlow::Break(());
+                }
 
                 let JoinResult {
                     true_res: _,
@@ -499,6 +686,18 @@ impl SuccessorsVisitor for CollectVariables<'_> {
                 }
                 ControlFlow::Break(())
             }
+            Instruction::Lwz {
+                dest: Gpr::STACK_POINTER,
+                source: Gpr::STACK_POINTER,
+                imm: Immediate(0),
+            } => {
+                // Tearing down the stack frame by loading the back chain that `stwu` stored,
+                // so this is the same stack pointer variable as before.
+                let source = self.variables.id_by_gpr(Gpr::STACK_POINTER, state);
+                cx.analysis().apply_effect(state, idx, &inst);
+                self.variables.mk_gpr_var(Gpr::STACK_POINTER, state, source);
+                ControlFlow::Continue(())
+            }
             Instruction::Lwz { dest, source, imm } => {
                 // TODO: normalize address
                 if source == Gpr::STACK_POINTER {
@@ -512,32 +711,221 @@ impl SuccessorsVisitor for CollectVariables<'_> {
                 }
                 ControlFlow::Continue(())
             }
-            Instruction::Mtspr { source, spr } => {
-                if let Spr::Lr = spr {
-                    let source = self.variables.id_by_gpr(source, &state);
-                    cx.analysis().apply_effect(state, idx, &inst);
+            Instruction::Mtspr {
+                source,
+                spr: spr @ (Spr::Lr | Spr::Ctr),
+            } => {
+                let reg = match spr {
+                    Spr::Lr => Register::Spr(Spr::Lr),
+                    _ => Register::Spr(Spr::Ctr),
+                };
+                let source = self.variables.id_by_gpr(source, &state);
+                cx.analysis().apply_effect(state, idx, &inst);
+                let generation = state.registers.by_register(reg).unwrap().generation;
+                self.variables.mk_reg_var(reg, generation, source);
+                ControlFlow::Continue(())
+            }
+            // Written as an opaque `__mtspr`, see `build_path`.
+            Instruction::Mtspr { .. } => {
+                cx.analysis().apply_effect(state, idx, &inst);
+                ControlFlow::Continue(())
+            }
+            Instruction::Bc { bo, link: true, .. }
+            | Instruction::Bclr {
+                bo,
+                hint: _,
+                bi: _,
+                link: true,
+            }
+            | Instruction::Bcctr {
+                bo,
+                hint: _,
+                bi: _,
+                link: true,
+            } => {
+                // Conditional calls and calls through a function pointer, which return to the next instruction like
+                // `bl`.
+                let ctr = bo.decrements_ctr().then(|| {
+                    self.variables
+                        .id_by_reg(Register::Spr(Spr::Ctr), state.registers.sprs.ctr.generation)
+                });
+                cx.analysis().apply_effect(state, idx, &inst);
+                if let Some(ctr) = ctr {
                     self.variables.mk_reg_var(
-                        Register::Spr(Spr::Lr),
-                        state.registers.sprs.lr.generation,
-                        source,
+                        Register::Spr(Spr::Ctr),
+                        state.registers.sprs.ctr.generation,
+                        ctr,
                     );
-                } else {
-                    todo!()
                 }
+                self.variables
+                    .mk_root_gpr_var(Gpr::RETURN, state, VariableVisibility::Visible);
                 ControlFlow::Continue(())
             }
-            Instruction::Bclr { bo, bi: _, link } => {
-                assert!(!link);
-
+            Instruction::Bclr {
+                bo,
+                hint: _,
+                bi: _,
+                link: false,
+            } => {
+                let ctr = bo.decrements_ctr().then(|| {
+                    self.variables
+                        .id_by_reg(Register::Spr(Spr::Ctr), state.registers.sprs.ctr.generation)
+                });
                 cx.analysis().apply_effect(state, idx, &inst);
-                if bo == BranchOptions::BranchAlways {
+                if let Some(ctr) = ctr {
+                    self.variables.mk_reg_var(
+                        Register::Spr(Spr::Ctr),
+                        state.registers.sprs.ctr.generation,
+                        ctr,
+                    );
+                }
+                let always_returns = matches!(
+                    decided_branch(cx.data.succs, absolute_idx),
+                    Some(SuccessorTarget::Return)
+                );
+                if bo == BranchOptions::BranchAlways || always_returns {
                     ControlFlow::Break(())
                 } else {
                     let _ = visit::visit_path(self, cx, Some(state), absolute_idx + 1, end_idx);
                     ControlFlow::Break(())
                 }
             }
-            _ => todo!("{inst:x?}"),
+            Instruction::Lfs { dest, source, imm } | Instruction::Lfd { dest, source, imm } => {
+                // TODO: normalize address
+                if source == Gpr::STACK_POINTER {
+                    let mem_var = self.variables.id_by_stack_mem(imm.0);
+                    cx.analysis().apply_effect(state, idx, &inst);
+                    self.variables.mk_fpr_var(dest, state, mem_var);
+                } else {
+                    cx.analysis().apply_effect(state, idx, &inst);
+                    self.variables
+                        .mk_root_fpr_var(dest, state, VariableVisibility::Visible);
+                }
+                ControlFlow::Continue(())
+            }
+            Instruction::Stfs { source, dest, imm } | Instruction::Stfd { source, dest, imm } => {
+                let source = self.variables.id_by_fpr(source, state);
+                cx.analysis().apply_effect(state, idx, &inst);
+                // Like `stw`, only stack-relative stores get a variable.
+                if dest == Gpr::STACK_POINTER {
+                    self.variables.mk_stack_mem_var(imm.0, source);
+                }
+                ControlFlow::Continue(())
+            }
+            Instruction::Fadd {
+                dest,
+                source_a,
+                source_b,
+                rc: false,
+            }
+            | Instruction::Fadds {
+                dest,
+                source_a,
+                source_b,
+                rc: false,
+            }
+            | Instruction::Fsub {
+                dest,
+                source_a,
+                source_b,
+                rc: false,
+            }
+            | Instruction::Fsubs {
+                dest,
+                source_a,
+                source_b,
+                rc: false,
+            }
+            | Instruction::Fmul {
+                dest,
+                source_a,
+                source_c: source_b,
+                rc: false,
+            }
+            | Instruction::Fmuls {
+                dest,
+                source_a,
+                source_c: source_b,
+                rc: false,
+            }
+            | Instruction::Fdiv {
+                dest,
+                source_a,
+                source_b,
+                rc: false,
+            }
+            | Instruction::Fdivs {
+                dest,
+                source_a,
+                source_b,
+                rc: false,
+            } => {
+                let source_a = self
+                    .variables
+                    .get_vis(self.variables.id_by_fpr(source_a, state));
+                let source_b = self
+                    .variables
+                    .get_vis(self.variables.id_by_fpr(source_b, state));
+                cx.analysis().apply_effect(state, idx, &inst);
+                self.variables
+                    .mk_root_fpr_var(dest, state, source_a & source_b);
+                ControlFlow::Continue(())
+            }
+            Instruction::Fmr {
+                dest,
+                source,
+                rc: false,
+            }
+            | Instruction::Fneg {
+                dest,
+                source,
+                rc: false,
+            }
+            | Instruction::Fabs {
+                dest,
+                source,
+                rc: false,
+            }
+            | Instruction::Fnabs {
+                dest,
+                source,
+                rc: false,
+            }
+            | Instruction::Frsp {
+                dest,
+                source,
+                rc: false,
+            } => {
+                let source = self.variables.id_by_fpr(source, state);
+                cx.analysis().apply_effect(state, idx, &inst);
+                self.variables.mk_fpr_var(dest, state, source);
+                ControlFlow::Continue(())
+            }
+            _ => {
+                tracing::warn!("no C equivalent for {inst:?}, writing it as an opaque intrinsic");
+                cx.analysis().apply_effect(state, idx, &inst);
+                for reg in opaque_operands(&inst).1 {
+                    // Untracked SPRs don't get variables.
+                    let Some(generation) =
+                        state.registers.by_register(reg).map(|reg| reg.generation)
+                    else {
+                        continue;
+                    };
+                    // Like the results of other instructions, GPRs and FPRs are always visible, and the CR and XER
+                    // bits only if something reads them.
+                    let vis = match reg {
+                        Register::Gpr(_) | Register::Fpr(_) => VariableVisibility::Visible,
+                        Register::Cr(..) | Register::Spr(_)
+                            if !self.def_use_map.has_uses(reg, generation) =>
+                        {
+                            VariableVisibility::Hidden
+                        }
+                        Register::Cr(..) | Register::Spr(_) => VariableVisibility::Visible,
+                    };
+                    self.variables.mk_root_reg_var(reg, generation, vis);
+                }
+                ControlFlow::Continue(())
+            }
         }
     }
 }
@@ -547,6 +935,7 @@ pub fn infer_variables<'a>(
     analysis: &LocalGenerationAnalysis<'a>,
     def_use_map: &DefUseMap,
     succs: &Successors<LocalGenerationAnalysis<'a>>,
+    lr_save_restore: &BTreeSet<InstId>,
 ) -> Variables {
     fn add_initial_hidden_root_var(variables: &mut Variables, register: Register) {
         variables.mk_root_reg_var(register, Generation::INITIAL, VariableVisibility::Hidden);
@@ -565,6 +954,14 @@ pub fn infer_variables<'a>(
         // Callee saved registers are hidden
         add_initial_hidden_root_var(&mut variables, Register::Gpr(Gpr(reg)));
     }
+    for reg in 14..=31 {
+        // Unlike the GPRs, these only get a variable if the function saves them, since most functions don't touch the
+        // FPRs at all.
+        let register = Register::Fpr(Fpr(reg));
+        if def_use_map.has_uses(register, Generation::INITIAL) {
+            add_initial_hidden_root_var(&mut variables, register);
+        }
+    }
 
     // Parameters
     let mut end_of_params = false;
@@ -580,9 +977,18 @@ pub fn infer_variables<'a>(
         }
     }
 
+    // Float parameters are passed in f1-f8, independently of the integer ones.
+    for reg in 1..=8 {
+        let register = Register::Fpr(Fpr(reg));
+        if def_use_map.has_uses(register, Generation::INITIAL) {
+            variables.mk_root_reg_var(register, Generation::INITIAL, VariableVisibility::Visible);
+        }
+    }
+
     let mut vars = CollectVariables {
         variables: &mut variables,
         def_use_map,
+        lr_save_restore,
     };
     let data = VisitorStaticData {
         analysis,
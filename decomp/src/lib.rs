@@ -1,37 +1,148 @@
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 
 use ppc32::{
     Decoder, Instruction,
-    decoder::DecodeError,
     instruction::{BranchOptions, Gpr, Spr, compute_branch_target},
 };
 use tracing::Level;
 
 use crate::{
-    ast::{build::AstBuildParams, write::WriteContext},
+    ast::{Ast, build::AstBuildParams, item::ItemKind, ty::TyKind, write::WriteContext},
     dataflow::{
-        InstructionsDeref,
-        core::DataflowArgs,
+        InstId, InstructionsDeref,
+        arguments::CallArguments,
+        cfg::inst_at,
+        core::{DEFAULT_MAX_ITERATIONS, DataflowArgs, DataflowError},
         loops::find_loops,
-        ssa::{LocalGenerationAnalysis, compute_preds_and_succs, def_use_map},
-        variables::infer_variables,
+        ssa::{LocalGenerationAnalysis, compute_feasible_preds_and_succs, def_use_map},
+        value::{ConstSection, Value, ValueAnalysis},
+        variables::{Variables, infer_variables},
     },
+    symbols::SymbolMap,
+    ti_utils::ti_iter,
 };
 
 pub mod ast;
 pub mod dataflow;
+pub mod symbols;
 pub mod ti_utils;
 pub mod visit;
 
+/// Decompiles the function and writes the C code to `writer`. Loads from `const_sections` are assumed to read the
+/// contents of the section, so that branches on them can be decided.
 pub fn decompile_into_ast_writer(
     instructions: &InstructionsDeref,
     fn_address: u32,
+    const_sections: &[ConstSection<'_>],
+    symbols: &SymbolMap,
     writer: &mut impl ast::write::Writer,
-) -> Result<(), DecodeError> {
+) -> Result<(), DataflowError<InstId>> {
+    let (ast, variables) = build_ast(instructions, fn_address, const_sections)?;
+
+    ast::write::write_ast(
+        &ast,
+        &WriteContext {
+            variables: &variables,
+            symbols,
+        },
+        writer,
+    );
+    Ok(())
+}
+
+/// The prototype of a function, along with a few other facts about it, as detected by the decompiler.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionSummary {
+    pub address: u32,
+    /// The GPRs that hold the parameters, in order.
+    pub params: Vec<Gpr>,
+    pub return_ty: TyKind,
+    /// How many bytes the function reserves on the stack with `stwu r1`, or `None` if it doesn't set up a frame.
+    pub frame_size: Option<u32>,
+    /// Functions called from this one, including tail calls. Calls through a function pointer are not included.
+    pub calls: BTreeSet<u32>,
+}
+
+/// Runs the decompiler over the function, but only reports its prototype and a few other facts instead of the code.
+pub fn summarize(
+    instructions: &InstructionsDeref,
+    fn_address: u32,
+    const_sections: &[ConstSection<'_>],
+) -> Result<FunctionSummary, DataflowError<InstId>> {
+    let (ast, _) = build_ast(instructions, fn_address, const_sections)?;
+    let ItemKind::Function(function) = &ast.items[0].kind;
+
+    let frame_size = instructions.iter().find_map(|&(_, inst)| match inst {
+        Instruction::Stwu {
+            source: Gpr::STACK_POINTER,
+            dest: Gpr::STACK_POINTER,
+            imm,
+        } if imm.0 < 0 => Some(imm.0.unsigned_abs().into()),
+        _ => None,
+    });
+
+    Ok(FunctionSummary {
+        address: fn_address,
+        // Float parameters are passed in FPRs, which aren't part of the summary.
+        params: (0..function.params.len())
+            .filter(|&i| !matches!(function.params[i].ty.kind, TyKind::F32 | TyKind::F64))
+            .map(|i| Gpr(3 + i as u8))
+            .collect(),
+        return_ty: function.return_ty.kind,
+        frame_size,
+        calls: find_calls(instructions, fn_address),
+    })
+}
+
+/// Collects the targets of all direct calls in the function, including tail calls. Calls through a function pointer
+/// are not included, since their target isn't known statically.
+pub fn find_calls(instructions: &InstructionsDeref, fn_address: u32) -> BTreeSet<u32> {
+    instructions
+        .iter()
+        .filter_map(|&(addr, inst)| {
+            let target = inst.branch_target(addr.0)?.address()?;
+            let link = matches!(
+                inst,
+                Instruction::Branch { link: true, .. } | Instruction::Bc { link: true, .. }
+            );
+            // Branches out of the function are tail calls.
+            (link || inst_at(fn_address, instructions.len(), target).is_none()).then_some(target)
+        })
+        .collect()
+}
+
+fn build_ast(
+    instructions: &InstructionsDeref,
+    fn_address: u32,
+    const_sections: &[ConstSection<'_>],
+) -> Result<(Ast, Variables), DataflowError<InstId>> {
+    let value_analysis = ValueAnalysis {
+        insts: instructions,
+        fn_address,
+        const_sections,
+    };
+    let value_results = value_analysis.run()?;
+    let returns_pointer = value_analysis.returns_pointer(&value_results);
+    let lr_save_restore = value_analysis.lr_save_restore(&value_results);
+    let pointer_writes = value_analysis.pointer_writes(&value_results);
+    let arguments = CallArguments {
+        insts: instructions,
+        fn_address,
+    }
+    .run()?;
+
+    // Everything else works on the CFG without the paths that the value analysis proved are never taken, so that
+    // e.g. a branch on a constant doesn't turn into an `if` whose other arm can't happen.
+    let infeasible = value_analysis.infeasible_edges(&value_results);
     let mut preds = BTreeMap::default();
     let mut succs = BTreeMap::default();
-
-    compute_preds_and_succs(&instructions, fn_address, &mut preds, &mut succs);
+    compute_feasible_preds_and_succs(
+        &instructions,
+        fn_address,
+        |from, edge| !infeasible.contains(&(from, edge)),
+        &mut preds,
+        &mut succs,
+    );
 
     let analysis = LocalGenerationAnalysis {
         insts: &instructions,
@@ -42,14 +153,31 @@ pub fn decompile_into_ast_writer(
         DataflowArgs {
             preds: &preds,
             succs: &succs,
+            max_iterations: DEFAULT_MAX_ITERATIONS,
         },
-    );
+    )?;
 
     let def_use_map = def_use_map(&analysis, &local_generations);
 
-    let variables = infer_variables(&local_generations, &analysis, &def_use_map, &succs);
+    let variables = infer_variables(
+        &local_generations,
+        &analysis,
+        &def_use_map,
+        &succs,
+        &lr_save_restore,
+    );
 
-    let loops = find_loops(&preds, &succs);
+    let loops = find_loops(&succs);
+    // The `bdnz` loops that are known to run at least once, which makes them `for` loops.
+    let counted_loops = ti_iter(instructions)
+        .map(|(idx, _)| idx)
+        .filter(|&latch| {
+            matches!(
+                value_analysis.ctr_loop_count(&value_results, latch),
+                Some(Value::Int(count)) if count.value != 0
+            )
+        })
+        .collect();
 
     let ast = ast::build(AstBuildParams {
         fn_address,
@@ -60,16 +188,14 @@ pub fn decompile_into_ast_writer(
         variables: &variables,
         succs: &succs,
         loops: &loops,
+        returns_pointer,
+        lr_save_restore: &lr_save_restore,
+        arguments: &arguments,
+        counted_loops: &counted_loops,
+        pointer_writes: &pointer_writes,
     });
 
-    ast::write::write_ast(
-        &ast,
-        &WriteContext {
-            variables: &variables,
-        },
-        writer,
-    );
-    Ok(())
+    Ok((ast, variables))
 }
 
 /// Heuristic to tell if a branch instruction is a tail call (true), or an intra-function branch (false).
@@ -113,7 +239,12 @@ pub fn branch_is_fn_call(buf: &[u8], fn_addr: u32, instr: Instruction, inst_addr
 
     {
         // Next heuristics revolve around checking what's around the target_addr.
-        let Some(data) = buf.get(target_off as usize - 4..target_off as usize + 4) else {
+        // A target at the very start of the buffer has no instruction before it, which is just as good a sign of a
+        // function start as being out of bounds.
+        let Some(data) = (target_off as usize)
+            .checked_sub(4)
+            .and_then(|before| buf.get(before..target_off as usize + 4))
+        else {
             tracing::debug!("target out of bounds, assuming tail call");
             return true;
         };
@@ -162,9 +293,23 @@ pub fn branch_is_fn_call(buf: &[u8], fn_addr: u32, instr: Instruction, inst_addr
     confidence >= 10
 }
 
+/// How far a path through a function has gotten with saving and restoring LR (`mflr r0` ... `mtlr r0`).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum LrState {
+    Untouched,
+    Saved,
+    Restored,
+}
+
 pub fn detect_fn_boundaries(buf: &[u8], fn_address: u32, start: usize) -> &[u8] {
     #[must_use]
-    fn visit_block(buf: &[u8], seen: &mut HashSet<usize>, fn_address: u32, start: usize) -> usize {
+    fn visit_block(
+        buf: &[u8],
+        seen: &mut HashSet<usize>,
+        fn_address: u32,
+        start: usize,
+        mut lr: LrState,
+    ) -> usize {
         if !seen.insert(start) {
             return start;
         }
@@ -181,13 +326,48 @@ pub fn detect_fn_boundaries(buf: &[u8], fn_address: u32, start: usize) -> &[u8]
                     match instr {
                         Instruction::Bclr {
                             bo: BranchOptions::BranchAlways,
+                            hint: _,
                             bi: _,
                             link: false,
                         } => {
                             return instr_off + 4;
                         }
+                        Instruction::Bcctr {
+                            bo: BranchOptions::BranchAlways,
+                            hint: _,
+                            bi: _,
+                            link: false,
+                        } => {
+                            // Indirect jump (jump table or tail call), we can't follow it.
+                            return instr_off + 4;
+                        }
+                        _ if instr.is_unconditional_trap() => {
+                            return instr_off + 4;
+                        }
+                        Instruction::Mfspr {
+                            dest: _,
+                            spr: Spr::Lr,
+                        } => {
+                            lr = LrState::Saved;
+                        }
+                        Instruction::Mtspr {
+                            source: _,
+                            spr: Spr::Lr,
+                        } if lr == LrState::Saved => {
+                            lr = LrState::Restored;
+                        }
+                        Instruction::Branch {
+                            target: _,
+                            mode: _,
+                            link: false,
+                        } if lr == LrState::Restored => {
+                            // LR was saved and restored again, so this is the epilogue and the branch can only be a
+                            // tail call.
+                            return instr_off + 4;
+                        }
                         Instruction::Bc {
                             bo: _,
+                            hint: _,
                             bi: _,
                             target,
                             mode,
@@ -199,13 +379,15 @@ pub fn detect_fn_boundaries(buf: &[u8], fn_address: u32, start: usize) -> &[u8]
                                 && let target_addr = compute_branch_target(inst_addr, mode, target)
                                 && let Some(target) = target_addr
                                     .checked_sub(fn_address)
+                                // Targets past the end of the buffer can't be followed, so they're tail calls too
+                                && (target as usize) < buf.len()
                             {
-                                Some(visit_block(buf, seen, fn_address, target as usize))
+                                Some(visit_block(buf, seen, fn_address, target as usize, lr))
                             } else {
                                 None
                             };
 
-                            let cur_end = visit_block(buf, seen, fn_address, instr_off + 4);
+                            let cur_end = visit_block(buf, seen, fn_address, instr_off + 4, lr);
 
                             return match (branch_end, cur_end) {
                                 (Some(be), ce) => be.max(ce),
@@ -218,9 +400,13 @@ pub fn detect_fn_boundaries(buf: &[u8], fn_address: u32, start: usize) -> &[u8]
                                 // i.e. definitely a fn call
                                 && let target_addr = compute_branch_target(inst_addr, mode, target)
                                 && let Some(target) = target_addr.checked_sub(fn_address)
-                                && !branch_is_fn_call(buf, fn_address, instr, inst_addr)
+                                && (target as usize) < buf.len()
+                                // While LR is saved, the function still has to go through its epilogue before it
+                                // can leave, so the branch can't be a tail call.
+                                && (lr == LrState::Saved
+                                    || !branch_is_fn_call(buf, fn_address, instr, inst_addr))
                             {
-                                return visit_block(buf, seen, fn_address, target as usize);
+                                return visit_block(buf, seen, fn_address, target as usize, lr);
                             }
                         }
                         _ => {}
@@ -234,6 +420,12 @@ pub fn detect_fn_boundaries(buf: &[u8], fn_address: u32, start: usize) -> &[u8]
         }
     }
 
-    let end = visit_block(buf, &mut HashSet::new(), fn_address, start);
+    let end = visit_block(
+        buf,
+        &mut HashSet::new(),
+        fn_address,
+        start,
+        LrState::Untouched,
+    );
     &buf[start..end]
 }
@@ -0,0 +1,75 @@
+use std::{collections::BTreeMap, error::Error, fmt::Display};
+
+/// Names for addresses, e.g. of functions and globals, so that they can be printed instead of the raw address.
+#[derive(Debug, Default, Clone)]
+pub struct SymbolMap {
+    names: BTreeMap<u32, String>,
+}
+
+#[derive(Debug)]
+pub enum ParseSymbolsError {
+    MissingName { line: usize },
+    InvalidAddress { line: usize },
+}
+
+impl Display for ParseSymbolsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseSymbolsError::MissingName { line } => {
+                write!(f, "line {line}: expected a name after the address")
+            }
+            ParseSymbolsError::InvalidAddress { line } => {
+                write!(f, "line {line}: invalid address, expected a hex number")
+            }
+        }
+    }
+}
+
+impl Error for ParseSymbolsError {}
+
+impl SymbolMap {
+    /// Parses a symbol map with one `<address> <name>` pair per line, where the address is in hex
+    /// (optionally prefixed with `0x`). Empty lines and lines starting with `#` are ignored.
+    pub fn parse(source: &str) -> Result<Self, ParseSymbolsError> {
+        let mut names = BTreeMap::new();
+
+        for (i, line) in source.lines().enumerate() {
+            let line_number = i + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (address, name) = line
+                .split_once(char::is_whitespace)
+                .ok_or(ParseSymbolsError::MissingName { line: line_number })?;
+            let address = u32::from_str_radix(address.trim_start_matches("0x"), 16)
+                .map_err(|_| ParseSymbolsError::InvalidAddress { line: line_number })?;
+            names.insert(address, name.trim().to_owned());
+        }
+
+        Ok(Self { names })
+    }
+
+    /// Returns the name of the symbol at exactly this address.
+    pub fn get(&self, address: u32) -> Option<&str> {
+        self.names.get(&address).map(String::as_str)
+    }
+
+    /// Describes the address for humans: the symbol name followed by the address if there is one, otherwise just the
+    /// address.
+    pub fn describe(&self, address: u32) -> String {
+        match self.get(address) {
+            Some(name) => format!("{name} ({address:#x})"),
+            None => format!("{address:#x}"),
+        }
+    }
+
+    /// Returns the address of the symbol with this name. If several symbols share the name, the lowest address wins.
+    pub fn address_of(&self, name: &str) -> Option<u32> {
+        self.names
+            .iter()
+            .find(|(_, n)| *n == name)
+            .map(|(&address, _)| address)
+    }
+}
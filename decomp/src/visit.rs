@@ -96,7 +96,7 @@ pub fn visit_and_join_paths<V: SuccessorsVisitor>(
         && let VisitPathResult::Value { state: true_state } = &true_res
         && let VisitPathResult::Value { state: false_state } = &false_res
     {
-        let next_state = cx.results().get(common_merge_inst).unwrap();
+        let next_state = cx.results().state_at(common_merge_inst).unwrap();
 
         next_state
             .registers
@@ -164,7 +164,7 @@ pub fn visit_path<V: SuccessorsVisitor>(
         id: start_idx,
         parent: Some(cx),
     };
-    let mut block_state = cx.results().get(start_idx).map_or_else(
+    let mut block_state = cx.results().state_at(start_idx).map_or_else(
         || {
             assert_eq!(start_idx, InstId(0));
             BlockState::default()
@@ -181,7 +181,7 @@ pub fn visit_path<V: SuccessorsVisitor>(
 
     for (idx, (inst_addr, inst)) in ti_iter(&cx.analysis().insts[start_idx..]) {
         let absolute_idx = InstId(start_idx.0 + idx.0);
-        if absolute_idx != start_idx && cx.results().get(absolute_idx).is_some() {
+        if absolute_idx != start_idx && cx.results().state_at(absolute_idx).is_some() {
             return visit_path(visitor, cx, Some(&mut block_state), absolute_idx, end_idx);
         }
 
@@ -0,0 +1,259 @@
+use decomp::{
+    ast::{
+        Ast,
+        expr::{BinaryExpr, BinaryOp, Expr, ExprKind, FnCallTarget, UnaryExpr, UnaryOp},
+        item::{Function, Item, ItemKind, Local, Parameter},
+        stmt::{Stmt, StmtKind, VarId, VariableVisibility},
+        ty::{Ty, TyKind},
+        write::{StringWriter, WriteContext, write_ast},
+    },
+    dataflow::{ssa::Generation, variables::Variables},
+    symbols::SymbolMap,
+};
+use ppc32::instruction::{Gpr, Register};
+
+/// Creates `count` visible variables, v0 through v(count - 1).
+fn variables(count: u8) -> Variables {
+    let mut variables = Variables::default();
+    for reg in 0..count {
+        variables.mk_root_reg_var(
+            Register::Gpr(Gpr(reg + 3)),
+            Generation::INITIAL,
+            VariableVisibility::Visible,
+        );
+    }
+    variables
+}
+
+fn render(function: Function, variables: &Variables) -> String {
+    let ast = Ast {
+        items: vec![Item {
+            kind: ItemKind::Function(function),
+        }],
+    };
+    let mut writer = StringWriter::new();
+    write_ast(
+        &ast,
+        &WriteContext {
+            variables,
+            symbols: &SymbolMap::default(),
+        },
+        &mut writer,
+    );
+    writer.into_string()
+}
+
+fn u32_ty() -> Ty {
+    Ty { kind: TyKind::U32 }
+}
+
+fn imm(value: i16) -> Expr {
+    Expr {
+        kind: ExprKind::Immediate16(value),
+    }
+}
+
+fn binary(left: Expr, op: BinaryOp, right: Expr) -> Expr {
+    Expr {
+        kind: ExprKind::Binary(BinaryExpr {
+            op,
+            left: Box::new(left),
+            right: Box::new(right),
+        }),
+    }
+}
+
+fn assign(dest: Expr, value: Expr) -> Stmt {
+    Stmt {
+        kind: StmtKind::Assign { dest, value },
+        source: None,
+    }
+}
+
+/// Renders `dest = value;` as the only statement of a function.
+fn render_assignment(value: Expr) -> String {
+    let variables = variables(3);
+    let output = render(
+        Function {
+            name: "f".to_owned(),
+            return_ty: Ty { kind: TyKind::Void },
+            params: Vec::new(),
+            locals: Vec::new(),
+            stmts: vec![assign(Expr::var(VarId(0)), value)],
+        },
+        &variables,
+    );
+    let line = output.lines().nth(1).unwrap().trim();
+    line.strip_prefix("v0 = ")
+        .and_then(|line| line.strip_suffix(';'))
+        .unwrap()
+        .to_owned()
+}
+
+#[test]
+fn function_with_control_flow() {
+    let variables = variables(3);
+    let function = Function {
+        name: "0x80001234".to_owned(),
+        return_ty: u32_ty(),
+        params: vec![Parameter {
+            var_id: VarId(0),
+            ty: u32_ty(),
+        }],
+        locals: vec![
+            Local {
+                var_id: VarId(1),
+                ty: u32_ty(),
+            },
+            Local {
+                var_id: VarId(2),
+                ty: Ty { kind: TyKind::Ptr },
+            },
+        ],
+        stmts: vec![
+            assign(Expr::var(VarId(1)), imm(0)),
+            Stmt {
+                kind: StmtKind::While {
+                    condition: binary(Expr::var(VarId(1)), BinaryOp::Lt, Expr::var(VarId(0))),
+                    body: vec![
+                        assign(
+                            Expr::var(VarId(1)),
+                            binary(Expr::var(VarId(1)), BinaryOp::Add, imm(1)),
+                        ),
+                        Stmt {
+                            kind: StmtKind::If {
+                                condition: binary(Expr::var(VarId(1)), BinaryOp::Eq, imm(5)),
+                                then_stmts: vec![Stmt {
+                                    kind: StmtKind::Break,
+                                    source: None,
+                                }],
+                                else_stmts: Vec::new(),
+                            },
+                            source: None,
+                        },
+                    ],
+                },
+                source: None,
+            },
+            assign(
+                Expr::var(VarId(2)),
+                Expr {
+                    kind: ExprKind::AddrOf(VarId(1)),
+                },
+            ),
+            Stmt {
+                kind: StmtKind::Return(Some(Expr {
+                    kind: ExprKind::FnCall(
+                        FnCallTarget::Addr(0x80005678),
+                        vec![Expr::var(VarId(2)), imm(-1)],
+                    ),
+                })),
+                source: None,
+            },
+        ],
+    };
+
+    assert_eq!(
+        render(function, &variables),
+        "\
+u32 0x80001234(u32 v0) {
+    u32 v1;
+    void *v2;
+    v1 = 0;
+    while (v1 < v0) {
+        v1 = v1 + 1;
+        if (v1 == 5) {
+            break;
+        }
+    }
+    v2 = &v1;
+    return 0x80005678(v2, -1);
+}"
+    );
+}
+
+#[test]
+fn parentheses_follow_precedence() {
+    let v1 = || Expr::var(VarId(1));
+    let v2 = || Expr::var(VarId(2));
+
+    // Left-associative operators only need parentheses on the right
+    assert_eq!(
+        render_assignment(binary(
+            binary(v1(), BinaryOp::Sub, v2()),
+            BinaryOp::Sub,
+            imm(1)
+        )),
+        "v1 - v2 - 1"
+    );
+    assert_eq!(
+        render_assignment(binary(
+            v1(),
+            BinaryOp::Sub,
+            binary(v2(), BinaryOp::Sub, imm(1))
+        )),
+        "v1 - (v2 - 1)"
+    );
+
+    // & binds looser than ==
+    assert_eq!(
+        render_assignment(binary(
+            binary(v1(), BinaryOp::BitAnd, v2()),
+            BinaryOp::Eq,
+            imm(0)
+        )),
+        "(v1 & v2) == 0"
+    );
+    assert_eq!(
+        render_assignment(binary(
+            v1(),
+            BinaryOp::BitAnd,
+            binary(v2(), BinaryOp::Eq, imm(0))
+        )),
+        "v1 & v2 == 0"
+    );
+
+    // Unary operators bind tighter than any binary operator
+    assert_eq!(
+        render_assignment(Expr {
+            kind: ExprKind::Unary(UnaryExpr {
+                op: UnaryOp::Not,
+                operand: Box::new(binary(v1(), BinaryOp::Lt, v2())),
+            }),
+        }),
+        "!(v1 < v2)"
+    );
+    assert_eq!(
+        render_assignment(binary(
+            Expr::deref(Some(VarId(1)), 8),
+            BinaryOp::Add,
+            Expr::deref(Some(VarId(2)), 0)
+        )),
+        "*(u32 *)(v1 + 8) + *(u32 *)v2"
+    );
+}
+
+#[test]
+fn negated_comparisons_flip_their_operator() {
+    let v1 = || Expr::var(VarId(1));
+    let v2 = || Expr::var(VarId(2));
+
+    let negated = [
+        (BinaryOp::Eq, "v1 != v2"),
+        (BinaryOp::Ne, "v1 == v2"),
+        (BinaryOp::Lt, "v1 >= v2"),
+        (BinaryOp::Ge, "v1 < v2"),
+        (BinaryOp::Gt, "v1 <= v2"),
+        (BinaryOp::Le, "v1 > v2"),
+    ];
+    for (op, expected) in negated {
+        assert_eq!(render_assignment(!binary(v1(), op, v2())), expected);
+    }
+
+    // Anything else is still negated as a whole
+    assert_eq!(
+        render_assignment(!binary(v1(), BinaryOp::BitAnd, v2())),
+        "!(v1 & v2)"
+    );
+    assert_eq!(render_assignment(!v1()), "!v1");
+}
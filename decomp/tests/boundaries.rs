@@ -0,0 +1,104 @@
+use decomp::detect_fn_boundaries;
+
+const FN_ADDRESS: u32 = 0x80003100;
+
+#[test]
+fn bctr_ends_function() {
+    let code = [
+        0x7d, 0x89, 0x03, 0xa6, // mtctr   r12
+        0x4e, 0x80, 0x04, 0x20, // bctr
+        0x38, 0x60, 0x00, 0x01, // li      r3,1
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ];
+
+    assert_eq!(detect_fn_boundaries(&code, FN_ADDRESS, 0).len(), 8);
+}
+
+#[test]
+fn bctrl_does_not_end_function() {
+    let code = [
+        0x7d, 0x89, 0x03, 0xa6, // mtctr   r12
+        0x4e, 0x80, 0x04, 0x21, // bctrl
+        0x38, 0x60, 0x00, 0x01, // li      r3,1
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ];
+
+    assert_eq!(detect_fn_boundaries(&code, FN_ADDRESS, 0).len(), 16);
+}
+
+#[test]
+fn early_return_does_not_end_function() {
+    let code = [
+        0x2c, 0x03, 0x00, 0x00, // cmpwi   r3,0
+        0x40, 0x82, 0x00, 0x0c, // bne     0x10
+        0x38, 0x60, 0x00, 0x00, // li      r3,0
+        0x4e, 0x80, 0x00, 0x20, // blr
+        0x38, 0x60, 0x00, 0x01, // li      r3,1
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ];
+
+    assert_eq!(detect_fn_boundaries(&code, FN_ADDRESS, 0).len(), 24);
+}
+
+#[test]
+fn branch_after_lr_restore_is_tail_call() {
+    let code = [
+        0x7c, 0x08, 0x02, 0xa6, // mflr    r0
+        0x90, 0x01, 0x00, 0x04, // stw     r0,4(r1)
+        0x94, 0x21, 0xff, 0xf0, // stwu    r1,-16(r1)
+        0x48, 0x00, 0x01, 0x01, // bl      0x10c
+        0x80, 0x01, 0x00, 0x14, // lwz     r0,20(r1)
+        0x38, 0x21, 0x00, 0x10, // addi    r1,r1,16
+        0x7c, 0x08, 0x03, 0xa6, // mtlr    r0
+        0x48, 0x00, 0x00, 0x04, // b       0x20
+        // Next function
+        0x38, 0x60, 0x00, 0x01, // li      r3,1
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ];
+
+    assert_eq!(detect_fn_boundaries(&code, FN_ADDRESS, 0).len(), 32);
+}
+
+#[test]
+fn branch_while_lr_is_saved_is_not_tail_call() {
+    let code = [
+        0x7c, 0x08, 0x02, 0xa6, // mflr    r0
+        0x90, 0x01, 0x00, 0x04, // stw     r0,4(r1)
+        0x94, 0x21, 0xff, 0xf0, // stwu    r1,-16(r1)
+        0x48, 0x00, 0x00, 0x14, // b       0x20
+        // Data that the heuristics would take as the end of the function
+        0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, //
+        0x80, 0x01, 0x00, 0x14, // lwz     r0,20(r1)
+        0x38, 0x21, 0x00, 0x10, // addi    r1,r1,16
+        0x7c, 0x08, 0x03, 0xa6, // mtlr    r0
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ];
+
+    assert_eq!(detect_fn_boundaries(&code, FN_ADDRESS, 0).len(), 48);
+}
+
+#[test]
+fn branches_out_of_the_buffer_are_tail_calls() {
+    let code = [
+        0x2c, 0x03, 0x00, 0x00, // cmpwi   r3,0
+        0x41, 0x82, 0x04, 0x00, // beq     0x404
+        0x7c, 0x08, 0x02, 0xa6, // mflr    r0
+        0x48, 0x00, 0x04, 0x00, // b       0x40c
+    ];
+
+    assert_eq!(detect_fn_boundaries(&code, FN_ADDRESS, 0).len(), 16);
+}
+
+#[test]
+fn branch_to_the_start_of_the_buffer() {
+    let code = [
+        0x38, 0x60, 0x00, 0x01, // li      r3,1
+        0x4b, 0xff, 0xff, 0xfc, // b       0x0
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ];
+
+    assert_eq!(detect_fn_boundaries(&code, FN_ADDRESS, 0).len(), 12);
+}
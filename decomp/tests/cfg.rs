@@ -0,0 +1,126 @@
+use decomp::dataflow::{
+    InstId,
+    cfg::{BasicBlock, BlockId, Cfg, Edge},
+    decode_instructions,
+};
+
+const FN_ADDRESS: u32 = 0x80003100;
+
+fn cfg(code: &[u8]) -> Cfg {
+    let insts = decode_instructions(code, FN_ADDRESS).unwrap();
+    Cfg::new(&insts, FN_ADDRESS)
+}
+
+fn block(start: u32, end: u32, succs: &[Edge]) -> BasicBlock {
+    BasicBlock {
+        start: InstId(start),
+        end: InstId(end),
+        succs: succs.to_vec(),
+    }
+}
+
+#[test]
+fn diamond() {
+    let cfg = cfg(&[
+        0x2c, 0x03, 0x00, 0x00, // cmpwi   r3,0
+        0x41, 0x82, 0x00, 0x0c, // beq     +0xc
+        0x38, 0x60, 0x00, 0x01, // li      r3,1
+        0x48, 0x00, 0x00, 0x08, // b       +0x8
+        0x38, 0x60, 0x00, 0x02, // li      r3,2
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    assert_eq!(
+        cfg.blocks.raw,
+        [
+            block(0, 2, &[Edge::Inst(InstId(4)), Edge::Inst(InstId(2))]),
+            block(2, 4, &[Edge::Inst(InstId(5))]),
+            block(4, 5, &[Edge::Inst(InstId(5))]),
+            block(5, 6, &[Edge::Return]),
+        ]
+    );
+    assert_eq!(cfg.block_of(InstId(1)), Some(BlockId(0)));
+    assert_eq!(cfg.block_of(InstId(3)), Some(BlockId(1)));
+    assert_eq!(cfg.block_of(InstId(5)), Some(BlockId(3)));
+    assert_eq!(cfg.block_of(InstId(6)), None);
+}
+
+#[test]
+fn calls_do_not_split_blocks() {
+    let cfg = cfg(&[
+        0x38, 0x60, 0x00, 0x01, // li      r3,1
+        0x48, 0x00, 0x00, 0x11, // bl      +0x10
+        0x38, 0x60, 0x00, 0x02, // li      r3,2
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    assert_eq!(cfg.blocks.raw, [block(0, 4, &[Edge::Return])]);
+}
+
+#[test]
+fn unconditional_forward_branch() {
+    let cfg = cfg(&[
+        0x38, 0x60, 0x00, 0x01, // li      r3,1
+        0x48, 0x00, 0x00, 0x0c, // b       +0xc
+        0x38, 0x60, 0x00, 0x02, // li      r3,2
+        0x38, 0x60, 0x00, 0x03, // li      r3,3
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    // The instructions skipped by the `b` form their own (unreachable) block.
+    assert_eq!(
+        cfg.blocks.raw,
+        [
+            block(0, 2, &[Edge::Inst(InstId(4))]),
+            block(2, 4, &[Edge::Inst(InstId(4))]),
+            block(4, 5, &[Edge::Return]),
+        ]
+    );
+}
+
+#[test]
+fn branches_out_of_the_function_are_tail_calls() {
+    let cfg = cfg(&[
+        0x2c, 0x03, 0x00, 0x00, // cmpwi   r3,0
+        0x41, 0x82, 0xff, 0x00, // beq     -0x100
+        0x48, 0x00, 0x01, 0x00, // b       +0x100
+    ]);
+
+    assert_eq!(
+        cfg.blocks.raw,
+        [
+            block(0, 2, &[Edge::Return, Edge::Inst(InstId(2))]),
+            block(2, 3, &[Edge::Return]),
+        ]
+    );
+}
+
+#[test]
+fn bdnzlr_returns_or_falls_through() {
+    let cfg = cfg(&[
+        0x7c, 0x69, 0x03, 0xa6, // mtctr   r3
+        0x4e, 0x00, 0x00, 0x20, // bdnzlr
+        0x38, 0x60, 0x00, 0x00, // li      r3,0
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    assert_eq!(
+        cfg.blocks.raw,
+        [
+            block(0, 2, &[Edge::Return, Edge::Inst(InstId(2))]),
+            block(2, 4, &[Edge::Return]),
+        ]
+    );
+}
+
+#[test]
+fn blrl_is_a_call() {
+    let cfg = cfg(&[
+        0x7c, 0x68, 0x03, 0xa6, // mtlr    r3
+        0x4e, 0x80, 0x00, 0x21, // blrl
+        0x4d, 0x82, 0x00, 0x21, // beqlrl
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    assert_eq!(cfg.blocks.raw, [block(0, 4, &[Edge::Return])]);
+}
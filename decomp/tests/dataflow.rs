@@ -0,0 +1,348 @@
+use std::{
+    cell::Cell,
+    collections::{BTreeMap, BTreeSet},
+    iter,
+};
+
+use decomp::dataflow::{
+    InstId, Instructions,
+    core::{self, DEFAULT_MAX_ITERATIONS, Dataflow, DataflowArgs, DataflowError, Join, Results},
+    decode_instructions,
+    ssa::{
+        BlockState, Generation, LocalGenerationAnalysis, LocalRegisterState,
+        compute_preds_and_succs,
+    },
+};
+use ppc32::Instruction;
+
+/// Records which instructions were executed on the way to a block, which changes at every join of two different
+/// paths, and counts how often `apply_effect` is called.
+struct Executed {
+    insts: Instructions,
+    effects: Cell<usize>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq)]
+struct ExecutedState(BTreeSet<InstId>);
+
+impl Join<()> for ExecutedState {
+    fn join(&self, other: &Self, _: &mut ()) -> Self {
+        Self(self.0.union(&other.0).copied().collect())
+    }
+}
+
+impl Dataflow for Executed {
+    type Idx = InstId;
+    type BlockState = ExecutedState;
+    type BlockItem = Instruction;
+    type RecordingState = ();
+
+    fn initial_idx() -> Self::Idx {
+        InstId(0)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (Self::Idx, Self::BlockItem)> {
+        self.iter_block(InstId(0))
+    }
+
+    fn iter_block(&self, idx: Self::Idx) -> impl Iterator<Item = (Self::Idx, Self::BlockItem)> {
+        iter::zip(idx.0.., self.insts.get(idx..).unwrap_or_default())
+            .map(|(i, &(_, inst))| (InstId(i), inst))
+    }
+
+    fn apply_effect(&self, state: &mut Self::BlockState, idx: Self::Idx, _: &Self::BlockItem) {
+        self.effects.set(self.effects.get() + 1);
+        state.0.insert(idx);
+    }
+
+    fn pre_block_record(&self, _: &mut (), _: &mut Self::BlockState) {}
+
+    fn post_block_record(&self, _: &mut (), _: &mut Self::BlockState) {}
+}
+
+fn executed(code: &[u8]) -> Executed {
+    Executed {
+        insts: decode_instructions(code, 0).unwrap(),
+        effects: Cell::new(0),
+    }
+}
+
+fn run(analysis: &Executed) -> Result<Results<Executed>, DataflowError<InstId>> {
+    let mut preds = BTreeMap::default();
+    let mut succs = BTreeMap::default();
+    compute_preds_and_succs(&analysis.insts, 0, &mut preds, &mut succs);
+    core::run(
+        analysis,
+        DataflowArgs {
+            preds: &preds,
+            succs: &succs,
+            max_iterations: DEFAULT_MAX_ITERATIONS,
+        },
+    )
+}
+
+/// Runs the analysis to a fixpoint and returns how many times `apply_effect` was called.
+fn count_effects(code: &[u8]) -> usize {
+    let analysis = executed(code);
+    run(&analysis).unwrap();
+    analysis.effects.get()
+}
+
+#[test]
+fn diamond_visits_each_block_once() {
+    let effects = count_effects(&[
+        0x2c, 0x03, 0x00, 0x00, // cmpwi   r3,0
+        0x41, 0x82, 0x00, 0x0c, // beq     +0xc
+        0x38, 0x60, 0x00, 0x01, // li      r3,1
+        0x48, 0x00, 0x00, 0x08, // b       +0x8
+        0x38, 0x60, 0x00, 0x02, // li      r3,2
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    // Visiting the join block before both arms are done would require a second visit of it (7 effects).
+    assert_eq!(effects, 6);
+}
+
+#[test]
+fn results_expose_cfg_and_entry_states() {
+    let analysis = executed(&[
+        0x2c, 0x03, 0x00, 0x00, // cmpwi   r3,0
+        0x41, 0x82, 0x00, 0x0c, // beq     +0xc
+        0x38, 0x60, 0x00, 0x01, // li      r3,1
+        0x48, 0x00, 0x00, 0x08, // b       +0x8
+        0x38, 0x60, 0x00, 0x02, // li      r3,2
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+    let results = run(&analysis).unwrap();
+
+    let succs: Vec<(InstId, Vec<Option<InstId>>)> = results
+        .successors()
+        .iter()
+        .map(|(&from, edges)| (from, edges.iter().map(|edge| edge.idx()).collect()))
+        .collect();
+    assert_eq!(
+        succs,
+        [
+            (InstId(1), vec![Some(InstId(4)), Some(InstId(2))]),
+            (InstId(3), vec![Some(InstId(5))]),
+            (InstId(4), vec![Some(InstId(5))]),
+            (InstId(5), vec![None]),
+        ]
+    );
+
+    assert_eq!(
+        results.predecessors().iter().collect::<Vec<_>>(),
+        [
+            (&InstId(2), &vec![InstId(1)]),
+            (&InstId(4), &vec![InstId(1)]),
+            (&InstId(5), &vec![InstId(3), InstId(4)]),
+        ]
+    );
+
+    let executed = |idx| {
+        results
+            .state_at(InstId(idx))
+            .map(|state| state.0.iter().map(|i| i.0).collect::<Vec<_>>())
+    };
+    assert_eq!(executed(0), Some(vec![]));
+    assert_eq!(executed(2), Some(vec![0, 1]));
+    assert_eq!(executed(5), Some(vec![0, 1, 2, 3, 4]));
+    // Not the start of a block
+    assert_eq!(executed(3), None);
+}
+
+#[test]
+fn loop_converges_after_one_revisit() {
+    let effects = count_effects(&[
+        0x38, 0x60, 0x00, 0x00, // li      r3,0
+        0x38, 0x63, 0x00, 0x01, // addi    r3,r3,1
+        0x2c, 0x03, 0x00, 0x0a, // cmpwi   r3,10
+        0x40, 0x82, 0xff, 0xf8, // bne     -0x8
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    // Every instruction once, plus one more visit of the loop body once the back edge has brought in the state
+    // from the end of the loop. The exit block is only visited after the loop has converged.
+    assert_eq!(effects, 5 + 3);
+}
+
+/// An analysis whose join never reaches a fixpoint: every join produces a new state.
+struct Diverging {
+    insts: Instructions,
+}
+
+#[derive(Default, Debug, Clone, PartialEq)]
+struct Counter(u32);
+
+impl Join<()> for Counter {
+    fn join(&self, other: &Self, _: &mut ()) -> Self {
+        Self(self.0.max(other.0) + 1)
+    }
+}
+
+impl Dataflow for Diverging {
+    type Idx = InstId;
+    type BlockState = Counter;
+    type BlockItem = Instruction;
+    type RecordingState = ();
+
+    fn initial_idx() -> Self::Idx {
+        InstId(0)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (Self::Idx, Self::BlockItem)> {
+        self.iter_block(InstId(0))
+    }
+
+    fn iter_block(&self, idx: Self::Idx) -> impl Iterator<Item = (Self::Idx, Self::BlockItem)> {
+        iter::zip(idx.0.., self.insts.get(idx..).unwrap_or_default())
+            .map(|(i, &(_, inst))| (InstId(i), inst))
+    }
+
+    fn apply_effect(&self, _: &mut Self::BlockState, _: Self::Idx, _: &Self::BlockItem) {}
+
+    fn widen(&self, state: &mut Self::BlockState) {
+        *state = Counter(u32::MAX);
+    }
+
+    fn describe_change(&self, old: &Self::BlockState, new: &Self::BlockState) -> Option<String> {
+        Some(format!("{} -> {}", old.0, new.0))
+    }
+
+    fn pre_block_record(&self, _: &mut (), _: &mut Self::BlockState) {}
+
+    fn post_block_record(&self, _: &mut (), _: &mut Self::BlockState) {}
+}
+
+#[test]
+fn non_converging_analysis_is_widened() {
+    let analysis = Diverging {
+        insts: decode_instructions(
+            &[
+                0x38, 0x63, 0x00, 0x01, // addi    r3,r3,1
+                0x2c, 0x03, 0x00, 0x0a, // cmpwi   r3,10
+                0x40, 0x82, 0xff, 0xf8, // bne     -0x8
+                0x4e, 0x80, 0x00, 0x20, // blr
+            ],
+            0,
+        )
+        .unwrap(),
+    };
+
+    let mut preds = BTreeMap::default();
+    let mut succs = BTreeMap::default();
+    compute_preds_and_succs(&analysis.insts, 0, &mut preds, &mut succs);
+    let results = core::run(
+        &analysis,
+        DataflowArgs {
+            preds: &preds,
+            succs: &succs,
+            max_iterations: 50,
+        },
+    )
+    .unwrap();
+
+    assert!(results.widened());
+    assert_eq!(results.last_change(), Some("49 -> 50"));
+    assert_eq!(results.state_at(InstId(0)), Some(&Counter(u32::MAX)));
+    assert_eq!(results.state_at(InstId(3)), Some(&Counter(u32::MAX)));
+}
+
+#[test]
+fn converging_analysis_is_not_widened() {
+    let analysis = Executed {
+        insts: decode_instructions(
+            &[
+                0x38, 0x63, 0x00, 0x01, // addi    r3,r3,1
+                0x2c, 0x03, 0x00, 0x0a, // cmpwi   r3,10
+                0x40, 0x82, 0xff, 0xf8, // bne     -0x8
+                0x4e, 0x80, 0x00, 0x20, // blr
+            ],
+            0,
+        )
+        .unwrap(),
+        effects: Cell::new(0),
+    };
+
+    let mut preds = BTreeMap::default();
+    let mut succs = BTreeMap::default();
+    compute_preds_and_succs(&analysis.insts, 0, &mut preds, &mut succs);
+    let results = core::run(
+        &analysis,
+        DataflowArgs {
+            preds: &preds,
+            succs: &succs,
+            max_iterations: 50,
+        },
+    )
+    .unwrap();
+
+    assert!(!results.widened());
+    assert_eq!(results.last_change(), None);
+}
+
+#[test]
+fn generation_changes_are_described() {
+    let insts = decode_instructions(&[0x4e, 0x80, 0x00, 0x20], 0).unwrap(); // blr
+    let analysis = LocalGenerationAnalysis {
+        insts: &insts,
+        fn_address: 0,
+    };
+
+    let old = BlockState::default();
+    let mut new = old.clone();
+    new.registers.gprs[7] = LocalRegisterState {
+        generation: Generation::INITIAL.next(),
+        phi_origins: Some([Generation::INITIAL, Generation::INITIAL.next()]),
+        highest_generation: Generation::INITIAL.next(),
+    };
+
+    assert_eq!(analysis.describe_change(&old, &old), None);
+    assert_eq!(
+        analysis.describe_change(&old, &new).as_deref(),
+        Some("r7: 0 -> 1 (phi of 0 and 1)")
+    );
+}
+
+#[test]
+fn falling_off_the_end_is_an_error() {
+    let analysis = executed(&[
+        0x38, 0x60, 0x00, 0x01, // li      r3,1
+        0x38, 0x60, 0x00, 0x02, // li      r3,2
+    ]);
+
+    assert_eq!(
+        run(&analysis).err(),
+        Some(DataflowError::OutOfBounds { block: InstId(2) })
+    );
+}
+
+#[test]
+fn block_without_successors_is_an_error() {
+    let analysis = executed(&[
+        0x38, 0x60, 0x00, 0x01, // li      r3,1
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    // Not something `compute_preds_and_succs` produces, but other analyses could get their CFG from elsewhere.
+    let result = core::run(
+        &analysis,
+        DataflowArgs {
+            preds: &BTreeMap::new(),
+            succs: &BTreeMap::new(),
+            max_iterations: DEFAULT_MAX_ITERATIONS,
+        },
+    );
+    assert_eq!(
+        result.err(),
+        Some(DataflowError::Unterminated {
+            block: InstId(0),
+            last: InstId(1)
+        })
+    );
+}
+
+#[test]
+fn empty_function_is_not_an_error() {
+    assert!(run(&executed(&[])).is_ok());
+}
@@ -0,0 +1,101 @@
+use decomp::dataflow::{
+    InstId, Instructions, decode_instructions,
+    liveness::{GprSet, Liveness},
+};
+use ppc32::instruction::Gpr;
+
+const FN_ADDRESS: u32 = 0x80003100;
+
+fn insts(code: &[u8]) -> Instructions {
+    decode_instructions(code, FN_ADDRESS).unwrap()
+}
+
+fn gprs(set: GprSet) -> Vec<u8> {
+    set.iter().map(|gpr| gpr.0).collect()
+}
+
+#[test]
+fn overwritten_register_is_dead() {
+    let insts = insts(&[
+        0x38, 0x80, 0x00, 0x05, // li      r4,5
+        0x38, 0x80, 0x00, 0x06, // li      r4,6
+        0x7c, 0x83, 0x23, 0x78, // mr      r3,r4
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+    let liveness = Liveness {
+        insts: &insts,
+        fn_address: FN_ADDRESS,
+    };
+    let results = liveness.run().unwrap();
+
+    // r3 and r4 are written before being read. What's left is what the caller expects to be preserved.
+    let live_in = *results.state_at(InstId(0)).unwrap();
+    assert_eq!(
+        gprs(live_in),
+        [1].into_iter().chain(14..32).collect::<Vec<_>>()
+    );
+
+    assert_eq!(liveness.dead_writes(&results), [(InstId(0), Gpr(4))]);
+}
+
+#[test]
+fn liveness_flows_through_branches() {
+    let insts = insts(&[
+        0x2c, 0x05, 0x00, 0x00, // cmpwi   r5,0
+        0x41, 0x82, 0x00, 0x0c, // beq     +0xc
+        0x7c, 0x83, 0x23, 0x78, // mr      r3,r4
+        0x4e, 0x80, 0x00, 0x20, // blr
+        0x38, 0x60, 0x00, 0x00, // li      r3,0
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+    let liveness = Liveness {
+        insts: &insts,
+        fn_address: FN_ADDRESS,
+    };
+    let results = liveness.run().unwrap();
+
+    // r4 is only read on one path, which is enough to make it live at the start.
+    let live_in = *results.state_at(InstId(0)).unwrap();
+    assert!(live_in.contains(Gpr(4)));
+    assert!(live_in.contains(Gpr(5)));
+    assert!(!live_in.contains(Gpr(3)));
+
+    let live_in = *results.state_at(InstId(4)).unwrap();
+    assert!(!live_in.contains(Gpr(4)));
+    assert!(liveness.dead_writes(&results).is_empty());
+}
+
+#[test]
+fn call_reads_arguments_and_clobbers_volatiles() {
+    let insts = insts(&[
+        0x38, 0xa0, 0x00, 0x01, // li      r5,1
+        0x39, 0x80, 0x00, 0x02, // li      r12,2
+        0x48, 0x00, 0x00, 0x01, // bl      +0x0
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+    let liveness = Liveness {
+        insts: &insts,
+        fn_address: FN_ADDRESS,
+    };
+    let results = liveness.run().unwrap();
+
+    // r5 might be an argument, but r12 is not and gets clobbered by the call. The call's r3 is also never read,
+    // since it is the return value of this function too.
+    assert_eq!(liveness.dead_writes(&results), [(InstId(1), Gpr(12))]);
+}
+
+#[test]
+fn tail_call_reads_arguments() {
+    let insts = insts(&[
+        0x38, 0xa0, 0x00, 0x01, // li      r5,1
+        0x39, 0x80, 0x00, 0x02, // li      r12,2
+        0x48, 0x00, 0x01, 0x00, // b       +0x100
+    ]);
+    let liveness = Liveness {
+        insts: &insts,
+        fn_address: FN_ADDRESS,
+    };
+    let results = liveness.run().unwrap();
+
+    assert_eq!(liveness.dead_writes(&results), [(InstId(1), Gpr(12))]);
+}
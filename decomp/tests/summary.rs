@@ -0,0 +1,74 @@
+use std::collections::BTreeSet;
+
+use decomp::{FunctionSummary, ast::ty::TyKind, dataflow::decode_instructions, summarize};
+use ppc32::instruction::Gpr;
+
+const FN_ADDRESS: u32 = 0x80003100;
+
+fn summary(code: &[u8]) -> FunctionSummary {
+    let insts = decode_instructions(code, FN_ADDRESS).unwrap();
+    summarize(&insts, FN_ADDRESS, &[]).unwrap()
+}
+
+#[test]
+fn call_with_frame() {
+    let summary = summary(&[
+        0x94, 0x21, 0xff, 0xe0, // stwu    r1,-32(r1)
+        0x7c, 0x08, 0x02, 0xa6, // mflr    r0
+        0x90, 0x01, 0x00, 0x24, // stw     r0,36(r1)
+        0x90, 0x83, 0x00, 0x08, // stw     r4,8(r3)
+        0x48, 0x00, 0x01, 0x01, // bl      0x80003210
+        0x80, 0x01, 0x00, 0x24, // lwz     r0,36(r1)
+        0x7c, 0x08, 0x03, 0xa6, // mtlr    r0
+        0x38, 0x21, 0x00, 0x20, // addi    r1,r1,32
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+    assert_eq!(
+        summary,
+        FunctionSummary {
+            address: FN_ADDRESS,
+            params: vec![Gpr(3), Gpr(4)],
+            return_ty: TyKind::U32,
+            frame_size: Some(0x20),
+            calls: BTreeSet::from([0x80003210]),
+        }
+    );
+}
+
+#[test]
+fn leaf_returning_pointer() {
+    let summary = summary(&[
+        0x3c, 0x60, 0x80, 0x40, // lis     r3,-32704
+        0x38, 0x63, 0x12, 0x34, // addi    r3,r3,4660
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+    assert_eq!(
+        summary,
+        FunctionSummary {
+            address: FN_ADDRESS,
+            params: Vec::new(),
+            return_ty: TyKind::Ptr,
+            frame_size: None,
+            calls: BTreeSet::new(),
+        }
+    );
+}
+
+#[test]
+fn leaf_returning_float() {
+    let summary = summary(&[
+        0xc0, 0x03, 0x00, 0x00, // lfs     f0,0(r3)
+        0xec, 0x20, 0x08, 0x2a, // fadds   f1,f0,f1
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+    assert_eq!(
+        summary,
+        FunctionSummary {
+            address: FN_ADDRESS,
+            params: vec![Gpr(3)],
+            return_ty: TyKind::F32,
+            frame_size: None,
+            calls: BTreeSet::new(),
+        }
+    );
+}
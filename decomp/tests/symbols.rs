@@ -0,0 +1,32 @@
+use decomp::symbols::{ParseSymbolsError, SymbolMap};
+
+#[test]
+fn parse_and_lookup() {
+    let symbols = SymbolMap::parse(
+        "\
+# comment
+0x80003100 main
+
+80003abc   OSReport
+",
+    )
+    .unwrap();
+
+    assert_eq!(symbols.get(0x80003100), Some("main"));
+    assert_eq!(symbols.get(0x80003abc), Some("OSReport"));
+    assert_eq!(symbols.get(0x80003104), None);
+    assert_eq!(symbols.address_of("OSReport"), Some(0x80003abc));
+    assert_eq!(symbols.address_of("OSPanic"), None);
+}
+
+#[test]
+fn parse_errors_report_the_line() {
+    assert!(matches!(
+        SymbolMap::parse("0x80003100 main\n0x80003104"),
+        Err(ParseSymbolsError::MissingName { line: 2 })
+    ));
+    assert!(matches!(
+        SymbolMap::parse("main 0x80003100"),
+        Err(ParseSymbolsError::InvalidAddress { line: 1 })
+    ));
+}
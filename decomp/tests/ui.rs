@@ -1,9 +1,8 @@
 use core::panic;
 use std::{env, io::ErrorKind, path::PathBuf, process::ExitCode};
 
-use decomp::{ast::write::StringWriter, dataflow::Instructions};
+use decomp::{ast::write::StringWriter, dataflow::decode_instructions, symbols::SymbolMap};
 use glob::Pattern;
-use ppc32::Decoder;
 
 struct TestCase {
     name: &'static str,
@@ -225,6 +224,274 @@ fn main() -> ExitCode {
             0x38, 0x21, 0x00, 0x10,         // addi    r1,r1,16
             0x4e, 0x80, 0x00, 0x20,          // blr
         ),
+        // while (x < 10) {
+        //     x += 2;
+        // }
+        test!(top_tested_while_loop
+            0x48, 0x00, 0x00, 0x08,         // b       8 <test+0x8>
+            0x38, 0x63, 0x00, 0x02,         // addi    r3,r3,2
+            0x2c, 0x03, 0x00, 0x0a,         // cmpwi   r3,10
+            0x41, 0x80, 0xff, 0xf8,         // blt     4 <test+0x4>
+            0x4e, 0x80, 0x00, 0x20,         // blr
+        ),
+        // for (int i = 0; i < 10; i++) {
+        //     x = i;
+        // }
+        test!(top_tested_for_loop
+            0x3b, 0xe0, 0x00, 0x00,         // li      r31,0
+            0x48, 0x00, 0x00, 0x0c,         // b       10 <test+0x10>
+            0x7f, 0xe3, 0xfb, 0x78,         // mr      r3,r31
+            0x3b, 0xff, 0x00, 0x01,         // addi    r31,r31,1
+            0x2c, 0x1f, 0x00, 0x0a,         // cmpwi   r31,10
+            0x41, 0x80, 0xff, 0xf4,         // blt     8 <test+0x8>
+            0x4e, 0x80, 0x00, 0x20,         // blr
+        ),
+        // *p += 1;
+        // return *p;
+        test!(load_add_store_return
+            0x80, 0x83, 0x00, 0x00,         // lwz     r4,0(r3)
+            0x38, 0x84, 0x00, 0x01,         // addi    r4,r4,1
+            0x90, 0x83, 0x00, 0x00,         // stw     r4,0(r3)
+            0x7c, 0x83, 0x23, 0x78,         // mr      r3,r4
+            0x4e, 0x80, 0x00, 0x20,         // blr
+        ),
+        // if (a < b) {
+        //     return 1;
+        // } else {
+        //     return 0;
+        // }
+        test!(if_less_than_return
+            0x7c, 0x03, 0x20, 0x00,         // cmpw    r3,r4
+            0x40, 0x80, 0x00, 0x0c,         // bge     10 <test+0x10>
+            0x38, 0x60, 0x00, 0x01,         // li      r3,1
+            0x4e, 0x80, 0x00, 0x20,         // blr
+            0x38, 0x60, 0x00, 0x00,         // li      r3,0
+            0x4e, 0x80, 0x00, 0x20,         // blr
+        ),
+        // if (a - b == 0) {
+        //     return 1;
+        // }
+        // return 0;
+        test!(subf_dot_branch
+            0x7c, 0xa4, 0x18, 0x51,         // subf.   r5,r4,r3
+            0x40, 0x82, 0x00, 0x0c,         // bne     10 <test+0x10>
+            0x38, 0x60, 0x00, 0x01,         // li      r3,1
+            0x4e, 0x80, 0x00, 0x20,         // blr
+            0x38, 0x60, 0x00, 0x00,         // li      r3,0
+            0x4e, 0x80, 0x00, 0x20,         // blr
+        ),
+        // if ((a & 1) == 0) {
+        //     return 1;
+        // }
+        // return 0;
+        test!(andi_dot_branch
+            0x70, 0x64, 0x00, 0x01,         // andi.   r4,r3,1
+            0x40, 0x82, 0x00, 0x0c,         // bne     10 <test+0x10>
+            0x38, 0x60, 0x00, 0x01,         // li      r3,1
+            0x4e, 0x80, 0x00, 0x20,         // blr
+            0x38, 0x60, 0x00, 0x00,         // li      r3,0
+            0x4e, 0x80, 0x00, 0x20,         // blr
+        ),
+        // return ~x;
+        test!(not_return
+            0x7c, 0x63, 0x18, 0xf8,         // not     r3,r3
+            0x4e, 0x80, 0x00, 0x20,         // blr
+        ),
+        // u32 x = 0;
+        // for (int i = 0; i < 10; i++) {
+        //     x += 3;
+        // }
+        // return x;
+        test!(ctr_loop
+            0x38, 0x80, 0x00, 0x00,         // li      r4,0
+            0x38, 0xa0, 0x00, 0x0a,         // li      r5,10
+            0x7c, 0xa9, 0x03, 0xa6,         // mtctr   r5
+            0x38, 0x84, 0x00, 0x03,         // addi    r4,r4,3
+            0x42, 0x00, 0xff, 0xfc,         // bdnz    c <test+0xc>
+            0x7c, 0x83, 0x23, 0x78,         // mr      r3,r4
+            0x4e, 0x80, 0x00, 0x20,         // blr
+        ),
+        // int n = 10;
+        // do {
+        //     x += 3;
+        // } while (--n != 0 && x >= 20);
+        // return x;
+        test!(ctr_and_condition_loop
+            0x38, 0x80, 0x00, 0x00,         // li      r4,0
+            0x38, 0xa0, 0x00, 0x0a,         // li      r5,10
+            0x7c, 0xa9, 0x03, 0xa6,         // mtctr   r5
+            0x38, 0x84, 0x00, 0x03,         // addi    r4,r4,3
+            0x2c, 0x04, 0x00, 0x14,         // cmpwi   r4,20
+            0x40, 0x00, 0xff, 0xf8,         // bdnzf   lt,c <test+0xc>
+            0x7c, 0x83, 0x23, 0x78,         // mr      r3,r4
+            0x4e, 0x80, 0x00, 0x20,         // blr
+        ),
+        // if (--n != 0) return 1;
+        // return 0;
+        test!(bdnzlr_return
+            0x7c, 0x69, 0x03, 0xa6,         // mtctr   r3
+            0x38, 0x60, 0x00, 0x01,         // li      r3,1
+            0x4e, 0x00, 0x00, 0x20,         // bdnzlr
+            0x38, 0x60, 0x00, 0x00,         // li      r3,0
+            0x4e, 0x80, 0x00, 0x20,         // blr
+        ),
+        // u32 srr0 = __mfspr(SRR0);
+        // __mtspr(SRR1, x);
+        // return srr0;
+        test!(opaque_sprs
+            0x7c, 0x9a, 0x02, 0xa6,         // mfsrr0  r4
+            0x7c, 0x7b, 0x03, 0xa6,         // mtsrr1  r3
+            0x7c, 0x83, 0x23, 0x78,         // mr      r3,r4
+            0x4e, 0x80, 0x00, 0x20,         // blr
+        ),
+        // __mtspr(XER, x);
+        // return __mfspr(CTR);
+        test!(unmodeled_sprs
+            0x7c, 0x61, 0x03, 0xa6,         // mtxer   r3
+            0x7c, 0x69, 0x02, 0xa6,         // mfctr   r3
+            0x4e, 0x80, 0x00, 0x20,         // blr
+        ),
+        // if (!__crxor(x == 0, x == 0)) { x = 1; }
+        test!(unmodeled_instruction
+            0x2c, 0x03, 0x00, 0x00,         // cmpwi   r3,0
+            0x4c, 0x42, 0x11, 0x82,         // crxor   eq,eq,eq
+            0x41, 0x82, 0x00, 0x08,         // beq     10
+            0x38, 0x60, 0x00, 0x01,         // li      r3,1
+            0x4e, 0x80, 0x00, 0x20,         // blr
+        ),
+        // return f();
+        test!(blrl_call
+            0x7c, 0x68, 0x03, 0xa6,         // mtlr    r3
+            0x4e, 0x80, 0x00, 0x21,         // blrl
+            0x4e, 0x80, 0x00, 0x20,         // blr
+        ),
+        // return f(1);
+        test!(tail_call
+            0x38, 0x60, 0x00, 0x01,         // li      r3,1
+            0x48, 0x00, 0x01, 0x00,         // b       104 <f>
+        ),
+        // if (x != 0) f(x);
+        test!(branch_over_call
+            0x2c, 0x03, 0x00, 0x00,         // cmpwi   r3,0
+            0x41, 0x82, 0x00, 0x08,         // beq     c
+            0x48, 0x00, 0x00, 0xf1,         // bl      f8 <f>
+            0x4e, 0x80, 0x00, 0x20,         // blr
+        ),
+        // if (x == 0) return f(); return 0;
+        test!(conditional_tail_call
+            0x2c, 0x03, 0x00, 0x00,         // cmpwi   r3,0
+            0x41, 0x82, 0x04, 0x00,         // beq     404 <f>
+            0x38, 0x60, 0x00, 0x00,         // li      r3,0
+            0x4e, 0x80, 0x00, 0x20,         // blr
+        ),
+        // if (x == 0) x = f(x); return x;
+        test!(conditional_call
+            0x2c, 0x03, 0x00, 0x00,         // cmpwi   r3,0
+            0x41, 0x82, 0x01, 0x01,         // beql    104 <f>
+            0x4e, 0x80, 0x00, 0x20,         // blr
+        ),
+        // int x = 0; return x == 0 ? 1 : 2;
+        test!(known_branch_is_pruned
+            0x38, 0x60, 0x00, 0x00,         // li      r3,0
+            0x2c, 0x03, 0x00, 0x00,         // cmpwi   r3,0
+            0x38, 0xa0, 0x00, 0x01,         // li      r5,1
+            0x41, 0x82, 0x00, 0x08,         // beq     14 <test+0x14>
+            0x38, 0xa0, 0x00, 0x02,         // li      r5,2
+            0x7c, 0xa3, 0x2b, 0x78,         // mr      r3,r5
+            0x4e, 0x80, 0x00, 0x20,         // blr
+        ),
+        // The second call only gets the result of the first one, since r4 doesn't survive it.
+        // g(f(1, 2));
+        test!(call_clobbers_arguments
+            0x38, 0x60, 0x00, 0x01,         // li      r3,1
+            0x38, 0x80, 0x00, 0x02,         // li      r4,2
+            0x48, 0x00, 0x01, 0x01,         // bl      108 <f>
+            0x48, 0x00, 0x02, 0x01,         // bl      20c <g>
+            0x4e, 0x80, 0x00, 0x20,         // blr
+        ),
+        // return f(1, 2);
+        test!(call_two_args
+            0x94, 0x21, 0xff, 0xf0,         // stwu    r1,-16(r1)
+            0x7c, 0x08, 0x02, 0xa6,         // mflr    r0
+            0x90, 0x01, 0x00, 0x14,         // stw     r0,20(r1)
+            0x38, 0x60, 0x00, 0x01,         // li      r3,1
+            0x38, 0x80, 0x00, 0x02,         // li      r4,2
+            0x48, 0x00, 0x00, 0x19,         // bl      2c <test+0x2c>
+            0x80, 0x01, 0x00, 0x14,         // lwz     r0,20(r1)
+            0x7c, 0x08, 0x03, 0xa6,         // mtlr    r0
+            0x38, 0x21, 0x00, 0x10,         // addi    r1,r1,16
+            0x4e, 0x80, 0x00, 0x20,         // blr
+        ),
+        // return &some_global;
+        test!(return_pointer
+            0x3c, 0x60, 0x80, 0x40,         // lis     r3,-32704
+            0x38, 0x63, 0x12, 0x34,         // addi    r3,r3,4660
+            0x4e, 0x80, 0x00, 0x20,         // blr
+        ),
+        // some_global.a = x;
+        // some_global.b = x;
+        test!(pointer_stored_through_twice
+            0x3c, 0x80, 0x80, 0x40,         // lis     r4,-32704
+            0x38, 0x84, 0x12, 0x34,         // addi    r4,r4,4660
+            0x90, 0x64, 0x00, 0x00,         // stw     r3,0(r4)
+            0x90, 0x64, 0x00, 0x04,         // stw     r3,4(r4)
+            0x4e, 0x80, 0x00, 0x20,         // blr
+        ),
+        // float f(float *p, float x) { return *p + x; }
+        test!(float_return
+            0xc0, 0x03, 0x00, 0x00,         // lfs     f0,0(r3)
+            0xec, 0x20, 0x08, 0x2a,         // fadds   f1,f0,f1
+            0x4e, 0x80, 0x00, 0x20,         // blr
+        ),
+        // double f(double a, double b) { return a * b; }
+        test!(double_return
+            0xfc, 0x21, 0x00, 0xb2,         // fmul    f1,f1,f2
+            0x4e, 0x80, 0x00, 0x20,         // blr
+        ),
+        // float f(double x) { return (float)x; }
+        test!(double_to_float
+            0xfc, 0x20, 0x08, 0x18,         // frsp    f1,f1
+            0x4e, 0x80, 0x00, 0x20,         // blr
+        ),
+        // The save and restore of f31 are left out.
+        test!(callee_saved_fpr
+            0x94, 0x21, 0xff, 0xf0,         // stwu    r1,-16(r1)
+            0xdb, 0xe1, 0x00, 0x08,         // stfd    f31,8(r1)
+            0xff, 0xe0, 0x08, 0x90,         // fmr     f31,f1
+            0xfc, 0x3f, 0xf8, 0x2a,         // fadd    f1,f31,f31
+            0xcb, 0xe1, 0x00, 0x08,         // lfd     f31,8(r1)
+            0x38, 0x21, 0x00, 0x10,         // addi    r1,r1,16
+            0x4e, 0x80, 0x00, 0x20,         // blr
+        ),
+        // Frame torn down with `lwz r1,0(r1)` instead of `addi r1,r1,16`
+        test!(frame_lwz_epilogue
+            0x94, 0x21, 0xff, 0xf0,         // stwu    r1,-16(r1)
+            0x7c, 0x08, 0x02, 0xa6,         // mflr    r0
+            0x90, 0x01, 0x00, 0x14,         // stw     r0,20(r1)
+            0x38, 0x60, 0x00, 0x01,         // li      r3,1
+            0x48, 0x00, 0x00, 0x19,         // bl      28 <test+0x28>
+            0x80, 0x01, 0x00, 0x14,         // lwz     r0,20(r1)
+            0x7c, 0x08, 0x03, 0xa6,         // mtlr    r0
+            0x80, 0x21, 0x00, 0x00,         // lwz     r1,0(r1)
+            0x4e, 0x80, 0x00, 0x20,         // blr
+        ),
+        // The LR save and restore around the call is left out.
+        // a(x);
+        // return x + 4;
+        test!(lr_save_restore
+            0x7c, 0x08, 0x02, 0xa6,         // mflr    r0
+            0x90, 0x01, 0x00, 0x04,         // stw     r0,4(r1)
+            0x94, 0x21, 0xff, 0xe8,         // stwu    r1,-24(r1)
+            0x93, 0xe1, 0x00, 0x14,         // stw     r31,20(r1)
+            0x7c, 0x7f, 0x1b, 0x78,         // mr      r31,r3
+            0x48, 0x00, 0x00, 0x21,         // bl      34 <test+0x34>
+            0x38, 0x7f, 0x00, 0x04,         // addi    r3,r31,4
+            0x80, 0x01, 0x00, 0x1c,         // lwz     r0,28(r1)
+            0x83, 0xe1, 0x00, 0x14,         // lwz     r31,20(r1)
+            0x7c, 0x08, 0x03, 0xa6,         // mtlr    r0
+            0x38, 0x21, 0x00, 0x18,         // addi    r1,r1,24
+            0x4e, 0x80, 0x00, 0x20,         // blr
+        ),
     ];
     let pattern = env::var("PATTERN")
         .map_or_else(|_| Pattern::new("*"), |pat| Pattern::new(&pat))
@@ -238,12 +505,16 @@ fn main() -> ExitCode {
         }
 
         let fn_addr = 0;
-        let instructions = Decoder::new(test.code)
-            .iter_until_eof(fn_addr)
-            .collect::<Result<Instructions, _>>()
-            .unwrap();
+        let instructions = decode_instructions(test.code, fn_addr).unwrap();
         let mut output = StringWriter::new();
-        decomp::decompile_into_ast_writer(&instructions, fn_addr, &mut output).unwrap();
+        decomp::decompile_into_ast_writer(
+            &instructions,
+            fn_addr,
+            &[],
+            &SymbolMap::default(),
+            &mut output,
+        )
+        .unwrap();
         let output = output.into_string();
 
         let mut path = PathBuf::from("tests/output");
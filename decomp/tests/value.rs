@@ -0,0 +1,1351 @@
+use std::{cmp::Ordering, rc::Rc};
+
+use decomp::dataflow::{
+    InstId,
+    core::Join,
+    decode_instructions,
+    value::{BlockState, ConstSection, IntType, VInt, Value, ValueAnalysis},
+};
+use ppc32::{
+    Decoder,
+    instruction::{Gpr, Spr},
+};
+
+const FN_ADDRESS: u32 = 0x80003100;
+
+/// Runs the value analysis over `code` and returns the state after the last instruction.
+fn final_state(code: &[u8]) -> BlockState {
+    final_state_with_sections(code, &[])
+}
+
+fn final_state_with_sections(code: &[u8], const_sections: &[ConstSection<'_>]) -> BlockState {
+    let insts = decode_instructions(code, FN_ADDRESS).unwrap();
+    let analysis = ValueAnalysis {
+        insts: &insts,
+        fn_address: FN_ADDRESS,
+        const_sections,
+    };
+    let results = analysis.run().unwrap();
+    results.for_each_with_input(&analysis, |cx| cx.effect())
+}
+
+fn int(value: Value) -> VInt {
+    value.as_int().expect("value should be a constant")
+}
+
+#[test]
+fn lis_addi_folds_into_pointer() {
+    // lis r3,0x8000; addi r3,r3,0x1234
+    let value = Value::high_half(-0x8000) + Value::u32(0x1234);
+    assert_eq!(
+        int(value),
+        VInt {
+            value: 0x80001234,
+            ty: IntType::Ptr
+        }
+    );
+
+    // lis r3,0x8001; addi r3,r3,-0x1000 (the high half accounts for the sign-extended low half)
+    let value = Value::high_half(-0x7fff) + Value::u32(-0x1000i32 as u32);
+    assert_eq!(
+        int(value),
+        VInt {
+            value: 0x80000000 + 0xf000,
+            ty: IntType::Ptr
+        }
+    );
+}
+
+#[test]
+fn lis_ori_folds_into_pointer() {
+    // lis r3,0x8000; ori r3,r3,0xabcd
+    let value = Value::high_half(-0x8000) | Value::u32(0xabcd);
+    assert_eq!(
+        int(value),
+        VInt {
+            value: 0x8000abcd,
+            ty: IntType::Ptr
+        }
+    );
+}
+
+#[test]
+fn plain_arithmetic_stays_integer() {
+    assert_eq!(int(Value::u32(5) + Value::u32(1)), VInt::u32(6));
+    assert_eq!(int(Value::u32(4) | Value::u32(1)), VInt::u32(5));
+    assert_eq!(Value::Any + Value::u32(1), Value::Any);
+    assert_eq!(Value::high_half(1) | Value::Any, Value::Any);
+
+    // Adding to an already assembled pointer (e.g. indexing into a global) keeps it a pointer
+    let ptr = Value::high_half(-0x8000) + Value::u32(0x10);
+    assert_eq!(int(ptr + Value::u32(4)).ty, IntType::Ptr);
+}
+
+#[test]
+fn lis_addi_dataflow() {
+    let state = final_state(&[
+        0x3c, 0x60, 0x80, 0x00, // lis     r3,-32768
+        0x38, 0x63, 0x12, 0x34, // addi    r3,r3,4660
+        0x3c, 0x80, 0x80, 0x01, // lis     r4,-32767
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    assert_eq!(
+        state.gpr(Gpr(3)),
+        Value::Int(VInt {
+            value: 0x80001234,
+            ty: IntType::Ptr
+        })
+    );
+    assert_eq!(
+        state.gpr(Gpr(4)),
+        Value::Int(VInt {
+            value: 0x80010000,
+            ty: IntType::HighHalf
+        })
+    );
+}
+
+#[test]
+fn ori_oris_dataflow() {
+    let state = final_state(&[
+        0x38, 0x60, 0x00, 0x01, // li      r3,1
+        0x60, 0x63, 0x12, 0x34, // ori     r3,r3,4660
+        0x3c, 0x80, 0x80, 0x00, // lis     r4,-32768
+        0x60, 0x84, 0xab, 0xcd, // ori     r4,r4,43981
+        0x64, 0x65, 0x00, 0x02, // oris    r5,r3,2
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    assert_eq!(state.gpr(Gpr(3)), Value::u32(0x1235));
+    assert_eq!(
+        state.gpr(Gpr(4)),
+        Value::Int(VInt {
+            value: 0x8000abcd,
+            ty: IntType::Ptr
+        })
+    );
+    assert_eq!(state.gpr(Gpr(5)), Value::u32(0x21235));
+}
+
+#[test]
+fn unmodeled_instruction_clobbers_outputs() {
+    let state = final_state(&[
+        0x38, 0x60, 0x00, 0x05, // li      r3,5
+        0x38, 0x80, 0x00, 0x06, // li      r4,6
+        0x7c, 0x64, 0x07, 0x74, // extsb   r4,r3
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    assert_eq!(state.gpr(Gpr(3)), Value::u32(5));
+    assert_eq!(state.gpr(Gpr(4)), Value::Any);
+}
+
+#[test]
+fn store_load_dataflow() {
+    let state = final_state(&[
+        0x3c, 0x60, 0x80, 0x00, // lis     r3,-32768
+        0x38, 0x80, 0x00, 0x2a, // li      r4,42
+        0x90, 0x83, 0x00, 0x10, // stw     r4,16(r3)
+        0x80, 0xa3, 0x00, 0x10, // lwz     r5,16(r3)
+        0x80, 0xc3, 0x00, 0x14, // lwz     r6,20(r3)
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    assert_eq!(state.memory.get(&0x80000010), Some(&Value::u32(42)));
+    assert_eq!(state.gpr(Gpr(5)), Value::u32(42));
+    assert_eq!(
+        format!("{:?}", state.gpr(Gpr(6))),
+        "*&0x80000014",
+        "memory that wasn't written to still has its original value"
+    );
+}
+
+#[test]
+fn join_keeps_only_agreeing_state() {
+    let mut a = BlockState::default();
+    a.set_gpr(Gpr(3), Value::u32(1));
+    a.set_gpr(Gpr(4), Value::u32(2));
+    a.memory.insert(0x80000000, Value::u32(3));
+    a.memory.insert(0x80000004, Value::u32(4));
+    a.memory.insert(0x80000008, Value::u32(5));
+
+    let mut b = BlockState::default();
+    b.set_gpr(Gpr(3), Value::u32(1));
+    b.set_gpr(Gpr(4), Value::u32(7));
+    b.memory.insert(0x80000000, Value::u32(3));
+    b.memory.insert(0x80000004, Value::u32(8));
+
+    let joined = a.join(&b, &mut ());
+    assert_eq!(joined.gpr(Gpr(3)), Value::u32(1));
+    assert_eq!(joined.gpr(Gpr(4)), Value::Any);
+    assert_eq!(
+        joined.memory.into_iter().collect::<Vec<_>>(),
+        [(0x80000000, Value::u32(3))]
+    );
+}
+
+#[test]
+fn xer_roundtrip() {
+    let mut state = BlockState::default();
+    assert_eq!(state.spr(Spr::Xer(())), Value::Any);
+
+    // SO and CA set, plus some bits that aren't modeled
+    state.set_spr(Spr::Xer(()), Value::u32(0xa000_00ff));
+    assert_eq!(state.registers.sprs.xer.so, Value::u32(1));
+    assert_eq!(state.registers.sprs.xer.ov, Value::u32(0));
+    assert_eq!(state.registers.sprs.xer.ca, Value::u32(1));
+    assert_eq!(state.spr(Spr::Xer(())), Value::u32(0xa000_0000));
+
+    state.registers.sprs.xer.ov = Value::Any;
+    assert_eq!(state.spr(Spr::Xer(())), Value::Any);
+}
+
+#[test]
+fn other_spr_dataflow() {
+    let state = final_state(&[
+        0x38, 0x60, 0x00, 0x07, // li      r3,7
+        0x7c, 0x70, 0x43, 0xa6, // mtsprg  0,r3
+        0x7c, 0x90, 0x42, 0xa6, // mfsprg  r4,0
+        0x7c, 0xb1, 0x42, 0xa6, // mfsprg  r5,1
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    assert_eq!(state.spr(Spr::Sprg(0)), Value::u32(7));
+    assert_eq!(state.gpr(Gpr(4)), Value::u32(7));
+    assert_eq!(state.gpr(Gpr(5)), Value::Any);
+}
+
+#[test]
+fn unreachable_block_does_not_inherit_state() {
+    let insts = decode_instructions(
+        &[
+            0x38, 0x60, 0x00, 0x01, // li      r3,1
+            0x48, 0x00, 0x00, 0x0c, // b       +0xc
+            0x38, 0x80, 0x00, 0x02, // li      r4,2
+            0x7c, 0x65, 0x1b, 0x78, // mr      r5,r3
+            0x4e, 0x80, 0x00, 0x20, // blr
+        ],
+        FN_ADDRESS,
+    )
+    .unwrap();
+    let analysis = ValueAnalysis {
+        insts: &insts,
+        fn_address: FN_ADDRESS,
+        const_sections: &[],
+    };
+    let results = analysis.run().unwrap();
+
+    let mut r3_before = Vec::new();
+    let state = results.for_each_with_input(&analysis, |cx| {
+        r3_before.push(cx.state().gpr(Gpr(3)));
+        cx.effect();
+    });
+
+    // The two instructions after the `b` are dead code. Linearly, they would look like they follow `li r3,1`.
+    assert_eq!(
+        r3_before,
+        [
+            Value::Param(Gpr(3)),
+            Value::u32(1),
+            Value::Any,
+            Value::Any,
+            Value::u32(1)
+        ]
+    );
+    assert_eq!(state.gpr(Gpr(3)), Value::u32(1));
+}
+
+#[test]
+fn indirect_call_clobbers_volatiles() {
+    let state = final_state(&[
+        0x38, 0x60, 0x00, 0x01, // li      r3,1
+        0x3b, 0xe0, 0x00, 0x02, // li      r31,2
+        0x4e, 0x80, 0x04, 0x21, // bctrl
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    assert_eq!(state.gpr(Gpr(3)), Value::Any);
+    assert_eq!(state.gpr(Gpr(31)), Value::u32(2));
+}
+
+#[test]
+fn scratch_registers_start_out_uninit() {
+    let state = final_state(&[
+        0x38, 0x8c, 0x00, 0x08, // addi    r4,r12,8
+        0x7c, 0x05, 0x03, 0x78, // mr      r5,r0
+        0x38, 0xc3, 0x00, 0x01, // addi    r6,r3,1
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    assert_eq!(state.gpr(Gpr(4)), Value::Uninit);
+    assert_eq!(state.gpr(Gpr(5)), Value::Uninit);
+    assert_eq!(state.gpr(Gpr(11)), Value::Uninit);
+    assert_eq!(state.gpr(Gpr(6)), Value::Param(Gpr(3)) + Value::u32(1));
+    assert_eq!(state.gpr(Gpr(10)), Value::Param(Gpr(10)));
+    assert_eq!(state.gpr(Gpr(31)), Value::Param(Gpr(31)));
+}
+
+#[test]
+fn scratch_register_written_on_one_path_keeps_its_value() {
+    let state = final_state(&[
+        0x2c, 0x03, 0x00, 0x00, // cmpwi   r3,0
+        0x41, 0x82, 0x00, 0x08, // beq     +0x8
+        0x38, 0x00, 0x00, 0x05, // li      r0,5
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    // The other path never wrote r0, so whatever it holds there is as good as 5.
+    assert_eq!(state.gpr(Gpr(0)), Value::u32(5));
+}
+
+#[test]
+fn call_clobbers_volatile_sprs() {
+    let state = final_state(&[
+        0x38, 0x60, 0x00, 0x00, // li      r3,0
+        0x2c, 0x03, 0x00, 0x00, // cmpwi   r3,0
+        0x2f, 0x83, 0x00, 0x00, // cmpwi   cr7,r3,0
+        0x2d, 0x03, 0x00, 0x00, // cmpwi   cr2,r3,0
+        0x7c, 0x69, 0x03, 0xa6, // mtctr   r3
+        0x41, 0x82, 0x01, 0x01, // beql    0x114
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    // The callee may have changed the volatile CR fields, CTR and XER, but not the non-volatile ones.
+    let sprs = &state.registers.sprs;
+    for crf in [0, 7] {
+        assert_eq!(sprs.cr[crf].eq, Value::Any, "cr{crf}");
+    }
+    assert_eq!(sprs.cr[2].eq, Value::u32(1));
+    assert_eq!(sprs.ctr, Value::Any);
+    assert_eq!(state.spr(Spr::Xer(())), Value::Any);
+    // `beql` sets LR to the return address even if it isn't taken.
+    assert_eq!(sprs.lr, Value::u32(FN_ADDRESS + 0x18));
+}
+
+#[test]
+fn sub_folds_constants() {
+    assert_eq!(int(Value::u32(7) - Value::u32(5)), VInt::u32(2));
+    assert_eq!(int(Value::u32(5) - Value::u32(7)), VInt::u32(-2i32 as u32));
+    assert_eq!(int(-Value::u32(1)), VInt::u32(u32::MAX));
+
+    let ptr = Value::high_half(-0x8000) + Value::u32(0x10);
+    assert_eq!(int(ptr - Value::u32(4)).ty, IntType::Ptr);
+    assert_eq!(Value::u32(1) - Value::Any, Value::Any);
+}
+
+#[test]
+fn sub_constant_from_symbolic() {
+    let sp = Value::Param(Gpr(1));
+    let frame = sp.clone() - Value::u32(8);
+    assert_eq!(frame, sp.clone() + Value::u32(-8i32 as u32));
+    assert_eq!(format!("{frame:?}"), "(r1 - 0x8)");
+
+    // The constants are folded back out again
+    assert_eq!(frame.clone() + Value::u32(8), sp);
+    assert_eq!(
+        format!("{:?}", frame - Value::u32(8) + Value::Param(Gpr(3))),
+        "((r1 + r3) - 0x10)"
+    );
+}
+
+#[test]
+fn sub_self_is_zero() {
+    let r3 = Value::Param(Gpr(3));
+    assert_eq!(r3.clone() - r3.clone(), Value::u32(0));
+
+    let offset = r3.clone() + Value::Param(Gpr(4)) + Value::u32(4);
+    assert_eq!(offset.clone() - offset.clone(), Value::u32(0));
+    assert_eq!(offset - r3, Value::Param(Gpr(4)) + Value::u32(4));
+}
+
+#[test]
+fn subtraction_dataflow() {
+    let state = final_state(&[
+        0x94, 0x21, 0xff, 0xf0, // stwu    r1,-16(r1)
+        0x7c, 0xa4, 0x18, 0x50, // subf    r5,r4,r3
+        0x20, 0xc3, 0x00, 0x10, // subfic  r6,r3,16
+        0x7c, 0xe3, 0x18, 0x50, // subf    r7,r3,r3
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    assert_eq!(state.gpr(Gpr(1)), Value::Param(Gpr(1)) - Value::u32(16));
+    assert_eq!(
+        state.gpr(Gpr(5)),
+        Value::Param(Gpr(3)) - Value::Param(Gpr(4))
+    );
+    assert_eq!(format!("{:?}", state.gpr(Gpr(6))), "(-r3 + 0x10)");
+    assert_eq!(state.gpr(Gpr(7)), Value::u32(0));
+    assert_eq!(state.registers.sprs.xer.ca, Value::Any);
+}
+
+#[test]
+fn mul_by_power_of_two_is_shift() {
+    let r3 = Value::Param(Gpr(3));
+    let scaled = r3.clone() * Value::u32(4);
+    assert_eq!(scaled, r3.clone() << Value::u32(2));
+    assert_eq!(format!("{scaled:?}"), "(r3 << 0x2)");
+
+    assert_eq!(r3.clone() * Value::u32(1), r3);
+    assert_eq!(r3.clone() * Value::u32(0), Value::u32(0));
+    assert_eq!(Value::u32(12) * r3.clone(), r3.clone() * Value::u32(12));
+    assert_eq!(format!("{:?}", r3.clone() * Value::u32(12)), "(r3 * 0xc)");
+    assert_eq!(
+        Value::Param(Gpr(4)) * r3.clone(),
+        r3.clone() * Value::Param(Gpr(4))
+    );
+}
+
+#[test]
+fn shifts_combine() {
+    let r3 = Value::Param(Gpr(3));
+    assert_eq!(
+        (r3.clone() << Value::u32(2)) << Value::u32(3),
+        r3.clone() << Value::u32(5)
+    );
+    assert_eq!(r3.clone() << Value::u32(0), r3);
+    assert_eq!(r3.clone() << Value::u32(32), Value::u32(0));
+    assert_eq!(format!("{:?}", r3 >> Value::u32(2)), "(r3 >> 0x2)");
+
+    // The address of a global isn't turned into some unrelated integer
+    let ptr = Value::high_half(-0x8000) + Value::u32(0x10);
+    assert!(matches!(ptr << Value::u32(2), Value::Expr(_)));
+}
+
+#[test]
+fn mul_and_shift_fold_constants() {
+    assert_eq!(int(Value::u32(6) * Value::u32(7)), VInt::u32(42));
+    assert_eq!(int(Value::u32(3) << Value::u32(4)), VInt::u32(0x30));
+    assert_eq!(
+        int(Value::u32(0xffff_fff0) >> Value::u32(2)),
+        VInt::u32(0xffff_fffc)
+    );
+    assert_eq!(
+        int(Value::u32(0x8000_0000) >> Value::u32(40)),
+        VInt::u32(u32::MAX)
+    );
+    assert_eq!(Value::u32(3) * Value::Any, Value::Any);
+}
+
+#[test]
+fn shift_dataflow() {
+    let state = final_state(&[
+        0x7c, 0x64, 0x16, 0x70, // srawi   r4,r3,2
+        0x38, 0xc0, 0x00, 0x02, // li      r6,2
+        0x7c, 0x65, 0x30, 0x30, // slw     r5,r3,r6
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    assert_eq!(state.gpr(Gpr(4)), Value::Param(Gpr(3)) >> Value::u32(2));
+    assert_eq!(state.gpr(Gpr(5)), Value::Param(Gpr(3)) * Value::u32(4));
+    assert_eq!(state.registers.sprs.xer.ca, Value::Any);
+}
+
+#[test]
+fn bitwise_ops_fold_constants() {
+    assert_eq!(
+        int(Value::u32(0xf0f0) & Value::u32(0xff00)),
+        VInt::u32(0xf000)
+    );
+    assert_eq!(
+        int(Value::u32(0xf0f0) ^ Value::u32(0xff00)),
+        VInt::u32(0x0ff0)
+    );
+    assert_eq!(int(!Value::u32(0)), VInt::u32(u32::MAX));
+    assert_eq!(Value::u32(1) & Value::Any, Value::Any);
+    assert_eq!(!Value::Any, Value::Any);
+}
+
+#[test]
+fn bitwise_ops_on_the_same_value() {
+    let r3 = Value::Param(Gpr(3));
+    assert_eq!(r3.clone() & r3.clone(), r3);
+    assert_eq!(r3.clone() | r3.clone(), r3);
+    assert_eq!(r3.clone() ^ r3.clone(), Value::u32(0));
+    assert_eq!(!(r3.clone() | r3.clone()), !r3.clone());
+    assert_eq!(!!r3.clone(), r3);
+}
+
+#[test]
+fn symbolic_bitwise_ops() {
+    let r3 = Value::Param(Gpr(3));
+    let r4 = Value::Param(Gpr(4));
+
+    let masked = Value::u32(0xff) & r3.clone();
+    assert_eq!(masked, r3.clone() & Value::u32(0xff));
+    assert_eq!(format!("{masked:?}"), "(r3 & 0xff)");
+    assert_eq!(r4.clone() | r3.clone(), r3.clone() | r4.clone());
+    assert_eq!(format!("{:?}", r4 ^ r3.clone()), "(r3 ^ r4)");
+
+    assert_eq!(r3.clone() & Value::u32(0), Value::u32(0));
+    assert_eq!(r3.clone() & Value::u32(u32::MAX), r3);
+    assert_eq!(r3.clone() | Value::u32(0), r3);
+    assert_eq!(r3.clone() ^ Value::u32(u32::MAX), !r3.clone());
+    assert_eq!(format!("{:?}", !r3), "~r3");
+}
+
+#[test]
+fn bitwise_dataflow() {
+    let state = final_state(&[
+        0x7c, 0x64, 0x18, 0xf8, // not     r4,r3
+        0x70, 0x65, 0x00, 0xff, // andi.   r5,r3,255
+        0x7c, 0x66, 0x1a, 0x78, // xor     r6,r3,r3
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    let r3 = Value::Param(Gpr(3));
+    assert_eq!(state.gpr(Gpr(4)), !r3.clone());
+    assert_eq!(state.gpr(Gpr(5)), r3 & Value::u32(0xff));
+    assert_eq!(state.gpr(Gpr(6)), Value::u32(0));
+}
+
+#[test]
+fn not_folds() {
+    assert_eq!(!Value::u32(0), Value::u32(u32::MAX));
+    assert_eq!(!Value::u32(0x0000ffff), Value::u32(0xffff0000));
+    assert_eq!(!Value::Any, Value::Any);
+
+    let r3 = Value::Param(Gpr(3));
+    assert_eq!(!!r3.clone(), r3);
+    assert_eq!(
+        format!("{:?}", !(r3.clone() + Value::u32(1))),
+        "~(r3 + 0x1)"
+    );
+}
+
+#[test]
+fn not_dataflow() {
+    let state = final_state(&[
+        0x38, 0x80, 0x00, 0x0f, // li      r4,15
+        0x7c, 0x84, 0x20, 0xf8, // not     r4,r4
+        0x7c, 0x65, 0x18, 0xf8, // not     r5,r3
+        0x7c, 0xa5, 0x28, 0xf8, // not     r5,r5
+        0x7c, 0x66, 0x20, 0xf8, // nor     r6,r3,r4
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    assert_eq!(state.gpr(Gpr(4)), Value::u32(!15));
+    assert_eq!(state.gpr(Gpr(5)), Value::Param(Gpr(3)));
+    assert_eq!(state.gpr(Gpr(6)), !(Value::Param(Gpr(3)) | Value::u32(!15)));
+}
+
+#[test]
+fn long_sums() {
+    let params = (3..9).map(|gpr| Value::Param(Gpr(gpr)));
+    let sum = params
+        .clone()
+        .rev()
+        .fold(Value::u32(1), |acc, param| acc + param + Value::u32(2));
+
+    assert_eq!(
+        format!("{sum:?}"),
+        "((((((r3 + r4) + r5) + r6) + r7) + r8) + 0xd)"
+    );
+    assert_eq!(
+        sum.clone(),
+        params.fold(Value::u32(13), |acc, param| param + acc)
+    );
+
+    // The constant is dropped if the terms cancel out
+    let sum = sum - Value::u32(13);
+    assert_eq!(format!("{sum:?}"), "(((((r3 + r4) + r5) + r6) + r7) + r8)");
+}
+
+#[test]
+fn rotate_and_mask_folds_constants() {
+    // Extract the top byte
+    let value = Value::u32(0x1234_5678).rotate_and_mask(8, 24, 31);
+    assert_eq!(int(value), VInt::u32(0x12));
+
+    // A wrapping mask keeps both ends
+    let value = Value::u32(0xffff_ffff).rotate_and_mask(0, 28, 3);
+    assert_eq!(int(value), VInt::u32(0xf000_000f));
+}
+
+#[test]
+fn rotate_and_mask_recovers_shifts_and_masks() {
+    let r4 = Value::Param(Gpr(4));
+
+    // slwi r3,r4,2
+    let value = r4.clone().rotate_and_mask(2, 0, 29);
+    assert_eq!(value, r4.clone() << Value::u32(2));
+
+    // clrlwi r3,r4,24
+    let value = r4.clone().rotate_and_mask(0, 24, 31);
+    assert_eq!(format!("{value:?}"), "(r4 & 0xff)");
+
+    // rlwinm r3,r4,2,24,29, i.e. `(r4 & 0x3f) << 2`
+    let value = r4.clone().rotate_and_mask(2, 24, 29);
+    assert_eq!(format!("{value:?}"), "((r4 << 0x2) & 0xfc)");
+
+    // srwi r3,r4,2
+    let value = r4.clone().rotate_and_mask(30, 2, 31);
+    assert_eq!(format!("{value:?}"), "((r4 >> 0x2) & 0x3fffffff)");
+
+    // rotlwi r3,r4,8 keeps bits from both sides of the rotation
+    assert_eq!(r4.rotate_and_mask(8, 0, 31), Value::Any);
+}
+
+#[test]
+fn rlwinm_dataflow() {
+    let state = final_state(&[
+        0x54, 0x83, 0x10, 0x3a, // slwi    r3,r4,2
+        0x54, 0x85, 0x06, 0x3e, // clrlwi  r5,r4,24
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    let r4 = Value::Param(Gpr(4));
+    assert_eq!(state.gpr(Gpr(3)), r4.clone() * Value::u32(4));
+    assert_eq!(state.gpr(Gpr(5)), r4 & Value::u32(0xff));
+}
+
+#[test]
+fn join_lattice() {
+    let join = |a: &Value, b: &Value| a.join(b, &mut ());
+    let r3 = Value::Param(Gpr(3));
+    let offset = r3.clone() + Value::u32(4);
+
+    // Uninit is the bottom
+    for value in [Value::Uninit, Value::u32(1), offset.clone(), Value::Any] {
+        assert_eq!(join(&Value::Uninit, &value), value);
+        assert_eq!(join(&value, &Value::Uninit), value);
+    }
+
+    // Any is the top
+    for value in [Value::u32(1), offset.clone(), Value::Any] {
+        assert_eq!(join(&Value::Any, &value), Value::Any);
+        assert_eq!(join(&value, &Value::Any), Value::Any);
+    }
+
+    // Everything else is only equal to itself
+    assert_eq!(join(&offset, &offset), offset);
+    assert_eq!(join(&offset, &(r3.clone() + Value::u32(8))), Value::Any);
+    assert_eq!(join(&Value::u32(1), &Value::u32(2)), Value::Any);
+    assert_eq!(join(&r3, &Value::Param(Gpr(4))), Value::Any);
+}
+
+#[test]
+fn uninit_operands() {
+    let r3 = Value::Param(Gpr(3));
+    assert_eq!(r3.clone() + Value::Uninit, Value::Uninit);
+    assert_eq!(Value::Any & Value::Uninit, Value::Uninit);
+    assert_eq!(!Value::Uninit, Value::Uninit);
+    assert_eq!(r3 << Value::Any, Value::Any);
+}
+
+#[test]
+fn join_memory_with_uninit() {
+    let mut a = BlockState::default();
+    a.memory.insert(0x80000000, Value::Uninit);
+    a.memory.insert(0x80000004, Value::Uninit);
+
+    let mut b = BlockState::default();
+    b.memory.insert(0x80000000, Value::u32(3));
+
+    let joined = a.join(&b, &mut ());
+    assert_eq!(
+        joined.memory.into_iter().collect::<Vec<_>>(),
+        [(0x80000000, Value::u32(3))]
+    );
+}
+
+#[test]
+fn equal_expressions_are_shared() {
+    let offset = || Value::Param(Gpr(1)) + Value::u32(8);
+    let (Value::Expr(a), Value::Expr(b)) = (offset(), offset()) else {
+        panic!("offsets should be expressions");
+    };
+    assert!(Rc::ptr_eq(&a, &b));
+
+    // Also when built in a different way
+    let Value::Expr(c) = Value::Param(Gpr(1)) + Value::u32(16) - Value::u32(8) else {
+        panic!("offset should be an expression");
+    };
+    assert!(Rc::ptr_eq(&a, &c));
+}
+
+#[test]
+fn pointer_arithmetic() {
+    let ptr = |value: u32| {
+        Value::Int(VInt {
+            value,
+            ty: IntType::Ptr,
+        })
+    };
+
+    // ptr + int and int + ptr are pointers, as is ptr - int
+    assert_eq!(ptr(0x80001000) + Value::u32(4), ptr(0x80001004));
+    assert_eq!(Value::u32(4) + ptr(0x80001000), ptr(0x80001004));
+    assert_eq!(ptr(0x80001000) - Value::u32(4), ptr(0x80000ffc));
+
+    // ptr - ptr is the distance between them
+    assert_eq!(ptr(0x80001010) - ptr(0x80001000), Value::u32(0x10));
+    assert_eq!(
+        (ptr(0x80001010) + Value::u32(4)) - (ptr(0x80001000) + Value::u32(8)),
+        Value::u32(0xc)
+    );
+
+    // Indexing into an array is still a pointer, and the index can be recovered from it
+    let index = Value::Param(Gpr(3)) * Value::u32(4);
+    let element = ptr(0x80001000) + index.clone();
+    assert!(element.is_ptr());
+    assert!(!index.is_ptr());
+    assert!(!(Value::Param(Gpr(3)) - ptr(0x80001000)).is_ptr());
+    assert_eq!(element - ptr(0x80001000), index);
+}
+
+#[test]
+fn load_from_unknown_address_is_symbolic() {
+    let state = final_state(&[
+        0x80, 0x83, 0x00, 0x04, // lwz     r4,4(r3)
+        0x80, 0xa4, 0x00, 0x00, // lwz     r5,0(r4)
+        0x90, 0xa3, 0x00, 0x08, // stw     r5,8(r3)
+        0x80, 0xc3, 0x00, 0x04, // lwz     r6,4(r3)
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    let r4 = state.gpr(Gpr(4));
+    assert_eq!(format!("{r4:?}"), "*(r3 + 0x4)");
+    assert_eq!(format!("{:?}", state.gpr(Gpr(5))), "**(r3 + 0x4)");
+    // The store could have overwritten `4(r3)`
+    assert_eq!(state.gpr(Gpr(6)), Value::Any);
+}
+
+#[test]
+fn load_after_call_is_unknown() {
+    let state = final_state(&[
+        0x48, 0x00, 0x00, 0x11, // bl      +0x10
+        0x80, 0x9f, 0x00, 0x04, // lwz     r4,4(r31)
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    assert!(!state.entry_memory);
+    assert_eq!(state.gpr(Gpr(4)), Value::Any);
+}
+
+#[test]
+fn join_with_entry_memory() {
+    let mut a = BlockState {
+        entry_memory: true,
+        ..BlockState::default()
+    };
+    a.memory.insert(0x80000000, Value::u32(1));
+    a.memory.insert(0x80000004, Value::u32(2));
+    let mut b = a.clone();
+    b.memory.remove(&0x80000000);
+
+    // One path didn't write to the first word, so it still has its original value there, which could be anything
+    let joined = a.join(&b, &mut ());
+    assert!(joined.entry_memory);
+    assert_eq!(joined.load_word(&Value::u32(0x80000000)), Value::Any);
+    assert_eq!(joined.load_word(&Value::u32(0x80000004)), Value::u32(2));
+    assert_eq!(
+        format!("{:?}", joined.load_word(&Value::u32(0x80000008))),
+        "*0x80000008"
+    );
+}
+
+#[test]
+fn load_from_const_section() {
+    let rodata = [0x00, 0x00, 0x00, 0x2a, 0x12, 0x34, 0x56, 0x78];
+    let sections = [ConstSection {
+        address: 0x80400000,
+        bytes: &rodata,
+    }];
+    let state = final_state_with_sections(
+        &[
+            0x3c, 0x60, 0x80, 0x40, // lis     r3,-32704
+            0x80, 0x83, 0x00, 0x00, // lwz     r4,0(r3)
+            0x80, 0xa3, 0x00, 0x04, // lwz     r5,4(r3)
+            0x80, 0xc3, 0x00, 0x06, // lwz     r6,6(r3)
+            0x4e, 0x80, 0x00, 0x20, // blr
+        ],
+        &sections,
+    );
+
+    assert_eq!(state.gpr(Gpr(4)), Value::u32(42));
+    assert_eq!(state.gpr(Gpr(5)), Value::u32(0x12345678));
+    // Runs past the end of the section
+    assert_eq!(format!("{:?}", state.gpr(Gpr(6))), "*&0x80400006");
+}
+
+#[test]
+fn const_section_not_trusted_after_call_or_unknown_store() {
+    let data = [0x00, 0x00, 0x00, 0x2a];
+    let sections = [ConstSection {
+        address: 0x80400000,
+        bytes: &data,
+    }];
+    let after_call = final_state_with_sections(
+        &[
+            0x48, 0x00, 0x00, 0x11, // bl      +0x10
+            0x3c, 0x60, 0x80, 0x40, // lis     r3,-32704
+            0x80, 0x63, 0x00, 0x00, // lwz     r3,0(r3)
+            0x4e, 0x80, 0x00, 0x20, // blr
+        ],
+        &sections,
+    );
+    let after_store = final_state_with_sections(
+        &[
+            0x90, 0xa4, 0x00, 0x00, // stw     r5,0(r4)
+            0x3c, 0x60, 0x80, 0x40, // lis     r3,-32704
+            0x80, 0x63, 0x00, 0x00, // lwz     r3,0(r3)
+            0x4e, 0x80, 0x00, 0x20, // blr
+        ],
+        &sections,
+    );
+
+    // Either could have changed the word, so it is no longer the one in the file
+    assert_eq!(after_call.gpr(Gpr(3)), Value::Any);
+    assert_eq!(after_store.gpr(Gpr(3)), Value::Any);
+}
+
+#[test]
+fn stack_slot_roundtrip() {
+    let state = final_state(&[
+        0x94, 0x21, 0xff, 0xf0, // stwu    r1,-16(r1)
+        0x93, 0xe1, 0x00, 0x0c, // stw     r31,12(r1)
+        0x3b, 0xe0, 0x00, 0x05, // li      r31,5
+        0x90, 0x61, 0x00, 0x08, // stw     r3,8(r1)
+        0x90, 0xa6, 0x00, 0x00, // stw     r5,0(r6)
+        0x48, 0x00, 0x00, 0x15, // bl      +0x14
+        0x80, 0x81, 0x00, 0x08, // lwz     r4,8(r1)
+        0x83, 0xe1, 0x00, 0x0c, // lwz     r31,12(r1)
+        0x38, 0x21, 0x00, 0x10, // addi    r1,r1,16
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    // Neither the store through r6 nor the call touch the stack frame
+    assert_eq!(
+        state.stack.clone().into_iter().collect::<Vec<_>>(),
+        [
+            (-16, Value::Param(Gpr(1))),
+            (-8, Value::Param(Gpr(3))),
+            (-4, Value::Param(Gpr(31))),
+        ]
+    );
+    assert_eq!(state.gpr(Gpr(4)), Value::Param(Gpr(3)));
+    assert_eq!(state.gpr(Gpr(31)), Value::Param(Gpr(31)));
+    assert_eq!(state.gpr(Gpr(1)), Value::Param(Gpr(1)));
+}
+
+#[test]
+fn stack_slots_overlap() {
+    let mut state = BlockState::default();
+    let slot = |offset: u32| Value::Param(Gpr(1)) - Value::u32(offset);
+    state.store_word(&slot(8), Value::u32(1));
+    state.store_word(&slot(4), Value::u32(2));
+    state.store_word(&slot(6), Value::u32(3));
+
+    assert_eq!(state.load_word(&slot(8)), Value::Any);
+    assert_eq!(state.load_word(&slot(6)), Value::u32(3));
+    assert_eq!(state.load_word(&slot(4)), Value::Any);
+    assert_eq!(state.load_word(&slot(12)), Value::Any);
+}
+
+#[test]
+fn escaped_stack_slot_is_clobbered_by_call() {
+    let state = final_state(&[
+        0x94, 0x21, 0xff, 0xf0, // stwu    r1,-16(r1)
+        0x38, 0x80, 0x00, 0x01, // li      r4,1
+        0x90, 0x81, 0x00, 0x08, // stw     r4,8(r1)
+        0x38, 0x61, 0x00, 0x08, // addi    r3,r1,8
+        0x48, 0x00, 0x00, 0x11, // bl      +0x10
+        0x80, 0x81, 0x00, 0x08, // lwz     r4,8(r1)
+        0x38, 0x21, 0x00, 0x10, // addi    r1,r1,16
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    assert!(state.stack.is_empty());
+    assert_eq!(state.gpr(Gpr(4)), Value::Any);
+}
+
+#[test]
+fn carry_folds_constants() {
+    let one = Value::u32(1);
+    assert_eq!(
+        Value::u32(0xffff_ffff).carry(&one, &Value::u32(0)),
+        Value::u32(1)
+    );
+    assert_eq!(
+        Value::u32(0xffff_fffe).carry(&one, &Value::u32(0)),
+        Value::u32(0)
+    );
+    assert_eq!(Value::u32(0xffff_fffe).carry(&one, &one), Value::u32(1));
+    assert_eq!(Value::Param(Gpr(3)).carry(&one, &one), Value::Any);
+    assert_eq!(one.carry(&one, &Value::Any), Value::Any);
+    assert_eq!(one.carry(&one, &Value::Uninit), Value::Uninit);
+}
+
+#[test]
+fn addc_adde_64_bit_add() {
+    let state = final_state(&[
+        0x38, 0x60, 0x00, 0x01, // li      r3,1
+        0x38, 0x80, 0xff, 0xff, // li      r4,-1
+        0x38, 0xa0, 0x00, 0x02, // li      r5,2
+        0x38, 0xc0, 0x00, 0x03, // li      r6,3
+        // (r3:r4) + (r5:r6) = 0x1_ffffffff + 0x2_00000003
+        0x7c, 0xe4, 0x30, 0x14, // addc    r7,r4,r6
+        0x7d, 0x03, 0x29, 0x14, // adde    r8,r3,r5
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    assert_eq!(state.gpr(Gpr(7)), Value::u32(2));
+    assert_eq!(state.gpr(Gpr(8)), Value::u32(4));
+    assert_eq!(state.registers.sprs.xer.ca, Value::u32(0));
+}
+
+#[test]
+fn subfc_subfe_64_bit_sub() {
+    let state = final_state(&[
+        0x38, 0x60, 0x00, 0x00, // li      r3,0
+        0x38, 0x80, 0x00, 0x01, // li      r4,1
+        0x38, 0xa0, 0x00, 0x00, // li      r5,0
+        0x38, 0xc0, 0x00, 0x02, // li      r6,2
+        // (r3:r4) - (r5:r6) = 1 - 2
+        0x7c, 0xe6, 0x20, 0x10, // subfc   r7,r6,r4
+        0x7d, 0x05, 0x19, 0x10, // subfe   r8,r5,r3
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    assert_eq!(state.gpr(Gpr(7)), Value::u32(0xffff_ffff));
+    assert_eq!(state.gpr(Gpr(8)), Value::u32(0xffff_ffff));
+    assert_eq!(state.registers.sprs.xer.ca, Value::u32(0));
+}
+
+#[test]
+fn symbolic_carry_widens() {
+    let state = final_state(&[
+        0x7c, 0xe4, 0x30, 0x14, // addc    r7,r4,r6
+        0x7d, 0x03, 0x29, 0x14, // adde    r8,r3,r5
+        0x7d, 0x23, 0x01, 0x94, // addze   r9,r3
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    assert_eq!(
+        state.gpr(Gpr(7)),
+        Value::Param(Gpr(4)) + Value::Param(Gpr(6))
+    );
+    assert_eq!(state.gpr(Gpr(8)), Value::Any);
+    assert_eq!(state.gpr(Gpr(9)), Value::Any);
+    assert_eq!(state.registers.sprs.xer.ca, Value::Any);
+}
+
+#[test]
+fn record_forms_update_cr0() {
+    let state = final_state(&[
+        0x38, 0x60, 0x00, 0x05, // li      r3,5
+        0x38, 0x80, 0x00, 0x05, // li      r4,5
+        0x7c, 0xa4, 0x18, 0x51, // subf.   r5,r4,r3
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+    let cr0 = state.registers.sprs.cr[0].clone();
+    assert_eq!(
+        [cr0.lt, cr0.gt, cr0.eq],
+        [Value::u32(0), Value::u32(0), Value::u32(1)]
+    );
+
+    let state = final_state(&[
+        0x38, 0x60, 0xff, 0xff, // li      r3,-1
+        0x70, 0x64, 0x80, 0x00, // andi.   r4,r3,0x8000
+        0x7c, 0xa4, 0x20, 0xd1, // neg.    r5,r4
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+    let cr0 = state.registers.sprs.cr[0].clone();
+    assert_eq!(
+        [cr0.lt, cr0.gt, cr0.eq],
+        [Value::u32(1), Value::u32(0), Value::u32(0)]
+    );
+
+    // The result isn't known, so neither is CR0.
+    let state = final_state(&[
+        0x7c, 0xa4, 0x18, 0x51, // subf.   r5,r4,r3
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+    assert_eq!(state.registers.sprs.cr[0].eq, Value::Any);
+}
+
+#[test]
+fn compare_values() {
+    let minus_one = Value::u32(u32::MAX);
+    let one = Value::u32(1);
+    assert_eq!(minus_one.compare(&one, true), Some(Ordering::Less));
+    assert_eq!(minus_one.compare(&one, false), Some(Ordering::Greater));
+
+    let r3 = Value::Param(Gpr(3));
+    assert_eq!(r3.compare(&r3, true), Some(Ordering::Equal));
+    assert_eq!(r3.compare(&one, true), None);
+    assert_eq!(Value::Any.compare(&Value::Any, true), None);
+}
+
+#[test]
+fn compares_set_cr_fields() {
+    let state = final_state(&[
+        0x38, 0x60, 0x00, 0x00, // li      r3,0
+        0x2c, 0x03, 0x00, 0x00, // cmpwi   r3,0
+        0x38, 0x80, 0xff, 0xff, // li      r4,-1
+        0x2f, 0x84, 0x00, 0x01, // cmpwi   cr7,r4,1
+        0x28, 0x84, 0x00, 0x01, // cmplwi  cr1,r4,1
+        0x7f, 0x03, 0x20, 0x00, // cmpw    cr6,r3,r4
+        0x7e, 0x83, 0x20, 0x40, // cmplw   cr5,r3,r4
+        0x2c, 0x05, 0x00, 0x00, // cmpwi   r5,0
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+    let cr = |crf: usize| {
+        let field = &state.registers.sprs.cr[crf];
+        [&field.lt, &field.gt, &field.eq].map(|bit| bit.as_int().map(|int| int.value))
+    };
+
+    // r5 is a parameter, so the last compare can't be decided.
+    assert_eq!(cr(0), [None, None, None]);
+    assert_eq!(cr(7), [Some(1), Some(0), Some(0)]);
+    assert_eq!(cr(1), [Some(0), Some(1), Some(0)]);
+    assert_eq!(cr(6), [Some(0), Some(1), Some(0)]);
+    assert_eq!(cr(5), [Some(1), Some(0), Some(0)]);
+}
+
+#[test]
+fn cmpwi_zero_sets_cr0() {
+    let state = final_state(&[
+        0x38, 0x60, 0x00, 0x00, // li      r3,0
+        0x2c, 0x03, 0x00, 0x00, // cmpwi   r3,0
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+    let cr0 = &state.registers.sprs.cr[0];
+    assert_eq!(cr0.lt, Value::u32(0));
+    assert_eq!(cr0.gt, Value::u32(0));
+    assert_eq!(cr0.eq, Value::u32(1));
+}
+
+#[test]
+fn known_branch_conditions_prune_edges() {
+    let run = |branch: [u8; 4]| {
+        let code = [
+            [0x38, 0x60, 0x00, 0x00], // li      r3,0
+            [0x2c, 0x03, 0x00, 0x00], // cmpwi   r3,0
+            branch,                   // beq/bne 0x14
+            [0x38, 0x80, 0x00, 0x01], // li      r4,1
+            [0x48, 0x00, 0x00, 0x08], // b       0x18
+            [0x38, 0x80, 0x00, 0x02], // li      r4,2
+            [0x4e, 0x80, 0x00, 0x20], // blr
+        ]
+        .concat();
+        let insts = decode_instructions(&code, FN_ADDRESS).unwrap();
+        let analysis = ValueAnalysis {
+            insts: &insts,
+            fn_address: FN_ADDRESS,
+            const_sections: &[],
+        };
+        let results = analysis.run().unwrap();
+        let reached = [3, 5].map(|idx| results.state_at(InstId(idx)).is_some());
+        let r4 = results
+            .for_each_with_input(&analysis, |cx| cx.effect())
+            .gpr(Gpr(4));
+        (reached, r4)
+    };
+
+    let state = final_state(&[
+        0x38, 0x60, 0x00, 0x00, // li      r3,0
+        0x2c, 0x03, 0x00, 0x00, // cmpwi   r3,0
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+    let decode = |word: u32| {
+        Decoder::new(&word.to_be_bytes())
+            .decode_instruction()
+            .unwrap()
+    };
+    assert_eq!(
+        ValueAnalysis::branch_taken(&state, &decode(0x4182000c)),
+        Some(true)
+    );
+    assert_eq!(
+        ValueAnalysis::branch_taken(&state, &decode(0x4082000c)),
+        Some(false)
+    );
+    // blt cr1 tests a field that is still unknown.
+    assert_eq!(
+        ValueAnalysis::branch_taken(&state, &decode(0x4184000c)),
+        None
+    );
+
+    // r3 is always 0, so `beq` is always taken and the fall through path never runs.
+    let (reached, r4) = run([0x41, 0x82, 0x00, 0x0c]);
+    assert_eq!(reached, [false, true]);
+    assert_eq!(r4, Value::u32(2));
+
+    // `bne` is never taken.
+    let (reached, r4) = run([0x40, 0x82, 0x00, 0x0c]);
+    assert_eq!(reached, [true, false]);
+    assert_eq!(r4, Value::u32(1));
+}
+
+#[test]
+fn unmodeled_cr_writes_keep_both_edges() {
+    let reached = |clobber: [u8; 4]| {
+        let code = [
+            [0x38, 0x60, 0x00, 0x00], // li      r3,0
+            [0x2c, 0x03, 0x00, 0x00], // cmpwi   r3,0
+            clobber,                  // crxor/fcmpu
+            [0x41, 0x82, 0x00, 0x0c], // beq     0x18
+            [0x38, 0x80, 0x00, 0x01], // li      r4,1
+            [0x48, 0x00, 0x00, 0x08], // b       0x1c
+            [0x38, 0x80, 0x00, 0x02], // li      r4,2
+            [0x4e, 0x80, 0x00, 0x20], // blr
+        ]
+        .concat();
+        let insts = decode_instructions(&code, FN_ADDRESS).unwrap();
+        let analysis = ValueAnalysis {
+            insts: &insts,
+            fn_address: FN_ADDRESS,
+            const_sections: &[],
+        };
+        let results = analysis.run().unwrap();
+        assert!(analysis.infeasible_edges(&results).is_empty());
+        [4, 6].map(|idx| results.state_at(InstId(idx)).is_some())
+    };
+
+    // `crxor eq,eq,eq` clears the bit that `cmpwi` set, so only the fall through path actually runs, and the branch
+    // can't be decided from the compare.
+    assert_eq!(reached([0x4c, 0x42, 0x11, 0x82]), [true, true]);
+    // `fcmpu cr0,f1,f2` overwrites all of CR0 with a comparison of unknown values.
+    assert_eq!(reached([0xfc, 0x01, 0x10, 0x00]), [true, true]);
+}
+
+#[test]
+fn stwux_stores_and_updates_base() {
+    let state = final_state(&[
+        0x38, 0xa0, 0xff, 0xe0, // li      r5,-32
+        0x7c, 0x21, 0x29, 0x6e, // stwux   r1,r1,r5
+        0x38, 0x80, 0x00, 0x07, // li      r4,7
+        0x38, 0xc0, 0x00, 0x08, // li      r6,8
+        0x7c, 0x81, 0x31, 0x2e, // stwx    r4,r1,r6
+        0x80, 0x61, 0x00, 0x08, // lwz     r3,8(r1)
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    let sp = Value::Param(Gpr(1));
+    // The back chain is stored at the new stack pointer.
+    assert_eq!(state.gpr(Gpr(1)), sp.clone() - Value::u32(32));
+    assert_eq!(state.stack.get(&-32), Some(&sp));
+    assert_eq!(state.stack.get(&-24), Some(&Value::u32(7)));
+    assert_eq!(state.gpr(Gpr(3)), Value::u32(7));
+}
+
+#[test]
+fn stwux_with_unknown_index_forgets_memory() {
+    let state = final_state(&[
+        0x3c, 0x80, 0x80, 0x00, // lis     r4,-32768
+        0x90, 0xa4, 0x00, 0x10, // stw     r5,16(r4)
+        0x7c, 0x21, 0x19, 0x6e, // stwux   r1,r1,r3
+        0x80, 0xc4, 0x00, 0x10, // lwz     r6,16(r4)
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    assert_eq!(
+        state.gpr(Gpr(1)),
+        Value::Param(Gpr(1)) + Value::Param(Gpr(3))
+    );
+    assert_eq!(state.gpr(Gpr(6)), Value::Any);
+}
+
+#[test]
+fn add_and_subf_neg_dataflow() {
+    let state = final_state(&[
+        0x7c, 0xa3, 0x22, 0x14, // add     r5,r3,r4
+        0x7c, 0x66, 0x20, 0x38, // and     r6,r3,r4
+        0x7c, 0xe3, 0x20, 0x50, // subf    r7,r3,r4
+        0x7d, 0x03, 0x00, 0xd0, // neg     r8,r3
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    let (r3, r4) = (Value::Param(Gpr(3)), Value::Param(Gpr(4)));
+    assert_eq!(state.gpr(Gpr(5)), r3.clone() + r4.clone());
+    assert_eq!(state.gpr(Gpr(6)), r3.clone() & r4.clone());
+    // subf subtracts its first source operand from the second one.
+    assert_eq!(state.gpr(Gpr(7)), r4 - r3.clone());
+    assert_eq!(state.gpr(Gpr(8)), -r3);
+}
+
+#[test]
+fn add_and_subf_neg_record_forms() {
+    let cr0 = |op: [u8; 4]| {
+        let code = [
+            [0x38, 0x60, 0x00, 0x05], // li      r3,5
+            [0x38, 0x80, 0xff, 0xfb], // li      r4,-5
+            op,
+            [0x4e, 0x80, 0x00, 0x20], // blr
+        ]
+        .concat();
+        let state = final_state(&code);
+        let field = &state.registers.sprs.cr[0];
+        [&field.lt, &field.gt, &field.eq].map(|bit| int(bit.clone()).value)
+    };
+
+    // add.    r5,r3,r4 (0)
+    assert_eq!(cr0([0x7c, 0xa3, 0x22, 0x15]), [0, 0, 1]);
+    // and.    r6,r3,r4 (1)
+    assert_eq!(cr0([0x7c, 0x66, 0x20, 0x39]), [0, 1, 0]);
+    // subf.   r7,r3,r4 (-10)
+    assert_eq!(cr0([0x7c, 0xe3, 0x20, 0x51]), [1, 0, 0]);
+    // neg.    r8,r3 (-5)
+    assert_eq!(cr0([0x7d, 0x03, 0x00, 0xd1]), [1, 0, 0]);
+}
+
+#[test]
+fn pointer_writes_of_lis_addi() {
+    let insts = decode_instructions(
+        &[
+            0x3c, 0x80, 0x80, 0x40, // lis     r4,-32704
+            0x38, 0x84, 0x12, 0x34, // addi    r4,r4,4660
+            0x38, 0xa4, 0x00, 0x08, // addi    r5,r4,8
+            0x38, 0xc0, 0x00, 0x08, // li      r6,8
+            0x4e, 0x80, 0x00, 0x20, // blr
+        ],
+        FN_ADDRESS,
+    )
+    .unwrap();
+    let analysis = ValueAnalysis {
+        insts: &insts,
+        fn_address: FN_ADDRESS,
+        const_sections: &[],
+    };
+    let results = analysis.run().unwrap();
+
+    // The `lis` on its own is only the upper half.
+    assert_eq!(
+        analysis.pointer_writes(&results),
+        [(InstId(1), Gpr(4)), (InstId(2), Gpr(5))].into()
+    );
+}
+
+#[test]
+fn lr_save_restore_idiom() {
+    let insts = decode_instructions(
+        &[
+            0x94, 0x21, 0xff, 0xf8, // stwu    r1,-8(r1)
+            0x7c, 0x08, 0x02, 0xa6, // mflr    r0
+            0x90, 0x01, 0x00, 0x0c, // stw     r0,12(r1)
+            0x90, 0x61, 0x00, 0x04, // stw     r3,4(r1)
+            0x48, 0x00, 0x00, 0x15, // bl      +0x14
+            0x80, 0x01, 0x00, 0x0c, // lwz     r0,12(r1)
+            0x7c, 0x08, 0x03, 0xa6, // mtlr    r0
+            0x38, 0x21, 0x00, 0x08, // addi    r1,r1,8
+            0x4e, 0x80, 0x00, 0x20, // blr
+        ],
+        FN_ADDRESS,
+    )
+    .unwrap();
+    let analysis = ValueAnalysis {
+        insts: &insts,
+        fn_address: FN_ADDRESS,
+        const_sections: &[],
+    };
+    let results = analysis.run().unwrap();
+
+    assert_eq!(
+        analysis.lr_save_restore(&results),
+        [1, 2, 5, 6].into_iter().map(InstId).collect()
+    );
+    let state = results.for_each_with_input(&analysis, |cx| cx.effect());
+    assert_eq!(state.spr(Spr::Lr), Value::ReturnAddress);
+}
+
+#[test]
+fn lr_restored_from_the_wrong_slot_is_not_the_idiom() {
+    let insts = decode_instructions(
+        &[
+            0x7c, 0x08, 0x02, 0xa6, // mflr    r0
+            0x90, 0x01, 0x00, 0x04, // stw     r0,4(r1)
+            0x80, 0x01, 0x00, 0x08, // lwz     r0,8(r1)
+            0x7c, 0x08, 0x03, 0xa6, // mtlr    r0
+            0x4e, 0x80, 0x00, 0x20, // blr
+        ],
+        FN_ADDRESS,
+    )
+    .unwrap();
+    let analysis = ValueAnalysis {
+        insts: &insts,
+        fn_address: FN_ADDRESS,
+        const_sections: &[],
+    };
+    let results = analysis.run().unwrap();
+
+    assert_eq!(
+        analysis.lr_save_restore(&results),
+        [0, 1].into_iter().map(InstId).collect()
+    );
+}
+
+#[test]
+fn stmw_lmw_dataflow() {
+    let state = final_state(&[
+        0x94, 0x21, 0xff, 0xf0, // stwu    r1,-16(r1)
+        0xbf, 0xc1, 0x00, 0x08, // stmw    r30,8(r1)
+        0x3b, 0xc0, 0x00, 0x01, // li      r30,1
+        0x3b, 0xe0, 0x00, 0x02, // li      r31,2
+        0x80, 0xa1, 0x00, 0x0c, // lwz     r5,12(r1)
+        0x90, 0x61, 0x00, 0x08, // stw     r3,8(r1)
+        0xbb, 0xc1, 0x00, 0x08, // lmw     r30,8(r1)
+        0x38, 0x21, 0x00, 0x10, // addi    r1,r1,16
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ]);
+
+    assert_eq!(state.stack.get(&-4), Some(&Value::Param(Gpr(31))));
+    assert_eq!(state.gpr(Gpr(5)), Value::Param(Gpr(31)));
+    // The slot of r30 was overwritten in between.
+    assert_eq!(state.gpr(Gpr(30)), Value::Param(Gpr(3)));
+    assert_eq!(state.gpr(Gpr(31)), Value::Param(Gpr(31)));
+    assert_eq!(
+        state.gpr(Gpr::STACK_POINTER),
+        Value::Param(Gpr::STACK_POINTER)
+    );
+}
+
+#[test]
+fn bdnz_counting_loop() {
+    let run = |count: u8| {
+        let insts = decode_instructions(
+            &[
+                0x38, 0x80, 0x00, 0x00, // li      r4,0
+                0x38, 0xa0, 0x00, count, // li      r5,count
+                0x7c, 0xa9, 0x03, 0xa6, // mtctr   r5
+                0x38, 0x84, 0x00, 0x03, // addi    r4,r4,3
+                0x42, 0x00, 0xff, 0xfc, // bdnz    -0x4
+                0x4e, 0x80, 0x00, 0x20, // blr
+            ],
+            FN_ADDRESS,
+        )
+        .unwrap();
+        let analysis = ValueAnalysis {
+            insts: &insts,
+            fn_address: FN_ADDRESS,
+            const_sections: &[],
+        };
+        let results = analysis.run().unwrap();
+        let mut ctr_after_bdnz = Vec::new();
+        let state = results.for_each_with_input(&analysis, |cx| {
+            cx.effect();
+            if cx.idx() == InstId(4) {
+                ctr_after_bdnz.push(cx.state().spr(Spr::Ctr));
+            }
+        });
+        // Only the `bdnz` closes a counting loop.
+        assert_eq!(analysis.ctr_loop_count(&results, InstId(3)), None);
+        (
+            analysis.ctr_loop_count(&results, InstId(4)),
+            ctr_after_bdnz,
+            state.gpr(Gpr(4)),
+        )
+    };
+
+    // A single iteration: the decremented CTR is 0, so the back edge is never taken.
+    let (count, ctr, r4) = run(1);
+    assert_eq!(count, Some(Value::u32(1)));
+    assert_eq!(ctr, [Value::u32(0)]);
+    assert_eq!(r4, Value::u32(3));
+
+    // With more iterations the values that change in the loop aren't known anymore, but the trip count still is.
+    let (count, _, r4) = run(10);
+    assert_eq!(count, Some(Value::u32(10)));
+    assert_eq!(r4, Value::Any);
+}
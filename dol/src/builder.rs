@@ -0,0 +1,103 @@
+use crate::{Dol, DolError, SectionKind};
+
+/// Section data in the file is aligned to this.
+const SECTION_ALIGNMENT: usize = 0x20;
+
+struct Section {
+    load_address: u32,
+    bytes: Vec<u8>,
+}
+
+/// Assembles a .dol from section contents. Sections are laid out after the header in the order they were added.
+#[derive(Default)]
+pub struct DolBuilder {
+    text: Vec<Section>,
+    data: Vec<Section>,
+    bss_address: u32,
+    bss_size: u32,
+    entrypoint: u32,
+}
+
+impl DolBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn text_section(mut self, load_address: u32, bytes: Vec<u8>) -> Self {
+        self.text.push(Section {
+            load_address,
+            bytes,
+        });
+        self
+    }
+
+    pub fn data_section(mut self, load_address: u32, bytes: Vec<u8>) -> Self {
+        self.data.push(Section {
+            load_address,
+            bytes,
+        });
+        self
+    }
+
+    pub fn bss(mut self, address: u32, size: u32) -> Self {
+        self.bss_address = address;
+        self.bss_size = size;
+        self
+    }
+
+    pub fn entrypoint(mut self, entrypoint: u32) -> Self {
+        self.entrypoint = entrypoint;
+        self
+    }
+
+    /// Serializes the header and sections, and validates the result like [`Dol::new`] does.
+    pub fn build(self) -> Result<Dol, DolError> {
+        for (sections, kind) in [
+            (&self.text, SectionKind::Text),
+            (&self.data, SectionKind::Data),
+        ] {
+            if sections.len() > kind.max_sections() {
+                return Err(DolError::TooManySections(kind));
+            }
+        }
+
+        let mut bytes = vec![0; 0x100];
+
+        let text = self.text.iter().enumerate();
+        let data = (self.data.iter().enumerate())
+            .map(|(index, section)| (SectionKind::Text.max_sections() + index, section));
+        for (index, section) in text.chain(data) {
+            // Empty sections are marked by a size of 0 and don't need any data.
+            if !section.bytes.is_empty() {
+                let file_offset = bytes.len().next_multiple_of(SECTION_ALIGNMENT);
+                bytes.resize(file_offset, 0);
+                put_u32(
+                    &mut bytes,
+                    Dol::SECTION_OFFSET_OFF + index * 4,
+                    file_offset as u32,
+                );
+                bytes.extend_from_slice(&section.bytes);
+            }
+            put_u32(
+                &mut bytes,
+                Dol::SECTION_ADDRESS_OFF + index * 4,
+                section.load_address,
+            );
+            put_u32(
+                &mut bytes,
+                Dol::SECTION_SIZE_OFF + index * 4,
+                section.bytes.len() as u32,
+            );
+        }
+
+        put_u32(&mut bytes, Dol::BSS_ADDR_OFF, self.bss_address);
+        put_u32(&mut bytes, Dol::BSS_SIZE_OFF, self.bss_size);
+        put_u32(&mut bytes, Dol::ENTRYPOINT_OFF, self.entrypoint);
+
+        Dol::new(bytes)
+    }
+}
+
+fn put_u32(bytes: &mut [u8], off: usize, value: u32) {
+    bytes[off..][..4].copy_from_slice(&value.to_be_bytes());
+}
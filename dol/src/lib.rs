@@ -1,18 +1,60 @@
+use std::{error::Error, fmt::Display, ops::Range};
+
+mod builder;
+
+pub use builder::DolBuilder;
+
+/// Whether a section contains code or data. A .dol always has 7 text sections followed by 11 data sections.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SectionKind {
+    Text,
+    Data,
+}
+
+impl SectionKind {
+    const TEXT_SECTIONS: usize = 7;
+    const DATA_SECTIONS: usize = 11;
+
+    /// How many sections of this kind a .dol can have.
+    pub fn max_sections(self) -> usize {
+        match self {
+            SectionKind::Text => Self::TEXT_SECTIONS,
+            SectionKind::Data => Self::DATA_SECTIONS,
+        }
+    }
+
+    pub fn of_index(index: usize) -> Self {
+        if index < Self::TEXT_SECTIONS {
+            SectionKind::Text
+        } else {
+            SectionKind::Data
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct SectionInfo {
     pub file_offset: u32,
     pub load_offset: u32,
     pub size: u32,
+    pub kind: SectionKind,
 }
 
 impl SectionInfo {
     pub fn contains_addr(&self, addr: u32) -> bool {
-        addr >= self.load_offset && addr < self.load_offset + self.size
+        // Written so that a section ending exactly at the top of the address space doesn't overflow.
+        addr >= self.load_offset && addr - self.load_offset < self.size
     }
 
     pub fn file_offset_of_addr(&self, addr: u32) -> u32 {
-        assert!(self.contains_addr(addr));
-        self.file_offset + (addr - self.load_offset)
+        self.checked_file_offset_of_addr(addr)
+            .expect("address is not in this section")
+    }
+
+    /// Like [`SectionInfo::file_offset_of_addr`], but returns `None` if the address is not in this section.
+    pub fn checked_file_offset_of_addr(&self, addr: u32) -> Option<u32> {
+        self.contains_addr(addr)
+            .then(|| self.file_offset + (addr - self.load_offset))
     }
 
     pub fn empty(&self) -> bool {
@@ -20,6 +62,124 @@ impl SectionInfo {
     }
 }
 
+/// The error returned when a load address is not part of any section.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct UnmappedAddress(pub u32);
+
+impl Display for UnmappedAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "address {:#x} is not in any section", self.0)
+    }
+}
+
+impl Error for UnmappedAddress {}
+
+/// Why [`Dol::new`] rejected a file.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DolError {
+    /// The file is too small to contain all headers.
+    TooSmall,
+    /// The file is too large for its offsets to fit in a u32.
+    TooLarge,
+    /// The section's data does not fit in the file.
+    SectionOutOfBounds { index: usize },
+    /// The section is loaded past the end of the 32-bit address space.
+    SectionOutOfAddressSpace { index: usize },
+    /// The two sections are loaded to overlapping addresses.
+    OverlappingSections { first: usize, second: usize },
+    /// More sections of this kind were added to a [`DolBuilder`] than fit in the header.
+    TooManySections(SectionKind),
+}
+
+impl Display for DolError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DolError::TooSmall => {
+                write!(
+                    f,
+                    ".dol file smaller than 255 bytes (does not contain all headers)"
+                )
+            }
+            DolError::TooLarge => {
+                write!(f, ".dol file larger than 4 GiB (file size exceeds u32)")
+            }
+            DolError::SectionOutOfBounds { index } => {
+                write!(f, "section #{index} extends past the end of the file")
+            }
+            DolError::SectionOutOfAddressSpace { index } => {
+                write!(
+                    f,
+                    "section #{index} extends past the end of the address space"
+                )
+            }
+            DolError::OverlappingSections { first, second } => {
+                write!(f, "sections #{first} and #{second} overlap in memory")
+            }
+            DolError::TooManySections(kind) => {
+                write!(
+                    f,
+                    "too many {kind:?} sections (at most {} are supported)",
+                    kind.max_sections()
+                )
+            }
+        }
+    }
+}
+
+impl Error for DolError {}
+
+/// A contiguous range of loaded memory. `bytes` is `None` for zero-filled (BSS) memory.
+#[derive(Debug)]
+struct Region<'a> {
+    start: u32,
+    len: u32,
+    bytes: Option<&'a [u8]>,
+}
+
+impl Region<'_> {
+    fn end(&self) -> u64 {
+        u64::from(self.start) + u64::from(self.len)
+    }
+}
+
+/// The memory of a loaded DOL: every non-empty section at its load address, plus the parts of the BSS range that
+/// aren't covered by a section as zeroes. Created by [`Dol::memory_image`].
+#[derive(Debug)]
+pub struct MemoryImage<'a> {
+    /// Sorted by start address and disjoint.
+    regions: Vec<Region<'a>>,
+    /// Backs reads from zero-filled regions, as long as the longest of them.
+    zeroes: Vec<u8>,
+}
+
+impl MemoryImage<'_> {
+    fn region_of(&self, addr: u32) -> Option<&Region<'_>> {
+        let idx = self.regions.partition_point(|region| region.start <= addr);
+        let region = self.regions.get(idx.checked_sub(1)?)?;
+        (u64::from(addr) < region.end()).then_some(region)
+    }
+
+    /// Whether the address is loaded to memory.
+    pub fn contains(&self, addr: u32) -> bool {
+        self.region_of(addr).is_some()
+    }
+
+    /// Reads `len` bytes starting at `addr`. Returns `None` if the address isn't mapped or the read would cross the
+    /// end of a section (or BSS range), even if the memory right after it is mapped too.
+    pub fn read(&self, addr: u32, len: u32) -> Option<&[u8]> {
+        let region = self.region_of(addr)?;
+        if u64::from(addr) + u64::from(len) > region.end() {
+            return None;
+        }
+
+        let len = len as usize;
+        match region.bytes {
+            Some(bytes) => Some(&bytes[(addr - region.start) as usize..][..len]),
+            None => Some(&self.zeroes[..len]),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Dol(Vec<u8>);
 
@@ -45,18 +205,59 @@ impl Dol {
     const ENTRYPOINT_OFF: usize = 0xE0;
 
     /// Create a new DOL from the given bytes, validating it in the process.
-    pub fn new(bytes: Vec<u8>) -> Result<Self, &'static str> {
+    pub fn new(bytes: Vec<u8>) -> Result<Self, DolError> {
         // TODO: can we have a check if this is a .elf instead of .dol, for a better error?
 
         if bytes.len() < 0xFF {
-            return Err(".dol file smaller than 255 bytes (does not contain all headers)");
+            return Err(DolError::TooSmall);
         }
 
         if bytes.len() > (1 << 32) {
-            return Err(".dol file larger than 4 GiB (file size exceeds u32)");
+            return Err(DolError::TooLarge);
+        }
+
+        let dol = Self(bytes);
+        dol.validate_sections()?;
+        Ok(dol)
+    }
+
+    /// Checks that every non-empty section is contained in the file and the address space, and that no two of them are
+    /// loaded to the same addresses. Computed in u64 so that bogus offsets and sizes can't overflow.
+    fn validate_sections(&self) -> Result<(), DolError> {
+        let sections: Vec<(usize, SectionInfo)> = self
+            .sections()
+            .enumerate()
+            .filter(|(_, section)| !section.empty())
+            .collect();
+
+        for (index, section) in &sections {
+            let file_end = u64::from(section.file_offset) + u64::from(section.size);
+            if file_end > self.0.len() as u64 {
+                return Err(DolError::SectionOutOfBounds { index: *index });
+            }
+            let load_end = u64::from(section.load_offset) + u64::from(section.size);
+            if load_end > 1 << 32 {
+                return Err(DolError::SectionOutOfAddressSpace { index: *index });
+            }
+        }
+
+        let load_range = |section: &SectionInfo| {
+            let start = u64::from(section.load_offset);
+            start..start + u64::from(section.size)
+        };
+        for (i, (first, a)) in sections.iter().enumerate() {
+            for (second, b) in &sections[i + 1..] {
+                let (a, b) = (load_range(a), load_range(b));
+                if a.start < b.end && b.start < a.end {
+                    return Err(DolError::OverlappingSections {
+                        first: *first,
+                        second: *second,
+                    });
+                }
+            }
         }
 
-        Ok(Self(bytes))
+        Ok(())
     }
 
     fn u32(&self, off: usize) -> u32 {
@@ -70,6 +271,7 @@ impl Dol {
             file_offset: self.u32(Self::SECTION_OFFSET_OFF + section * 4),
             load_offset: self.u32(Self::SECTION_ADDRESS_OFF + section * 4),
             size: self.u32(Self::SECTION_SIZE_OFF + section * 4),
+            kind: SectionKind::of_index(section),
         }
     }
 
@@ -77,6 +279,24 @@ impl Dol {
         (0..18).map(|i| self.section(i))
     }
 
+    /// The non-empty sections of the given kind, along with their index.
+    pub fn sections_of_kind(
+        &self,
+        kind: SectionKind,
+    ) -> impl Iterator<Item = (usize, SectionInfo)> + '_ {
+        self.sections()
+            .enumerate()
+            .filter(move |(_, section)| section.kind == kind && !section.empty())
+    }
+
+    pub fn text_sections(&self) -> impl Iterator<Item = (usize, SectionInfo)> + '_ {
+        self.sections_of_kind(SectionKind::Text)
+    }
+
+    pub fn data_sections(&self) -> impl Iterator<Item = (usize, SectionInfo)> + '_ {
+        self.sections_of_kind(SectionKind::Data)
+    }
+
     pub fn section_of_load_addr(&self, addr: u32) -> Option<SectionInfo> {
         self.sections().find(|s| s.contains_addr(addr))
     }
@@ -93,13 +313,108 @@ impl Dol {
         self.u32(Self::BSS_SIZE_OFF)
     }
 
+    /// The addresses covered by BSS. Clamped to the end of the address space if the size is bogus.
+    pub fn bss_range(&self) -> Range<u32> {
+        let start = self.bss_address();
+        start..start.saturating_add(self.bss_size())
+    }
+
+    /// Whether the address is in the BSS range. Note that this range often also contains small data sections.
+    pub fn bss_contains(&self, addr: u32) -> bool {
+        self.bss_range().contains(&addr)
+    }
+
     pub fn as_bytes(&self) -> &[u8] {
         &self.0
     }
 
+    /// Maps all sections and the BSS range into a single address space.
+    pub fn memory_image(&self) -> MemoryImage<'_> {
+        // Sections are validated to be in bounds and disjoint in `Dol::new`.
+        let mut regions: Vec<Region<'_>> = self
+            .sections()
+            .filter(|section| !section.empty())
+            .map(|section| Region {
+                start: section.load_offset,
+                len: section.size,
+                bytes: Some(&self.0[section.file_offset as usize..][..section.size as usize]),
+            })
+            .collect();
+        regions.sort_by_key(|region| region.start);
+
+        // The BSS range usually also covers some small data sections, which take precedence,
+        // so only the gaps between them are zero-filled.
+        let bss_end = u64::from(self.bss_address()) + u64::from(self.bss_size());
+        let mut cursor = u64::from(self.bss_address());
+        let mut zeroed = Vec::new();
+        for region in &regions {
+            if u64::from(region.start) > cursor {
+                zeroed.push(cursor..u64::from(region.start).min(bss_end));
+            }
+            cursor = cursor.max(region.end());
+        }
+        zeroed.push(cursor..bss_end);
+
+        let zeroed: Vec<Region<'_>> = zeroed
+            .into_iter()
+            .filter(|range| range.start < range.end)
+            .map(|range| Region {
+                start: range.start as u32,
+                len: (range.end - range.start) as u32,
+                bytes: None,
+            })
+            .collect();
+        let longest = zeroed.iter().map(|region| region.len).max().unwrap_or(0);
+
+        regions.extend(zeroed);
+        regions.sort_by_key(|region| region.start);
+
+        MemoryImage {
+            regions,
+            zeroes: vec![0; longest as usize],
+        }
+    }
+
+    /// Returns the offset in the file that the given load address maps to, or `None` if it is not in any section
+    /// or the section claims to extend past the end of the file.
+    pub fn file_offset_of_addr(&self, addr: u32) -> Option<u32> {
+        self.section_of_load_addr(addr)?
+            .checked_file_offset_of_addr(addr)
+            .filter(|&off| (off as usize) < self.0.len())
+    }
+
+    /// Like [`Dol::file_offset_of_addr`], but with an error that can be reported.
+    pub fn try_file_offset_of_addr(&self, addr: u32) -> Result<u32, UnmappedAddress> {
+        self.file_offset_of_addr(addr).ok_or(UnmappedAddress(addr))
+    }
+
+    /// Returns the bytes from the given load address to the end of the section containing it, so that decoding
+    /// from the returned slice stops at the section boundary.
     pub fn slice_from_load_addr(&self, addr: u32) -> Option<&[u8]> {
         let section = self.section_of_load_addr(addr)?;
-        let off = section.file_offset_of_addr(addr);
-        Some(&self.0[off as usize..])
+        let off = section.file_offset_of_addr(addr) as usize;
+        let end = section.file_offset as usize + section.size as usize;
+        self.0.get(off..end)
+    }
+
+    /// Reads `N` bytes at the given load address, as long as they are all in the same section.
+    fn bytes_at<const N: usize>(&self, addr: u32) -> Option<[u8; N]> {
+        self.slice_from_load_addr(addr)?.get(..N)?.try_into().ok()
+    }
+
+    /// Reads a big-endian word at the given load address.
+    /// Returns `None` if the address is not in any section or the word would cross the end of the section.
+    pub fn read_u32(&self, addr: u32) -> Option<u32> {
+        self.bytes_at(addr).map(u32::from_be_bytes)
+    }
+
+    /// Reads a big-endian halfword at the given load address. See [`Dol::read_u32`].
+    pub fn read_u16(&self, addr: u32) -> Option<u16> {
+        self.bytes_at(addr).map(u16::from_be_bytes)
+    }
+
+    /// Reads a byte at the given load address. See [`Dol::read_u32`].
+    pub fn read_u8(&self, addr: u32) -> Option<u8> {
+        self.bytes_at(addr).map(u8::from_be_bytes)
     }
 }
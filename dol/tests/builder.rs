@@ -0,0 +1,77 @@
+use dol::{Dol, DolBuilder, DolError, SectionKind};
+
+#[test]
+fn round_trip() {
+    let dol = DolBuilder::new()
+        .text_section(
+            0x80003100,
+            vec![0x60, 0x00, 0x00, 0x00, 0x4e, 0x80, 0x00, 0x20],
+        )
+        .text_section(0x80005000, vec![0x38, 0x60, 0x00, 0x01])
+        .data_section(0x80100000, vec![1, 2, 3])
+        .bss(0x80200000, 0x1000)
+        .entrypoint(0x80003100)
+        .build()
+        .unwrap();
+
+    let reparsed = Dol::new(dol.as_bytes().to_vec()).unwrap();
+    assert_eq!(reparsed.as_bytes(), dol.as_bytes());
+    assert_eq!(reparsed.entrypoint(), 0x80003100);
+    assert_eq!(reparsed.bss_range(), 0x80200000..0x80201000);
+
+    let sections: Vec<(usize, u32, u32)> = reparsed
+        .sections()
+        .enumerate()
+        .filter(|(_, section)| !section.empty())
+        .map(|(index, section)| (index, section.load_offset, section.size))
+        .collect();
+    assert_eq!(
+        sections,
+        [(0, 0x80003100, 8), (1, 0x80005000, 4), (7, 0x80100000, 3)]
+    );
+    for (_, section) in reparsed.text_sections().chain(reparsed.data_sections()) {
+        assert_eq!(section.file_offset % 0x20, 0);
+    }
+
+    assert_eq!(reparsed.read_u32(0x80003104), Some(0x4e800020));
+    assert_eq!(reparsed.read_u32(0x80005000), Some(0x38600001));
+    assert_eq!(
+        reparsed.slice_from_load_addr(0x80100000),
+        Some(&[1, 2, 3][..])
+    );
+}
+
+#[test]
+fn too_many_sections() {
+    let builder = (0..8).fold(DolBuilder::new(), |builder, i| {
+        builder.text_section(0x80003100 + i * 0x100, vec![0; 4])
+    });
+    assert_eq!(
+        builder.build().unwrap_err(),
+        DolError::TooManySections(SectionKind::Text)
+    );
+
+    let builder = (0..12).fold(DolBuilder::new(), |builder, i| {
+        builder.data_section(0x80003100 + i * 0x100, vec![0; 4])
+    });
+    assert_eq!(
+        builder.build().unwrap_err(),
+        DolError::TooManySections(SectionKind::Data)
+    );
+}
+
+#[test]
+fn overlapping_sections_are_rejected() {
+    let err = DolBuilder::new()
+        .text_section(0x80003100, vec![0; 8])
+        .data_section(0x80003104, vec![0; 4])
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        err,
+        DolError::OverlappingSections {
+            first: 0,
+            second: 7
+        }
+    );
+}
@@ -0,0 +1,84 @@
+use dol::{Dol, DolBuilder};
+
+const TEXT_ADDRESS: u32 = 0x80003100;
+const DATA_ADDRESS: u32 = 0x80003108;
+const BSS_ADDRESS: u32 = 0x80003200;
+const SDATA_ADDRESS: u32 = 0x80003280;
+
+/// A .dol with a text section directly followed by a data section, and a BSS range with a small data section in the
+/// middle of it.
+fn dol() -> Dol {
+    DolBuilder::new()
+        .text_section(
+            TEXT_ADDRESS,
+            vec![0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde, 0xf0],
+        )
+        .data_section(DATA_ADDRESS, vec![0xaa, 0xbb, 0xcc, 0xdd])
+        .data_section(SDATA_ADDRESS, vec![0x11, 0x22, 0x33, 0x44])
+        .bss(BSS_ADDRESS, 0x100)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn reads_within_a_section() {
+    let dol = dol();
+    let image = dol.memory_image();
+    assert_eq!(
+        image.read(TEXT_ADDRESS, 8),
+        Some(&[0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde, 0xf0][..])
+    );
+    assert_eq!(image.read(TEXT_ADDRESS + 6, 2), Some(&[0xde, 0xf0][..]));
+    assert_eq!(
+        image.read(DATA_ADDRESS, 4),
+        Some(&[0xaa, 0xbb, 0xcc, 0xdd][..])
+    );
+    assert_eq!(image.read(SDATA_ADDRESS + 1, 2), Some(&[0x22, 0x33][..]));
+    assert_eq!(image.read(TEXT_ADDRESS + 8, 0), Some(&[][..]));
+}
+
+#[test]
+fn reads_bss_as_zeroes() {
+    let dol = dol();
+    let image = dol.memory_image();
+    assert_eq!(image.read(BSS_ADDRESS, 0x80), Some(&[0; 0x80][..]));
+    assert_eq!(image.read(SDATA_ADDRESS + 4, 0x7c), Some(&[0; 0x7c][..]));
+    assert!(image.contains(BSS_ADDRESS + 0xff));
+    assert!(!image.contains(BSS_ADDRESS + 0x100));
+}
+
+#[test]
+fn reads_across_section_boundaries_fail() {
+    let dol = dol();
+    let image = dol.memory_image();
+    // Both sections are mapped, but a read can't span them.
+    assert_eq!(image.read(TEXT_ADDRESS + 6, 4), None);
+    // Into and out of the data section in the BSS range.
+    assert_eq!(image.read(SDATA_ADDRESS - 2, 4), None);
+    assert_eq!(image.read(SDATA_ADDRESS + 2, 4), None);
+    // Past the end of the BSS range.
+    assert_eq!(image.read(BSS_ADDRESS + 0xfc, 8), None);
+    assert_eq!(image.read(u32::MAX, 2), None);
+}
+
+#[test]
+fn unmapped_reads_fail() {
+    let dol = dol();
+    let image = dol.memory_image();
+    assert_eq!(image.read(TEXT_ADDRESS - 1, 1), None);
+    assert_eq!(image.read(DATA_ADDRESS + 4, 1), None);
+    assert_eq!(image.read(0, 4), None);
+    assert!(!image.contains(DATA_ADDRESS + 4));
+}
+
+#[test]
+fn bss_range() {
+    let dol = dol();
+    assert_eq!(dol.bss_range(), BSS_ADDRESS..BSS_ADDRESS + 0x100);
+    assert!(dol.bss_contains(BSS_ADDRESS));
+    assert!(dol.bss_contains(BSS_ADDRESS + 0x80));
+    assert!(dol.bss_contains(BSS_ADDRESS + 0xff));
+    assert!(!dol.bss_contains(BSS_ADDRESS + 0x100));
+    assert!(!dol.bss_contains(BSS_ADDRESS - 1));
+    assert!(!dol.bss_contains(TEXT_ADDRESS));
+}
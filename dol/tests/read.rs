@@ -0,0 +1,84 @@
+use dol::{Dol, DolBuilder, UnmappedAddress};
+
+const LOAD_ADDRESS: u32 = 0x80003100;
+
+/// A .dol with a single 8 byte text section at [`LOAD_ADDRESS`].
+fn dol() -> Dol {
+    DolBuilder::new()
+        .text_section(
+            LOAD_ADDRESS,
+            vec![0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde, 0xf0],
+        )
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn reads_in_range() {
+    let dol = dol();
+    assert_eq!(dol.read_u32(LOAD_ADDRESS), Some(0x12345678));
+    assert_eq!(dol.read_u32(LOAD_ADDRESS + 4), Some(0x9abcdef0));
+    assert_eq!(dol.read_u32(LOAD_ADDRESS + 2), Some(0x56789abc));
+    assert_eq!(dol.read_u16(LOAD_ADDRESS + 6), Some(0xdef0));
+    assert_eq!(dol.read_u8(LOAD_ADDRESS + 7), Some(0xf0));
+}
+
+#[test]
+fn reads_out_of_range() {
+    let dol = dol();
+    assert_eq!(dol.read_u32(LOAD_ADDRESS - 4), None);
+    assert_eq!(dol.read_u8(LOAD_ADDRESS + 8), None);
+    assert_eq!(dol.read_u32(0), None);
+}
+
+#[test]
+fn reads_crossing_the_section_end() {
+    let dol = dol();
+    // The bytes after the section are in the file, but they don't belong to the section.
+    assert_eq!(dol.read_u32(LOAD_ADDRESS + 5), None);
+    assert_eq!(dol.read_u16(LOAD_ADDRESS + 7), None);
+}
+
+#[test]
+fn file_offset_of_valid_address() {
+    let dol = dol();
+    assert_eq!(dol.file_offset_of_addr(LOAD_ADDRESS), Some(0x100));
+    assert_eq!(dol.file_offset_of_addr(LOAD_ADDRESS + 7), Some(0x107));
+    assert_eq!(dol.try_file_offset_of_addr(LOAD_ADDRESS + 4), Ok(0x104));
+}
+
+#[test]
+fn file_offset_of_invalid_address() {
+    let dol = dol();
+    assert_eq!(dol.file_offset_of_addr(LOAD_ADDRESS + 8), None);
+    assert_eq!(dol.slice_from_load_addr(LOAD_ADDRESS - 1), None);
+
+    let err = dol.try_file_offset_of_addr(0x80000000).unwrap_err();
+    assert_eq!(err, UnmappedAddress(0x80000000));
+    assert_eq!(err.to_string(), "address 0x80000000 is not in any section");
+}
+
+#[test]
+fn slice_at_section_start() {
+    let dol = dol();
+    assert_eq!(
+        dol.slice_from_load_addr(LOAD_ADDRESS),
+        Some(&[0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde, 0xf0][..])
+    );
+}
+
+#[test]
+fn slice_in_section_middle() {
+    let dol = dol();
+    // Ends at the section end, not the end of the file.
+    assert_eq!(
+        dol.slice_from_load_addr(LOAD_ADDRESS + 5),
+        Some(&[0xbc, 0xde, 0xf0][..])
+    );
+}
+
+#[test]
+fn slice_past_section_end() {
+    let dol = dol();
+    assert_eq!(dol.slice_from_load_addr(LOAD_ADDRESS + 8), None);
+}
@@ -0,0 +1,118 @@
+use dol::{Dol, DolBuilder, DolError, SectionKind};
+
+/// A .dol with 4 byte sections at the given indices.
+fn dol_with_sections(indices: &[usize]) -> Dol {
+    let text_sections = SectionKind::Text.max_sections();
+    let last = indices.iter().max().map_or(0, |&last| last + 1);
+    (0..last)
+        .fold(DolBuilder::new(), |builder, index| {
+            let load_address = 0x80003100 + index as u32 * 0x100;
+            // The builder numbers sections in the order they are added, so fill the gaps with empty ones.
+            let bytes = if indices.contains(&index) {
+                vec![0; 4]
+            } else {
+                Vec::new()
+            };
+            if index < text_sections {
+                builder.text_section(load_address, bytes)
+            } else {
+                builder.data_section(load_address, bytes)
+            }
+        })
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn index_to_kind() {
+    for index in 0..=6 {
+        assert_eq!(SectionKind::of_index(index), SectionKind::Text);
+    }
+    for index in 7..=17 {
+        assert_eq!(SectionKind::of_index(index), SectionKind::Data);
+    }
+
+    let dol = dol_with_sections(&[]);
+    assert_eq!(dol.section(6).kind, SectionKind::Text);
+    assert_eq!(dol.section(7).kind, SectionKind::Data);
+}
+
+#[test]
+fn sections_by_kind_skip_empty_ones() {
+    let dol = dol_with_sections(&[0, 2, 7, 17]);
+
+    let text: Vec<usize> = dol.text_sections().map(|(index, _)| index).collect();
+    assert_eq!(text, [0, 2]);
+
+    let data: Vec<(usize, u32)> = dol
+        .data_sections()
+        .map(|(index, section)| (index, section.load_offset))
+        .collect();
+    assert_eq!(data, [(7, 0x80003800), (17, 0x80004200)]);
+}
+
+/// Sets the file offset, load address and size of a section in a header-only .dol.
+fn set_section(bytes: &mut [u8], index: usize, file_offset: u32, load_address: u32, size: u32) {
+    bytes[index * 4..][..4].copy_from_slice(&file_offset.to_be_bytes());
+    bytes[0x48 + index * 4..][..4].copy_from_slice(&load_address.to_be_bytes());
+    bytes[0x90 + index * 4..][..4].copy_from_slice(&size.to_be_bytes());
+}
+
+#[test]
+fn truncated_section_is_rejected() {
+    let mut bytes = vec![0; 0x108];
+    set_section(&mut bytes, 7, 0x100, 0x80003100, 0x10);
+    assert_eq!(
+        Dol::new(bytes.clone()).unwrap_err(),
+        DolError::SectionOutOfBounds { index: 7 }
+    );
+
+    // Exactly fits
+    set_section(&mut bytes, 7, 0x100, 0x80003100, 0x8);
+    assert!(Dol::new(bytes.clone()).is_ok());
+
+    // Doesn't overflow
+    set_section(&mut bytes, 7, u32::MAX, 0x80003100, 0x8);
+    assert_eq!(
+        Dol::new(bytes).unwrap_err(),
+        DolError::SectionOutOfBounds { index: 7 }
+    );
+}
+
+#[test]
+fn section_past_the_address_space_is_rejected() {
+    let mut bytes = vec![0; 0x208];
+    set_section(&mut bytes, 0, 0x100, 0xFFFFFF08, 0x100);
+    let err = Dol::new(bytes.clone()).unwrap_err();
+    assert_eq!(err, DolError::SectionOutOfAddressSpace { index: 0 });
+    assert_eq!(
+        err.to_string(),
+        "section #0 extends past the end of the address space"
+    );
+
+    // Ending exactly at the top of the address space is fine
+    set_section(&mut bytes, 0, 0x100, 0xFFFFFF00, 0x100);
+    let dol = Dol::new(bytes).unwrap();
+    assert!(dol.section(0).contains_addr(0xFFFFFFFC));
+    assert!(!dol.section(0).contains_addr(0xFFFFFEFC));
+}
+
+#[test]
+fn overlapping_sections_are_rejected() {
+    let mut bytes = vec![0; 0x110];
+    set_section(&mut bytes, 0, 0x100, 0x80003100, 0x8);
+    set_section(&mut bytes, 8, 0x108, 0x80003104, 0x8);
+    let err = Dol::new(bytes.clone()).unwrap_err();
+    assert_eq!(
+        err,
+        DolError::OverlappingSections {
+            first: 0,
+            second: 8
+        }
+    );
+    assert_eq!(err.to_string(), "sections #0 and #8 overlap in memory");
+
+    // Adjacent is fine
+    set_section(&mut bytes, 8, 0x108, 0x80003108, 0x8);
+    assert!(Dol::new(bytes).is_ok());
+}
@@ -6,6 +6,8 @@ use crate::{instruction::Instruction, word::Word};
 pub enum AddrRangeEnd {
     Unbounded,
     Bounded(u32),
+    /// A number of instructions.
+    Count(u32),
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -22,23 +24,80 @@ impl Display for Address {
 
 #[derive(Debug)]
 pub enum DecodeError {
-    UnhandledOpcode { word: Word, offset: usize },
-    UnexpectedEof { offset: usize },
+    UnhandledOpcode {
+        word: Word,
+        offset: usize,
+    },
+    UnexpectedEof {
+        offset: usize,
+    },
+    /// The opcode is known, but one of the operands holds an encoding that the architecture doesn't define.
+    InvalidOperand {
+        word: Word,
+        offset: usize,
+        operand: &'static str,
+    },
 }
 
+/// An operand field with an invalid encoding, named after the field in the manual (e.g. `BO`).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct InvalidOperand(pub &'static str);
+
 impl Display for DecodeError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            DecodeError::UnhandledOpcode { word, offset } => {
-                write!(f, "unhandled opcode {word:x?} at +{offset:x?}")
+            DecodeError::UnhandledOpcode { word, offset } => write!(
+                f,
+                "unhandled opcode {:#08b}/{:#012b} at offset {offset:#x} (word {:#010x})",
+                word.opcode(),
+                word.xform_opcode(),
+                word.0
+            ),
+            DecodeError::UnexpectedEof { offset } => {
+                write!(f, "unexpected eof at offset {offset:#x}")
             }
-            DecodeError::UnexpectedEof { offset } => write!(f, "unexpected eof at +{offset:x?}"),
+            DecodeError::InvalidOperand {
+                word,
+                offset,
+                operand,
+            } => write!(
+                f,
+                "invalid {operand} operand at offset {offset:#x} (word {:#010x})",
+                word.0
+            ),
         }
     }
 }
 
 impl Error for DecodeError {}
 
+#[derive(Debug)]
+pub enum SeekError {
+    Misaligned { offset: usize },
+    OutOfBounds { offset: usize, len: usize },
+}
+
+impl Display for SeekError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SeekError::Misaligned { offset } => {
+                write!(
+                    f,
+                    "cannot seek to +{offset:x?}: not aligned to an instruction"
+                )
+            }
+            SeekError::OutOfBounds { offset, len } => {
+                write!(
+                    f,
+                    "cannot seek to +{offset:x?}: input is only {len:#x} bytes long"
+                )
+            }
+        }
+    }
+}
+
+impl Error for SeekError {}
+
 pub struct Decoder<'a> {
     input: &'a [u8],
     offset: usize,
@@ -51,11 +110,33 @@ impl<'a> Decoder<'a> {
 
     /// Decodes the next word.
     fn word(&mut self) -> Option<Word> {
-        self.input.split_at_checked(4).map(|(bytes, rest)| {
-            self.input = rest;
-            self.offset += 4;
-            Word(u32::from_be_bytes(bytes.try_into().unwrap()))
-        })
+        let word = self.peek()?;
+        self.offset += 4;
+        Some(word)
+    }
+
+    /// Returns the next word without advancing the decoder.
+    pub fn peek(&self) -> Option<Word> {
+        self.input
+            .get(self.offset..)?
+            .first_chunk()
+            .map(|bytes| Word(u32::from_be_bytes(*bytes)))
+    }
+
+    /// Moves the decoder to `offset` bytes from the start of the input, so that the next instruction is decoded from there.
+    pub fn seek(&mut self, offset: usize) -> Result<(), SeekError> {
+        if !offset.is_multiple_of(4) {
+            return Err(SeekError::Misaligned { offset });
+        }
+        if offset > self.input.len() {
+            return Err(SeekError::OutOfBounds {
+                offset,
+                len: self.input.len(),
+            });
+        }
+
+        self.offset = offset;
+        Ok(())
     }
 
     pub fn offset(&self) -> usize {
@@ -76,16 +157,23 @@ impl<'a> Decoder<'a> {
         self.decode_from_word(word)
     }
 
-    /// Returns an iterator over instructions until the end of the input is reached.
-    /// `DecodeError::UnexpectedEof` is never returned by this iterator.
-    pub fn iter_until_eof(
-        &mut self,
-        fn_addr: u32,
-    ) -> impl Iterator<Item = Result<(Address, Instruction), DecodeError>> {
+    /// Like [`Decoder::decode_instruction`], but also returns the word that the instruction was decoded from.
+    pub fn decode_instruction_with_word(&mut self) -> Result<(Word, Instruction), DecodeError> {
+        let word = self.peek();
+        let instr = self.decode_instruction()?;
+        // `decode_instruction` would have returned an error if there was no word
+        Ok((word.unwrap(), instr))
+    }
+
+    /// Consumes the decoder and returns an iterator over the remaining instructions, along with their offset in the input.
+    /// This stops at the end of the input instead of returning `DecodeError::UnexpectedEof`.
+    pub fn instructions(
+        mut self,
+    ) -> impl Iterator<Item = Result<(u32, Instruction), DecodeError>> + 'a {
         iter::from_fn(move || {
             let offset = self.offset_u32();
             match self.decode_instruction() {
-                Ok(instr) => Some(Ok((Address(fn_addr + offset), instr))),
+                Ok(instr) => Some(Ok((offset, instr))),
                 Err(DecodeError::UnexpectedEof { .. }) => None,
                 Err(err) => Some(Err(err)),
             }
@@ -0,0 +1,614 @@
+use crate::instruction::{AddressingMode, Instruction};
+use crate::word::Word;
+
+impl Instruction {
+    /// Encodes this instruction back into a [`Word`].
+    ///
+    /// This reverses the field extraction done when decoding, so decoding the returned word yields this instruction
+    /// again. Bits that the decoder ignores (reserved fields) are encoded as zero.
+    ///
+    /// Panics if an operand does not fit in its field, e.g. a `Gpr(32)`.
+    pub fn encode(&self) -> Word {
+        let word = self.opcode_word();
+
+        match *self {
+            Instruction::Branch { target, mode, link } => word
+                .with_i32::<6, 29>(target >> 2)
+                .with_bit::<30>(matches!(mode, AddressingMode::Absolute))
+                .with_bit::<31>(link),
+            Instruction::Bc {
+                bo,
+                hint,
+                bi,
+                target,
+                mode,
+                link,
+            } => word
+                .with_u32::<6, 10>(bo.bo().into())
+                .with_bit::<10>(hint)
+                .with_u32::<11, 15>(bi.into())
+                .with_i32::<16, 29>(target >> 2)
+                .with_bit::<30>(matches!(mode, AddressingMode::Absolute))
+                .with_bit::<31>(link),
+            Instruction::Bclr { bo, hint, bi, link }
+            | Instruction::Bcctr { bo, hint, bi, link } => word
+                .with_u32::<6, 10>(bo.bo().into())
+                .with_bit::<10>(hint)
+                .with_u32::<11, 15>(bi.into())
+                .with_bit::<31>(link),
+
+            Instruction::Rlwnm {
+                source,
+                dest,
+                rot_bits,
+                mask_start,
+                mask_end,
+                rc,
+            } => word
+                .with_u32::<6, 10>(source.0.into())
+                .with_u32::<11, 15>(dest.0.into())
+                .with_u32::<16, 20>(rot_bits.0.into())
+                .with_u32::<21, 25>(mask_start.0.into())
+                .with_u32::<26, 30>(mask_end.0.into())
+                .with_bit::<31>(rc),
+            Instruction::Rlwinm {
+                source,
+                dest,
+                rot_bits,
+                mask_start,
+                mask_end,
+                rc,
+            }
+            | Instruction::Rlwimi {
+                source,
+                dest,
+                rot_bits,
+                mask_start,
+                mask_end,
+                rc,
+            } => word
+                .with_u32::<6, 10>(source.0.into())
+                .with_u32::<11, 15>(dest.0.into())
+                .with_u32::<16, 20>(rot_bits.0.into())
+                .with_u32::<21, 25>(mask_start.0.into())
+                .with_u32::<26, 30>(mask_end.0.into())
+                .with_bit::<31>(rc),
+
+            Instruction::Slw {
+                source,
+                dest,
+                shift,
+                rc,
+            }
+            | Instruction::Srw {
+                source,
+                dest,
+                shift,
+                rc,
+            }
+            | Instruction::Sraw {
+                source,
+                dest,
+                shift,
+                rc,
+            }
+            | Instruction::Or {
+                source,
+                dest,
+                or_with: shift,
+                rc,
+            }
+            | Instruction::Xor {
+                source,
+                dest,
+                rhs: shift,
+                rc,
+            }
+            | Instruction::Nand {
+                source,
+                dest,
+                rhs: shift,
+                rc,
+            }
+            | Instruction::Nor {
+                source,
+                dest,
+                rhs: shift,
+                rc,
+            }
+            | Instruction::Eqv {
+                source,
+                dest,
+                rhs: shift,
+                rc,
+            }
+            | Instruction::Andc {
+                source,
+                dest,
+                rhs: shift,
+                rc,
+            }
+            | Instruction::Orc {
+                source,
+                dest,
+                rhs: shift,
+                rc,
+            } => word
+                .with_u32::<6, 10>(source.0.into())
+                .with_u32::<11, 15>(dest.0.into())
+                .with_u32::<16, 20>(shift.0.into())
+                .with_bit::<31>(rc),
+            Instruction::And {
+                source1,
+                source2,
+                dest,
+                rc,
+            } => word
+                .with_u32::<6, 10>(source1.0.into())
+                .with_u32::<11, 15>(dest.0.into())
+                .with_u32::<16, 20>(source2.0.into())
+                .with_bit::<31>(rc),
+            Instruction::Srawi {
+                source,
+                dest,
+                sh,
+                rc,
+            } => word
+                .with_u32::<6, 10>(source.0.into())
+                .with_u32::<11, 15>(dest.0.into())
+                .with_u32::<16, 20>(sh.0.into())
+                .with_bit::<31>(rc),
+            Instruction::Extsb { source, dest, rc }
+            | Instruction::Extsh { source, dest, rc }
+            | Instruction::Cntlzw { source, dest, rc } => word
+                .with_u32::<6, 10>(source.0.into())
+                .with_u32::<11, 15>(dest.0.into())
+                .with_bit::<31>(rc),
+
+            Instruction::Addis { dest, add, imm } => word
+                .with_u32::<6, 10>(dest.0.into())
+                .with_u32::<11, 15>(add.map_or(0, |gpr| gpr.0).into())
+                .with_u32::<16, 31>(imm.0 as u16 as u32),
+            Instruction::Addi { dest, source, imm }
+            | Instruction::Lwz { dest, source, imm }
+            | Instruction::Lwzu { dest, source, imm }
+            | Instruction::Lhz { dest, source, imm }
+            | Instruction::Lhzu { dest, source, imm }
+            | Instruction::Lha { dest, source, imm }
+            | Instruction::Lhau { dest, source, imm }
+            | Instruction::Lbz { dest, source, imm }
+            | Instruction::Lbzu { dest, source, imm }
+            | Instruction::Lmw { dest, source, imm } => word
+                .with_u32::<6, 10>(dest.0.into())
+                .with_u32::<11, 15>(source.0.into())
+                .with_u32::<16, 31>(imm.0 as u16 as u32),
+            Instruction::Subfic { dest, source, simm }
+            | Instruction::AddicRc { dest, source, simm } => word
+                .with_u32::<6, 10>(dest.0.into())
+                .with_u32::<11, 15>(source.0.into())
+                .with_u32::<16, 31>(simm as u16 as u32),
+            Instruction::Ori { source, dest, imm }
+            | Instruction::Oris { source, dest, imm }
+            | Instruction::Andi { source, dest, imm } => word
+                .with_u32::<6, 10>(source.0.into())
+                .with_u32::<11, 15>(dest.0.into())
+                .with_u32::<16, 31>(imm.0.into()),
+            Instruction::Stw { source, dest, imm }
+            | Instruction::Stwu { source, dest, imm }
+            | Instruction::Sth { source, dest, imm }
+            | Instruction::Sthu { source, dest, imm }
+            | Instruction::Stb { source, dest, imm }
+            | Instruction::Stbu { source, dest, imm }
+            | Instruction::Stmw { source, dest, imm } => word
+                .with_u32::<6, 10>(source.0.into())
+                .with_u32::<11, 15>(dest.0.into())
+                .with_u32::<16, 31>(imm.0 as u16 as u32),
+            Instruction::Lfs { dest, source, imm }
+            | Instruction::Lfsu { dest, source, imm }
+            | Instruction::Lfd { dest, source, imm }
+            | Instruction::Lfdu { dest, source, imm } => word
+                .with_u32::<6, 10>(dest.0.into())
+                .with_u32::<11, 15>(source.0.into())
+                .with_u32::<16, 31>(imm.0 as u16 as u32),
+            Instruction::Stfs { source, dest, imm }
+            | Instruction::Stfsu { source, dest, imm }
+            | Instruction::Stfd { source, dest, imm }
+            | Instruction::Stfdu { source, dest, imm } => word
+                .with_u32::<6, 10>(source.0.into())
+                .with_u32::<11, 15>(dest.0.into())
+                .with_u32::<16, 31>(imm.0 as u16 as u32),
+
+            Instruction::Cmpi { source, imm, crf } => word
+                .with_u32::<6, 8>(crf.0.into())
+                .with_u32::<11, 15>(source.0.into())
+                .with_u32::<16, 31>(imm.0.into()),
+            Instruction::Cmpli {
+                source,
+                imm,
+                crf,
+                l,
+            } => word
+                .with_u32::<6, 8>(crf.0.into())
+                .with_bit::<10>(l)
+                .with_u32::<11, 15>(source.0.into())
+                .with_u32::<16, 31>(imm.0.into()),
+            Instruction::Cmp {
+                source_a,
+                source_b,
+                crf,
+                l,
+            }
+            | Instruction::Cmpl {
+                source_a,
+                source_b,
+                crf,
+                l,
+            } => word
+                .with_u32::<6, 8>(crf.0.into())
+                .with_bit::<10>(l)
+                .with_u32::<11, 15>(source_a.0.into())
+                .with_u32::<16, 20>(source_b.0.into()),
+            Instruction::Tw {
+                to,
+                source_a,
+                source_b,
+            } => word
+                .with_u32::<6, 10>(to.0.into())
+                .with_u32::<11, 15>(source_a.0.into())
+                .with_u32::<16, 20>(source_b.0.into()),
+            Instruction::Twi { to, source, simm } => word
+                .with_u32::<6, 10>(to.0.into())
+                .with_u32::<11, 15>(source.0.into())
+                .with_u32::<16, 31>(simm as u16 as u32),
+
+            Instruction::Stwux {
+                source,
+                dest,
+                index,
+            }
+            | Instruction::Stwx {
+                source,
+                dest,
+                index,
+            }
+            | Instruction::Sthx {
+                source,
+                dest,
+                index,
+            }
+            | Instruction::Stbx {
+                source,
+                dest,
+                index,
+            } => word
+                .with_u32::<6, 10>(source.0.into())
+                .with_u32::<11, 15>(dest.0.into())
+                .with_u32::<16, 20>(index.0.into()),
+            // `stwcx.` only exists in its record form.
+            Instruction::StwcxRc {
+                source,
+                dest,
+                index,
+            } => word
+                .with_u32::<6, 10>(source.0.into())
+                .with_u32::<11, 15>(dest.0.into())
+                .with_u32::<16, 20>(index.0.into())
+                .with_bit::<31>(true),
+            Instruction::Lwzx {
+                dest,
+                source,
+                index,
+            }
+            | Instruction::Lhzx {
+                dest,
+                source,
+                index,
+            }
+            | Instruction::Lbzx {
+                dest,
+                source,
+                index,
+            }
+            | Instruction::Lwarx {
+                dest,
+                source,
+                index,
+            } => word
+                .with_u32::<6, 10>(dest.0.into())
+                .with_u32::<11, 15>(source.0.into())
+                .with_u32::<16, 20>(index.0.into()),
+
+            Instruction::Subf {
+                dest,
+                source_b,
+                source_a,
+                oe,
+                rc,
+            } => word
+                .with_u32::<6, 10>(dest.0.into())
+                .with_u32::<11, 15>(source_b.0.into())
+                .with_u32::<16, 20>(source_a.0.into())
+                .with_bit::<21>(oe)
+                .with_bit::<31>(rc),
+            Instruction::Subfe {
+                dest,
+                source_a,
+                source_b,
+                oe,
+                rc,
+            }
+            | Instruction::Subfc {
+                dest,
+                source_a,
+                source_b,
+                oe,
+                rc,
+            }
+            | Instruction::Add {
+                dest,
+                source_a,
+                source_b,
+                oe,
+                rc,
+            }
+            | Instruction::Addc {
+                dest,
+                source_a,
+                source_b,
+                oe,
+                rc,
+            }
+            | Instruction::Adde {
+                dest,
+                source_a,
+                source_b,
+                oe,
+                rc,
+            } => word
+                .with_u32::<6, 10>(dest.0.into())
+                .with_u32::<11, 15>(source_a.0.into())
+                .with_u32::<16, 20>(source_b.0.into())
+                .with_bit::<21>(oe)
+                .with_bit::<31>(rc),
+            Instruction::Neg {
+                dest,
+                source,
+                rc,
+                oe,
+            }
+            | Instruction::Addme {
+                dest,
+                source,
+                oe,
+                rc,
+            }
+            | Instruction::Addze {
+                dest,
+                source,
+                oe,
+                rc,
+            } => word
+                .with_u32::<6, 10>(dest.0.into())
+                .with_u32::<11, 15>(source.0.into())
+                .with_bit::<21>(oe)
+                .with_bit::<31>(rc),
+
+            Instruction::Mfspr { dest: gpr, spr } | Instruction::Mtspr { source: gpr, spr } => {
+                word.with_u32::<6, 10>(gpr.0.into()).with_spr(spr.number())
+            }
+            Instruction::Mftb { dest, tbr } => {
+                word.with_u32::<6, 10>(dest.0.into()).with_spr(tbr.number())
+            }
+            Instruction::Mfmsr { dest: gpr }
+            | Instruction::Mtmsr { source: gpr }
+            | Instruction::Mfcr { dest: gpr } => word.with_u32::<6, 10>(gpr.0.into()),
+            Instruction::Mtcrf { source, crm } => word
+                .with_u32::<6, 10>(source.0.into())
+                .with_u32::<12, 19>(crm.into()),
+            Instruction::Mcrf {
+                crf_dest,
+                crf_source,
+            } => word
+                .with_u32::<6, 8>(crf_dest.0.into())
+                .with_u32::<11, 13>(crf_source.0.into()),
+            Instruction::Crand {
+                crb_dest,
+                crb_a,
+                crb_b,
+            }
+            | Instruction::Cror {
+                crb_dest,
+                crb_a,
+                crb_b,
+            }
+            | Instruction::Crnand {
+                crb_dest,
+                crb_a,
+                crb_b,
+            }
+            | Instruction::Crnor {
+                crb_dest,
+                crb_a,
+                crb_b,
+            }
+            | Instruction::Creqv {
+                crb_dest,
+                crb_a,
+                crb_b,
+            }
+            | Instruction::Crandc {
+                crb_dest,
+                crb_a,
+                crb_b,
+            }
+            | Instruction::Crorc {
+                crb_dest,
+                crb_a,
+                crb_b,
+            }
+            | Instruction::Crxor {
+                crb_dest,
+                crb_a,
+                crb_b,
+            } => word
+                .with_u32::<6, 10>(crb_dest.into())
+                .with_u32::<11, 15>(crb_a.into())
+                .with_u32::<16, 20>(crb_b.into()),
+
+            Instruction::Sync { kind } => word.with_u32::<9, 10>(kind.l().into()),
+            Instruction::Isync {} | Instruction::Eieio {} => word,
+            // `sc` has a reserved bit 30 that must be set.
+            Instruction::Sc {} => word.with_bit::<30>(true),
+            Instruction::Dcbz { base, index }
+            | Instruction::Dcbf { base, index }
+            | Instruction::Dcbst { base, index }
+            | Instruction::Dcbt { base, index }
+            | Instruction::Icbi { base, index } => word
+                .with_u32::<11, 15>(base.0.into())
+                .with_u32::<16, 20>(index.0.into()),
+
+            Instruction::Mtfsb1 { crf, rc } => {
+                word.with_u32::<6, 10>(crf.0.into()).with_bit::<31>(rc)
+            }
+            Instruction::Fadd {
+                dest,
+                source_a,
+                source_b,
+                rc,
+            }
+            | Instruction::Fadds {
+                dest,
+                source_a,
+                source_b,
+                rc,
+            }
+            | Instruction::Fsub {
+                dest,
+                source_a,
+                source_b,
+                rc,
+            }
+            | Instruction::Fsubs {
+                dest,
+                source_a,
+                source_b,
+                rc,
+            }
+            | Instruction::Fdiv {
+                dest,
+                source_a,
+                source_b,
+                rc,
+            }
+            | Instruction::Fdivs {
+                dest,
+                source_a,
+                source_b,
+                rc,
+            } => word
+                .with_u32::<6, 10>(dest.0.into())
+                .with_u32::<11, 15>(source_a.0.into())
+                .with_u32::<16, 20>(source_b.0.into())
+                .with_bit::<31>(rc),
+            Instruction::Fmul {
+                dest,
+                source_a,
+                source_c,
+                rc,
+            }
+            | Instruction::Fmuls {
+                dest,
+                source_a,
+                source_c,
+                rc,
+            } => word
+                .with_u32::<6, 10>(dest.0.into())
+                .with_u32::<11, 15>(source_a.0.into())
+                .with_u32::<21, 25>(source_c.0.into())
+                .with_bit::<31>(rc),
+            Instruction::Fmadd {
+                dest,
+                source_a,
+                source_b,
+                source_c,
+                rc,
+            }
+            | Instruction::Fmadds {
+                dest,
+                source_a,
+                source_b,
+                source_c,
+                rc,
+            }
+            | Instruction::Fmsub {
+                dest,
+                source_a,
+                source_b,
+                source_c,
+                rc,
+            }
+            | Instruction::Fmsubs {
+                dest,
+                source_a,
+                source_b,
+                source_c,
+                rc,
+            }
+            | Instruction::Fnmadd {
+                dest,
+                source_a,
+                source_b,
+                source_c,
+                rc,
+            }
+            | Instruction::Fnmadds {
+                dest,
+                source_a,
+                source_b,
+                source_c,
+                rc,
+            }
+            | Instruction::Fnmsub {
+                dest,
+                source_a,
+                source_b,
+                source_c,
+                rc,
+            }
+            | Instruction::Fnmsubs {
+                dest,
+                source_a,
+                source_b,
+                source_c,
+                rc,
+            } => word
+                .with_u32::<6, 10>(dest.0.into())
+                .with_u32::<11, 15>(source_a.0.into())
+                .with_u32::<16, 20>(source_b.0.into())
+                .with_u32::<21, 25>(source_c.0.into())
+                .with_bit::<31>(rc),
+            Instruction::Fcmpu {
+                source_a,
+                source_b,
+                crf,
+            }
+            | Instruction::Fcmpo {
+                source_a,
+                source_b,
+                crf,
+            } => word
+                .with_u32::<6, 8>(crf.0.into())
+                .with_u32::<11, 15>(source_a.0.into())
+                .with_u32::<16, 20>(source_b.0.into()),
+            Instruction::Fmr { dest, source, rc }
+            | Instruction::Fneg { dest, source, rc }
+            | Instruction::Fabs { dest, source, rc }
+            | Instruction::Fnabs { dest, source, rc }
+            | Instruction::Frsp { dest, source, rc }
+            | Instruction::Fctiwz { dest, source, rc } => word
+                .with_u32::<6, 10>(dest.0.into())
+                .with_u32::<16, 20>(source.0.into())
+                .with_bit::<31>(rc),
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! Configurable rendering of instructions, for when the derived `Debug` output isn't what the reader wants.
+
+use std::fmt::{self, Debug, Display};
+
+use crate::Instruction;
+use crate::instruction::{
+    AddressingMode, BranchOptions, Crf, Fpr, Gpr, Immediate, MacroSpr, SyncKind, TimeBaseRegister,
+    TrapCondition,
+};
+
+/// The base that 16-bit immediate operands are printed in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Radix {
+    Decimal,
+    Hex,
+}
+
+/// Options for [`Instruction::display`].
+///
+/// Only 16-bit immediates (displacements and the operands of `addi`, `ori`, `cmpwi` and friends) are affected. Shift
+/// amounts and mask bounds are small bit positions that are always printed in decimal.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AsmFormatOptions {
+    pub radix: Radix,
+    /// Whether signed immediates are sign-extended, i.e. `-0x8` rather than the raw `0xfff8`.
+    pub signed: bool,
+}
+
+impl Default for AsmFormatOptions {
+    /// The same rendering as the `Debug` impl of [`Instruction`].
+    fn default() -> Self {
+        Self {
+            radix: Radix::Decimal,
+            signed: true,
+        }
+    }
+}
+
+/// A 16-bit immediate, printed according to the options.
+struct Imm16 {
+    bits: u16,
+    /// Whether the field is signed, so that the options apply sign extension.
+    signed: bool,
+    options: AsmFormatOptions,
+}
+
+impl Debug for Imm16 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.bits as i16;
+        let sign_extend = self.signed && self.options.signed;
+        match self.options.radix {
+            Radix::Decimal if sign_extend => write!(f, "{value}"),
+            Radix::Decimal => write!(f, "{}", self.bits),
+            Radix::Hex if sign_extend && value < 0 => write!(f, "-{:#x}", value.unsigned_abs()),
+            Radix::Hex => write!(f, "{:#x}", self.bits),
+        }
+    }
+}
+
+/// An instruction operand that can be printed according to [`AsmFormatOptions`].
+pub(crate) trait FormatOperand {
+    fn fmt_operand(&self, f: &mut fmt::Formatter<'_>, options: AsmFormatOptions) -> fmt::Result;
+}
+
+macro_rules! format_operand_as_debug {
+    ($($ty:ty),*) => {
+        $(
+            impl FormatOperand for $ty {
+                fn fmt_operand(&self, f: &mut fmt::Formatter<'_>, _: AsmFormatOptions) -> fmt::Result {
+                    Debug::fmt(self, f)
+                }
+            }
+        )*
+    };
+}
+
+format_operand_as_debug!(
+    bool,
+    u8,
+    u32,
+    i32,
+    Gpr,
+    Option<Gpr>,
+    Fpr,
+    Crf,
+    MacroSpr,
+    BranchOptions,
+    TrapCondition,
+    AddressingMode,
+    SyncKind,
+    TimeBaseRegister,
+    Immediate<u8>
+);
+
+impl FormatOperand for i16 {
+    fn fmt_operand(&self, f: &mut fmt::Formatter<'_>, options: AsmFormatOptions) -> fmt::Result {
+        Imm16 {
+            bits: *self as u16,
+            signed: true,
+            options,
+        }
+        .fmt(f)
+    }
+}
+
+impl FormatOperand for Immediate<i16> {
+    fn fmt_operand(&self, f: &mut fmt::Formatter<'_>, options: AsmFormatOptions) -> fmt::Result {
+        let imm = Imm16 {
+            bits: self.0 as u16,
+            signed: true,
+            options,
+        };
+        f.debug_tuple("Immediate").field(&imm).finish()
+    }
+}
+
+impl FormatOperand for Immediate<u16> {
+    fn fmt_operand(&self, f: &mut fmt::Formatter<'_>, options: AsmFormatOptions) -> fmt::Result {
+        let imm = Imm16 {
+            bits: self.0,
+            signed: false,
+            options,
+        };
+        f.debug_tuple("Immediate").field(&imm).finish()
+    }
+}
+
+/// Adapts a [`FormatOperand`] to `Debug`, so that it can be passed to [`fmt::DebugStruct::field`].
+pub(crate) struct Operand<'a, T>(pub &'a T, pub AsmFormatOptions);
+
+impl<T: FormatOperand> Debug for Operand<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt_operand(f, self.1)
+    }
+}
+
+/// The return value of [`Instruction::display`].
+pub struct InstructionDisplay<'a> {
+    pub(crate) instruction: &'a Instruction,
+    pub(crate) options: AsmFormatOptions,
+}
+
+impl Display for InstructionDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.instruction.fmt_with(f, self.options)
+    }
+}
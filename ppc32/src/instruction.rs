@@ -1,6 +1,7 @@
 use std::fmt::Debug;
 
-use crate::decoder::{DecodeError, Decoder};
+use crate::decoder::{DecodeError, Decoder, InvalidOperand};
+use crate::format::{AsmFormatOptions, InstructionDisplay, Operand};
 use crate::word::Word;
 use paste::paste;
 
@@ -26,6 +27,26 @@ impl Gpr {
     pub fn is_callee_saved(&self) -> bool {
         matches!(self.0, 14..=31)
     }
+
+    /// Registers that are not preserved across function calls.
+    pub fn is_volatile(&self) -> bool {
+        matches!(self.0, 0 | 3..=12)
+    }
+}
+
+/// A floating point register, numbered through 0 to 31.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Fpr(pub u8);
+
+impl Debug for Fpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "fr{}", self.0)
+    }
+}
+
+impl Fpr {
+    /// Float and double return values are passed in f1.
+    pub const RETURN: Self = Self(1);
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -50,6 +71,18 @@ pub enum Spr<Xer> {
     Ctr,
     Msr,
     Pc,
+    /// Save/restore register 0, holds the address to return to from an exception.
+    Srr0,
+    /// Save/restore register 1, holds the MSR to restore when returning from an exception.
+    Srr1,
+    /// One of the four general purpose SPRs reserved for the operating system, SPRG0 through SPRG3.
+    Sprg(u8),
+    /// Decrementer.
+    Dec,
+    /// Data address register, set on a DSI exception.
+    Dar,
+    /// DSI exception cause register.
+    Dsisr,
     /// Only usable in supervisor mode.
     Other(u16),
 }
@@ -64,6 +97,12 @@ impl Debug for MicroSpr {
             Spr::Ctr => write!(f, "CTR"),
             Spr::Msr => write!(f, "MSR"),
             Spr::Pc => write!(f, "PC"),
+            Spr::Srr0 => write!(f, "SRR0"),
+            Spr::Srr1 => write!(f, "SRR1"),
+            Spr::Sprg(num) => write!(f, "SPRG{}", num),
+            Spr::Dec => write!(f, "DEC"),
+            Spr::Dar => write!(f, "DAR"),
+            Spr::Dsisr => write!(f, "DSISR"),
             Spr::Other(num) => write!(f, "SPR({})", num),
         }
     }
@@ -77,6 +116,12 @@ impl Debug for MacroSpr {
             Spr::Ctr => write!(f, "CTR"),
             Spr::Msr => write!(f, "MSR"),
             Spr::Pc => write!(f, "PC"),
+            Spr::Srr0 => write!(f, "SRR0"),
+            Spr::Srr1 => write!(f, "SRR1"),
+            Spr::Sprg(num) => write!(f, "SPRG{}", num),
+            Spr::Dec => write!(f, "DEC"),
+            Spr::Dar => write!(f, "DAR"),
+            Spr::Dsisr => write!(f, "DSISR"),
             Spr::Other(num) => write!(f, "SPR({})", num),
         }
     }
@@ -84,18 +129,44 @@ impl Debug for MacroSpr {
 
 impl MacroSpr {
     pub fn from_word(word: Word) -> Self {
-        match word.u16::<11, 15>() | (word.u16::<16, 20>() << 5) {
+        match word.spr() {
             1 => Spr::Xer(()),
             8 => Spr::Lr,
             9 => Spr::Ctr,
+            18 => Spr::Dsisr,
+            19 => Spr::Dar,
+            22 => Spr::Dec,
+            26 => Spr::Srr0,
+            27 => Spr::Srr1,
+            spr @ 272..=275 => Spr::Sprg((spr - 272) as u8),
             other => Spr::Other(other),
         }
     }
+
+    /// The SPR number of this register, as encoded in an `mfspr`/`mtspr` instruction.
+    ///
+    /// Panics for MSR and PC, which are not accessed through an SPR number.
+    pub fn number(self) -> u16 {
+        match self {
+            Spr::Xer(()) => 1,
+            Spr::Lr => 8,
+            Spr::Ctr => 9,
+            Spr::Dsisr => 18,
+            Spr::Dar => 19,
+            Spr::Dec => 22,
+            Spr::Srr0 => 26,
+            Spr::Srr1 => 27,
+            Spr::Sprg(num) => 272 + u16::from(num),
+            Spr::Other(num) => num,
+            Spr::Msr | Spr::Pc => panic!("{self:?} has no SPR number"),
+        }
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Register {
     Gpr(Gpr),
+    Fpr(Fpr),
     Cr(Crf, Crb),
     Spr(MicroSpr),
 }
@@ -103,6 +174,7 @@ impl Debug for Register {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Gpr(arg0) => arg0.fmt(f),
+            Self::Fpr(arg0) => arg0.fmt(f),
             Self::Cr(arg0, arg1) => write!(f, "CR{}.{:?}", arg0.0, arg1),
             Self::Spr(arg0) => arg0.fmt(f),
         }
@@ -152,6 +224,10 @@ mk_ordinal_enum! {
     }
 }
 
+impl Crb {
+    pub const ALL: [Self; 4] = [Self::Negative, Self::Positive, Self::Zero, Self::Overflow];
+}
+
 /// Maps a condition register bit index to the corresponding CRF and CRB.
 pub fn crb_from_index(index: u8) -> (Crf, Crb) {
     let crf = index / 4;
@@ -162,6 +238,65 @@ pub fn crb_from_index(index: u8) -> (Crf, Crb) {
 #[derive(Debug, Copy, Clone)]
 pub struct Immediate<T>(pub T);
 
+/// The L field of a `sync` instruction.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SyncKind {
+    /// `sync`, also known as `hwsync`.
+    Heavyweight,
+    /// `lwsync`
+    Lightweight,
+}
+
+impl SyncKind {
+    pub fn from_word(word: Word) -> Self {
+        if word.u8::<9, 10>() == 1 {
+            SyncKind::Lightweight
+        } else {
+            SyncKind::Heavyweight
+        }
+    }
+
+    /// The L field of a `sync` instruction of this kind.
+    pub fn l(self) -> u8 {
+        match self {
+            SyncKind::Heavyweight => 0,
+            SyncKind::Lightweight => 1,
+        }
+    }
+}
+
+/// The TO field of a trap instruction: a mask of the comparison outcomes that cause a trap.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct TrapCondition(pub u8);
+
+impl TrapCondition {
+    /// Less than, greater than, equal, and their unsigned counterparts all trap, i.e. `trap`.
+    pub const ALWAYS: Self = Self(0b11111);
+
+    pub fn is_unconditional(self) -> bool {
+        self == Self::ALWAYS
+    }
+}
+
+impl Debug for TrapCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_unconditional() {
+            write!(f, "Always")
+        } else {
+            write!(f, "TO({:#07b})", self.0)
+        }
+    }
+}
+
+/// What an instruction that records into CR0 sets it from, see [`Instruction::cr0_result`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Cr0Result {
+    /// The result in this register, compared against zero.
+    Compare(Gpr),
+    /// Whether a `stwcx.` performed its store, in CR0[EQ].
+    StoreConditional,
+}
+
 #[derive(Debug, Copy, Clone)]
 pub enum AddressingMode {
     Absolute,
@@ -179,7 +314,7 @@ impl AddressingMode {
 }
 
 macro_rules! define_instructions {
-    ($($name:ident { $(op: $op:expr,)? $(xform_op: $xform_op:expr ,)? { $( $field:ident: $ty:ty = $decode:expr ),* } }),*) => {
+    ($($name:ident { $(op: $op:expr,)? $(xform_op: $xform_op:expr ,)? $(xoform_op: $xoform_op:expr ,)? $(aform_op: $aform_op:expr ,)? { $( $field:ident: $ty:ty = $decode:expr ),* } }),*) => {
         paste! {
             #[derive(Debug, Copy, Clone)]
             pub enum Instruction {
@@ -193,20 +328,87 @@ macro_rules! define_instructions {
 
             fn __assert_decode_fn<T: FnOnce(Word) -> R, R>(t: T) -> T { t }
 
+            /// What the decode closure of a field returns: either the field itself, or a `Result` for fields that can
+            /// have an invalid encoding.
+            trait DecodedField<T> {
+                fn into_field(self) -> Result<T, InvalidOperand>;
+            }
+
+            impl<T> DecodedField<T> for T {
+                fn into_field(self) -> Result<T, InvalidOperand> {
+                    Ok(self)
+                }
+            }
+
+            impl<T> DecodedField<T> for Result<T, InvalidOperand> {
+                fn into_field(self) -> Result<T, InvalidOperand> {
+                    self
+                }
+            }
+
             impl Instruction {
                 $(
-                    pub fn [<parse_ $name:lower>](#[allow(unused)] word: Word) -> Result<Self, DecodeError> {
+                    pub fn [<parse_ $name:lower>](#[allow(unused)] word: Word) -> Result<Self, InvalidOperand> {
                         $(
                             // This dummy function call helps type inference by constraining the argument to be `Word`, so we don't need to put
                             // `word: Word` annotations on each closure.
                             let decode = __assert_decode_fn($decode);
-                            let $field = decode(word);
+                            let $field: $ty = decode(word).into_field()?;
                         )*
                         Ok(Instruction::$name { $( $field ),* })
                     }
                 )*
             }
 
+            impl Instruction {
+                /// The name of the variant in lowercase, e.g. `crxor` or `stwcxrc`. This is close to the mnemonic, but
+                /// leaves out the suffixes that depend on the operands.
+                pub fn name(&self) -> &'static str {
+                    match self {
+                        $(
+                            Instruction::$name { .. } => stringify!([<$name:lower>]),
+                        )*
+                    }
+                }
+
+                /// The word with only this instruction's opcode fields set and every operand field zeroed.
+                pub(crate) fn opcode_word(&self) -> Word {
+                    match self {
+                        $(
+                            Instruction::$name { .. } => {
+                                #[allow(unused_mut)]
+                                let mut word = Word(0);
+                                $( word = word.with_u32::<0, 5>($op); )?
+                                $( word = word.with_u32::<21, 30>($xform_op); )?
+                                $( word = word.with_u32::<22, 30>($xoform_op); )?
+                                $( word = word.with_u32::<26, 30>($aform_op); )?
+                                word
+                            }
+                        )*
+                    }
+                }
+            }
+
+            impl Instruction {
+                /// Renders this instruction like its `Debug` impl, but with immediate operands printed according to
+                /// `options`.
+                pub fn display(&self, options: AsmFormatOptions) -> InstructionDisplay<'_> {
+                    InstructionDisplay { instruction: self, options }
+                }
+
+                pub(crate) fn fmt_with(&self, f: &mut std::fmt::Formatter<'_>, #[allow(unused)] options: AsmFormatOptions) -> std::fmt::Result {
+                    match self {
+                        $(
+                            Instruction::$name { $( $field ),* } => {
+                                f.debug_struct(stringify!($name))
+                                    $( .field(stringify!($field), &Operand($field, options)) )*
+                                    .finish()
+                            }
+                        )*
+                    }
+                }
+            }
+
             impl Decoder<'_> {
                 pub(crate) fn decode_from_word(&mut self, word: Word) -> Result<Instruction, DecodeError> {
                     macro_rules! opt_pattern {
@@ -214,9 +416,17 @@ macro_rules! define_instructions {
                         () => { _ };
                     }
 
-                    match (word.opcode(), word.xform_opcode()) {
+                    match (word.opcode(), word.xform_opcode(), word.xoform_opcode(), word.aform_opcode()) {
                         $(
-                            (opt_pattern!($($op)?), opt_pattern!($($xform_op)?)) => Instruction::[<parse_ $name:lower>](word),
+                            (opt_pattern!($($op)?), opt_pattern!($($xform_op)?), opt_pattern!($($xoform_op)?), opt_pattern!($($aform_op)?)) => {
+                                Instruction::[<parse_ $name:lower>](word).map_err(|InvalidOperand(operand)| {
+                                    DecodeError::InvalidOperand {
+                                        word,
+                                        offset: self.offset() - 4,
+                                        operand,
+                                    }
+                                })
+                            }
                         )*
                         _ => Err(DecodeError::UnhandledOpcode {
                             word,
@@ -230,6 +440,8 @@ macro_rules! define_instructions {
 }
 
 const EXTENDED_OPCODE: u32 = 0b011111;
+const DOUBLE_FP_OPCODE: u32 = 0b111111;
+const SINGLE_FP_OPCODE: u32 = 0b111011;
 define_instructions! {
     Branch {
         op: 0b010010,
@@ -261,6 +473,84 @@ define_instructions! {
             rc: bool = |word| word.bit::<31>() != 0
         }
     },
+    Rlwimi {
+        op: 0b010100,
+        {
+            source: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            dest: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            rot_bits: Immediate<u8> = |word| Immediate(word.u8::<16, 20>()),
+            mask_start: Immediate<u8> = |word| Immediate(word.u8::<21, 25>()),
+            mask_end: Immediate<u8> = |word| Immediate(word.u8::<26, 30>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Slw {
+        op: EXTENDED_OPCODE,
+        xform_op: 24,
+        {
+            source: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            dest: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            shift: Gpr = |word| Gpr(word.u8::<16, 20>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Srw {
+        op: EXTENDED_OPCODE,
+        xform_op: 536,
+        {
+            source: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            dest: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            shift: Gpr = |word| Gpr(word.u8::<16, 20>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Sraw {
+        op: EXTENDED_OPCODE,
+        xform_op: 792,
+        {
+            source: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            dest: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            shift: Gpr = |word| Gpr(word.u8::<16, 20>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Srawi {
+        op: EXTENDED_OPCODE,
+        xform_op: 824,
+        {
+            source: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            dest: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            sh: Immediate<u8> = |word| Immediate(word.u8::<16, 20>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Extsb {
+        op: EXTENDED_OPCODE,
+        xform_op: 954,
+        {
+            source: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            dest: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Extsh {
+        op: EXTENDED_OPCODE,
+        xform_op: 922,
+        {
+            source: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            dest: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Cntlzw {
+        op: EXTENDED_OPCODE,
+        xform_op: 26,
+        {
+            source: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            dest: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
     Addis {
         op: 0b001111,
         {
@@ -326,6 +616,7 @@ define_instructions! {
         op: 0b010000,
         {
             bo: BranchOptions = BranchOptions::from_word,
+            hint: bool = |word| word.bit::<10>() != 0,
             bi: u8 = |word| word.u8::<11, 15>(),
             target: i32 = |word| word.i32::<16, 29>() << 2,
             mode: AddressingMode = |word| AddressingMode::from_absolute_bit(word.bit::<30>()),
@@ -337,6 +628,17 @@ define_instructions! {
         xform_op: 0b010000,
         {
             bo: BranchOptions = BranchOptions::from_word,
+            hint: bool = |word| word.bit::<10>() != 0,
+            bi: u8 = |word| word.u8::<11, 15>(),
+            link: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Bcctr {
+        op: 0b010011,
+        xform_op: 528,
+        {
+            bo: BranchOptions = BranchOptions::from_word,
+            hint: bool = |word| word.bit::<10>() != 0,
             bi: u8 = |word| word.u8::<11, 15>(),
             link: bool = |word| word.bit::<31>() != 0
         }
@@ -358,9 +660,81 @@ define_instructions! {
             index: Gpr = |word| Gpr(word.u8::<16, 20>())
         }
     },
+    Stwx {
+        op: EXTENDED_OPCODE,
+        xform_op: 151,
+        {
+            source: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            dest: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            index: Gpr = |word| Gpr(word.u8::<16, 20>())
+        }
+    },
+    Sthx {
+        op: EXTENDED_OPCODE,
+        xform_op: 407,
+        {
+            source: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            dest: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            index: Gpr = |word| Gpr(word.u8::<16, 20>())
+        }
+    },
+    Stbx {
+        op: EXTENDED_OPCODE,
+        xform_op: 215,
+        {
+            source: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            dest: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            index: Gpr = |word| Gpr(word.u8::<16, 20>())
+        }
+    },
+    Lwzx {
+        op: EXTENDED_OPCODE,
+        xform_op: 23,
+        {
+            dest: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            source: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            index: Gpr = |word| Gpr(word.u8::<16, 20>())
+        }
+    },
+    Lhzx {
+        op: EXTENDED_OPCODE,
+        xform_op: 279,
+        {
+            dest: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            source: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            index: Gpr = |word| Gpr(word.u8::<16, 20>())
+        }
+    },
+    Lbzx {
+        op: EXTENDED_OPCODE,
+        xform_op: 87,
+        {
+            dest: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            source: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            index: Gpr = |word| Gpr(word.u8::<16, 20>())
+        }
+    },
+    Lwarx {
+        op: EXTENDED_OPCODE,
+        xform_op: 20,
+        {
+            dest: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            source: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            index: Gpr = |word| Gpr(word.u8::<16, 20>())
+        }
+    },
+    StwcxRc {
+        op: EXTENDED_OPCODE,
+        xform_op: 150,
+        {
+            source: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            dest: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            index: Gpr = |word| Gpr(word.u8::<16, 20>())
+        }
+    },
     Subf {
         op: EXTENDED_OPCODE,
-        xform_op: 0b101000,
+        xoform_op: 0b101000,
         {
             dest: Gpr = |word| Gpr(word.u8::<6, 10>()),
             source_b: Gpr = |word| Gpr(word.u8::<11, 15>()),
@@ -379,7 +753,7 @@ define_instructions! {
     },
     Subfe {
         op: EXTENDED_OPCODE,
-        xform_op: 0b0010001000,
+        xoform_op: 0b010001000,
         {
             dest: Gpr = |word| Gpr(word.u8::<6, 10>()),
             source_a: Gpr = |word| Gpr(word.u8::<11, 15>()),
@@ -434,7 +808,68 @@ define_instructions! {
         {
             source1: Gpr = |word| Gpr(word.u8::<6, 10>()),
             source2: Gpr = |word| Gpr(word.u8::<16, 20>()),
-            dest: Gpr = |word| Gpr(word.u8::<11, 15>())
+            dest: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Xor {
+        op: EXTENDED_OPCODE,
+        xform_op: 316,
+        {
+            source: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            dest: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            rhs: Gpr = |word| Gpr(word.u8::<16, 20>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Nand {
+        op: EXTENDED_OPCODE,
+        xform_op: 476,
+        {
+            source: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            dest: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            rhs: Gpr = |word| Gpr(word.u8::<16, 20>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Nor {
+        op: EXTENDED_OPCODE,
+        xform_op: 124,
+        {
+            source: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            dest: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            rhs: Gpr = |word| Gpr(word.u8::<16, 20>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Eqv {
+        op: EXTENDED_OPCODE,
+        xform_op: 284,
+        {
+            source: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            dest: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            rhs: Gpr = |word| Gpr(word.u8::<16, 20>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Andc {
+        op: EXTENDED_OPCODE,
+        xform_op: 60,
+        {
+            source: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            dest: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            rhs: Gpr = |word| Gpr(word.u8::<16, 20>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Orc {
+        op: EXTENDED_OPCODE,
+        xform_op: 412,
+        {
+            source: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            dest: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            rhs: Gpr = |word| Gpr(word.u8::<16, 20>()),
+            rc: bool = |word| word.bit::<31>() != 0
         }
     },
     Andi {
@@ -442,7 +877,7 @@ define_instructions! {
         {
             source: Gpr = |word| Gpr(word.u8::<6, 10>()),
             dest: Gpr = |word| Gpr(word.u8::<11, 15>()),
-            simm: i16 = |word| word.i16::<16, 31>()
+            imm: Immediate<u16> = |word| Immediate(word.u16::<16, 31>())
         }
     },
     Stw {
@@ -484,34 +919,344 @@ define_instructions! {
 
         }
     },
-    Hwsync {
+    Sync {
         op: EXTENDED_OPCODE,
         xform_op: 0b1001010110,
         {
-
+            kind: SyncKind = SyncKind::from_word
         }
     },
-    Oris {
-        op: 0b11001,
+    Eieio {
+        op: EXTENDED_OPCODE,
+        xform_op: 854,
         {
-            source: Gpr = |word| Gpr(word.u8::<6, 10>()),
-            dest: Gpr = |word| Gpr(word.u8::<11, 15>()),
-            imm: Immediate<u16> = |word| Immediate(word.u16::<16, 31>())
+
         }
     },
-    Mtfsb1 {
-        op: 0b111111,
-        xform_op: 0b100110,
+    Dcbz {
+        op: EXTENDED_OPCODE,
+        xform_op: 1014,
         {
-            crf: Crf = |word| Crf(word.u8::<6, 10>()),
-            rc: bool = |word| word.bit::<31>() != 0
+            base: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            index: Gpr = |word| Gpr(word.u8::<16, 20>())
         }
     },
-    Lmw {
-        op: 0b101110,
+    Dcbf {
+        op: EXTENDED_OPCODE,
+        xform_op: 86,
         {
-            source: Gpr = |word| Gpr(word.u8::<6, 10>()),
-            dest: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            base: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            index: Gpr = |word| Gpr(word.u8::<16, 20>())
+        }
+    },
+    Dcbst {
+        op: EXTENDED_OPCODE,
+        xform_op: 54,
+        {
+            base: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            index: Gpr = |word| Gpr(word.u8::<16, 20>())
+        }
+    },
+    Dcbt {
+        op: EXTENDED_OPCODE,
+        xform_op: 278,
+        {
+            base: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            index: Gpr = |word| Gpr(word.u8::<16, 20>())
+        }
+    },
+    Icbi {
+        op: EXTENDED_OPCODE,
+        xform_op: 982,
+        {
+            base: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            index: Gpr = |word| Gpr(word.u8::<16, 20>())
+        }
+    },
+    Oris {
+        op: 0b11001,
+        {
+            source: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            dest: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            imm: Immediate<u16> = |word| Immediate(word.u16::<16, 31>())
+        }
+    },
+    Mtfsb1 {
+        op: DOUBLE_FP_OPCODE,
+        xform_op: 0b100110,
+        {
+            crf: Crf = |word| Crf(word.u8::<6, 10>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Fadd {
+        op: DOUBLE_FP_OPCODE,
+        aform_op: 21,
+        {
+            dest: Fpr = |word| Fpr(word.u8::<6, 10>()),
+            source_a: Fpr = |word| Fpr(word.u8::<11, 15>()),
+            source_b: Fpr = |word| Fpr(word.u8::<16, 20>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Fadds {
+        op: SINGLE_FP_OPCODE,
+        aform_op: 21,
+        {
+            dest: Fpr = |word| Fpr(word.u8::<6, 10>()),
+            source_a: Fpr = |word| Fpr(word.u8::<11, 15>()),
+            source_b: Fpr = |word| Fpr(word.u8::<16, 20>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Fsub {
+        op: DOUBLE_FP_OPCODE,
+        aform_op: 20,
+        {
+            dest: Fpr = |word| Fpr(word.u8::<6, 10>()),
+            source_a: Fpr = |word| Fpr(word.u8::<11, 15>()),
+            source_b: Fpr = |word| Fpr(word.u8::<16, 20>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Fsubs {
+        op: SINGLE_FP_OPCODE,
+        aform_op: 20,
+        {
+            dest: Fpr = |word| Fpr(word.u8::<6, 10>()),
+            source_a: Fpr = |word| Fpr(word.u8::<11, 15>()),
+            source_b: Fpr = |word| Fpr(word.u8::<16, 20>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Fmul {
+        op: DOUBLE_FP_OPCODE,
+        aform_op: 25,
+        {
+            dest: Fpr = |word| Fpr(word.u8::<6, 10>()),
+            source_a: Fpr = |word| Fpr(word.u8::<11, 15>()),
+            source_c: Fpr = |word| Fpr(word.u8::<21, 25>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Fmuls {
+        op: SINGLE_FP_OPCODE,
+        aform_op: 25,
+        {
+            dest: Fpr = |word| Fpr(word.u8::<6, 10>()),
+            source_a: Fpr = |word| Fpr(word.u8::<11, 15>()),
+            source_c: Fpr = |word| Fpr(word.u8::<21, 25>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Fdiv {
+        op: DOUBLE_FP_OPCODE,
+        aform_op: 18,
+        {
+            dest: Fpr = |word| Fpr(word.u8::<6, 10>()),
+            source_a: Fpr = |word| Fpr(word.u8::<11, 15>()),
+            source_b: Fpr = |word| Fpr(word.u8::<16, 20>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Fdivs {
+        op: SINGLE_FP_OPCODE,
+        aform_op: 18,
+        {
+            dest: Fpr = |word| Fpr(word.u8::<6, 10>()),
+            source_a: Fpr = |word| Fpr(word.u8::<11, 15>()),
+            source_b: Fpr = |word| Fpr(word.u8::<16, 20>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Fmadd {
+        op: DOUBLE_FP_OPCODE,
+        aform_op: 29,
+        {
+            dest: Fpr = |word| Fpr(word.u8::<6, 10>()),
+            source_a: Fpr = |word| Fpr(word.u8::<11, 15>()),
+            source_b: Fpr = |word| Fpr(word.u8::<16, 20>()),
+            source_c: Fpr = |word| Fpr(word.u8::<21, 25>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Fmadds {
+        op: SINGLE_FP_OPCODE,
+        aform_op: 29,
+        {
+            dest: Fpr = |word| Fpr(word.u8::<6, 10>()),
+            source_a: Fpr = |word| Fpr(word.u8::<11, 15>()),
+            source_b: Fpr = |word| Fpr(word.u8::<16, 20>()),
+            source_c: Fpr = |word| Fpr(word.u8::<21, 25>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Fmsub {
+        op: DOUBLE_FP_OPCODE,
+        aform_op: 28,
+        {
+            dest: Fpr = |word| Fpr(word.u8::<6, 10>()),
+            source_a: Fpr = |word| Fpr(word.u8::<11, 15>()),
+            source_b: Fpr = |word| Fpr(word.u8::<16, 20>()),
+            source_c: Fpr = |word| Fpr(word.u8::<21, 25>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Fmsubs {
+        op: SINGLE_FP_OPCODE,
+        aform_op: 28,
+        {
+            dest: Fpr = |word| Fpr(word.u8::<6, 10>()),
+            source_a: Fpr = |word| Fpr(word.u8::<11, 15>()),
+            source_b: Fpr = |word| Fpr(word.u8::<16, 20>()),
+            source_c: Fpr = |word| Fpr(word.u8::<21, 25>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Fnmadd {
+        op: DOUBLE_FP_OPCODE,
+        aform_op: 31,
+        {
+            dest: Fpr = |word| Fpr(word.u8::<6, 10>()),
+            source_a: Fpr = |word| Fpr(word.u8::<11, 15>()),
+            source_b: Fpr = |word| Fpr(word.u8::<16, 20>()),
+            source_c: Fpr = |word| Fpr(word.u8::<21, 25>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Fnmadds {
+        op: SINGLE_FP_OPCODE,
+        aform_op: 31,
+        {
+            dest: Fpr = |word| Fpr(word.u8::<6, 10>()),
+            source_a: Fpr = |word| Fpr(word.u8::<11, 15>()),
+            source_b: Fpr = |word| Fpr(word.u8::<16, 20>()),
+            source_c: Fpr = |word| Fpr(word.u8::<21, 25>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Fnmsub {
+        op: DOUBLE_FP_OPCODE,
+        aform_op: 30,
+        {
+            dest: Fpr = |word| Fpr(word.u8::<6, 10>()),
+            source_a: Fpr = |word| Fpr(word.u8::<11, 15>()),
+            source_b: Fpr = |word| Fpr(word.u8::<16, 20>()),
+            source_c: Fpr = |word| Fpr(word.u8::<21, 25>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Fnmsubs {
+        op: SINGLE_FP_OPCODE,
+        aform_op: 30,
+        {
+            dest: Fpr = |word| Fpr(word.u8::<6, 10>()),
+            source_a: Fpr = |word| Fpr(word.u8::<11, 15>()),
+            source_b: Fpr = |word| Fpr(word.u8::<16, 20>()),
+            source_c: Fpr = |word| Fpr(word.u8::<21, 25>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Fcmpu {
+        op: DOUBLE_FP_OPCODE,
+        xform_op: 0,
+        {
+            source_a: Fpr = |word| Fpr(word.u8::<11, 15>()),
+            source_b: Fpr = |word| Fpr(word.u8::<16, 20>()),
+            crf: Crf = |word| Crf(word.u8::<6, 8>())
+        }
+    },
+    Fcmpo {
+        op: DOUBLE_FP_OPCODE,
+        xform_op: 32,
+        {
+            source_a: Fpr = |word| Fpr(word.u8::<11, 15>()),
+            source_b: Fpr = |word| Fpr(word.u8::<16, 20>()),
+            crf: Crf = |word| Crf(word.u8::<6, 8>())
+        }
+    },
+    Fmr {
+        op: DOUBLE_FP_OPCODE,
+        xform_op: 72,
+        {
+            dest: Fpr = |word| Fpr(word.u8::<6, 10>()),
+            source: Fpr = |word| Fpr(word.u8::<16, 20>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Fneg {
+        op: DOUBLE_FP_OPCODE,
+        xform_op: 40,
+        {
+            dest: Fpr = |word| Fpr(word.u8::<6, 10>()),
+            source: Fpr = |word| Fpr(word.u8::<16, 20>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Fabs {
+        op: DOUBLE_FP_OPCODE,
+        xform_op: 264,
+        {
+            dest: Fpr = |word| Fpr(word.u8::<6, 10>()),
+            source: Fpr = |word| Fpr(word.u8::<16, 20>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Fnabs {
+        op: DOUBLE_FP_OPCODE,
+        xform_op: 136,
+        {
+            dest: Fpr = |word| Fpr(word.u8::<6, 10>()),
+            source: Fpr = |word| Fpr(word.u8::<16, 20>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Frsp {
+        op: DOUBLE_FP_OPCODE,
+        xform_op: 12,
+        {
+            dest: Fpr = |word| Fpr(word.u8::<6, 10>()),
+            source: Fpr = |word| Fpr(word.u8::<16, 20>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Fctiwz {
+        op: DOUBLE_FP_OPCODE,
+        xform_op: 15,
+        {
+            dest: Fpr = |word| Fpr(word.u8::<6, 10>()),
+            source: Fpr = |word| Fpr(word.u8::<16, 20>()),
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Sc {
+        op: 0b010001,
+        {
+
+        }
+    },
+    Tw {
+        op: EXTENDED_OPCODE,
+        xform_op: 4,
+        {
+            to: TrapCondition = |word| TrapCondition(word.u8::<6, 10>()),
+            source_a: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            source_b: Gpr = |word| Gpr(word.u8::<16, 20>())
+        }
+    },
+    Twi {
+        op: 0b000011,
+        {
+            to: TrapCondition = |word| TrapCondition(word.u8::<6, 10>()),
+            source: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            simm: i16 = |word| word.i16::<16, 31>()
+        }
+    },
+    Lmw {
+        op: 0b101110,
+        {
+            dest: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            source: Gpr = |word| Gpr(word.u8::<11, 15>()),
             imm: Immediate<i16> = |word| Immediate(word.i16::<16, 31>())
         }
     },
@@ -539,9 +1284,137 @@ define_instructions! {
             imm: Immediate<i16> = |word| Immediate(word.i16::<16, 31>())
         }
     },
+    Lhzu {
+        op: 0b101001,
+        {
+            dest: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            source: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            imm: Immediate<i16> = |word| Immediate(word.i16::<16, 31>())
+        }
+    },
+    Lha {
+        op: 0b101010,
+        {
+            dest: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            source: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            imm: Immediate<i16> = |word| Immediate(word.i16::<16, 31>())
+        }
+    },
+    Lhau {
+        op: 0b101011,
+        {
+            dest: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            source: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            imm: Immediate<i16> = |word| Immediate(word.i16::<16, 31>())
+        }
+    },
+    Lbzu {
+        op: 0b100011,
+        {
+            dest: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            source: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            imm: Immediate<i16> = |word| Immediate(word.i16::<16, 31>())
+        }
+    },
+    Sth {
+        op: 0b101100,
+        {
+            source: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            dest: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            imm: Immediate<i16> = |word| Immediate(word.i16::<16, 31>())
+        }
+    },
+    Sthu {
+        op: 0b101101,
+        {
+            source: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            dest: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            imm: Immediate<i16> = |word| Immediate(word.i16::<16, 31>())
+        }
+    },
+    Stb {
+        op: 0b100110,
+        {
+            source: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            dest: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            imm: Immediate<i16> = |word| Immediate(word.i16::<16, 31>())
+        }
+    },
+    Stbu {
+        op: 0b100111,
+        {
+            source: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            dest: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            imm: Immediate<i16> = |word| Immediate(word.i16::<16, 31>())
+        }
+    },
+    Lfs {
+        op: 0b110000,
+        {
+            dest: Fpr = |word| Fpr(word.u8::<6, 10>()),
+            source: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            imm: Immediate<i16> = |word| Immediate(word.i16::<16, 31>())
+        }
+    },
+    Lfsu {
+        op: 0b110001,
+        {
+            dest: Fpr = |word| Fpr(word.u8::<6, 10>()),
+            source: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            imm: Immediate<i16> = |word| Immediate(word.i16::<16, 31>())
+        }
+    },
+    Lfd {
+        op: 0b110010,
+        {
+            dest: Fpr = |word| Fpr(word.u8::<6, 10>()),
+            source: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            imm: Immediate<i16> = |word| Immediate(word.i16::<16, 31>())
+        }
+    },
+    Lfdu {
+        op: 0b110011,
+        {
+            dest: Fpr = |word| Fpr(word.u8::<6, 10>()),
+            source: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            imm: Immediate<i16> = |word| Immediate(word.i16::<16, 31>())
+        }
+    },
+    Stfs {
+        op: 0b110100,
+        {
+            source: Fpr = |word| Fpr(word.u8::<6, 10>()),
+            dest: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            imm: Immediate<i16> = |word| Immediate(word.i16::<16, 31>())
+        }
+    },
+    Stfsu {
+        op: 0b110101,
+        {
+            source: Fpr = |word| Fpr(word.u8::<6, 10>()),
+            dest: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            imm: Immediate<i16> = |word| Immediate(word.i16::<16, 31>())
+        }
+    },
+    Stfd {
+        op: 0b110110,
+        {
+            source: Fpr = |word| Fpr(word.u8::<6, 10>()),
+            dest: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            imm: Immediate<i16> = |word| Immediate(word.i16::<16, 31>())
+        }
+    },
+    Stfdu {
+        op: 0b110111,
+        {
+            source: Fpr = |word| Fpr(word.u8::<6, 10>()),
+            dest: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            imm: Immediate<i16> = |word| Immediate(word.i16::<16, 31>())
+        }
+    },
     Neg {
         op: EXTENDED_OPCODE,
-        xform_op: 0b1101000,
+        xoform_op: 0b1101000,
         {
             dest: Gpr = |word| Gpr(word.u8::<6, 10>()),
             source: Gpr = |word| Gpr(word.u8::<11, 15>()),
@@ -549,6 +1422,93 @@ define_instructions! {
             oe: bool = |word| word.bit::<21>() != 0
         }
     },
+    Mfcr {
+        op: EXTENDED_OPCODE,
+        xform_op: 19,
+        {
+            dest: Gpr = |word| Gpr(word.u8::<6, 10>())
+        }
+    },
+    Mtcrf {
+        op: EXTENDED_OPCODE,
+        xform_op: 144,
+        {
+            source: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            // Mask of the CR fields to update. The most significant bit corresponds to CR0.
+            crm: u8 = |word| word.u8::<12, 19>()
+        }
+    },
+    Mcrf {
+        op: 0b010011,
+        xform_op: 0,
+        {
+            crf_dest: Crf = |word| Crf(word.u8::<6, 8>()),
+            crf_source: Crf = |word| Crf(word.u8::<11, 13>())
+        }
+    },
+    Crand {
+        op: 0b010011,
+        xform_op: 257,
+        {
+            crb_dest: u8 = |word| word.u8::<6, 10>(),
+            crb_a: u8 = |word| word.u8::<11, 15>(),
+            crb_b: u8 = |word| word.u8::<16, 20>()
+        }
+    },
+    Cror {
+        op: 0b010011,
+        xform_op: 449,
+        {
+            crb_dest: u8 = |word| word.u8::<6, 10>(),
+            crb_a: u8 = |word| word.u8::<11, 15>(),
+            crb_b: u8 = |word| word.u8::<16, 20>()
+        }
+    },
+    Crnand {
+        op: 0b010011,
+        xform_op: 225,
+        {
+            crb_dest: u8 = |word| word.u8::<6, 10>(),
+            crb_a: u8 = |word| word.u8::<11, 15>(),
+            crb_b: u8 = |word| word.u8::<16, 20>()
+        }
+    },
+    Crnor {
+        op: 0b010011,
+        xform_op: 33,
+        {
+            crb_dest: u8 = |word| word.u8::<6, 10>(),
+            crb_a: u8 = |word| word.u8::<11, 15>(),
+            crb_b: u8 = |word| word.u8::<16, 20>()
+        }
+    },
+    Creqv {
+        op: 0b010011,
+        xform_op: 289,
+        {
+            crb_dest: u8 = |word| word.u8::<6, 10>(),
+            crb_a: u8 = |word| word.u8::<11, 15>(),
+            crb_b: u8 = |word| word.u8::<16, 20>()
+        }
+    },
+    Crandc {
+        op: 0b010011,
+        xform_op: 129,
+        {
+            crb_dest: u8 = |word| word.u8::<6, 10>(),
+            crb_a: u8 = |word| word.u8::<11, 15>(),
+            crb_b: u8 = |word| word.u8::<16, 20>()
+        }
+    },
+    Crorc {
+        op: 0b010011,
+        xform_op: 417,
+        {
+            crb_dest: u8 = |word| word.u8::<6, 10>(),
+            crb_a: u8 = |word| word.u8::<11, 15>(),
+            crb_b: u8 = |word| word.u8::<16, 20>()
+        }
+    },
     Crxor {
         op: 0b010011,
         xform_op: 0b11000001,
@@ -560,7 +1520,7 @@ define_instructions! {
     },
     Add {
         op: EXTENDED_OPCODE,
-        xform_op: 0b100001010,
+        xoform_op: 0b100001010,
         {
             dest: Gpr = |word| Gpr(word.u8::<6, 10>()),
             source_a: Gpr = |word| Gpr(word.u8::<11, 15>()),
@@ -569,6 +1529,59 @@ define_instructions! {
             rc: bool = |word| word.bit::<31>() != 0
         }
     },
+    Addc {
+        op: EXTENDED_OPCODE,
+        xoform_op: 10,
+        {
+            dest: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            source_a: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            source_b: Gpr = |word| Gpr(word.u8::<16, 20>()),
+            oe: bool = |word| word.bit::<21>() != 0,
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Adde {
+        op: EXTENDED_OPCODE,
+        xoform_op: 138,
+        {
+            dest: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            source_a: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            source_b: Gpr = |word| Gpr(word.u8::<16, 20>()),
+            oe: bool = |word| word.bit::<21>() != 0,
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Subfc {
+        op: EXTENDED_OPCODE,
+        xoform_op: 8,
+        {
+            dest: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            source_a: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            source_b: Gpr = |word| Gpr(word.u8::<16, 20>()),
+            oe: bool = |word| word.bit::<21>() != 0,
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Addme {
+        op: EXTENDED_OPCODE,
+        xoform_op: 234,
+        {
+            dest: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            source: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            oe: bool = |word| word.bit::<21>() != 0,
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
+    Addze {
+        op: EXTENDED_OPCODE,
+        xoform_op: 202,
+        {
+            dest: Gpr = |word| Gpr(word.u8::<6, 10>()),
+            source: Gpr = |word| Gpr(word.u8::<11, 15>()),
+            oe: bool = |word| word.bit::<21>() != 0,
+            rc: bool = |word| word.bit::<31>() != 0
+        }
+    },
     AddicRc {
         op: 0b001101,
         {
@@ -582,10 +1595,24 @@ define_instructions! {
 pub trait RegisterVisitor {
     fn read_gpr(&mut self, _gpr: Gpr) {}
     fn write_gpr(&mut self, _gpr: Gpr) {}
+    fn read_fpr(&mut self, _fpr: Fpr) {}
+    fn write_fpr(&mut self, _fpr: Fpr) {}
     fn read_spr(&mut self, _spr: MicroSpr) {}
     fn write_spr(&mut self, _spr: MicroSpr) {}
-    fn read_crf(&mut self, _crf: Crf) {}
-    fn write_crf(&mut self, _crf: Crf) {}
+    /// Called when an instruction reads an entire CR field.
+    /// By default this reports a read of each of the field's bits through [`RegisterVisitor::read_crb`].
+    fn read_crf(&mut self, crf: Crf) {
+        for crb in Crb::ALL {
+            self.read_crb(crf, crb);
+        }
+    }
+    /// Called when an instruction writes an entire CR field (e.g. compares, or any instruction with `rc` set).
+    /// By default this reports a write of each of the field's bits through [`RegisterVisitor::write_crb`].
+    fn write_crf(&mut self, crf: Crf) {
+        for crb in Crb::ALL {
+            self.write_crb(crf, crb);
+        }
+    }
     fn read_crb(&mut self, _crf: Crf, _crb: Crb) {}
     fn write_crb(&mut self, _crf: Crf, _crb: Crb) {}
     /// Called when the instruction's effect "happens".
@@ -599,18 +1626,77 @@ pub trait RegisterVisitor {
 }
 
 impl Instruction {
-    pub fn branch_target(&self, instr_addr: u32) -> Option<u32> {
+    /// Where this instruction branches to, if it is a branch. `instr_addr` is the address of the instruction itself.
+    pub fn branch_target(&self, instr_addr: u32) -> Option<BranchTarget> {
         match self {
-            Instruction::Branch { target, mode, .. } => {
-                Some(compute_branch_target(instr_addr, *mode, *target))
-            }
-            Instruction::Bc { target, mode, .. } => {
-                Some(compute_branch_target(instr_addr, *mode, *target))
+            Instruction::Branch { target, mode, .. } | Instruction::Bc { target, mode, .. } => {
+                Some(BranchTarget::Static(compute_branch_target(
+                    instr_addr, *mode, *target,
+                )))
             }
+            Instruction::Bclr { .. } => Some(BranchTarget::Register(Spr::Lr)),
+            Instruction::Bcctr { .. } => Some(BranchTarget::Register(Spr::Ctr)),
+            _ => None,
+        }
+    }
+
+    /// Returns true if this is a trap instruction that traps regardless of its operands, i.e. never falls through.
+    pub fn is_unconditional_trap(&self) -> bool {
+        match *self {
+            Instruction::Tw { to, .. } | Instruction::Twi { to, .. } => to.is_unconditional(),
+            _ => false,
+        }
+    }
+
+    /// If this instruction is the `not rA, rS` simplified mnemonic (`nor rA, rS, rS`), returns the `(dest, source)` registers.
+    pub fn as_not(&self) -> Option<(Gpr, Gpr)> {
+        match *self {
+            Instruction::Nor {
+                source, dest, rhs, ..
+            } if source == rhs => Some((dest, source)),
             _ => None,
         }
     }
 
+    /// If this is an integer instruction that records into CR0 (e.g. `subf.`, `andi.` or `stwcx.`), returns what CR0
+    /// is set from.
+    pub fn cr0_result(&self) -> Option<Cr0Result> {
+        let dest = match *self {
+            Instruction::Rlwnm { dest, rc, .. }
+            | Instruction::Rlwinm { dest, rc, .. }
+            | Instruction::Rlwimi { dest, rc, .. }
+            | Instruction::Slw { dest, rc, .. }
+            | Instruction::Srw { dest, rc, .. }
+            | Instruction::Sraw { dest, rc, .. }
+            | Instruction::Srawi { dest, rc, .. }
+            | Instruction::Extsb { dest, rc, .. }
+            | Instruction::Extsh { dest, rc, .. }
+            | Instruction::Cntlzw { dest, rc, .. }
+            | Instruction::Or { dest, rc, .. }
+            | Instruction::And { dest, rc, .. }
+            | Instruction::Xor { dest, rc, .. }
+            | Instruction::Nand { dest, rc, .. }
+            | Instruction::Nor { dest, rc, .. }
+            | Instruction::Eqv { dest, rc, .. }
+            | Instruction::Andc { dest, rc, .. }
+            | Instruction::Orc { dest, rc, .. }
+            | Instruction::Subf { dest, rc, .. }
+            | Instruction::Subfc { dest, rc, .. }
+            | Instruction::Subfe { dest, rc, .. }
+            | Instruction::Add { dest, rc, .. }
+            | Instruction::Addc { dest, rc, .. }
+            | Instruction::Adde { dest, rc, .. }
+            | Instruction::Addme { dest, rc, .. }
+            | Instruction::Addze { dest, rc, .. }
+            | Instruction::Neg { dest, rc, .. } => rc.then_some(dest),
+            // These only exist in their record form.
+            Instruction::Andi { dest, .. } | Instruction::AddicRc { dest, .. } => Some(dest),
+            Instruction::StwcxRc { .. } => return Some(Cr0Result::StoreConditional),
+            _ => None,
+        };
+        dest.map(Cr0Result::Compare)
+    }
+
     #[rustfmt::skip]
     pub fn visit_registers(&self, mut visitor: impl RegisterVisitor) {
         match *self {
@@ -637,6 +1723,54 @@ impl Instruction {
                     visitor.write_crf(Crf(0));
                 }
             },
+            Instruction::Rlwimi { source, dest, rot_bits: _, mask_start: _, mask_end: _, rc } => {
+                visitor.read_gpr(source);
+                // The rotated bits are inserted into the existing value, so the destination is also an input.
+                visitor.read_gpr(dest);
+                visitor.effect();
+                visitor.write_gpr(dest);
+                if rc {
+                    visitor.write_crf(Crf(0));
+                }
+            },
+            Instruction::Slw { source, dest, shift, rc } | Instruction::Srw { source, dest, shift, rc } => {
+                visitor.read_gpr(source);
+                visitor.read_gpr(shift);
+                visitor.effect();
+                visitor.write_gpr(dest);
+                if rc {
+                    visitor.write_crf(Crf(0));
+                }
+            },
+            Instruction::Sraw { source, dest, shift, rc } => {
+                visitor.read_gpr(source);
+                visitor.read_gpr(shift);
+                visitor.effect();
+                visitor.write_gpr(dest);
+                visitor.write_spr(Spr::Xer(XerRegister::Ca));
+                if rc {
+                    visitor.write_crf(Crf(0));
+                }
+            },
+            Instruction::Srawi { source, dest, sh: _, rc } => {
+                visitor.read_gpr(source);
+                visitor.effect();
+                visitor.write_gpr(dest);
+                visitor.write_spr(Spr::Xer(XerRegister::Ca));
+                if rc {
+                    visitor.write_crf(Crf(0));
+                }
+            },
+            Instruction::Extsb { source, dest, rc }
+            | Instruction::Extsh { source, dest, rc }
+            | Instruction::Cntlzw { source, dest, rc } => {
+                visitor.read_gpr(source);
+                visitor.effect();
+                visitor.write_gpr(dest);
+                if rc {
+                    visitor.write_crf(Crf(0));
+                }
+            },
             Instruction::Addis { dest, add, imm: _ } => {
                 if let Some(gpr) = add {
                     visitor.read_gpr(gpr);
@@ -645,7 +1779,10 @@ impl Instruction {
                 visitor.write_gpr(dest);
             },
             Instruction::Addi { dest, source, imm: _ } => {
-                visitor.read_gpr(source);
+                // `li` is `addi` with r0, which reads as 0 rather than the register
+                if source != Gpr::ZERO {
+                    visitor.read_gpr(source);
+                }
                 visitor.effect();
                 visitor.write_gpr(dest);
             },
@@ -683,19 +1820,54 @@ impl Instruction {
                 visitor.effect();
                 visitor.write_crf(crf);
             },
-            Instruction::Bc { bo, bi, target: _, mode: _, link: _ } => {
-                if !matches!(bo, BranchOptions::BranchAlways) {
+            Instruction::Bc { bo, hint: _, bi, target: _, mode: _, link } => {
+                if bo.tests_condition() {
+                    let (crf, crb) = crb_from_index(bi);
+                    visitor.read_crb(crf, crb);
+                }
+                if bo.decrements_ctr() {
+                    visitor.read_spr(Spr::Ctr);
+                }
+                visitor.effect();
+                if bo.decrements_ctr() {
+                    visitor.write_spr(Spr::Ctr);
+                }
+                // `bcl` is a conditional call
+                if link {
+                    visitor.write_gpr(Gpr::RETURN);
+                }
+            },
+            Instruction::Bclr { bo, hint: _, bi, link } => {
+                if bo.tests_condition() {
                     let (crf, crb) = crb_from_index(bi);
                     visitor.read_crb(crf, crb);
                 }
+                if bo.decrements_ctr() {
+                    visitor.read_spr(Spr::Ctr);
+                }
+                // `blrl` calls the function in LR
+                if link {
+                    visitor.read_spr(Spr::Lr);
+                }
                 visitor.effect();
+                if bo.decrements_ctr() {
+                    visitor.write_spr(Spr::Ctr);
+                }
+                if link {
+                    visitor.write_gpr(Gpr::RETURN);
+                }
             },
-            Instruction::Bclr { bo, bi, link: _ } => {
-                if !matches!(bo, BranchOptions::BranchAlways) {
+            Instruction::Bcctr { bo, hint: _, bi, link } => {
+                if bo.tests_condition() {
                     let (crf, crb) = crb_from_index(bi);
                     visitor.read_crb(crf, crb);
                 }
+                visitor.read_spr(Spr::Ctr);
                 visitor.effect();
+                // Like `bl`, `bctrl` is a call
+                if link {
+                    visitor.write_gpr(Gpr::RETURN);
+                }
             },
             Instruction::Stwu { source, dest, imm: _ } => {
                 visitor.read_gpr(source);
@@ -708,6 +1880,37 @@ impl Instruction {
                 visitor.read_gpr(dest);
                 visitor.read_gpr(index);
                 visitor.effect();
+                visitor.write_gpr(dest);
+            },
+            Instruction::Stwx { source, dest, index }
+            | Instruction::Sthx { source, dest, index }
+            | Instruction::Stbx { source, dest, index } => {
+                visitor.read_gpr(source);
+                visitor.read_gpr(dest);
+                visitor.read_gpr(index);
+                visitor.effect();
+            },
+            Instruction::Lwzx { dest, source, index }
+            | Instruction::Lhzx { dest, source, index }
+            | Instruction::Lbzx { dest, source, index } => {
+                visitor.read_gpr(source);
+                visitor.read_gpr(index);
+                visitor.effect();
+                visitor.write_gpr(dest);
+            },
+            Instruction::Lwarx { dest, source, index } => {
+                visitor.read_gpr(source);
+                visitor.read_gpr(index);
+                visitor.effect();
+                visitor.write_gpr(dest);
+            },
+            Instruction::StwcxRc { source, dest, index } => {
+                visitor.read_gpr(source);
+                visitor.read_gpr(dest);
+                visitor.read_gpr(index);
+                visitor.effect();
+                // Whether the store happened is recorded in CR0[EQ]
+                visitor.write_crf(Crf(0));
             },
             Instruction::Subf { dest, source_b, source_a, oe: _, rc } => {
                 visitor.read_gpr(source_a);
@@ -739,6 +1942,51 @@ impl Instruction {
                     visitor.write_spr(Spr::Xer(XerRegister::Ov));
                 }
             }
+            Instruction::Addc { dest, source_a, source_b, oe, rc }
+            | Instruction::Subfc { dest, source_a, source_b, oe, rc } => {
+                visitor.read_gpr(source_a);
+                visitor.read_gpr(source_b);
+                visitor.effect();
+                visitor.write_gpr(dest);
+                if rc {
+                    visitor.write_crf(Crf(0));
+                }
+                visitor.write_spr(Spr::Xer(XerRegister::Ca));
+                if oe {
+                    visitor.write_spr(Spr::Xer(XerRegister::So));
+                    visitor.write_spr(Spr::Xer(XerRegister::Ov));
+                }
+            }
+            Instruction::Adde { dest, source_a, source_b, oe, rc } => {
+                visitor.read_gpr(source_a);
+                visitor.read_gpr(source_b);
+                visitor.read_spr(Spr::Xer(XerRegister::Ca));
+                visitor.effect();
+                visitor.write_gpr(dest);
+                if rc {
+                    visitor.write_crf(Crf(0));
+                }
+                visitor.write_spr(Spr::Xer(XerRegister::Ca));
+                if oe {
+                    visitor.write_spr(Spr::Xer(XerRegister::So));
+                    visitor.write_spr(Spr::Xer(XerRegister::Ov));
+                }
+            }
+            Instruction::Addme { dest, source, oe, rc }
+            | Instruction::Addze { dest, source, oe, rc } => {
+                visitor.read_gpr(source);
+                visitor.read_spr(Spr::Xer(XerRegister::Ca));
+                visitor.effect();
+                visitor.write_gpr(dest);
+                if rc {
+                    visitor.write_crf(Crf(0));
+                }
+                visitor.write_spr(Spr::Xer(XerRegister::Ca));
+                if oe {
+                    visitor.write_spr(Spr::Xer(XerRegister::So));
+                    visitor.write_spr(Spr::Xer(XerRegister::Ov));
+                }
+            }
             Instruction::Mfspr { dest, spr } => {
                 match spr {
                     Spr::Xer(()) => {
@@ -750,6 +1998,12 @@ impl Instruction {
                     Spr::Ctr => visitor.read_spr(Spr::Ctr),
                     Spr::Msr => visitor.read_spr(Spr::Msr),
                     Spr::Pc => visitor.read_spr(Spr::Pc),
+                    Spr::Srr0 => visitor.read_spr(Spr::Srr0),
+                    Spr::Srr1 => visitor.read_spr(Spr::Srr1),
+                    Spr::Sprg(num) => visitor.read_spr(Spr::Sprg(num)),
+                    Spr::Dec => visitor.read_spr(Spr::Dec),
+                    Spr::Dar => visitor.read_spr(Spr::Dar),
+                    Spr::Dsisr => visitor.read_spr(Spr::Dsisr),
                     Spr::Other(other) => visitor.read_spr(Spr::Other(other)),
                 }
                 visitor.effect();
@@ -768,6 +2022,12 @@ impl Instruction {
                     Spr::Ctr => visitor.write_spr(Spr::Ctr),
                     Spr::Msr => visitor.write_spr(Spr::Msr),
                     Spr::Pc => visitor.write_spr(Spr::Pc),
+                    Spr::Srr0 => visitor.write_spr(Spr::Srr0),
+                    Spr::Srr1 => visitor.write_spr(Spr::Srr1),
+                    Spr::Sprg(num) => visitor.write_spr(Spr::Sprg(num)),
+                    Spr::Dec => visitor.write_spr(Spr::Dec),
+                    Spr::Dar => visitor.write_spr(Spr::Dar),
+                    Spr::Dsisr => visitor.write_spr(Spr::Dsisr),
                     Spr::Other(other) => visitor.write_spr(Spr::Other(other)),
                 }
             },
@@ -790,16 +2050,35 @@ impl Instruction {
                     visitor.write_crf(Crf(0));
                 }
             },
-            Instruction::And { source1, source2, dest } => {
+            Instruction::And { source1, source2, dest, rc } => {
                 visitor.read_gpr(source1);
                 visitor.read_gpr(source2);
                 visitor.effect();
                 visitor.write_gpr(dest);
+                if rc {
+                    visitor.write_crf(Crf(0));
+                }
+            },
+            Instruction::Xor { source, dest, rhs, rc }
+            | Instruction::Nand { source, dest, rhs, rc }
+            | Instruction::Nor { source, dest, rhs, rc }
+            | Instruction::Eqv { source, dest, rhs, rc }
+            | Instruction::Andc { source, dest, rhs, rc }
+            | Instruction::Orc { source, dest, rhs, rc } => {
+                visitor.read_gpr(source);
+                visitor.read_gpr(rhs);
+                visitor.effect();
+                visitor.write_gpr(dest);
+                if rc {
+                    visitor.write_crf(Crf(0));
+                }
             },
-            Instruction::Andi { source, dest, simm: _ } => {
+            Instruction::Andi { source, dest, imm: _ } => {
                 visitor.read_gpr(source);
                 visitor.effect();
                 visitor.write_gpr(dest);
+                // `andi.` always records
+                visitor.write_crf(Crf(0));
             }
             Instruction::Stw { source, dest, imm: _ } => {
                 visitor.read_gpr(source);
@@ -807,7 +2086,10 @@ impl Instruction {
                 visitor.effect();
             },
             Instruction::Stmw { source, dest, imm: _ } => {
-                visitor.read_gpr(source);
+                // Stores every register from `source` up to r31
+                for gpr in source.0..32 {
+                    visitor.read_gpr(Gpr(gpr));
+                }
                 visitor.read_gpr(dest);
                 visitor.effect();
             },
@@ -816,15 +2098,138 @@ impl Instruction {
                 visitor.effect();
                 visitor.write_gpr(dest);
             },
-            Instruction::Lwzu { dest, source, imm: _ } => {
+            Instruction::Lwzu { dest, source, imm: _ }
+            | Instruction::Lhzu { dest, source, imm: _ }
+            | Instruction::Lhau { dest, source, imm: _ }
+            | Instruction::Lbzu { dest, source, imm: _ } => {
+                visitor.read_gpr(source);
+                visitor.effect();
+                visitor.write_gpr(dest);
+                // Update forms write the effective address back into the base register
+                visitor.write_gpr(source);
+            },
+            Instruction::Lfs { dest, source, imm: _ }
+            | Instruction::Lfd { dest, source, imm: _ } => {
+                visitor.read_gpr(source);
+                visitor.effect();
+                visitor.write_fpr(dest);
+            },
+            Instruction::Lfsu { dest, source, imm: _ }
+            | Instruction::Lfdu { dest, source, imm: _ } => {
+                visitor.read_gpr(source);
+                visitor.effect();
+                visitor.write_fpr(dest);
+                visitor.write_gpr(source);
+            },
+            Instruction::Stfs { source, dest, imm: _ }
+            | Instruction::Stfd { source, dest, imm: _ } => {
+                visitor.read_fpr(source);
+                visitor.read_gpr(dest);
+                visitor.effect();
+            },
+            Instruction::Stfsu { source, dest, imm: _ }
+            | Instruction::Stfdu { source, dest, imm: _ } => {
+                visitor.read_fpr(source);
+                visitor.read_gpr(dest);
+                visitor.effect();
+                visitor.write_gpr(dest);
+            },
+            Instruction::Fadd { dest, source_a, source_b, rc }
+            | Instruction::Fadds { dest, source_a, source_b, rc }
+            | Instruction::Fsub { dest, source_a, source_b, rc }
+            | Instruction::Fsubs { dest, source_a, source_b, rc }
+            | Instruction::Fdiv { dest, source_a, source_b, rc }
+            | Instruction::Fdivs { dest, source_a, source_b, rc }
+            | Instruction::Fmul { dest, source_a, source_c: source_b, rc }
+            | Instruction::Fmuls { dest, source_a, source_c: source_b, rc } => {
+                visitor.read_fpr(source_a);
+                visitor.read_fpr(source_b);
+                visitor.effect();
+                visitor.write_fpr(dest);
+                if rc {
+                    visitor.write_crf(Crf(1));
+                }
+            },
+            Instruction::Fmadd { dest, source_a, source_b, source_c, rc }
+            | Instruction::Fmadds { dest, source_a, source_b, source_c, rc }
+            | Instruction::Fmsub { dest, source_a, source_b, source_c, rc }
+            | Instruction::Fmsubs { dest, source_a, source_b, source_c, rc }
+            | Instruction::Fnmadd { dest, source_a, source_b, source_c, rc }
+            | Instruction::Fnmadds { dest, source_a, source_b, source_c, rc }
+            | Instruction::Fnmsub { dest, source_a, source_b, source_c, rc }
+            | Instruction::Fnmsubs { dest, source_a, source_b, source_c, rc } => {
+                visitor.read_fpr(source_a);
+                visitor.read_fpr(source_b);
+                visitor.read_fpr(source_c);
+                visitor.effect();
+                visitor.write_fpr(dest);
+                if rc {
+                    visitor.write_crf(Crf(1));
+                }
+            },
+            Instruction::Fcmpu { source_a, source_b, crf }
+            | Instruction::Fcmpo { source_a, source_b, crf } => {
+                visitor.read_fpr(source_a);
+                visitor.read_fpr(source_b);
+                visitor.effect();
+                visitor.write_crf(crf);
+            },
+            Instruction::Sc {} => {
+                visitor.effect();
+            },
+            Instruction::Tw { to: _, source_a, source_b } => {
+                visitor.read_gpr(source_a);
+                visitor.read_gpr(source_b);
+                visitor.effect();
+            },
+            Instruction::Twi { to: _, source, simm: _ } => {
+                visitor.read_gpr(source);
+                visitor.effect();
+            },
+            Instruction::Fmr { dest, source, rc }
+            | Instruction::Fneg { dest, source, rc }
+            | Instruction::Fabs { dest, source, rc }
+            | Instruction::Fnabs { dest, source, rc }
+            | Instruction::Frsp { dest, source, rc }
+            | Instruction::Fctiwz { dest, source, rc } => {
+                visitor.read_fpr(source);
+                visitor.effect();
+                visitor.write_fpr(dest);
+                if rc {
+                    visitor.write_crf(Crf(1));
+                }
+            },
+            Instruction::Lha { dest, source, imm: _ } => {
+                visitor.read_gpr(source);
+                visitor.effect();
+                visitor.write_gpr(dest);
+            },
+            Instruction::Sth { source, dest, imm: _ }
+            | Instruction::Stb { source, dest, imm: _ } => {
+                visitor.read_gpr(source);
+                visitor.read_gpr(dest);
+                visitor.effect();
+            },
+            Instruction::Sthu { source, dest, imm: _ }
+            | Instruction::Stbu { source, dest, imm: _ } => {
                 visitor.read_gpr(source);
+                visitor.read_gpr(dest);
                 visitor.effect();
                 visitor.write_gpr(dest);
             },
             Instruction::Isync {} => {
                 visitor.effect();
             },
-            Instruction::Hwsync {} => {
+            Instruction::Sync { kind: _ } | Instruction::Eieio {} => {
+                visitor.effect();
+            },
+            Instruction::Dcbz { base, index }
+            | Instruction::Dcbf { base, index }
+            | Instruction::Dcbst { base, index }
+            | Instruction::Dcbt { base, index }
+            | Instruction::Icbi { base, index } => {
+                visitor.read_gpr(base);
+                visitor.read_gpr(index);
                 visitor.effect();
             },
             Instruction::Oris { source, dest, imm: _ } => {
@@ -832,17 +2237,20 @@ impl Instruction {
                 visitor.effect();
                 visitor.write_gpr(dest);
             },
-            Instruction::Mtfsb1 { crf, rc } => {
+            Instruction::Mtfsb1 { crf: _, rc } => {
+                // `crf` names an FPSCR bit, which we don't track.
                 visitor.effect();
-                visitor.write_crf(crf);
                 if rc {
                     visitor.write_crf(Crf(1));
                 }
             },
-            Instruction::Lmw { source, dest, imm: _ } => {
+            Instruction::Lmw { dest, source, imm: _ } => {
                 visitor.read_gpr(source);
                 visitor.effect();
-                visitor.write_gpr(dest);
+                // Loads every register from `dest` up to r31
+                for gpr in dest.0..32 {
+                    visitor.write_gpr(Gpr(gpr));
+                }
             },
             Instruction::Mftb { dest, tbr: _ } => {
                 visitor.effect();
@@ -870,7 +2278,35 @@ impl Instruction {
                     visitor.write_crf(Crf(0));
                 }
             },
-            Instruction::Crxor { crb_dest, crb_a, crb_b } => {
+            Instruction::Mfcr { dest } => {
+                for crf in 0..8 {
+                    visitor.read_crf(Crf(crf));
+                }
+                visitor.effect();
+                visitor.write_gpr(dest);
+            },
+            Instruction::Mtcrf { source, crm } => {
+                visitor.read_gpr(source);
+                visitor.effect();
+                for crf in 0..8 {
+                    if crm & (0x80 >> crf) != 0 {
+                        visitor.write_crf(Crf(crf));
+                    }
+                }
+            },
+            Instruction::Mcrf { crf_dest, crf_source } => {
+                visitor.read_crf(crf_source);
+                visitor.effect();
+                visitor.write_crf(crf_dest);
+            },
+            Instruction::Crxor { crb_dest, crb_a, crb_b }
+            | Instruction::Crand { crb_dest, crb_a, crb_b }
+            | Instruction::Cror { crb_dest, crb_a, crb_b }
+            | Instruction::Crnand { crb_dest, crb_a, crb_b }
+            | Instruction::Crnor { crb_dest, crb_a, crb_b }
+            | Instruction::Creqv { crb_dest, crb_a, crb_b }
+            | Instruction::Crandc { crb_dest, crb_a, crb_b }
+            | Instruction::Crorc { crb_dest, crb_a, crb_b } => {
                 let (crf_dest, crb_dest) = crb_from_index(crb_dest);
                 let (crf_a, crb_a) = crb_from_index(crb_a);
                 let (crf_b, crb_b) = crb_from_index(crb_b);
@@ -906,13 +2342,49 @@ impl Instruction {
         let visitor = Visitor { f: &mut f };
         self.visit_registers(visitor);
     }
+
+    pub fn for_each_written_gpr(&self, mut f: impl FnMut(Gpr)) {
+        struct Visitor<'a, F: FnMut(Gpr)> {
+            f: &'a mut F,
+        }
+
+        impl<'a, F: FnMut(Gpr)> RegisterVisitor for Visitor<'a, F> {
+            fn write_gpr(&mut self, gpr: Gpr) {
+                (self.f)(gpr);
+            }
+        }
+
+        let visitor = Visitor { f: &mut f };
+        self.visit_registers(visitor);
+    }
+
+    pub fn for_each_read_fpr(&self, mut f: impl FnMut(Fpr)) {
+        struct Visitor<'a, F: FnMut(Fpr)> {
+            f: &'a mut F,
+        }
+
+        impl<'a, F: FnMut(Fpr)> RegisterVisitor for Visitor<'a, F> {
+            fn read_fpr(&mut self, fpr: Fpr) {
+                (self.f)(fpr);
+            }
+        }
+
+        let visitor = Visitor { f: &mut f };
+        self.visit_registers(visitor);
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum BranchOptions {
+    /// `bdnzf`: decrement CTR, then branch if it isn't zero and the condition is false.
     DecCTRBranchIfFalse,
+    /// `bdzf`: decrement CTR, then branch if it is zero and the condition is false.
+    DecCTRZeroBranchIfFalse,
     BranchIfFalse,
+    /// `bdnzt`: decrement CTR, then branch if it isn't zero and the condition is true.
     DecCTRBranchIfTrue,
+    /// `bdzt`: decrement CTR, then branch if it is zero and the condition is true.
+    DecCTRZeroBranchIfTrue,
     BranchIfTrue,
     DecCTRBranchIfNotZero,
     DecCTRBranchIfZero,
@@ -920,26 +2392,75 @@ pub enum BranchOptions {
 }
 
 impl BranchOptions {
-    pub fn from_word(word: Word) -> Self {
+    /// Decodes the BO field. The bits marked `z` in the manual are reserved and must be zero, otherwise the
+    /// instruction form is invalid. The `y` bit is the branch prediction hint, which is decoded separately.
+    pub fn from_word(word: Word) -> Result<Self, InvalidOperand> {
         let mask = word.u8::<6, 10>();
 
-        if let 0b00010 | 0 = mask & 0b11110 {
+        let options = if mask & 0b11110 == 0 {
             BranchOptions::DecCTRBranchIfFalse
-        } else if mask & 0b11100 == 0b00100 {
+        } else if mask & 0b11110 == 0b00010 {
+            BranchOptions::DecCTRZeroBranchIfFalse
+        } else if mask & 0b11110 == 0b00100 {
             BranchOptions::BranchIfFalse
-        } else if let 0b01000 | 0b01010 = mask & 0b11110 {
+        } else if mask & 0b11110 == 0b01000 {
             BranchOptions::DecCTRBranchIfTrue
-        } else if mask & 0b11100 == 0b01100 {
+        } else if mask & 0b11110 == 0b01010 {
+            BranchOptions::DecCTRZeroBranchIfTrue
+        } else if mask & 0b11110 == 0b01100 {
             BranchOptions::BranchIfTrue
-        } else if mask & 0b10110 == 0b10000 {
+        } else if mask & 0b11110 == 0b10000 {
             BranchOptions::DecCTRBranchIfNotZero
-        } else if mask & 0b10110 == 0b10010 {
+        } else if mask & 0b11110 == 0b10010 {
             BranchOptions::DecCTRBranchIfZero
-        } else {
-            assert!((mask & 0b10100) == 0b10100, "invalid BO operand");
+        } else if mask == 0b10100 {
             BranchOptions::BranchAlways
+        } else {
+            return Err(InvalidOperand("BO"));
+        };
+        Ok(options)
+    }
+
+    /// The BO field for these options, without the branch prediction hint bit.
+    pub fn bo(self) -> u8 {
+        match self {
+            BranchOptions::DecCTRBranchIfFalse => 0b00000,
+            BranchOptions::DecCTRZeroBranchIfFalse => 0b00010,
+            BranchOptions::BranchIfFalse => 0b00100,
+            BranchOptions::DecCTRBranchIfTrue => 0b01000,
+            BranchOptions::DecCTRZeroBranchIfTrue => 0b01010,
+            BranchOptions::BranchIfTrue => 0b01100,
+            BranchOptions::DecCTRBranchIfNotZero => 0b10000,
+            BranchOptions::DecCTRBranchIfZero => 0b10010,
+            BranchOptions::BranchAlways => 0b10100,
         }
     }
+
+    /// Whether the branch depends on the CR bit given by the BI operand.
+    pub fn tests_condition(self) -> bool {
+        matches!(
+            self,
+            BranchOptions::DecCTRBranchIfFalse
+                | BranchOptions::DecCTRZeroBranchIfFalse
+                | BranchOptions::BranchIfFalse
+                | BranchOptions::DecCTRBranchIfTrue
+                | BranchOptions::DecCTRZeroBranchIfTrue
+                | BranchOptions::BranchIfTrue
+        )
+    }
+
+    /// Whether the branch decrements CTR (and depends on the new value).
+    pub fn decrements_ctr(self) -> bool {
+        matches!(
+            self,
+            BranchOptions::DecCTRBranchIfFalse
+                | BranchOptions::DecCTRZeroBranchIfFalse
+                | BranchOptions::DecCTRBranchIfTrue
+                | BranchOptions::DecCTRZeroBranchIfTrue
+                | BranchOptions::DecCTRBranchIfNotZero
+                | BranchOptions::DecCTRBranchIfZero
+        )
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -952,12 +2473,39 @@ pub enum TimeBaseRegister {
 
 impl TimeBaseRegister {
     pub fn from_word(word: Word) -> Self {
-        match word.u16::<11, 15>() | (word.u16::<16, 20>() << 5) {
+        match word.spr() {
             268 => TimeBaseRegister::Tbu,
             269 => TimeBaseRegister::Tbl,
             other => panic!("invalid TBR register code: {} (word: {word:x?})", other),
         }
     }
+
+    /// The TBR number of this register, as encoded in an `mftb` instruction.
+    pub fn number(self) -> u16 {
+        match self {
+            TimeBaseRegister::Tbu => 268,
+            TimeBaseRegister::Tbl => 269,
+        }
+    }
+}
+
+/// Where a branch instruction goes, as returned by [`Instruction::branch_target`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BranchTarget {
+    /// A fixed address that is encoded in the instruction.
+    Static(u32),
+    /// Whatever address is in LR (`bclr`) or CTR (`bcctr`) when the branch executes.
+    Register(MacroSpr),
+}
+
+impl BranchTarget {
+    /// The target address, if it is known statically.
+    pub fn address(self) -> Option<u32> {
+        match self {
+            BranchTarget::Static(address) => Some(address),
+            BranchTarget::Register(_) => None,
+        }
+    }
 }
 
 pub fn compute_branch_target(base: u32, mode: AddressingMode, target: i32) -> u32 {
@@ -1,4 +1,6 @@
 pub mod decoder;
+mod encoder;
+pub mod format;
 pub mod instruction;
 pub mod word;
 
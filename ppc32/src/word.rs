@@ -11,6 +11,17 @@ impl Word {
         self.u32::<21, 30>()
     }
 
+    /// The XO field of XO-form instructions, which leaves out the OE bit at 21.
+    pub fn xoform_opcode(self) -> u32 {
+        // Bits 22-30
+        self.u32::<22, 30>()
+    }
+
+    pub fn aform_opcode(self) -> u32 {
+        // Bits 26-30
+        self.u32::<26, 30>()
+    }
+
     /// Extracts a u32 bit range in big endian (reversed) from this word.
     pub fn u32<const FROM: u32, const TO: u32>(self) -> u32 {
         const { assert!(TO >= FROM && TO - FROM < 32) };
@@ -21,8 +32,8 @@ impl Word {
 
     pub fn i32<const FROM: u32, const TO: u32>(self) -> i32 {
         let value = self.u32::<FROM, TO>();
-        // Sign extension: make sure the 24th bit becomes the sign bit
-        (value << (32 - (TO - FROM))) as i32 >> (32 - (TO - FROM))
+        // Sign extension: make sure the highest bit of the field becomes the sign bit
+        (value << (31 - (TO - FROM))) as i32 >> (31 - (TO - FROM))
     }
 
     pub fn u16<const FROM: u32, const TO: u32>(self) -> u16 {
@@ -46,4 +57,61 @@ impl Word {
     pub fn bit<const BIT: u32>(self) -> u32 {
         self.0 & (1 << (31 - BIT))
     }
+
+    /// Returns this word with the big endian bit range `FROM..=TO` replaced by `value`. This is the inverse of
+    /// [`Word::u32`].
+    pub fn with_u32<const FROM: u32, const TO: u32>(self, value: u32) -> Self {
+        const { assert!(TO >= FROM && TO - FROM < 32) };
+
+        let max = const { (!0u32) >> (31 - (TO - FROM)) };
+        assert!(
+            value <= max,
+            "value {value:#x} does not fit in bits {FROM}-{TO}"
+        );
+        let mask = max << (31 - TO);
+        Self((self.0 & !mask) | (value << (31 - TO)))
+    }
+
+    /// Like [`Word::with_u32`], but for a signed value. This is the inverse of [`Word::i32`].
+    pub fn with_i32<const FROM: u32, const TO: u32>(self, value: i32) -> Self {
+        let bits = TO - FROM + 1;
+        let (min, max) = (-(1i64 << (bits - 1)), (1i64 << (bits - 1)) - 1);
+        assert!(
+            (min..=max).contains(&i64::from(value)),
+            "value {value} does not fit in bits {FROM}-{TO}"
+        );
+        self.with_u32::<FROM, TO>(value as u32 & ((!0u32) >> (32 - bits)))
+    }
+
+    pub fn with_bit<const BIT: u32>(self, set: bool) -> Self {
+        self.with_u32::<BIT, BIT>(u32::from(set))
+    }
+
+    /// The inverse of [`Word::spr`].
+    pub fn with_spr(self, spr: u16) -> Self {
+        self.with_u32::<11, 15>(u32::from(spr) & 0b11111)
+            .with_u32::<16, 20>(u32::from(spr) >> 5)
+    }
+
+    /// Reassembles a field that is split into two bit ranges, where the range `LO_FROM..=LO_TO` holds the low bits
+    /// and `HI_FROM..=HI_TO` the high bits.
+    pub fn split_field<
+        const LO_FROM: u32,
+        const LO_TO: u32,
+        const HI_FROM: u32,
+        const HI_TO: u32,
+    >(
+        self,
+    ) -> u32 {
+        const { assert!((LO_TO - LO_FROM + 1) + (HI_TO - HI_FROM + 1) <= 32) };
+        self.u32::<LO_FROM, LO_TO>() | (self.u32::<HI_FROM, HI_TO>() << (LO_TO - LO_FROM + 1))
+    }
+
+    /// The SPR number of an `mfspr`/`mtspr` (or TBR number of an `mftb`) instruction.
+    ///
+    /// The 10-bit number is encoded with its two 5-bit halves swapped: bits 11-15 hold the low half and bits 16-20 the
+    /// high half.
+    pub fn spr(self) -> u16 {
+        self.split_field::<11, 15, 16, 20>() as u16
+    }
 }
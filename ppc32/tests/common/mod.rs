@@ -0,0 +1,7 @@
+use ppc32::{Decoder, Instruction};
+
+pub fn decode(word: u32) -> Instruction {
+    Decoder::new(&word.to_be_bytes())
+        .decode_instruction()
+        .unwrap_or_else(|err| panic!("failed to decode {word:#010x}: {err}"))
+}
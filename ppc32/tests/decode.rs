@@ -0,0 +1,774 @@
+mod common;
+
+use common::decode;
+use ppc32::{
+    Instruction,
+    instruction::{
+        BranchOptions, BranchTarget, Crf, Fpr, Gpr, Immediate, Spr, SyncKind, TrapCondition,
+    },
+};
+
+#[test]
+fn shifts() {
+    // slw r4,r3,r5
+    assert!(matches!(
+        decode(0x7c642830),
+        Instruction::Slw {
+            source: Gpr(3),
+            dest: Gpr(4),
+            shift: Gpr(5),
+            rc: false
+        }
+    ));
+    // srw. r4,r3,r5
+    assert!(matches!(
+        decode(0x7c642c31),
+        Instruction::Srw {
+            source: Gpr(3),
+            dest: Gpr(4),
+            shift: Gpr(5),
+            rc: true
+        }
+    ));
+    // sraw r7,r6,r8
+    assert!(matches!(
+        decode(0x7cc74630),
+        Instruction::Sraw {
+            source: Gpr(6),
+            dest: Gpr(7),
+            shift: Gpr(8),
+            rc: false
+        }
+    ));
+    // srawi. r4,r3,2
+    assert!(matches!(
+        decode(0x7c641671),
+        Instruction::Srawi {
+            source: Gpr(3),
+            dest: Gpr(4),
+            sh: Immediate(2),
+            rc: true
+        }
+    ));
+}
+
+#[test]
+fn rotate_mask_insert() {
+    // rlwimi r4,r3,8,16,23
+    assert!(matches!(
+        decode(0x5064442e),
+        Instruction::Rlwimi {
+            source: Gpr(3),
+            dest: Gpr(4),
+            rot_bits: Immediate(8),
+            mask_start: Immediate(16),
+            mask_end: Immediate(23),
+            rc: false
+        }
+    ));
+}
+
+#[test]
+fn extend_and_count_zeros() {
+    // extsb r4,r3
+    assert!(matches!(
+        decode(0x7c640774),
+        Instruction::Extsb {
+            source: Gpr(3),
+            dest: Gpr(4),
+            rc: false
+        }
+    ));
+    // extsh. r4,r3
+    assert!(matches!(
+        decode(0x7c640735),
+        Instruction::Extsh {
+            source: Gpr(3),
+            dest: Gpr(4),
+            rc: true
+        }
+    ));
+    // cntlzw r6,r5
+    assert!(matches!(
+        decode(0x7ca60034),
+        Instruction::Cntlzw {
+            source: Gpr(5),
+            dest: Gpr(6),
+            rc: false
+        }
+    ));
+}
+
+#[test]
+fn logical() {
+    macro_rules! assert_logical {
+        ($word:expr, $variant:ident) => {
+            assert!(matches!(
+                decode($word),
+                Instruction::$variant {
+                    source: Gpr(3),
+                    dest: Gpr(4),
+                    rhs: Gpr(5),
+                    rc: false
+                }
+            ));
+        };
+    }
+
+    // <op> r4,r3,r5
+    assert_logical!(0x7c642a78, Xor);
+    assert_logical!(0x7c642bb8, Nand);
+    assert_logical!(0x7c6428f8, Nor);
+    assert_logical!(0x7c642a38, Eqv);
+    assert_logical!(0x7c642878, Andc);
+    assert_logical!(0x7c642b38, Orc);
+
+    // and. r4,r3,r5
+    assert!(matches!(
+        decode(0x7c642839),
+        Instruction::And {
+            source1: Gpr(3),
+            dest: Gpr(4),
+            source2: Gpr(5),
+            rc: true
+        }
+    ));
+
+    // not. r4,r3 (nor. r4,r3,r3)
+    let not = decode(0x7c6418f9);
+    assert!(matches!(not, Instruction::Nor { rc: true, .. }));
+    assert_eq!(not.as_not(), Some((Gpr(4), Gpr(3))));
+    assert_eq!(decode(0x7c6428f8).as_not(), None);
+}
+
+#[test]
+fn carrying_arithmetic() {
+    macro_rules! assert_carrying {
+        ($word:expr, $variant:ident) => {
+            assert!(matches!(
+                decode($word),
+                Instruction::$variant {
+                    dest: Gpr(3),
+                    source_a: Gpr(4),
+                    source_b: Gpr(5),
+                    oe: false,
+                    rc: false
+                }
+            ));
+        };
+    }
+
+    // <op> r3,r4,r5
+    assert_carrying!(0x7c642814, Addc);
+    assert_carrying!(0x7c642914, Adde);
+    assert_carrying!(0x7c642810, Subfc);
+    assert_carrying!(0x7c642910, Subfe);
+
+    // addme. r3,r4
+    assert!(matches!(
+        decode(0x7c6401d5),
+        Instruction::Addme {
+            dest: Gpr(3),
+            source: Gpr(4),
+            oe: false,
+            rc: true
+        }
+    ));
+    // addze. r3,r4
+    assert!(matches!(
+        decode(0x7c640195),
+        Instruction::Addze {
+            dest: Gpr(3),
+            source: Gpr(4),
+            oe: false,
+            rc: true
+        }
+    ));
+}
+
+#[test]
+fn overflow_forms() {
+    macro_rules! assert_overflow {
+        ($word:expr, $variant:ident) => {
+            assert!(matches!(
+                decode($word),
+                Instruction::$variant {
+                    dest: Gpr(3),
+                    source_a: Gpr(4),
+                    source_b: Gpr(5),
+                    oe: true,
+                    rc: false
+                }
+            ));
+        };
+    }
+
+    // <op>o r3,r4,r5
+    assert_overflow!(0x7c642e14, Add);
+    assert_overflow!(0x7c642c14, Addc);
+    assert_overflow!(0x7c642d14, Adde);
+    assert_overflow!(0x7c642c10, Subfc);
+    assert_overflow!(0x7c642d10, Subfe);
+
+    // subfo. r3,r4,r5
+    assert!(matches!(
+        decode(0x7c642c51),
+        Instruction::Subf {
+            dest: Gpr(3),
+            source_b: Gpr(4),
+            source_a: Gpr(5),
+            oe: true,
+            rc: true
+        }
+    ));
+    // nego r3,r4
+    assert!(matches!(
+        decode(0x7c6404d0),
+        Instruction::Neg {
+            dest: Gpr(3),
+            source: Gpr(4),
+            rc: false,
+            oe: true
+        }
+    ));
+    // addmeo r3,r4
+    assert!(matches!(
+        decode(0x7c6405d4),
+        Instruction::Addme {
+            dest: Gpr(3),
+            source: Gpr(4),
+            oe: true,
+            rc: false
+        }
+    ));
+    // addzeo. r3,r4
+    assert!(matches!(
+        decode(0x7c640595),
+        Instruction::Addze {
+            dest: Gpr(3),
+            source: Gpr(4),
+            oe: true,
+            rc: true
+        }
+    ));
+}
+
+#[test]
+fn indexed_loads_and_stores() {
+    macro_rules! assert_store {
+        ($word:expr, $variant:ident) => {
+            assert!(matches!(
+                decode($word),
+                Instruction::$variant {
+                    source: Gpr(3),
+                    dest: Gpr(4),
+                    index: Gpr(5)
+                }
+            ));
+        };
+    }
+    macro_rules! assert_load {
+        ($word:expr, $variant:ident) => {
+            assert!(matches!(
+                decode($word),
+                Instruction::$variant {
+                    dest: Gpr(3),
+                    source: Gpr(4),
+                    index: Gpr(5)
+                }
+            ));
+        };
+    }
+
+    // <op> r3,r4,r5
+    assert_store!(0x7c64292e, Stwx);
+    assert_store!(0x7c642b2e, Sthx);
+    assert_store!(0x7c6429ae, Stbx);
+    assert_load!(0x7c64282e, Lwzx);
+    assert_load!(0x7c642a2e, Lhzx);
+    assert_load!(0x7c6428ae, Lbzx);
+}
+
+#[test]
+fn byte_and_halfword_loads_and_stores() {
+    macro_rules! assert_load {
+        ($word:expr, $variant:ident) => {
+            assert!(matches!(
+                decode($word),
+                Instruction::$variant {
+                    dest: Gpr(3),
+                    source: Gpr(4),
+                    imm: Immediate(-8)
+                }
+            ));
+        };
+    }
+    macro_rules! assert_store {
+        ($word:expr, $variant:ident) => {
+            assert!(matches!(
+                decode($word),
+                Instruction::$variant {
+                    source: Gpr(3),
+                    dest: Gpr(4),
+                    imm: Immediate(-8)
+                }
+            ));
+        };
+    }
+
+    // <op> r3,-8(r4)
+    // lhz zero-extends and lha sign-extends, so they must not decode to the same instruction
+    assert_load!(0xa064fff8, Lhz);
+    assert_load!(0xa464fff8, Lhzu);
+    assert_load!(0xa864fff8, Lha);
+    assert_load!(0xac64fff8, Lhau);
+    assert_load!(0x8c64fff8, Lbzu);
+    assert_store!(0xb064fff8, Sth);
+    assert_store!(0xb464fff8, Sthu);
+    assert_store!(0x9864fff8, Stb);
+    assert_store!(0x9c64fff8, Stbu);
+}
+
+#[test]
+fn float_loads_and_stores() {
+    macro_rules! assert_load {
+        ($word:expr, $variant:ident) => {
+            assert!(matches!(
+                decode($word),
+                Instruction::$variant {
+                    dest: Fpr(1),
+                    source: Gpr(3),
+                    imm: Immediate(8)
+                }
+            ));
+        };
+    }
+    macro_rules! assert_store {
+        ($word:expr, $variant:ident) => {
+            assert!(matches!(
+                decode($word),
+                Instruction::$variant {
+                    source: Fpr(1),
+                    dest: Gpr(3),
+                    imm: Immediate(8)
+                }
+            ));
+        };
+    }
+
+    // <op> fr1,8(r3)
+    assert_load!(0xc0230008, Lfs);
+    assert_load!(0xc4230008, Lfsu);
+    assert_load!(0xc8230008, Lfd);
+    assert_load!(0xcc230008, Lfdu);
+    assert_store!(0xd0230008, Stfs);
+    assert_store!(0xd4230008, Stfsu);
+    assert_store!(0xd8230008, Stfd);
+    assert_store!(0xdc230008, Stfdu);
+
+    assert_eq!(
+        format!("{:?}", decode(0xc0230008)),
+        "Lfs { dest: fr1, source: r3, imm: Immediate(8) }"
+    );
+}
+
+#[test]
+fn float_arithmetic() {
+    macro_rules! assert_arith {
+        ($word:expr, $variant:ident) => {
+            assert!(matches!(
+                decode($word),
+                Instruction::$variant {
+                    dest: Fpr(1),
+                    source_a: Fpr(2),
+                    source_b: Fpr(3),
+                    rc: false
+                }
+            ));
+        };
+    }
+
+    // <op> fr1,fr2,fr3
+    assert_arith!(0xfc22182a, Fadd);
+    assert_arith!(0xec22182a, Fadds);
+    assert_arith!(0xfc221828, Fsub);
+    assert_arith!(0xec221828, Fsubs);
+    assert_arith!(0xfc221824, Fdiv);
+    assert_arith!(0xec221824, Fdivs);
+
+    // fmul fr1,fr2,fr3
+    assert!(matches!(
+        decode(0xfc2200f2),
+        Instruction::Fmul {
+            dest: Fpr(1),
+            source_a: Fpr(2),
+            source_c: Fpr(3),
+            rc: false
+        }
+    ));
+    // fmuls. fr1,fr2,fr3
+    assert!(matches!(
+        decode(0xec2200f3),
+        Instruction::Fmuls {
+            dest: Fpr(1),
+            source_a: Fpr(2),
+            source_c: Fpr(3),
+            rc: true
+        }
+    ));
+}
+
+#[test]
+fn float_moves_and_conversions() {
+    macro_rules! assert_unary {
+        ($word:expr, $variant:ident) => {
+            assert!(matches!(
+                decode($word),
+                Instruction::$variant {
+                    dest: Fpr(1),
+                    source: Fpr(2),
+                    rc: false
+                }
+            ));
+        };
+    }
+
+    // <op> fr1,fr2
+    assert_unary!(0xfc201090, Fmr);
+    assert_unary!(0xfc201050, Fneg);
+    assert_unary!(0xfc201210, Fabs);
+    assert_unary!(0xfc201110, Fnabs);
+    assert_unary!(0xfc201018, Frsp);
+    assert_unary!(0xfc20101e, Fctiwz);
+
+    assert_eq!(
+        format!("{:?}", decode(0xfc20101e)),
+        "Fctiwz { dest: fr1, source: fr2, rc: false }"
+    );
+}
+
+#[test]
+fn fused_multiply_add() {
+    macro_rules! assert_fma {
+        ($word:expr, $variant:ident) => {
+            assert!(matches!(
+                decode($word),
+                Instruction::$variant {
+                    dest: Fpr(1),
+                    source_a: Fpr(2),
+                    source_b: Fpr(3),
+                    source_c: Fpr(4),
+                    rc: false
+                }
+            ));
+        };
+    }
+
+    // <op> fr1,fr2,fr4,fr3
+    assert_fma!(0xfc22193a, Fmadd);
+    assert_fma!(0xec22193a, Fmadds);
+    assert_fma!(0xfc221938, Fmsub);
+    assert_fma!(0xec221938, Fmsubs);
+    assert_fma!(0xfc22193e, Fnmadd);
+    assert_fma!(0xec22193e, Fnmadds);
+    assert_fma!(0xfc22193c, Fnmsub);
+    assert_fma!(0xec22193c, Fnmsubs);
+}
+
+#[test]
+fn float_compares() {
+    // fcmpu cr0,fr1,fr2
+    assert!(matches!(
+        decode(0xfc011000),
+        Instruction::Fcmpu {
+            source_a: Fpr(1),
+            source_b: Fpr(2),
+            crf: Crf(0)
+        }
+    ));
+    // fcmpo cr7,fr1,fr2
+    assert!(matches!(
+        decode(0xff811040),
+        Instruction::Fcmpo {
+            source_a: Fpr(1),
+            source_b: Fpr(2),
+            crf: Crf(7)
+        }
+    ));
+}
+
+#[test]
+fn syscalls_and_traps() {
+    // sc
+    assert!(matches!(decode(0x44000002), Instruction::Sc {}));
+
+    // tweq r3,r4
+    let tweq = decode(0x7c832008);
+    assert!(matches!(
+        tweq,
+        Instruction::Tw {
+            to: TrapCondition(0b00100),
+            source_a: Gpr(3),
+            source_b: Gpr(4)
+        }
+    ));
+    assert!(!tweq.is_unconditional_trap());
+
+    // twlti r3,-1
+    assert!(matches!(
+        decode(0x0e03ffff),
+        Instruction::Twi {
+            to: TrapCondition(0b10000),
+            source: Gpr(3),
+            simm: -1
+        }
+    ));
+
+    // trap (tw 31,r0,r0)
+    assert!(decode(0x7fe00008).is_unconditional_trap());
+
+    // twi 31,r0,0
+    let trap = decode(0x0fe00000);
+    assert!(trap.is_unconditional_trap());
+    assert_eq!(
+        format!("{trap:?}"),
+        "Twi { to: Always, source: r0, simm: 0 }"
+    );
+}
+
+#[test]
+fn branch_to_count_register() {
+    // bctr
+    assert!(matches!(
+        decode(0x4e800420),
+        Instruction::Bcctr {
+            bo: BranchOptions::BranchAlways,
+            link: false,
+            ..
+        }
+    ));
+    // bctrl
+    assert!(matches!(
+        decode(0x4e800421),
+        Instruction::Bcctr {
+            bo: BranchOptions::BranchAlways,
+            link: true,
+            ..
+        }
+    ));
+    // beqctr
+    assert!(matches!(
+        decode(0x4d820420),
+        Instruction::Bcctr {
+            bo: BranchOptions::BranchIfTrue,
+            hint: _,
+            bi: 2,
+            link: false
+        }
+    ));
+}
+
+#[test]
+fn decrement_and_branch_if_zero() {
+    // bdzf eq, -0x8
+    assert!(matches!(
+        decode(0x4042fff8),
+        Instruction::Bc {
+            bo: BranchOptions::DecCTRZeroBranchIfFalse,
+            bi: 2,
+            ..
+        }
+    ));
+    // bdnzt+ eq, -0x8
+    assert!(matches!(
+        decode(0x4122fff8),
+        Instruction::Bc {
+            bo: BranchOptions::DecCTRBranchIfTrue,
+            hint: true,
+            bi: 2,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn condition_register_moves() {
+    // mfcr r3
+    assert!(matches!(
+        decode(0x7c600026),
+        Instruction::Mfcr { dest: Gpr(3) }
+    ));
+    // mtcrf 0x81,r3
+    assert!(matches!(
+        decode(0x7c681120),
+        Instruction::Mtcrf {
+            source: Gpr(3),
+            crm: 0x81
+        }
+    ));
+    // mcrf cr7,cr1
+    assert!(matches!(
+        decode(0x4f840000),
+        Instruction::Mcrf {
+            crf_dest: Crf(7),
+            crf_source: Crf(1)
+        }
+    ));
+}
+
+#[test]
+fn condition_register_logical() {
+    macro_rules! assert_cr_logical {
+        ($word:expr, $variant:ident) => {
+            assert!(matches!(
+                decode($word),
+                Instruction::$variant {
+                    crb_dest: 30,
+                    crb_a: 2,
+                    crb_b: 6
+                }
+            ));
+        };
+    }
+
+    // <op> 4*cr7+eq,eq,4*cr1+eq
+    assert_cr_logical!(0x4fc23202, Crand);
+    assert_cr_logical!(0x4fc23382, Cror);
+    assert_cr_logical!(0x4fc231c2, Crnand);
+    assert_cr_logical!(0x4fc23042, Crnor);
+    assert_cr_logical!(0x4fc23242, Creqv);
+    assert_cr_logical!(0x4fc23102, Crandc);
+    assert_cr_logical!(0x4fc23342, Crorc);
+    assert_cr_logical!(0x4fc23182, Crxor);
+}
+
+#[test]
+fn cache_and_sync() {
+    macro_rules! assert_cache {
+        ($word:expr, $variant:ident) => {
+            assert!(matches!(
+                decode($word),
+                Instruction::$variant {
+                    base: Gpr(3),
+                    index: Gpr(4)
+                }
+            ));
+        };
+    }
+
+    // <op> r3,r4
+    assert_cache!(0x7c0327ec, Dcbz);
+    assert_cache!(0x7c0320ac, Dcbf);
+    assert_cache!(0x7c03206c, Dcbst);
+    assert_cache!(0x7c03222c, Dcbt);
+    assert_cache!(0x7c0327ac, Icbi);
+
+    assert!(matches!(
+        decode(0x7c0004ac),
+        Instruction::Sync {
+            kind: SyncKind::Heavyweight
+        }
+    ));
+    assert!(matches!(
+        decode(0x7c2004ac),
+        Instruction::Sync {
+            kind: SyncKind::Lightweight
+        }
+    ));
+    assert!(matches!(decode(0x7c0006ac), Instruction::Eieio {}));
+}
+
+#[test]
+fn reservations() {
+    // lwarx r3,r4,r5
+    assert!(matches!(
+        decode(0x7c642828),
+        Instruction::Lwarx {
+            dest: Gpr(3),
+            source: Gpr(4),
+            index: Gpr(5)
+        }
+    ));
+    // stwcx. r3,r4,r5
+    assert!(matches!(
+        decode(0x7c64292d),
+        Instruction::StwcxRc {
+            source: Gpr(3),
+            dest: Gpr(4),
+            index: Gpr(5)
+        }
+    ));
+}
+
+#[test]
+fn special_purpose_registers() {
+    fn mfspr(spr: u32) -> u32 {
+        // mfspr r3,<spr>
+        0x7c6002a6 | (spr & 0x1f) << 16 | (spr >> 5) << 11
+    }
+
+    let cases = [
+        (1, Spr::Xer(())),
+        (8, Spr::Lr),
+        (9, Spr::Ctr),
+        (18, Spr::Dsisr),
+        (19, Spr::Dar),
+        (22, Spr::Dec),
+        (26, Spr::Srr0),
+        (27, Spr::Srr1),
+        (272, Spr::Sprg(0)),
+        (273, Spr::Sprg(1)),
+        (274, Spr::Sprg(2)),
+        (275, Spr::Sprg(3)),
+        (912, Spr::Other(912)),
+    ];
+
+    for (code, expected) in cases {
+        match decode(mfspr(code)) {
+            Instruction::Mfspr { dest: Gpr(3), spr } => assert_eq!(spr, expected, "SPR {code}"),
+            other => panic!("SPR {code} decoded to {other:?}"),
+        }
+    }
+
+    assert_eq!(
+        format!("{:?}", decode(mfspr(26))),
+        "Mfspr { dest: r3, spr: SRR0 }"
+    );
+}
+
+#[test]
+fn branch_targets() {
+    let addr = 0x80003100;
+    let target = |word| decode(word).branch_target(addr);
+
+    // b 0x80003110
+    assert_eq!(target(0x48000010), Some(BranchTarget::Static(0x80003110)));
+    // bl 0x800030f0
+    assert_eq!(target(0x4bfffff1), Some(BranchTarget::Static(0x800030f0)));
+    // ba 0x100
+    assert_eq!(target(0x48000102), Some(BranchTarget::Static(0x100)));
+    // beq 0x8000310c
+    assert_eq!(target(0x4182000c), Some(BranchTarget::Static(0x8000310c)));
+    // bdnz 0x800030f8
+    assert_eq!(target(0x4200fff8), Some(BranchTarget::Static(0x800030f8)));
+    // blr
+    assert_eq!(target(0x4e800020), Some(BranchTarget::Register(Spr::Lr)));
+    // bnelr
+    assert_eq!(target(0x4c820020), Some(BranchTarget::Register(Spr::Lr)));
+    // bctr
+    assert_eq!(target(0x4e800420), Some(BranchTarget::Register(Spr::Ctr)));
+    // bctrl
+    assert_eq!(target(0x4e800421), Some(BranchTarget::Register(Spr::Ctr)));
+    // li r3,1
+    assert_eq!(target(0x38600001), None);
+
+    assert_eq!(BranchTarget::Static(0x100).address(), Some(0x100));
+    assert_eq!(BranchTarget::Register(Spr::Lr).address(), None);
+}
+
+#[test]
+fn instruction_names() {
+    assert_eq!(decode(0x4c421182).name(), "crxor");
+    assert_eq!(decode(0xfc011000).name(), "fcmpu");
+    assert_eq!(decode(0x7c642c14).name(), "addc");
+}
@@ -0,0 +1,154 @@
+use ppc32::{
+    Decoder, Instruction,
+    decoder::{DecodeError, SeekError},
+    instruction::{Gpr, Immediate},
+};
+
+const CODE: &[u8] = &[
+    0x38, 0x63, 0x00, 0x02, // addi r3,r3,2
+    0x4e, 0x80, 0x00, 0x20, // blr
+];
+
+#[test]
+fn instructions_iterator() {
+    let instructions = Decoder::new(CODE)
+        .instructions()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_eq!(instructions.len(), 2);
+    assert!(matches!(
+        instructions[0],
+        (
+            0,
+            Instruction::Addi {
+                dest: Gpr(3),
+                source: Gpr(3),
+                imm: Immediate(2)
+            }
+        )
+    ));
+    assert!(matches!(instructions[1], (4, Instruction::Bclr { .. })));
+}
+
+#[test]
+fn instructions_iterator_reports_unhandled_opcodes() {
+    let mut instructions = Decoder::new(&[0, 0, 0, 0]).instructions();
+
+    assert!(matches!(
+        instructions.next(),
+        Some(Err(DecodeError::UnhandledOpcode { offset: 0, .. }))
+    ));
+    assert!(instructions.next().is_none());
+}
+
+#[test]
+fn invalid_bo_is_a_decode_error() {
+    let code = [
+        0x40, 0xc2, 0x00, 0x08, // bc 6,2,+0x8: the z bit of BO 001zy is set
+        0x4f, 0xe0, 0x00, 0x20, // bclr 31,0: only 10100 is a valid "branch always"
+        0x4e, 0x80, 0x00, 0x20, // blr
+    ];
+    let mut decoder = Decoder::new(&code);
+
+    let err = decoder.decode_instruction().unwrap_err();
+    assert!(matches!(
+        err,
+        DecodeError::InvalidOperand {
+            offset: 0,
+            operand: "BO",
+            ..
+        }
+    ));
+    assert_eq!(
+        err.to_string(),
+        "invalid BO operand at offset 0x0 (word 0x40c20008)"
+    );
+    assert!(matches!(
+        decoder.decode_instruction(),
+        Err(DecodeError::InvalidOperand { offset: 4, .. })
+    ));
+    // Decoding carries on after the bad words.
+    assert!(matches!(
+        decoder.decode_instruction(),
+        Ok(Instruction::Bclr { .. })
+    ));
+}
+
+#[test]
+fn peek_then_decode() {
+    let mut decoder = Decoder::new(CODE);
+
+    let peeked = decoder.peek().unwrap();
+    assert_eq!(peeked.0, 0x38630002);
+    assert_eq!(decoder.offset(), 0);
+
+    let peeked = Instruction::parse_addi(peeked).unwrap();
+    let decoded = decoder.decode_instruction().unwrap();
+    assert_eq!(format!("{peeked:?}"), format!("{decoded:?}"));
+    assert_eq!(decoder.offset(), 4);
+
+    decoder.decode_instruction().unwrap();
+    assert!(decoder.peek().is_none());
+}
+
+#[test]
+fn seek() {
+    let mut decoder = Decoder::new(CODE);
+    decoder.decode_instruction().unwrap();
+
+    decoder.seek(0).unwrap();
+    assert!(matches!(
+        decoder.decode_instruction(),
+        Ok(Instruction::Addi { .. })
+    ));
+
+    decoder.seek(8).unwrap();
+    assert!(decoder.peek().is_none());
+
+    assert!(matches!(
+        decoder.seek(2),
+        Err(SeekError::Misaligned { offset: 2 })
+    ));
+    assert!(matches!(
+        decoder.seek(12),
+        Err(SeekError::OutOfBounds { offset: 12, len: 8 })
+    ));
+    // A failed seek leaves the position unchanged
+    assert_eq!(decoder.offset(), 8);
+}
+
+#[test]
+fn decode_error_display() {
+    let mut decoder = Decoder::new(CODE);
+    decoder.seek(4).unwrap();
+    decoder.decode_instruction().unwrap();
+    let err = decoder.decode_instruction().unwrap_err();
+    assert_eq!(err.to_string(), "unexpected eof at offset 0x8");
+
+    // eciwx r3,r4,r5
+    let mut decoder = Decoder::new(&[0x7c, 0x64, 0x2a, 0x6c]);
+    let err = decoder.decode_instruction().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "unhandled opcode 0b011111/0b0100110110 at offset 0x0 (word 0x7c642a6c)"
+    );
+}
+
+#[test]
+fn decode_with_word() {
+    let mut decoder = Decoder::new(CODE);
+
+    let (word, instr) = decoder.decode_instruction_with_word().unwrap();
+    assert_eq!(word.0, 0x38630002);
+    assert!(matches!(instr, Instruction::Addi { .. }));
+
+    let (word, instr) = decoder.decode_instruction_with_word().unwrap();
+    assert_eq!(word.0, 0x4e800020);
+    assert!(matches!(instr, Instruction::Bclr { .. }));
+
+    assert!(matches!(
+        decoder.decode_instruction_with_word(),
+        Err(DecodeError::UnexpectedEof { offset: 8 })
+    ));
+}
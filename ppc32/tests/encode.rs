@@ -0,0 +1,196 @@
+mod common;
+
+use common::decode;
+use ppc32::Decoder;
+
+/// A mask with the big endian bit range `from..=to` set.
+const fn bits(from: u32, to: u32) -> u32 {
+    (!0u32) >> (31 - (to - from)) << (31 - to)
+}
+
+const RD_RA_IMM: u32 = bits(6, 31);
+const RS_RA_RB_RC: u32 = bits(6, 20) | bits(31, 31);
+const RS_RA_RB: u32 = bits(6, 20);
+const RD_RA_RC: u32 = bits(6, 15) | bits(31, 31);
+// XO-form instructions also have the OE bit in front of their opcode.
+const RD_RA_RB_OE_RC: u32 = bits(6, 21) | bits(31, 31);
+const RD_RA_OE_RC: u32 = bits(6, 15) | bits(21, 21) | bits(31, 31);
+const CMP_IMM: u32 = bits(6, 8) | bits(11, 31);
+const CMPL_IMM: u32 = bits(6, 8) | bits(10, 31);
+const CMP_REG: u32 = bits(6, 8) | bits(10, 20);
+
+/// The opcode bits of D-form, X-form and XO-form integer instructions, and the bits of their operand fields.
+const TEMPLATES: &[(&str, u32, u32)] = &[
+    ("addi", 0x38000000, RD_RA_IMM),
+    ("addis", 0x3c000000, RD_RA_IMM),
+    ("addic.", 0x34000000, RD_RA_IMM),
+    ("subfic", 0x20000000, RD_RA_IMM),
+    ("ori", 0x60000000, RD_RA_IMM),
+    ("oris", 0x64000000, RD_RA_IMM),
+    ("andi.", 0x70000000, RD_RA_IMM),
+    ("twi", 0x0c000000, RD_RA_IMM),
+    ("cmpi", 0x2c000000, CMP_IMM),
+    ("cmpli", 0x28000000, CMPL_IMM),
+    ("lwz", 0x80000000, RD_RA_IMM),
+    ("lwzu", 0x84000000, RD_RA_IMM),
+    ("lbz", 0x88000000, RD_RA_IMM),
+    ("lbzu", 0x8c000000, RD_RA_IMM),
+    ("stw", 0x90000000, RD_RA_IMM),
+    ("stwu", 0x94000000, RD_RA_IMM),
+    ("stb", 0x98000000, RD_RA_IMM),
+    ("stbu", 0x9c000000, RD_RA_IMM),
+    ("lhz", 0xa0000000, RD_RA_IMM),
+    ("lhzu", 0xa4000000, RD_RA_IMM),
+    ("lha", 0xa8000000, RD_RA_IMM),
+    ("lhau", 0xac000000, RD_RA_IMM),
+    ("sth", 0xb0000000, RD_RA_IMM),
+    ("sthu", 0xb4000000, RD_RA_IMM),
+    ("lmw", 0xb8000000, RD_RA_IMM),
+    ("stmw", 0xbc000000, RD_RA_IMM),
+    ("cmp", 0x7c000000, CMP_REG),
+    ("cmpl", 0x7c000040, CMP_REG),
+    ("tw", 0x7c000008, RS_RA_RB),
+    ("slw", 0x7c000030, RS_RA_RB_RC),
+    ("srw", 0x7c000430, RS_RA_RB_RC),
+    ("sraw", 0x7c000630, RS_RA_RB_RC),
+    ("srawi", 0x7c000670, RS_RA_RB_RC),
+    ("cntlzw", 0x7c000034, RD_RA_RC),
+    ("extsh", 0x7c000734, RD_RA_RC),
+    ("extsb", 0x7c000774, RD_RA_RC),
+    ("and", 0x7c000038, RS_RA_RB_RC),
+    ("andc", 0x7c000078, RS_RA_RB_RC),
+    ("nor", 0x7c0000f8, RS_RA_RB_RC),
+    ("eqv", 0x7c000238, RS_RA_RB_RC),
+    ("xor", 0x7c000278, RS_RA_RB_RC),
+    ("orc", 0x7c000338, RS_RA_RB_RC),
+    ("or", 0x7c000378, RS_RA_RB_RC),
+    ("nand", 0x7c0003b8, RS_RA_RB_RC),
+    ("lwarx", 0x7c000028, RS_RA_RB),
+    ("lwzx", 0x7c00002e, RS_RA_RB),
+    ("lbzx", 0x7c0000ae, RS_RA_RB),
+    ("lhzx", 0x7c00022e, RS_RA_RB),
+    ("stwcx.", 0x7c00012d, RS_RA_RB),
+    ("stwx", 0x7c00012e, RS_RA_RB),
+    ("stwux", 0x7c00016e, RS_RA_RB),
+    ("stbx", 0x7c0001ae, RS_RA_RB),
+    ("sthx", 0x7c00032e, RS_RA_RB),
+    ("subfc", 0x7c000010, RD_RA_RB_OE_RC),
+    ("addc", 0x7c000014, RD_RA_RB_OE_RC),
+    ("subf", 0x7c000050, RD_RA_RB_OE_RC),
+    ("neg", 0x7c0000d0, RD_RA_OE_RC),
+    ("subfe", 0x7c000110, RD_RA_RB_OE_RC),
+    ("adde", 0x7c000114, RD_RA_RB_OE_RC),
+    ("addze", 0x7c000194, RD_RA_OE_RC),
+    ("addme", 0x7c0001d4, RD_RA_OE_RC),
+    ("add", 0x7c000214, RD_RA_RB_OE_RC),
+];
+
+/// A xorshift PRNG, so the test is deterministic without pulling in a dependency.
+struct Rng(u32);
+
+impl Rng {
+    fn next(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0
+    }
+}
+
+#[test]
+fn integer_instructions_round_trip() {
+    let mut rng = Rng(0x2545f491);
+
+    for &(name, opcode, operands) in TEMPLATES {
+        for _ in 0..1000 {
+            let word = opcode | (rng.next() & operands);
+            let encoded = decode(word).encode();
+            assert_eq!(
+                encoded.0, word,
+                "{name}: {word:#010x} re-encoded as {:#010x}",
+                encoded.0
+            );
+        }
+    }
+}
+
+#[test]
+fn branch_instructions_round_trip() {
+    let mut rng = Rng(0x9e3779b9);
+
+    // bc, bclr and bcctr, with the operand bits other than BO.
+    let forms = [
+        ("bc", 0x40000000, bits(11, 31)),
+        ("bclr", 0x4c000020, bits(11, 15) | bits(31, 31)),
+        ("bcctr", 0x4c000420, bits(11, 15) | bits(31, 31)),
+    ];
+
+    for (name, opcode, operands) in forms {
+        for bo in 0..32u32 {
+            let template = opcode | (bo << 21);
+            // Not every BO value is a valid encoding.
+            if Decoder::new(&template.to_be_bytes())
+                .decode_instruction()
+                .is_err()
+            {
+                continue;
+            }
+
+            for _ in 0..100 {
+                let word = template | (rng.next() & operands);
+                let encoded = decode(word).encode();
+                assert_eq!(
+                    encoded.0, word,
+                    "{name}: {word:#010x} re-encoded as {:#010x}",
+                    encoded.0
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn encode_known_words() {
+    // One word per remaining instruction form, taken from real code.
+    let words = [
+        0x4800_0011, // bl +0x10
+        0x4bff_fff0, // b -0x10
+        0x4182_000c, // beq +0xc
+        0x4082_fff4, // bne -0xc
+        0x4200_fff8, // bdnz -0x8
+        0x4042_fff8, // bdzf eq, -0x8
+        0x4142_fff8, // bdzt eq, -0x8
+        0x4ca2_0020, // bnelr+
+        0x4e80_0020, // blr
+        0x4e80_0421, // bctrl
+        0x5464_103a, // rlwinm r4,r3,2,0,29
+        0x5064_442e, // rlwimi r4,r3,8,16,23
+        0x5c64_283e, // rlwnm r4,r3,r5,0,31
+        0x7c08_02a6, // mflr r0
+        0x7c08_03a6, // mtlr r0
+        0x7c6c_42e6, // mftbl r3
+        0x7c60_00a6, // mfmsr r3
+        0x7c60_0026, // mfcr r3
+        0x7c6f_f120, // mtcrf 0xff,r3
+        0x4c00_012c, // isync
+        0x7c00_04ac, // sync
+        0x7c20_04ac, // lwsync
+        0x7c00_06ac, // eieio
+        0x7c00_1fec, // dcbz r0,r3
+        0x4400_0002, // sc
+        0xc021_0008, // lfs f1,8(r1)
+        0xd821_0010, // stfd f1,16(r1)
+        0xfc21_102a, // fadd f1,f1,f2
+        0xec21_00b2, // fmuls f1,f1,f2
+        0xfc21_10fa, // fmadd f1,f1,f3,f2
+        0xfc01_1000, // fcmpu cr0,f1,f2
+        0xfc20_1090, // fmr f1,f2
+        0xfc20_101e, // fctiwz f1,f2
+        0x4c42_1182, // crxor 2,2,2
+        0x4c8c_0000, // mcrf cr1,cr3
+    ];
+
+    for word in words {
+        assert_eq!(decode(word).encode().0, word, "{word:#010x}");
+    }
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use common::decode;
+use ppc32::format::{AsmFormatOptions, Radix};
+
+const HEX: AsmFormatOptions = AsmFormatOptions {
+    radix: Radix::Hex,
+    signed: true,
+};
+
+#[test]
+fn hex_and_decimal_immediates() {
+    // lwz r3,-0x10(r4)
+    let lwz = decode(0x8064fff0);
+    assert_eq!(
+        lwz.display(AsmFormatOptions::default()).to_string(),
+        "Lwz { dest: r3, source: r4, imm: Immediate(-16) }"
+    );
+    assert_eq!(
+        lwz.display(HEX).to_string(),
+        "Lwz { dest: r3, source: r4, imm: Immediate(-0x10) }"
+    );
+    assert_eq!(
+        lwz.display(AsmFormatOptions {
+            radix: Radix::Hex,
+            signed: false
+        })
+        .to_string(),
+        "Lwz { dest: r3, source: r4, imm: Immediate(0xfff0) }"
+    );
+    assert_eq!(
+        lwz.display(AsmFormatOptions {
+            radix: Radix::Decimal,
+            signed: false
+        })
+        .to_string(),
+        "Lwz { dest: r3, source: r4, imm: Immediate(65520) }"
+    );
+
+    // ori r3,r3,0x8000
+    assert_eq!(
+        decode(0x60638000).display(HEX).to_string(),
+        "Ori { source: r3, dest: r3, imm: Immediate(0x8000) }"
+    );
+    // andi. r3,r4,0xfff8
+    assert_eq!(
+        decode(0x7083fff8).display(HEX).to_string(),
+        "Andi { source: r4, dest: r3, imm: Immediate(0xfff8) }"
+    );
+    // subfic r3,r4,-1
+    assert_eq!(
+        decode(0x2064ffff).display(HEX).to_string(),
+        "Subfic { dest: r3, source: r4, simm: -0x1 }"
+    );
+}
+
+#[test]
+fn shift_amounts_stay_decimal() {
+    // rlwinm r4,r3,16,0,15
+    let rlwinm = decode(0x5464801e);
+    assert_eq!(rlwinm.display(HEX).to_string(), format!("{rlwinm:?}"));
+    assert!(rlwinm.display(HEX).to_string().contains("Immediate(16)"));
+}
+
+#[test]
+fn default_options_match_debug() {
+    let words = [
+        0x38600005, // li r3,5
+        0x9421ffe0, // stwu r1,-0x20(r1)
+        0x2c030000, // cmpwi r3,0
+        0x4182000c, // beq +0xc
+        0x7c0802a6, // mflr r0
+        0xfc21102a, // fadd f1,f1,f2
+        0x4c00012c, // isync
+    ];
+
+    for word in words {
+        let ins = decode(word);
+        assert_eq!(
+            ins.display(AsmFormatOptions::default()).to_string(),
+            format!("{ins:?}")
+        );
+        assert_eq!(
+            format!("{:#}", ins.display(AsmFormatOptions::default())),
+            format!("{ins:#?}")
+        );
+    }
+}
@@ -0,0 +1,184 @@
+mod common;
+
+use common::decode;
+use ppc32::{
+    Instruction,
+    instruction::{Fpr, Gpr, RegisterVisitor},
+};
+
+#[derive(Default)]
+struct FprAccesses {
+    reads: Vec<Fpr>,
+    writes: Vec<Fpr>,
+}
+
+impl RegisterVisitor for &mut FprAccesses {
+    fn read_fpr(&mut self, fpr: Fpr) {
+        self.reads.push(fpr);
+    }
+
+    fn write_fpr(&mut self, fpr: Fpr) {
+        self.writes.push(fpr);
+    }
+}
+
+#[test]
+fn fadd_fprs() {
+    // fadd fr1,fr2,fr3
+    let fadd = decode(0xfc22182a);
+
+    let mut accesses = FprAccesses::default();
+    fadd.visit_registers(&mut accesses);
+    assert_eq!(accesses.reads, [Fpr(2), Fpr(3)]);
+    assert_eq!(accesses.writes, [Fpr(1)]);
+
+    let mut reads = Vec::new();
+    fadd.for_each_read_fpr(|fpr| reads.push(fpr));
+    assert_eq!(reads, [Fpr(2), Fpr(3)]);
+}
+
+#[test]
+fn lfd_reads_gpr_writes_fpr() {
+    // lfd fr1,8(r3)
+    let lfd = decode(0xc8230008);
+
+    let mut accesses = FprAccesses::default();
+    lfd.visit_registers(&mut accesses);
+    assert!(accesses.reads.is_empty());
+    assert_eq!(accesses.writes, [Fpr(1)]);
+
+    let mut reads = Vec::new();
+    lfd.for_each_read_gpr(|gpr| reads.push(gpr));
+    assert_eq!(reads, [Gpr(3)]);
+}
+
+/// Every instruction form that can be decoded, along with the GPRs it reads and writes. FPR-only and CR-only
+/// instructions are listed too, to make sure they don't report any GPRs.
+const GPR_ACCESSES: &[(u32, &[u8], &[u8])] = &[
+    (0x3864_0008, &[4], &[3]),            // addi r3,r4,8
+    (0x3860_0008, &[], &[3]),             // li r3,8
+    (0x3c64_0001, &[4], &[3]),            // addis r3,r4,1
+    (0x3c60_0001, &[], &[3]),             // lis r3,1
+    (0x3464_0001, &[4], &[3]),            // addic. r3,r4,1
+    (0x2064_0001, &[4], &[3]),            // subfic r3,r4,1
+    (0x6064_0001, &[3], &[4]),            // ori r4,r3,1
+    (0x6464_0001, &[3], &[4]),            // oris r4,r3,1
+    (0x7064_0001, &[3], &[4]),            // andi. r4,r3,1
+    (0x2c03_0000, &[3], &[]),             // cmpwi r3,0
+    (0x2803_0000, &[3], &[]),             // cmplwi r3,0
+    (0x0c83_0000, &[3], &[]),             // twi 4,r3,0
+    (0x8064_0008, &[4], &[3]),            // lwz r3,8(r4)
+    (0x8464_0008, &[4], &[3, 4]),         // lwzu r3,8(r4)
+    (0x8864_0008, &[4], &[3]),            // lbz r3,8(r4)
+    (0x8c64_0008, &[4], &[3, 4]),         // lbzu r3,8(r4)
+    (0xa064_0008, &[4], &[3]),            // lhz r3,8(r4)
+    (0xa464_0008, &[4], &[3, 4]),         // lhzu r3,8(r4)
+    (0xa864_0008, &[4], &[3]),            // lha r3,8(r4)
+    (0xac64_0008, &[4], &[3, 4]),         // lhau r3,8(r4)
+    (0x9064_0008, &[3, 4], &[]),          // stw r3,8(r4)
+    (0x9464_0008, &[3, 4], &[4]),         // stwu r3,8(r4)
+    (0x9864_0008, &[3, 4], &[]),          // stb r3,8(r4)
+    (0x9c64_0008, &[3, 4], &[4]),         // stbu r3,8(r4)
+    (0xb064_0008, &[3, 4], &[]),          // sth r3,8(r4)
+    (0xb464_0008, &[3, 4], &[4]),         // sthu r3,8(r4)
+    (0xbba1_0008, &[1], &[29, 30, 31]),   // lmw r29,8(r1)
+    (0xbfa1_0008, &[1, 29, 30, 31], &[]), // stmw r29,8(r1)
+    (0xc024_0008, &[4], &[]),             // lfs f1,8(r4)
+    (0xc424_0008, &[4], &[4]),            // lfsu f1,8(r4)
+    (0xc824_0008, &[4], &[]),             // lfd f1,8(r4)
+    (0xcc24_0008, &[4], &[4]),            // lfdu f1,8(r4)
+    (0xd024_0008, &[4], &[]),             // stfs f1,8(r4)
+    (0xd424_0008, &[4], &[4]),            // stfsu f1,8(r4)
+    (0xd824_0008, &[4], &[]),             // stfd f1,8(r4)
+    (0xdc24_0008, &[4], &[4]),            // stfdu f1,8(r4)
+    (0x7c03_2000, &[3, 4], &[]),          // cmpw r3,r4
+    (0x7c03_2040, &[3, 4], &[]),          // cmplw r3,r4
+    (0x7c83_2008, &[3, 4], &[]),          // tw 4,r3,r4
+    (0x7c64_282e, &[4, 5], &[3]),         // lwzx r3,r4,r5
+    (0x7c64_28ae, &[4, 5], &[3]),         // lbzx r3,r4,r5
+    (0x7c64_2a2e, &[4, 5], &[3]),         // lhzx r3,r4,r5
+    (0x7c64_2828, &[4, 5], &[3]),         // lwarx r3,r4,r5
+    (0x7c64_292e, &[3, 4, 5], &[]),       // stwx r3,r4,r5
+    (0x7c64_29ae, &[3, 4, 5], &[]),       // stbx r3,r4,r5
+    (0x7c64_2b2e, &[3, 4, 5], &[]),       // sthx r3,r4,r5
+    (0x7c64_296e, &[3, 4, 5], &[4]),      // stwux r3,r4,r5
+    (0x7c64_292d, &[3, 4, 5], &[]),       // stwcx. r3,r4,r5
+    (0x7c64_2830, &[3, 5], &[4]),         // slw r4,r3,r5
+    (0x7c64_2c30, &[3, 5], &[4]),         // srw r4,r3,r5
+    (0x7c64_2e30, &[3, 5], &[4]),         // sraw r4,r3,r5
+    (0x7c64_2838, &[3, 5], &[4]),         // and r4,r3,r5
+    (0x7c64_2878, &[3, 5], &[4]),         // andc r4,r3,r5
+    (0x7c64_28f8, &[3, 5], &[4]),         // nor r4,r3,r5
+    (0x7c64_2a38, &[3, 5], &[4]),         // eqv r4,r3,r5
+    (0x7c64_2a78, &[3, 5], &[4]),         // xor r4,r3,r5
+    (0x7c64_2b38, &[3, 5], &[4]),         // orc r4,r3,r5
+    (0x7c64_2b78, &[3, 5], &[4]),         // or r4,r3,r5
+    (0x7c64_2bb8, &[3, 5], &[4]),         // nand r4,r3,r5
+    (0x7c64_2e70, &[3], &[4]),            // srawi r4,r3,5
+    (0x7c64_0034, &[3], &[4]),            // cntlzw r4,r3
+    (0x7c64_0734, &[3], &[4]),            // extsh r4,r3
+    (0x7c64_0774, &[3], &[4]),            // extsb r4,r3
+    (0x7c64_2850, &[4, 5], &[3]),         // subf r3,r4,r5
+    (0x7c64_2810, &[4, 5], &[3]),         // subfc r3,r4,r5
+    (0x7c64_2910, &[4, 5], &[3]),         // subfe r3,r4,r5
+    (0x7c64_2814, &[4, 5], &[3]),         // addc r3,r4,r5
+    (0x7c64_2914, &[4, 5], &[3]),         // adde r3,r4,r5
+    (0x7c64_2a14, &[4, 5], &[3]),         // add r3,r4,r5
+    (0x7c64_00d0, &[4], &[3]),            // neg r3,r4
+    (0x7c64_0194, &[4], &[3]),            // addze r3,r4
+    (0x7c64_01d4, &[4], &[3]),            // addme r3,r4
+    (0x7c68_02a6, &[], &[3]),             // mflr r3
+    (0x7c08_03a6, &[0], &[]),             // mtlr r0
+    (0x7c60_00a6, &[], &[3]),             // mfmsr r3
+    (0x7c60_0124, &[3], &[]),             // mtmsr r3
+    (0x7c6c_42e6, &[], &[3]),             // mftbl r3
+    (0x7c60_0026, &[], &[3]),             // mfcr r3
+    (0x7c6f_f120, &[3], &[]),             // mtcrf 0xff,r3
+    (0x7c03_27ec, &[3, 4], &[]),          // dcbz r3,r4
+    (0x7c03_20ac, &[3, 4], &[]),          // dcbf r3,r4
+    (0x7c03_206c, &[3, 4], &[]),          // dcbst r3,r4
+    (0x7c03_222c, &[3, 4], &[]),          // dcbt r3,r4
+    (0x7c03_27ac, &[3, 4], &[]),          // icbi r3,r4
+    (0x4c00_012c, &[], &[]),              // isync
+    (0x7c00_04ac, &[], &[]),              // sync
+    (0x7c00_06ac, &[], &[]),              // eieio
+    (0x4400_0002, &[], &[]),              // sc
+    (0x5464_103a, &[3], &[4]),            // rlwinm r4,r3,2,0,29
+    (0x5064_442e, &[3, 4], &[4]),         // rlwimi r4,r3,8,16,23
+    (0x5c64_283e, &[3, 5], &[4]),         // rlwnm r4,r3,r5,0,31
+    (0x4800_0010, &[], &[]),              // b 0x10
+    (0x4800_0011, &[], &[3]),             // bl 0x10
+    (0x4182_000c, &[], &[]),              // beq 0xc
+    (0x4182_000d, &[], &[3]),             // beql 0xc
+    (0x4e80_0020, &[], &[]),              // blr
+    (0x4e80_0021, &[], &[3]),             // blrl
+    (0x4e80_0420, &[], &[]),              // bctr
+    (0x4e80_0421, &[], &[3]),             // bctrl
+    (0xfc22_182a, &[], &[]),              // fadd f1,f2,f3
+    (0xec21_00b2, &[], &[]),              // fmuls f1,f1,f2
+    (0xfc21_10fa, &[], &[]),              // fmadd f1,f1,f3,f2
+    (0xfc01_1000, &[], &[]),              // fcmpu cr0,f1,f2
+    (0xfc20_1090, &[], &[]),              // fmr f1,f2
+    (0xfc20_101e, &[], &[]),              // fctiwz f1,f2
+    (0xfc00_004c, &[], &[]),              // mtfsb1 0
+    (0x4c42_1182, &[], &[]),              // crxor 2,2,2
+    (0x4c8c_0000, &[], &[]),              // mcrf cr1,cr3
+];
+
+#[test]
+fn gpr_accesses() {
+    for &(word, reads, writes) in GPR_ACCESSES {
+        let inst = decode(word);
+        let collect = |f: fn(&Instruction, &mut dyn FnMut(Gpr))| {
+            let mut gprs = Vec::new();
+            f(&inst, &mut |gpr| gprs.push(gpr.0));
+            gprs.sort();
+            gprs
+        };
+
+        let actual_reads = collect(|inst, f| inst.for_each_read_gpr(f));
+        let actual_writes = collect(|inst, f| inst.for_each_written_gpr(f));
+        assert_eq!(actual_reads, reads, "{word:#010x} ({inst:?}) reads");
+        assert_eq!(actual_writes, writes, "{word:#010x} ({inst:?}) writes");
+    }
+}
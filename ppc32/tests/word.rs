@@ -0,0 +1,48 @@
+use ppc32::word::Word;
+
+#[test]
+fn split_field_matches_spr_swizzle() {
+    // mfspr r3,<spr> for a few SPRs, plus an arbitrary word with every bit in the SPR field set.
+    let words = [
+        0x7c6802a6, 0x7c6902a6, 0x7c7a02a6, 0x7c7042a6, 0x7c6c42e6, 0xffffffff,
+    ];
+
+    for word in words.map(Word) {
+        let inline = word.u16::<11, 15>() | (word.u16::<16, 20>() << 5);
+        assert_eq!(word.split_field::<11, 15, 16, 20>(), u32::from(inline));
+        assert_eq!(word.spr(), inline);
+    }
+}
+
+#[test]
+fn split_field_uneven_halves() {
+    // 0b101 in bits 0-2 (low) and 0b1 in bit 31 (high)
+    let word = Word(0b101 << 29 | 1);
+    assert_eq!(word.split_field::<0, 2, 31, 31>(), 0b1101);
+}
+
+#[test]
+fn i32_sign_extends_from_highest_field_bit() {
+    // bc with a 14-bit displacement of -5000 words
+    let word = Word((-5000i32 as u32 & 0x3fff) << 2);
+    assert_eq!(word.i32::<16, 29>(), -5000);
+    // b with the largest positive 24-bit displacement
+    let word = Word(0x007f_ffff << 2);
+    assert_eq!(word.i32::<6, 29>(), 0x007f_ffff);
+}
+
+#[test]
+fn with_u32_inverts_u32() {
+    let word = Word(0x7c642830)
+        .with_u32::<6, 10>(7)
+        .with_u32::<16, 20>(31)
+        .with_bit::<31>(true);
+    assert_eq!(word.u32::<6, 10>(), 7);
+    assert_eq!(word.u32::<11, 15>(), 4);
+    assert_eq!(word.u32::<16, 20>(), 31);
+    assert_ne!(word.bit::<31>(), 0);
+    assert_eq!(word.xform_opcode(), 24);
+
+    assert_eq!(Word(0).with_i32::<16, 29>(-5000).i32::<16, 29>(), -5000);
+    assert_eq!(Word(0).with_spr(272).spr(), 272);
+}